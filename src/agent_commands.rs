@@ -1,5 +1,7 @@
-use crate::cli::Commands;
+use crate::backend::{ExecOptions, OutputLine};
+use crate::cli::{Commands, ResourceLimitOptions};
 use crate::config::{BackendType, JailConfig};
+use crate::drift::{config_drift, ConfigChange};
 use crate::error::{self, Result};
 use crate::git_gpg::{
     create_claude_json_in_container, create_gitconfig_in_container, setup_git_gpg_config,
@@ -8,9 +10,10 @@ use crate::jail::{JailBuilder, JailManager};
 use crate::jail_setup::{self, mount_agent_configs, setup_default_environment};
 use crate::strings;
 use std::path::{Path, PathBuf};
-use tracing::{info, warn};
+use tracing::{debug, info, warn};
 
 /// Parameters for AI agent commands
+#[derive(Clone)]
 pub struct AgentCommandParams {
     pub backend: Option<String>,
     pub image: String,
@@ -21,6 +24,7 @@ pub struct AgentCommandParams {
     pub host_network: bool,
     pub memory: Option<u64>,
     pub cpu: Option<u32>,
+    pub limits: ResourceLimitOptions,
     pub no_workspace: bool,
     pub workspace_path: String,
     pub claude_dir: bool,
@@ -44,7 +48,16 @@ pub struct AgentCommandParams {
     pub no_nix: bool,
     pub no_block_host: bool,
     pub podman: bool,
+    pub hardened: bool,
+    pub seccomp: bool,
+    pub cow: bool,
+    pub ephemeral: bool,
+    /// Named session running in its own jail (empty for the next free numbered session)
+    pub session: Option<String>,
+    pub shared_home: bool,
+    pub auto_recreate: bool,
     pub tui: bool,
+    pub profile: Option<String>,
     pub args: Vec<String>,
 }
 
@@ -155,24 +168,37 @@ pub fn validate_workspace_directory(workspace_dir: &Path) -> Result<()> {
 
 /// Map agent command names to normalized agent identifiers
 /// (e.g., "cursor-agent" -> "cursor")
-fn normalize_agent_name(agent_command: &str) -> &str {
+pub fn normalize_agent_name(agent_command: &str) -> &str {
     crate::agents::Agent::from_str(agent_command)
         .map(|a| a.normalized_name())
         .unwrap_or(agent_command)
 }
 
+/// Build the jail name for an agent from the workspace base name
+pub fn agent_jail_name(base_name: &str, agent_command: &str) -> String {
+    let agent_suffix = Commands::sanitize_jail_name(normalize_agent_name(agent_command));
+    format!("{base_name}__{agent_suffix}")
+}
+
+/// Use the user-specified backend or auto-detect one
+pub fn resolve_backend_type(backend: Option<&str>) -> Result<BackendType> {
+    match backend {
+        Some(backend_str) => Commands::parse_backend(backend_str).map_err(error::JailError::Config),
+        None => Ok(BackendType::detect()),
+    }
+}
+
 /// Check if a container's image is outdated and needs an upgrade
 /// Returns (needs_upgrade, current_image, expected_image)
 async fn check_container_upgrade_needed(
-    jail_name: &str,
+    jail: &JailManager,
     workspace_path: &Path,
     agent_name: &str,
     isolated: bool,
     no_nix: bool,
 ) -> Result<(bool, String, String)> {
     // Get the current image used by the container
-    let backend = crate::backend::podman::PodmanBackend::new();
-    let current_image = backend.get_container_image(jail_name).await?;
+    let current_image = jail.inspect().await?.base_image;
 
     // Determine what image should be used now based on current project state
     let expected_image = crate::image_layers::get_expected_image_name(
@@ -183,8 +209,9 @@ async fn check_container_upgrade_needed(
     )
     .await?;
 
-    // Check if images differ
-    let needs_upgrade = current_image != expected_image;
+    // Check if images differ (a jail restored from a snapshot keeps its snapshot image)
+    let needs_upgrade =
+        current_image != expected_image && !crate::image::is_snapshot_image(&current_image);
 
     Ok((needs_upgrade, current_image, expected_image))
 }
@@ -207,6 +234,333 @@ fn prompt_upgrade() -> Result<bool> {
     Ok(answer == "y" || answer == "yes")
 }
 
+/// Check whether an agent needs the authentication workflow on first run
+/// (credentials missing or empty). Only checked when the agent's config
+/// directory is mounted, otherwise credentials won't be accessible.
+pub fn first_run_auth_needed(agent_command: &str, params: &AgentCommandParams) -> bool {
+    let Some(agent) = crate::agents::Agent::from_str(agent_command) else {
+        return false;
+    };
+
+    // Only auto-enable auth for agents that support the auth workflow
+    if !agent.supports_auth_workflow() {
+        return false;
+    }
+
+    // Check if the appropriate config directory flag is set
+    let config_dir_mounted = match agent {
+        crate::agents::Agent::Codex => params.codex_dir || params.agent_configs,
+        crate::agents::Agent::Jules => params.jules_dir || params.agent_configs,
+        _ => false, // Other agents don't use this auto-detection
+    };
+
+    if !config_dir_mounted {
+        return false;
+    }
+
+    let home = std::env::var("HOME").unwrap_or_else(|_| "/root".to_string());
+    let home_path = PathBuf::from(&home);
+
+    if agent.needs_auth(&home_path) {
+        info!(
+            "First run detected - {} credentials not found or empty",
+            agent.display_name()
+        );
+        return true;
+    }
+
+    false
+}
+
+/// Build the jail configuration used by an agent command, without creating anything
+pub fn build_agent_jail(
+    agent_command: &str,
+    jail_name: &str,
+    backend_type: BackendType,
+    params: &AgentCommandParams,
+    cwd: &Path,
+) -> Result<JailBuilder> {
+    // Only use custom image if explicitly provided (not default)
+    // This allows the layered image system to auto-detect and build agent-specific images
+    let use_custom_image = params.image != crate::cli::DEFAULT_IMAGE;
+
+    let mut builder = JailBuilder::new(jail_name).backend(backend_type);
+
+    // Configure network mode based on flags
+    if params.auth || params.host_network {
+        // OAuth authentication or explicit --host-network requires host networking
+        builder = builder.host_network(true);
+        if params.auth {
+            info!("Using host networking for OAuth authentication");
+        } else {
+            info!("Using host networking (--host-network)");
+        }
+    } else {
+        // Normal operation: use private or shared networking
+        builder = builder.network(!params.no_network, true);
+    }
+
+    // Set image: use custom if provided, otherwise let layered system auto-detect
+    if use_custom_image {
+        builder = builder.base_image(&params.image);
+    } else {
+        // Use default image name, which triggers layered image auto-detection
+        builder = builder.base_image(crate::image::DEFAULT_IMAGE_NAME);
+    }
+
+    // Setup default environment variables
+    builder = setup_default_environment(builder);
+
+    // Auto-mount workspace (git root if available, otherwise current directory)
+    // Special handling for git worktrees
+    if !params.no_workspace {
+        let workspace_dir = get_git_root().unwrap_or_else(|| cwd.to_path_buf());
+
+        // Validate workspace directory is safe
+        validate_workspace_directory(&workspace_dir)?;
+
+        // Check if this is a git worktree
+        if let Some(worktree_info) = crate::worktree::detect_worktree(&workspace_dir)? {
+            info!("Detected git worktree, setting up dual-mount configuration");
+            if params.cow {
+                return Err(error::JailError::Config(
+                    "Copy-on-write workspaces (--cow) are not supported in git worktrees: the worktree and its repository are mounted read-write".to_string(),
+                ));
+            }
+
+            // Collect paths that need parent directory creation
+            let paths_to_mount = vec![
+                worktree_info.worktree_path.as_path(),
+                worktree_info.main_git_dir.as_path(),
+            ];
+            let parent_dirs = crate::worktree::get_required_parent_dirs(&paths_to_mount);
+
+            info!(
+                "Will create {} parent directories in container",
+                parent_dirs.len()
+            );
+            builder = builder.pre_create_dirs(parent_dirs);
+
+            // Mount 1: Worktree at /workspace (familiar location)
+            info!(
+                "Mounting worktree {} to /workspace",
+                worktree_info.worktree_path.display()
+            );
+            builder = builder.workspace_mount(&worktree_info.worktree_path, "/workspace", false);
+
+            // Mount 2: Worktree at original absolute path (preserve .git file reference)
+            info!(
+                "Mounting worktree {} to {} (preserve absolute path)",
+                worktree_info.worktree_path.display(),
+                worktree_info.worktree_path.display()
+            );
+            builder = builder.bind_mount(
+                &worktree_info.worktree_path,
+                &worktree_info.worktree_path,
+                false,
+            );
+
+            // Mount 3: Main .git at original absolute path (read-write for git operations)
+            info!(
+                "Mounting main git directory {} to {} (read-write)",
+                worktree_info.main_git_dir.display(),
+                worktree_info.main_git_dir.display()
+            );
+            builder = builder.bind_mount(
+                &worktree_info.main_git_dir,
+                &worktree_info.main_git_dir,
+                false,
+            );
+        } else {
+            // Regular directory, not a worktree
+            info!(
+                "Auto-mounting {} to {}",
+                workspace_dir.display(),
+                params.workspace_path
+            );
+            builder = builder.workspace_mount(workspace_dir, &params.workspace_path, false);
+        }
+    }
+
+    // Auto-mount agent config directories
+    let home = std::env::var("HOME").unwrap_or_else(|_| "/root".to_string());
+    let home_path = PathBuf::from(&home);
+
+    builder = mount_agent_configs(
+        builder,
+        &home_path,
+        agent_command,
+        &jail_setup::AgentConfigFlags {
+            claude_dir: params.claude_dir,
+            claude_code_router_dir: params.claude_code_router_dir,
+            copilot_dir: params.copilot_dir,
+            cursor_dir: params.cursor_dir,
+            gemini_dir: params.gemini_dir,
+            coderabbit_dir: params.coderabbit_dir,
+            codex_dir: params.codex_dir,
+            jules_dir: params.jules_dir,
+            pi_dir: params.pi_dir,
+            agent_configs: params.agent_configs,
+        },
+    );
+
+    // Opt-in: GPG configuration
+    if params.git_gpg {
+        builder = setup_git_gpg_config(builder, cwd, &home_path)?;
+    }
+
+    // Parse mounts
+    for mount_str in &params.mount {
+        let mount = Commands::parse_mount(mount_str).map_err(error::JailError::Config)?;
+        builder = builder.bind_mount(mount.source, mount.target, mount.readonly);
+    }
+
+    // Parse port mappings
+    for port_str in &params.port {
+        let port_mapping = Commands::parse_port(port_str).map_err(error::JailError::Config)?;
+        builder = builder.port_mapping(port_mapping);
+    }
+
+    // Parse environment variables
+    for env_str in &params.env {
+        let (key, value) = Commands::parse_env(env_str).map_err(error::JailError::Config)?;
+        builder = builder.env(key, value);
+    }
+
+    // Set resource limits
+    if let Some(mem) = params.memory {
+        builder = builder.memory_limit(mem);
+    }
+    if let Some(cpu_quota) = params.cpu {
+        builder = builder.cpu_quota(cpu_quota);
+    }
+
+    // Extended limits on top of the agent jail defaults
+    builder = jail_setup::agent_limit_defaults(builder);
+    builder = jail_setup::apply_resource_limits(builder, &params.limits)?;
+
+    // Set upgrade flag - this controls whether layers are rebuilt
+    // Container removal is handled manually above when should_recreate is true
+    builder = builder.upgrade(params.upgrade);
+
+    // Set force layers (include cloud layers if --cloud is specified)
+    let mut force_layers = params.force_layers.clone();
+    if params.cloud {
+        if !force_layers.contains(&"aws".to_string()) {
+            force_layers.push("aws".to_string());
+        }
+        if !force_layers.contains(&"gcp".to_string()) {
+            force_layers.push("gcp".to_string());
+        }
+    }
+    builder = builder.force_layers(force_layers);
+
+    // Set isolated flag
+    builder = builder.isolated(params.isolated);
+
+    // Set verbose flag
+    builder = builder.verbose(params.verbose);
+
+    // Set no_nix flag
+    builder = builder.no_nix(params.no_nix);
+
+    // Set block_host flag (inverted: !no_block_host means blocking is enabled)
+    builder = builder.block_host(!params.no_block_host);
+
+    // Set podman_socket flag for Podman-in-Podman support
+    builder = builder.podman_socket(params.podman);
+
+    builder = builder
+        .hardened(params.hardened)
+        .seccomp(params.seccomp)
+        .cow(params.cow)
+        .shared_home(params.shared_home);
+
+    Ok(builder)
+}
+
+/// What to do with an agent jail before running the agent
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum JailAction {
+    /// No jail yet
+    Create,
+    /// The jail exists but must be recreated (forced, or its isolation settings changed)
+    Recreate,
+    /// The jail exists but other settings differ from the request
+    Drifted(Vec<ConfigChange>),
+    /// The jail exists and matches the requested settings
+    Reuse,
+}
+
+/// Decide whether an agent jail is created, recreated or reused
+///
+/// `--upgrade`, `--layers` and `--auth` force recreation; otherwise the existing
+/// container is compared with `desired`. Network mode or eBPF blocking changes
+/// always recreate it, other changes are returned for confirmation.
+async fn plan_jail_action(
+    jail: &JailManager,
+    params: &AgentCommandParams,
+    desired: &JailConfig,
+) -> Result<JailAction> {
+    if !jail.exists().await? {
+        return Ok(JailAction::Create);
+    }
+
+    if params.upgrade || !params.force_layers.is_empty() || params.auth {
+        return Ok(JailAction::Recreate);
+    }
+
+    let Ok(existing_config) = jail.inspect().await else {
+        return Ok(JailAction::Reuse);
+    };
+    let changes = config_drift(&existing_config, desired);
+
+    if changes.iter().any(ConfigChange::affects_isolation) {
+        for change in changes.iter().filter(|change| change.affects_isolation()) {
+            info!("Isolation setting mismatch detected: {}", change);
+        }
+        // Only the container is recreated, image layers are not rebuilt
+        info!("Container will be recreated with the requested isolation settings");
+        return Ok(JailAction::Recreate);
+    }
+
+    Ok(if changes.is_empty() {
+        JailAction::Reuse
+    } else {
+        JailAction::Drifted(changes)
+    })
+}
+
+/// Show how the jail differs from the request and ask whether to recreate it
+fn confirm_drift_recreate(jail_name: &str, changes: &[ConfigChange], auto: bool) -> Result<bool> {
+    println!(
+        "{}",
+        strings::format_string(strings::CONFIG_DRIFT_DETECTED, &jail_name)
+    );
+    for change in changes {
+        println!("  {}", change);
+    }
+    println!("{}", strings::DATA_PRESERVED);
+
+    if auto {
+        return Ok(true);
+    }
+    prompt_upgrade()
+}
+
+/// Whether the jail image ships the Nix wrapper loading the flake environment
+async fn uses_nix_wrapper(jail: &JailManager) -> bool {
+    let check = [
+        "test".to_string(),
+        "-x".to_string(),
+        "/usr/local/bin/nix-wrapper".to_string(),
+    ];
+    jail.exec_with(&check, &ExecOptions::default())
+        .await
+        .map(|result| result.success())
+        .unwrap_or(false)
+}
+
 /// Helper function to run AI agent commands (claude, copilot, cursor-agent, gemini)
 pub async fn run_ai_agent_command(
     agent_command: &str,
@@ -215,23 +569,68 @@ pub async fn run_ai_agent_command(
     let cwd = std::env::current_dir()?;
     let base_name = auto_detect_jail_name()?;
 
-    // Normalize agent name for jail naming and image building
-    let normalized_agent = normalize_agent_name(agent_command);
-    let agent_suffix = Commands::sanitize_jail_name(normalized_agent);
-    let jail_name = format!("{base_name}__{agent_suffix}");
-
-    info!("Using jail: {} for agent: {}", jail_name, agent_command);
+    // Merge configuration files (global defaults, profile, project .jail-ai.toml), CLI flags win
+    let settings =
+        crate::settings::resolve_settings(params.profile.as_deref(), get_git_root().as_deref())?;
+    settings.apply_to_params(&mut params);
 
     // Determine backend type early - use user-specified or auto-detect
-    let backend_type = if let Some(backend_str) = params.backend {
-        Commands::parse_backend(&backend_str).map_err(error::JailError::Config)?
+    let backend_type = resolve_backend_type(params.backend.as_deref())?;
+
+    // A numbered session stays reserved until the agent exits
+    let (jail_name, _reservation) = if params.ephemeral {
+        (
+            crate::ephemeral::ephemeral_jail_name(&base_name, agent_command),
+            None,
+        )
+    } else if let Some(session) = &params.session {
+        let (session, reservation) = if session.is_empty() {
+            let temp_config = JailConfig {
+                name: "temp".to_string(),
+                backend: backend_type,
+                ..Default::default()
+            };
+            let backend = crate::backend::create_backend(&temp_config);
+            let reservation =
+                crate::session::next_session(backend.as_ref(), &base_name, agent_command).await?;
+            (reservation.session.clone(), Some(reservation))
+        } else {
+            (session.clone(), None)
+        };
+        (
+            crate::session::session_jail_name(&base_name, agent_command, &session),
+            reservation,
+        )
     } else {
-        BackendType::detect()
+        (agent_jail_name(&base_name, agent_command), None)
     };
 
+    info!("Using jail: {} for agent: {}", jail_name, agent_command);
+
+    if params.ephemeral {
+        // Removed with its volumes and images however the session ends
+        let jail = JailBuilder::new(&jail_name).backend(backend_type).build();
+        let session = run_agent_in_jail(agent_command, &jail_name, backend_type, params, cwd);
+        return crate::ephemeral::run_session(&jail, session).await;
+    }
+    run_agent_in_jail(agent_command, &jail_name, backend_type, params, cwd).await
+}
+
+/// Create, recreate or reuse the jail of an agent, then run the agent in it
+async fn run_agent_in_jail(
+    agent_command: &str,
+    jail_name: &str,
+    backend_type: BackendType,
+    mut params: AgentCommandParams,
+    cwd: PathBuf,
+) -> Result<()> {
+    // Normalize agent name for image building
+    let normalized_agent = normalize_agent_name(agent_command);
+    let jail_name = jail_name.to_string();
+
     // Check for weekly upgrade opportunity
     if !params.upgrade && params.force_layers.is_empty() {
-        if let Ok(mut state) = crate::state::State::load() {
+        if let Ok(state) = crate::state::State::load() {
             let now = chrono::Utc::now();
             let should_check = match state.last_weekly_upgrade_check {
                 Some(last_check) => {
@@ -253,8 +652,7 @@ pub async fn run_ai_agent_command(
                     params.upgrade = true;
                 }
 
-                state.last_weekly_upgrade_check = Some(now);
-                if let Err(e) = state.save() {
+                if let Err(e) = crate::state::State::record_upgrade_check(now) {
                     warn!("Failed to save state: {}", e);
                 }
             }
@@ -266,34 +664,9 @@ pub async fn run_ai_agent_command(
     // --auth only forces container recreation (for host networking) without rebuilding layers
 
     // Auto-detect if agent needs authentication (first run - credentials missing or empty)
-    // Only check if the user has mounted the config directory, otherwise credentials won't be accessible
-    if !params.auth {
-        if let Some(agent) = crate::agents::Agent::from_str(agent_command) {
-            // Only auto-enable auth for agents that support the auth workflow
-            if agent.supports_auth_workflow() {
-                // Check if the appropriate config directory flag is set
-                let config_dir_mounted = match agent {
-                    crate::agents::Agent::Codex => params.codex_dir || params.agent_configs,
-                    crate::agents::Agent::Jules => params.jules_dir || params.agent_configs,
-                    _ => false, // Other agents don't use this auto-detection
-                };
-
-                // Only check for credentials if the config directory will be mounted
-                if config_dir_mounted {
-                    let home = std::env::var("HOME").unwrap_or_else(|_| "/root".to_string());
-                    let home_path = PathBuf::from(&home);
-
-                    if agent.needs_auth(&home_path) {
-                        info!(
-                            "First run detected - {} credentials not found or empty",
-                            agent.display_name()
-                        );
-                        info!("Automatically enabling authentication mode");
-                        params.auth = true;
-                    }
-                }
-            }
-        }
+    if !params.auth && first_run_auth_needed(agent_command, &params) {
+        info!("Automatically enabling authentication mode");
+        params.auth = true;
     }
 
     let temp_config = JailConfig {
@@ -302,56 +675,24 @@ pub async fn run_ai_agent_command(
         ..Default::default()
     };
     let temp_jail = JailManager::new(temp_config);
-    let jail_exists = temp_jail.exists().await?;
+    let desired = build_agent_jail(agent_command, &jail_name, backend_type, &params, &cwd)?
+        .build_with_backend(temp_jail.backend());
+    let action = plan_jail_action(&temp_jail, &params, desired.config()).await?;
+    let jail_exists = action != JailAction::Create;
 
     // Track container recreation separately from layer rebuilding
     // --auth should force container recreation (for networking) but not layer rebuilding
-    let mut should_recreate = params.upgrade || !params.force_layers.is_empty() || params.auth;
-
-    // Check for network mode mismatch when container exists
-    if jail_exists && !should_recreate {
-        // Inspect existing container to get its network configuration
-        if let Ok(existing_config) = temp_jail.inspect().await {
-            // Determine desired network mode based on --auth or --host-network flags
-            let desired_host_network = params.auth || params.host_network;
-            let current_host_network = existing_config.network.host;
-
-            // If network mode has changed, force recreation
-            if desired_host_network != current_host_network {
-                if desired_host_network {
-                    info!(
-                        "Network mode mismatch detected: container has private networking but --auth requires host networking"
-                    );
-                } else {
-                    info!(
-                        "Network mode mismatch detected: container has host networking but normal mode requires private networking"
-                    );
-                    info!("Container will be recreated to restore secure network isolation");
-                }
-                should_recreate = true;
-                // Note: We don't set params.upgrade = true here because we only need to
-                // recreate the container, not rebuild the image layers
-            }
-
-            // Check for block_host mismatch
-            let desired_block_host = !params.no_block_host;
-            let current_block_host = existing_config.block_host;
-
-            if desired_block_host != current_block_host {
-                if desired_block_host {
-                    info!(
-                        "eBPF blocking mode mismatch detected: container has eBPF blocking disabled but current settings require it enabled"
-                    );
-                } else {
-                    info!(
-                        "eBPF blocking mode mismatch detected: container has eBPF blocking enabled but --no-block-host flag requires it disabled"
-                    );
-                }
-                info!("Container will be recreated with the correct eBPF blocking configuration");
-                should_recreate = true;
+    let mut should_recreate = match &action {
+        JailAction::Recreate => true,
+        JailAction::Drifted(changes) => {
+            let recreate = confirm_drift_recreate(&jail_name, changes, params.auto_recreate)?;
+            if !recreate {
+                info!("{}", strings::USER_DECLINED_DRIFT_RECREATE);
             }
+            recreate
         }
-    }
+        JailAction::Create | JailAction::Reuse => false,
+    };
 
     if !jail_exists {
         info!(
@@ -363,6 +704,7 @@ pub async fn run_ai_agent_command(
         // (e.g., after upgrading jail-ai binary, old cached layers may be outdated)
         let workspace_dir = get_git_root().unwrap_or_else(|| cwd.clone());
         let outdated_layers = match crate::image_layers::check_layers_need_rebuild(
+            backend_type,
             &workspace_dir,
             Some(normalized_agent),
             params.no_nix,
@@ -398,6 +740,7 @@ pub async fn run_ai_agent_command(
 
         // Check if any layers need rebuilding (e.g., after jail-ai binary upgrade)
         let outdated_layers = match crate::image_layers::check_layers_need_rebuild(
+            backend_type,
             &workspace_dir,
             Some(normalized_agent),
             params.no_nix,
@@ -413,7 +756,7 @@ pub async fn run_ai_agent_command(
 
         // Check if container image is outdated
         let container_outdated = match check_container_upgrade_needed(
-            &jail_name,
+            &temp_jail,
             &workspace_dir,
             normalized_agent,
             params.isolated,
@@ -508,194 +851,13 @@ pub async fn run_ai_agent_command(
             temp_jail.remove(false).await?; // false = don't remove volume (preserve data)
         }
 
-        // Only use custom image if explicitly provided (not default)
-        // This allows the layered image system to auto-detect and build agent-specific images
-        let use_custom_image = params.image != crate::cli::DEFAULT_IMAGE;
-
-        let mut builder = JailBuilder::new(&jail_name).backend(backend_type);
-
-        // Configure network mode based on flags
-        if params.auth || params.host_network {
-            // OAuth authentication or explicit --host-network requires host networking
-            builder = builder.host_network(true);
-            if params.auth {
-                info!("Using host networking for OAuth authentication");
-            } else {
-                info!("Using host networking (--host-network)");
-            }
-        } else {
-            // Normal operation: use private or shared networking
-            builder = builder.network(!params.no_network, true);
-        }
-
-        // Set image: use custom if provided, otherwise let layered system auto-detect
-        if use_custom_image {
-            builder = builder.base_image(params.image);
-        } else {
-            // Use default image name, which triggers layered image auto-detection
-            builder = builder.base_image(crate::image::DEFAULT_IMAGE_NAME);
-        }
-
-        // Setup default environment variables
-        builder = setup_default_environment(builder);
-
-        // Auto-mount workspace (git root if available, otherwise current directory)
-        // Special handling for git worktrees
-        if !params.no_workspace {
-            let workspace_dir = get_git_root().unwrap_or_else(|| cwd.clone());
-
-            // Validate workspace directory is safe
-            validate_workspace_directory(&workspace_dir)?;
-
-            // Check if this is a git worktree
-            if let Some(worktree_info) = crate::worktree::detect_worktree(&workspace_dir)? {
-                info!("Detected git worktree, setting up dual-mount configuration");
-
-                // Collect paths that need parent directory creation
-                let paths_to_mount = vec![
-                    worktree_info.worktree_path.as_path(),
-                    worktree_info.main_git_dir.as_path(),
-                ];
-                let parent_dirs = crate::worktree::get_required_parent_dirs(&paths_to_mount);
-
-                info!(
-                    "Will create {} parent directories in container",
-                    parent_dirs.len()
-                );
-                builder = builder.pre_create_dirs(parent_dirs);
-
-                // Mount 1: Worktree at /workspace (familiar location)
-                info!(
-                    "Mounting worktree {} to /workspace",
-                    worktree_info.worktree_path.display()
-                );
-                builder = builder.bind_mount(&worktree_info.worktree_path, "/workspace", false);
-
-                // Mount 2: Worktree at original absolute path (preserve .git file reference)
-                info!(
-                    "Mounting worktree {} to {} (preserve absolute path)",
-                    worktree_info.worktree_path.display(),
-                    worktree_info.worktree_path.display()
-                );
-                builder = builder.bind_mount(
-                    &worktree_info.worktree_path,
-                    &worktree_info.worktree_path,
-                    false,
-                );
-
-                // Mount 3: Main .git at original absolute path (read-write for git operations)
-                info!(
-                    "Mounting main git directory {} to {} (read-write)",
-                    worktree_info.main_git_dir.display(),
-                    worktree_info.main_git_dir.display()
-                );
-                builder = builder.bind_mount(
-                    &worktree_info.main_git_dir,
-                    &worktree_info.main_git_dir,
-                    false,
-                );
-            } else {
-                // Regular directory, not a worktree
-                info!(
-                    "Auto-mounting {} to {}",
-                    workspace_dir.display(),
-                    params.workspace_path
-                );
-                builder = builder.bind_mount(workspace_dir, params.workspace_path, false);
-            }
-        }
+        let mut jail = build_agent_jail(agent_command, &jail_name, backend_type, &params, &cwd)?
+            .build_with_backend(temp_jail.backend());
+        jail.create().await?;
 
-        // Auto-mount agent config directories
         let home = std::env::var("HOME").unwrap_or_else(|_| "/root".to_string());
         let home_path = PathBuf::from(&home);
 
-        builder = mount_agent_configs(
-            builder,
-            &home_path,
-            agent_command,
-            &jail_setup::AgentConfigFlags {
-                claude_dir: params.claude_dir,
-                claude_code_router_dir: params.claude_code_router_dir,
-                copilot_dir: params.copilot_dir,
-                cursor_dir: params.cursor_dir,
-                gemini_dir: params.gemini_dir,
-                coderabbit_dir: params.coderabbit_dir,
-                codex_dir: params.codex_dir,
-                jules_dir: params.jules_dir,
-                pi_dir: params.pi_dir,
-                agent_configs: params.agent_configs,
-            },
-        );
-
-        // Opt-in: GPG configuration
-        if params.git_gpg {
-            builder = setup_git_gpg_config(builder, &cwd, &home_path)?;
-        }
-
-        // Parse mounts
-        for mount_str in params.mount {
-            let mount = Commands::parse_mount(&mount_str).map_err(error::JailError::Config)?;
-            builder = builder.bind_mount(mount.source, mount.target, mount.readonly);
-        }
-
-        // Parse port mappings
-        for port_str in params.port {
-            let port_mapping = Commands::parse_port(&port_str).map_err(error::JailError::Config)?;
-            builder = builder.port_mapping(
-                port_mapping.host_port,
-                port_mapping.container_port,
-                &port_mapping.protocol,
-            );
-        }
-
-        // Parse environment variables
-        for env_str in params.env {
-            let (key, value) = Commands::parse_env(&env_str).map_err(error::JailError::Config)?;
-            builder = builder.env(key, value);
-        }
-
-        // Set resource limits
-        if let Some(mem) = params.memory {
-            builder = builder.memory_limit(mem);
-        }
-        if let Some(cpu_quota) = params.cpu {
-            builder = builder.cpu_quota(cpu_quota);
-        }
-
-        // Set upgrade flag - this controls whether layers are rebuilt
-        // Container removal is handled manually above when should_recreate is true
-        builder = builder.upgrade(params.upgrade);
-
-        // Set force layers (include cloud layers if --cloud is specified)
-        let mut force_layers = params.force_layers;
-        if params.cloud {
-            if !force_layers.contains(&"aws".to_string()) {
-                force_layers.push("aws".to_string());
-            }
-            if !force_layers.contains(&"gcp".to_string()) {
-                force_layers.push("gcp".to_string());
-            }
-        }
-        builder = builder.force_layers(force_layers);
-
-        // Set isolated flag
-        builder = builder.isolated(params.isolated);
-
-        // Set verbose flag
-        builder = builder.verbose(params.verbose);
-
-        // Set no_nix flag
-        builder = builder.no_nix(params.no_nix);
-
-        // Set block_host flag (inverted: !no_block_host means blocking is enabled)
-        builder = builder.block_host(!params.no_block_host);
-
-        // Set podman_socket flag for Podman-in-Podman support
-        builder = builder.podman_socket(params.podman);
-
-        let jail = builder.build();
-        jail.create().await?;
-
         // Create .claude.json file inside the container for Claude agent
         if let Some(agent) = crate::agents::Agent::from_str(agent_command) {
             if agent == crate::agents::Agent::Claude {
@@ -717,23 +879,12 @@ pub async fn run_ai_agent_command(
     let jail = JailBuilder::new(&jail_name)
         .backend(backend_type)
         .verbose(params.verbose)
-        .build();
+        .build_with_backend(temp_jail.backend());
 
     // If --tui flag is set, launch the ratatui TUI with agent + shell tabs
     if params.tui {
         info!("Launching TUI for jail '{}'", jail_name);
-        let uses_nix_wrapper_for_tui = jail
-            .exec(
-                &[
-                    "sh".to_string(),
-                    "-c".to_string(),
-                    "[ -x /usr/local/bin/nix-wrapper ] && echo yes || echo no".to_string(),
-                ],
-                false,
-            )
-            .await
-            .map(|o| o.trim() == "yes")
-            .unwrap_or(false);
+        let uses_nix_wrapper_for_tui = uses_nix_wrapper(&jail).await;
 
         let agent_cmd = if uses_nix_wrapper_for_tui {
             let mut cmd = vec![
@@ -748,8 +899,13 @@ pub async fn run_ai_agent_command(
             cmd
         };
 
+        let pane_options = crate::tui::Tui::pane_exec_options();
+        let agent_label = agent_cmd[0].clone();
+        let agent_argv = jail.exec_argv(&agent_cmd, &pane_options)?;
+        let shell_argv = jail.exec_argv(&["/usr/bin/zsh".to_string()], &pane_options)?;
+
         return tokio::task::spawn_blocking(move || {
-            crate::tui::Tui::new(&jail_name, agent_cmd).run()
+            crate::tui::Tui::new(agent_label, agent_argv, shell_argv).run()
         })
         .await
         .map_err(|e| crate::error::JailError::Backend(format!("TUI task panicked: {e}")))?;
@@ -772,22 +928,8 @@ pub async fn run_ai_agent_command(
 
     info!("about to run");
 
-    // Check if the jail uses the Nix wrapper by testing if it exists in the agent's home directory
-    // If it does, wrap the command with nix-wrapper to ensure flake environment is loaded
-    // Use a simple file check that's fast and won't hang
-    let wrapper_check = jail
-        .exec(
-            &[
-                "sh".to_string(),
-                "-c".to_string(),
-                "[ -x /usr/local/bin/nix-wrapper ] && echo yes || echo no".to_string(),
-            ],
-            false,
-        )
-        .await;
-    let uses_nix_wrapper = wrapper_check
-        .map(|output| output.trim() == "yes")
-        .unwrap_or(false);
+    // If the jail has the Nix wrapper, wrap the command with it to ensure the flake environment is loaded
+    let uses_nix_wrapper = uses_nix_wrapper(&jail).await;
 
     // Check if this agent requires a server to be started first
     if let Some(agent) = crate::agents::Agent::from_str(agent_command) {
@@ -813,11 +955,17 @@ pub async fn run_ai_agent_command(
                     vec![agent_command.to_string(), server_cmd.to_string()]
                 };
 
-                // Execute server start in background (non-interactive)
-                let server_output = jail.exec(&server_command, false).await?;
-                if !server_output.is_empty() {
-                    info!("Server start output: {}", server_output);
+                // Execute server start in background (non-interactive), logging output as it arrives
+                let mut server = jail
+                    .exec_stream(&server_command, &ExecOptions::default())
+                    .await?;
+                while let Some(line) = server.next_line().await {
+                    match line {
+                        OutputLine::Stdout(line) => info!("Server start: {}", line),
+                        OutputLine::Stderr(line) => warn!("Server start: {}", line),
+                    }
                 }
+                server.wait().await?.into_output()?;
 
                 // Wait a bit for server to start
                 tokio::time::sleep(tokio::time::Duration::from_secs(2)).await;
@@ -873,26 +1021,26 @@ pub async fn run_ai_agent_command(
     Ok(())
 }
 
-/// Find all jails matching the current directory pattern
+/// Find all jails matching the current directory pattern, on every available backend
 pub async fn find_jails_for_directory(workspace_dir: &Path) -> Result<Vec<String>> {
     let base_name = Commands::generate_jail_name(workspace_dir);
-    let backend_type = BackendType::detect();
-
-    // Create a temporary config just to access the backend
-    let temp_config = JailConfig {
-        name: "temp".to_string(),
-        backend: backend_type,
-        ..Default::default()
-    };
-    let backend = crate::backend::create_backend(&temp_config);
 
     // List all jails
-    let all_jails = backend.list_all().await?;
+    let mut all_jails = Vec::new();
+    for (backend_type, backend) in crate::backend::available_backends() {
+        match backend.list_all().await {
+            Ok(jails) => all_jails.extend(jails),
+            Err(e) => debug!("Could not list {:?} jails: {}", backend_type, e),
+        }
+    }
+    all_jails.sort();
+    all_jails.dedup();
 
     // Filter jails that match the base pattern (jail-{project}-{hash}-)
     let matching_jails: Vec<String> = all_jails
         .into_iter()
         .filter(|name| name.starts_with(&base_name) && name.len() > base_name.len())
+        .filter(|name| !crate::ephemeral::is_ephemeral(name))
         .collect();
 
     Ok(matching_jails)
@@ -1031,7 +1179,16 @@ pub fn select_jail(jails: &[String]) -> Result<String> {
     for (i, jail) in jails.iter().enumerate() {
         // Extract agent name from jail name
         let agent_name = extract_agent_name(jail);
-        println!("  {}. {} (agent: {})", i + 1, jail, agent_name);
+        match crate::session::session_of(jail) {
+            Some(session) => println!(
+                "  {}. {} (agent: {}, session: {})",
+                i + 1,
+                jail,
+                agent_name,
+                session
+            ),
+            None => println!("  {}. {} (agent: {})", i + 1, jail, agent_name),
+        }
     }
 
     print!("Select a jail (1-{}): ", jails.len());
@@ -1057,3 +1214,167 @@ pub fn select_jail(jails: &[String]) -> Result<String> {
 
     Ok(jails[selection - 1].clone())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::fake::FakeBackend;
+    use std::sync::Arc;
+
+    const JAIL_NAME: &str = "jail__project__abc12345__claude";
+
+    fn default_params() -> AgentCommandParams {
+        AgentCommandParams {
+            backend: None,
+            image: crate::cli::DEFAULT_IMAGE.to_string(),
+            mount: vec![],
+            port: vec![],
+            env: vec![],
+            no_network: false,
+            host_network: false,
+            memory: None,
+            cpu: None,
+            limits: Default::default(),
+            no_workspace: false,
+            workspace_path: crate::cli::DEFAULT_WORKSPACE_PATH.to_string(),
+            claude_dir: false,
+            claude_code_router_dir: false,
+            copilot_dir: false,
+            cursor_dir: false,
+            gemini_dir: false,
+            coderabbit_dir: false,
+            codex_dir: false,
+            jules_dir: false,
+            pi_dir: false,
+            agent_configs: false,
+            git_gpg: false,
+            upgrade: false,
+            force_layers: vec![],
+            cloud: false,
+            shell: false,
+            isolated: false,
+            verbose: false,
+            auth: false,
+            no_nix: false,
+            no_block_host: false,
+            podman: false,
+            hardened: false,
+            seccomp: false,
+            cow: false,
+            ephemeral: false,
+            session: None,
+            shared_home: false,
+            auto_recreate: false,
+            tui: false,
+            profile: None,
+            args: vec![],
+        }
+    }
+
+    /// Network and eBPF settings of a jail created by a previous run with `params`
+    fn existing_jail(params: &AgentCommandParams) -> JailConfig {
+        JailBuilder::new(JAIL_NAME)
+            .network(!params.no_network, true)
+            .host_network(params.auth || params.host_network)
+            .block_host(!params.no_block_host)
+            .build()
+            .config()
+            .clone()
+    }
+
+    async fn plan(fake: FakeBackend, params: &AgentCommandParams) -> JailAction {
+        let jail = JailBuilder::new(JAIL_NAME).build_with_backend(Arc::new(fake));
+        plan_jail_action(&jail, params, &existing_jail(params))
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_plan_creates_missing_jail() {
+        assert_eq!(
+            plan(FakeBackend::new(), &default_params()).await,
+            JailAction::Create
+        );
+    }
+
+    #[tokio::test]
+    async fn test_plan_reuses_matching_jail() {
+        let params = default_params();
+        let fake = FakeBackend::new().with_jail(existing_jail(&params), true);
+        assert_eq!(plan(fake, &params).await, JailAction::Reuse);
+
+        let params = AgentCommandParams {
+            host_network: true,
+            no_block_host: true,
+            ..default_params()
+        };
+        let fake = FakeBackend::new().with_jail(existing_jail(&params), false);
+        assert_eq!(plan(fake, &params).await, JailAction::Reuse);
+    }
+
+    #[tokio::test]
+    async fn test_plan_forced_recreation() {
+        let existing = existing_jail(&default_params());
+
+        for params in [
+            AgentCommandParams {
+                upgrade: true,
+                ..default_params()
+            },
+            AgentCommandParams {
+                force_layers: vec!["rust".to_string()],
+                ..default_params()
+            },
+        ] {
+            let fake = FakeBackend::new().with_jail(existing.clone(), true);
+            assert_eq!(plan(fake, &params).await, JailAction::Recreate);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_plan_auth_mode_transitions() {
+        // Entering auth mode switches a private jail to host networking
+        let private_jail = existing_jail(&default_params());
+        let auth = AgentCommandParams {
+            auth: true,
+            ..default_params()
+        };
+        let fake = FakeBackend::new().with_jail(private_jail, true);
+        assert_eq!(plan(fake, &auth).await, JailAction::Recreate);
+
+        // Leaving auth mode restores private networking
+        let auth_jail = existing_jail(&auth);
+        assert!(auth_jail.network.host);
+        let fake = FakeBackend::new().with_jail(auth_jail, true);
+        assert_eq!(plan(fake, &default_params()).await, JailAction::Recreate);
+    }
+
+    #[tokio::test]
+    async fn test_plan_block_host_mismatch() {
+        let existing = existing_jail(&default_params());
+        let params = AgentCommandParams {
+            no_block_host: true,
+            ..default_params()
+        };
+        let fake = FakeBackend::new().with_jail(existing, true);
+        assert_eq!(plan(fake, &params).await, JailAction::Recreate);
+    }
+
+    #[tokio::test]
+    async fn test_plan_reports_other_drift() {
+        let params = default_params();
+        let mut desired = existing_jail(&params);
+        desired.limits.memory_mb = Some(4096);
+        desired.podman_socket = true;
+
+        let fake = FakeBackend::new().with_jail(existing_jail(&params), true);
+        let jail = JailBuilder::new(JAIL_NAME).build_with_backend(Arc::new(fake));
+        let JailAction::Drifted(changes) =
+            plan_jail_action(&jail, &params, &desired).await.unwrap()
+        else {
+            panic!("expected drift");
+        };
+        let settings: Vec<&str> = changes.iter().map(|change| change.setting).collect();
+        assert_eq!(settings, vec!["memory", "podman-socket"]);
+    }
+}
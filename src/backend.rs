@@ -1,9 +1,253 @@
-use crate::config::JailConfig;
+use crate::config::{BackendType, BindMount, JailConfig, ResourceLimits};
+use crate::cow::CowDirs;
 use crate::error::{JailError, Result};
+use crate::image;
 use async_trait::async_trait;
-use std::process::Stdio;
-use tokio::process::Command;
-use tracing::debug;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::{ExitStatus, Stdio};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+use tokio::process::{Child, Command};
+use tokio::sync::mpsc;
+use tracing::{debug, info, warn};
+
+/// Options for running a command in a jail
+#[derive(Debug, Clone, Default)]
+pub struct ExecOptions {
+    /// User to run as (name or UID[:GID]), defaults to the jail user
+    pub user: Option<String>,
+    /// Working directory inside the jail
+    pub workdir: Option<PathBuf>,
+    /// Extra environment variables
+    pub env: Vec<(String, String)>,
+    /// Allocate a pseudo-terminal
+    pub tty: bool,
+    /// Keep stdin attached
+    pub stdin: bool,
+}
+
+impl ExecOptions {
+    /// Options of a plain `exec`: terminal and stdin attached when interactive
+    pub fn interactive(interactive: bool) -> Self {
+        Self {
+            tty: interactive,
+            stdin: interactive,
+            ..Default::default()
+        }
+    }
+
+    /// Whether the command is attached to the terminal instead of capturing its output
+    pub fn is_attached(&self) -> bool {
+        self.tty || self.stdin
+    }
+}
+
+/// Outcome of a command run inside a jail
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ExecResult {
+    /// Exit code of the command (128 + signal number when killed by a signal)
+    pub exit_code: i32,
+    /// Captured standard output, empty when attached to the terminal
+    pub stdout: String,
+    /// Captured standard error, empty when attached to the terminal
+    pub stderr: String,
+    pub duration: Duration,
+}
+
+impl ExecResult {
+    fn new(status: ExitStatus, stdout: String, stderr: String, started: Instant) -> Self {
+        Self {
+            exit_code: exit_code(status),
+            stdout,
+            stderr,
+            duration: started.elapsed(),
+        }
+    }
+
+    pub fn success(&self) -> bool {
+        self.exit_code == 0
+    }
+
+    /// Standard output of a successful command, [`JailError::CommandExit`] otherwise
+    pub fn into_output(self) -> Result<String> {
+        if self.success() {
+            Ok(self.stdout)
+        } else {
+            Err(JailError::CommandExit {
+                code: self.exit_code,
+                stderr: self.stderr,
+            })
+        }
+    }
+}
+
+/// Exit code of a process, using the shell convention for signals
+fn exit_code(status: ExitStatus) -> i32 {
+    use std::os::unix::process::ExitStatusExt;
+    status
+        .code()
+        .or_else(|| status.signal().map(|signal| 128 + signal))
+        .unwrap_or(1)
+}
+
+/// Line of output from an [`ExecStream`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OutputLine {
+    Stdout(String),
+    Stderr(String),
+}
+
+/// Command running inside a jail, yielding its output lines as they arrive
+pub struct ExecStream {
+    child: Child,
+    lines: mpsc::UnboundedReceiver<OutputLine>,
+    stdout: String,
+    stderr: String,
+    started: Instant,
+    /// Resources living as long as the command (e.g. a network helper)
+    _session: Option<Box<dyn Send>>,
+}
+
+impl ExecStream {
+    /// Spawn a command with its output piped into the stream
+    pub fn spawn(mut cmd: Command) -> Result<Self> {
+        debug!("Streaming command: {:?}", cmd);
+        cmd.stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        let child = cmd
+            .spawn()
+            .map_err(|e| JailError::Backend(format!("Failed to execute command: {e}")))?;
+        Ok(Self::from_child(child))
+    }
+
+    /// Stream the output of an already spawned command with piped stdout and stderr
+    fn from_child(mut child: Child) -> Self {
+        let (sender, lines) = mpsc::unbounded_channel();
+        if let Some(stdout) = child.stdout.take() {
+            forward_lines(stdout, sender.clone(), OutputLine::Stdout);
+        }
+        if let Some(stderr) = child.stderr.take() {
+            forward_lines(stderr, sender, OutputLine::Stderr);
+        }
+
+        Self {
+            child,
+            lines,
+            stdout: String::new(),
+            stderr: String::new(),
+            started: Instant::now(),
+            _session: None,
+        }
+    }
+
+    /// Keep `session` alive until the command has finished
+    fn with_session(mut self, session: Box<dyn Send>) -> Self {
+        self._session = Some(session);
+        self
+    }
+
+    /// Next line of output, `None` once both streams are closed
+    pub async fn next_line(&mut self) -> Option<OutputLine> {
+        let line = self.lines.recv().await?;
+        let (buffer, text) = match &line {
+            OutputLine::Stdout(text) => (&mut self.stdout, text),
+            OutputLine::Stderr(text) => (&mut self.stderr, text),
+        };
+        buffer.push_str(text);
+        buffer.push('\n');
+        Some(line)
+    }
+
+    /// Wait for the command to finish, collecting the output not read yet
+    pub async fn wait(mut self) -> Result<ExecResult> {
+        while self.next_line().await.is_some() {}
+        let status = self
+            .child
+            .wait()
+            .await
+            .map_err(|e| JailError::Backend(format!("Failed to wait for command: {e}")))?;
+        Ok(ExecResult::new(
+            status,
+            std::mem::take(&mut self.stdout),
+            std::mem::take(&mut self.stderr),
+            self.started,
+        ))
+    }
+}
+
+fn forward_lines(
+    reader: impl AsyncRead + Unpin + Send + 'static,
+    sender: mpsc::UnboundedSender<OutputLine>,
+    wrap: fn(String) -> OutputLine,
+) {
+    tokio::spawn(async move {
+        let mut lines = BufReader::new(reader).lines();
+        while let Ok(Some(line)) = lines.next_line().await {
+            if sender.send(wrap(line)).is_err() {
+                break;
+            }
+        }
+    });
+}
+
+/// Options for reading jail logs
+#[derive(Debug, Clone, Default)]
+pub struct LogOptions {
+    /// Keep streaming new output
+    pub follow: bool,
+    /// Only show the last N lines
+    pub tail: Option<usize>,
+}
+
+/// Resource usage of a running jail, as reported by the engine
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct JailStats {
+    pub cpu: String,
+    pub memory: String,
+    pub net_io: String,
+    pub block_io: String,
+    pub pids: String,
+}
+
+/// Overview of a jail, as shown by `jail-ai list`
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct JailSummary {
+    pub name: String,
+    /// Agent display name (`Claude`, `Copilot`...)
+    pub agent: String,
+    /// Host directory mounted as the workspace
+    pub workspace: Option<PathBuf>,
+    pub running: bool,
+    pub image: String,
+    /// The image tag now points to a newer build than the one the jail runs
+    pub outdated: bool,
+    /// An eBPF loader is currently blocking host access for the jail
+    pub host_blocking: bool,
+    /// Seconds since the jail was started, when running
+    pub uptime_secs: Option<u64>,
+    /// Size of the home volume in bytes, when readable
+    pub home_size: Option<u64>,
+}
+
+/// Volume created for jails (`<name>__home` or `<base>__nix`)
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VolumeSummary {
+    pub name: String,
+    /// Size of the volume in bytes, when readable
+    pub size: Option<u64>,
+}
+
+/// Image built by jail-ai (`localhost/jail-ai-*`)
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ImageSummary {
+    pub name: String,
+    /// Size of the image in bytes, including layers shared with other images
+    pub size: Option<u64>,
+}
 
 #[async_trait]
 pub trait JailBackend: Send + Sync {
@@ -13,8 +257,41 @@ pub trait JailBackend: Send + Sync {
     /// Remove the jail
     async fn remove(&self, name: &str, remove_volume: bool) -> Result<()>;
 
-    /// Execute a command inside the jail
-    async fn exec(&self, name: &str, command: &[String], interactive: bool) -> Result<String>;
+    /// Execute a command inside the jail, failing when it exits with a non-zero status
+    async fn exec(&self, name: &str, command: &[String], interactive: bool) -> Result<String> {
+        self.exec_with(name, command, &ExecOptions::interactive(interactive))
+            .await?
+            .into_output()
+    }
+
+    /// Execute a command inside the jail with explicit options
+    ///
+    /// Output is captured unless a terminal or stdin is attached. A non-zero exit
+    /// is reported in the result; errors mean the command could not be run.
+    async fn exec_with(
+        &self,
+        name: &str,
+        command: &[String],
+        options: &ExecOptions,
+    ) -> Result<ExecResult>;
+
+    /// Execute a command inside the jail, streaming its output line by line
+    ///
+    /// `options.tty` and `options.stdin` are ignored: stdin is closed and output piped.
+    async fn exec_stream(
+        &self,
+        name: &str,
+        command: &[String],
+        options: &ExecOptions,
+    ) -> Result<ExecStream>;
+
+    /// Command line running a command inside the jail (for terminals such as the TUI panes)
+    fn exec_argv(
+        &self,
+        name: &str,
+        command: &[String],
+        options: &ExecOptions,
+    ) -> Result<Vec<String>>;
 
     /// Check if jail exists
     async fn exists(&self, name: &str) -> Result<bool>;
@@ -22,6 +299,9 @@ pub trait JailBackend: Send + Sync {
     /// List all jail-ai containers (names starting with "jail-")
     async fn list_all(&self) -> Result<Vec<String>>;
 
+    /// Summaries of all jails, gathered with as few engine queries as possible
+    async fn list_summaries(&self) -> Result<Vec<JailSummary>>;
+
     /// Inspect jail and return its configuration
     async fn inspect(&self, name: &str) -> Result<JailConfig>;
 
@@ -30,13 +310,1333 @@ pub trait JailBackend: Send + Sync {
 
     /// Start a stopped container
     async fn start(&self, name: &str) -> Result<()>;
+
+    /// Stop a running container, keeping it for later use
+    async fn stop(&self, name: &str) -> Result<()>;
+
+    /// Restart the container
+    async fn restart(&self, name: &str) -> Result<()>;
+
+    /// Freeze all processes of the container
+    async fn pause(&self, name: &str) -> Result<()>;
+
+    /// Resume a paused container
+    async fn unpause(&self, name: &str) -> Result<()>;
+
+    /// Print the container output to stdout
+    async fn logs(&self, name: &str, options: &LogOptions) -> Result<()>;
+
+    /// Current resource usage
+    async fn stats(&self, name: &str) -> Result<JailStats>;
+
+    /// Host ports published by a running jail, as actually bound
+    async fn port_mappings(&self, name: &str) -> Result<Vec<crate::config::PortMapping>>;
+
+    /// Number of commands currently executed or attached in the jail
+    async fn active_sessions(&self, name: &str) -> Result<usize>;
+
+    /// Volumes created for jails, whether or not their jail still exists
+    async fn list_volumes(&self) -> Result<Vec<VolumeSummary>>;
+
+    /// Remove a jail volume
+    async fn remove_volume(&self, volume: &str) -> Result<()>;
+
+    /// Write the content of a volume to a tar archive on the host
+    async fn export_volume(&self, volume: &str, archive: &Path) -> Result<()>;
+
+    /// Extract a tar archive into a volume, creating the volume when missing
+    async fn import_volume(&self, volume: &str, archive: &Path) -> Result<()>;
+
+    /// Delete the content of a volume except the given paths (relative to its root)
+    async fn reset_volume(&self, volume: &str, keep: &[String]) -> Result<()>;
+
+    /// Save the filesystem of a jail as an image (volumes and bind mounts are not included)
+    async fn commit(&self, name: &str, image: &str, labels: &[(String, String)]) -> Result<()>;
+
+    /// Images built by jail-ai in the image store used by this backend
+    async fn list_images(&self) -> Result<Vec<ImageSummary>>;
+
+    /// Remove an image built by jail-ai
+    async fn remove_image(&self, image: &str) -> Result<()>;
+
+    /// Copy a host file or directory into the jail
+    async fn copy_in(&self, name: &str, source: &Path, target: &Path) -> Result<()>;
+
+    /// Copy a file or directory out of the jail to the host
+    async fn copy_out(&self, name: &str, source: &Path, target: &Path) -> Result<()>;
 }
 
+pub mod bubblewrap;
+pub mod docker;
+#[cfg(test)]
+pub mod fake;
+mod host_blocking;
 pub mod podman;
+pub mod podman_api;
 
 /// Create a backend based on the configuration
-pub fn create_backend(_config: &JailConfig) -> Box<dyn JailBackend> {
-    Box::new(podman::PodmanBackend::new())
+pub fn create_backend(config: &JailConfig) -> Box<dyn JailBackend> {
+    match config.backend {
+        BackendType::Podman => Box::new(podman::PodmanBackend::new()),
+        BackendType::Docker => Box::new(docker::DockerBackend::new()),
+        BackendType::Bubblewrap => Box::new(bubblewrap::BubblewrapBackend::new()),
+    }
+}
+
+/// Backends of the engines available on this system, with the type of each
+pub fn available_backends() -> Vec<(BackendType, Arc<dyn JailBackend>)> {
+    BackendType::all_available()
+        .into_iter()
+        .map(|backend_type| {
+            let config = JailConfig {
+                backend: backend_type,
+                ..Default::default()
+            };
+            (backend_type, Arc::from(create_backend(&config)))
+        })
+        .collect()
+}
+
+/// Backend owning a jail: the first of `backends` where it exists
+///
+/// A backend whose engine cannot be reached is skipped.
+pub async fn find_jail_backend(
+    name: &str,
+    backends: &[(BackendType, Arc<dyn JailBackend>)],
+) -> Option<(BackendType, Arc<dyn JailBackend>)> {
+    for (backend_type, backend) in backends {
+        match backend.exists(name).await {
+            Ok(true) => return Some((*backend_type, Arc::clone(backend))),
+            Ok(false) => {}
+            Err(e) => debug!(
+                "Could not look for jail {} with {:?}: {}",
+                name, backend_type, e
+            ),
+        }
+    }
+    None
+}
+
+/// Layered images a jail resolves to with the current workspace, none for custom images
+pub fn layer_images(config: &JailConfig) -> Vec<String> {
+    if config.base_image != image::DEFAULT_IMAGE_NAME || !config.use_layered_images {
+        return Vec::new();
+    }
+    match workspace_source(config) {
+        Some(workspace) => {
+            crate::image_layers::layer_images(config, &workspace, agent_from_name(&config.name))
+        }
+        None => Vec::new(),
+    }
+}
+
+/// Determine the image a jail runs, building layered images or pulling custom ones
+async fn resolve_image(config: &JailConfig) -> Result<String> {
+    let actual_image = if config.base_image == image::DEFAULT_IMAGE_NAME
+        && config.use_layered_images
+    {
+        // Use layered image system with auto-detection
+        info!("Using layered image system with auto-detection");
+
+        let workspace_path = workspace_source(config).unwrap_or_else(|| {
+            std::env::current_dir().unwrap_or_else(|_| std::path::PathBuf::from("."))
+        });
+        let agent_name = agent_from_name(&config.name);
+
+        debug!(
+            "Workspace path: {:?}, Agent: {:?}",
+            workspace_path, agent_name
+        );
+
+        // Build the appropriate layered image
+        // Images are built, inspected and pulled with the engine that runs the jail
+        crate::image_layers::ensure_layered_image_available(config, &workspace_path, agent_name)
+            .await?
+    } else if config.base_image == image::DEFAULT_IMAGE_NAME {
+        // Default image should use layered system
+        // If use_layered_images is false, it's likely a configuration error
+        return Err(JailError::Backend(
+                "Default image requires layered images to be enabled. Please set use_layered_images to true.".to_string()
+            ));
+    } else {
+        // For custom images, check if they exist and pull if needed
+        if !crate::image_layers::image_exists(config.backend, &config.base_image).await? {
+            debug!("Image {} not found locally, pulling...", config.base_image);
+            let mut pull_cmd = Command::new(config.backend.image_engine().command());
+            pull_cmd.arg("pull").arg(&config.base_image);
+
+            run_command(&mut pull_cmd)
+                .await
+                .map_err(|e| JailError::Backend(format!("Failed to pull image: {e}")))?;
+        } else {
+            debug!("Using local image: {}", config.base_image);
+        }
+        config.base_image.clone()
+    };
+
+    Ok(actual_image)
+}
+
+/// Pre-create directories inside a freshly started jail (for worktree support)
+async fn pre_create_dirs(config: &JailConfig) -> Result<()> {
+    if config.hardened && !config.pre_create_dirs.is_empty() {
+        // The root filesystem is read-only: the engine creates the mount points itself
+        debug!("Skipping directory pre-creation in hardened jail");
+        return Ok(());
+    }
+
+    if !config.pre_create_dirs.is_empty() {
+        info!(
+            "Pre-creating {} directories in container for worktree support",
+            config.pre_create_dirs.len()
+        );
+
+        for dir in &config.pre_create_dirs {
+            debug!("Creating directory: {}", dir.display());
+
+            let mut mkdir_cmd = Command::new(config.backend.command());
+            mkdir_cmd
+                .arg("exec")
+                .arg(&config.name)
+                .arg("mkdir")
+                .arg("-p")
+                .arg(dir.to_str().ok_or_else(|| {
+                    JailError::Backend(format!("Invalid directory path: {}", dir.display()))
+                })?);
+
+            run_command(&mut mkdir_cmd).await.map_err(|e| {
+                JailError::Backend(format!(
+                    "Failed to create directory {} in container: {}",
+                    dir.display(),
+                    e
+                ))
+            })?;
+        }
+
+        info!("Pre-created directories successfully");
+    }
+
+    Ok(())
+}
+
+/// Home volume of an existing jail, read before it is removed
+///
+/// Falls back to the jail's own volume when the configuration cannot be read.
+async fn jail_home_volume(backend: &dyn JailBackend, name: &str) -> String {
+    match backend.inspect(name).await {
+        Ok(config) => home_volume_name(&config),
+        Err(e) => {
+            debug!("Could not read the configuration of {}: {}", name, e);
+            format!("{name}__home")
+        }
+    }
+}
+
+/// Label holding the serialized jail configuration, secrets redacted
+const CONFIG_LABEL: &str = "jail-ai.config";
+
+/// Home volume of a jail: its own, or the one of its main jail for sessions sharing it
+pub fn home_volume_name(config: &JailConfig) -> String {
+    if config.shared_home {
+        format!("{}__home", crate::session::main_jail_name(&config.name))
+    } else {
+        format!("{}__home", config.name)
+    }
+}
+
+/// Bind mount of the workspace
+///
+/// Configurations recorded before the workspace mount was marked fall back to the
+/// first mount whose target contains "workspace".
+pub fn workspace_mount(config: &JailConfig) -> Option<&BindMount> {
+    let mut mounts = config.bind_mounts.iter();
+    mounts.clone().find(|m| m.workspace).or_else(|| {
+        mounts.find(|m| {
+            m.target
+                .to_str()
+                .map(|s| s.contains("workspace"))
+                .unwrap_or(false)
+        })
+    })
+}
+
+/// Workspace directory mounted into the jail, found from its bind mounts
+fn workspace_source(config: &JailConfig) -> Option<PathBuf> {
+    workspace_mount(config).map(|m| m.source.clone())
+}
+
+/// How a bind mount is set up in the jail
+enum MountMode {
+    ReadOnly,
+    ReadWrite,
+    /// Overlay of a copy-on-write workspace
+    Overlay(CowDirs),
+}
+
+/// Mode of a bind mount
+///
+/// The workspace of a copy-on-write jail is an overlay. Should its directories be
+/// unavailable, it falls back to read-only rather than exposing the host tree.
+fn mount_mode(config: &JailConfig, mount: &BindMount) -> MountMode {
+    let is_cow_workspace =
+        config.cow && workspace_mount(config).is_some_and(|w| std::ptr::eq(w, mount));
+    if mount.readonly {
+        MountMode::ReadOnly
+    } else if is_cow_workspace {
+        CowDirs::for_jail(&config.name)
+            .map(MountMode::Overlay)
+            .unwrap_or(MountMode::ReadOnly)
+    } else {
+        MountMode::ReadWrite
+    }
+}
+
+/// Agent of a jail, from its name (format: jail__{project}__{hash}__{agent})
+fn agent_from_name(name: &str) -> Option<&str> {
+    name.rsplit("__").next().and_then(|suffix| match suffix {
+        "claude" | "claude-code-router" | "copilot" | "cursor" | "gemini" | "jules" | "codex"
+        | "pi" => Some(suffix),
+        _ => None,
+    })
+}
+
+/// Labels recording how a jail was created
+///
+/// `jail-ai.block-host` lets eBPF blocking be reattached after reboots, and
+/// `jail-ai.config` lets `inspect` return the exact configuration.
+fn jail_labels(config: &JailConfig) -> Vec<(&'static str, String)> {
+    let mut labels = vec![
+        ("jail-ai.block-host", config.block_host.to_string()),
+        ("jail-ai.version", env!("CARGO_PKG_VERSION").to_string()),
+        ("jail-ai.created", chrono::Utc::now().to_rfc3339()),
+    ];
+    if let Some(agent) = agent_from_name(&config.name) {
+        labels.push(("jail-ai.agent", agent.to_string()));
+    }
+    if let Some(workspace) = workspace_source(config) {
+        labels.push(("jail-ai.workspace", workspace.display().to_string()));
+    }
+    match crate::config_schema::to_json_line(&config.without_secrets()) {
+        Ok(recorded) => labels.push((CONFIG_LABEL, recorded)),
+        Err(e) => warn!("Failed to record jail configuration: {}", e),
+    }
+    labels
+}
+
+/// `--label key=value` arguments for `<engine> run`
+fn label_args(config: &JailConfig) -> Vec<String> {
+    jail_labels(config)
+        .into_iter()
+        .flat_map(|(key, value)| ["--label".to_string(), format!("{key}={value}")])
+        .collect()
+}
+
+/// Resource limit arguments for `<engine> run`
+fn limit_args(limits: &ResourceLimits) -> Vec<String> {
+    let mut args = Vec::new();
+    if let Some(memory_mb) = limits.memory_mb {
+        args.push("-m".to_string());
+        args.push(format!("{memory_mb}m"));
+    }
+    if let Some(memory_swap_mb) = limits.memory_swap_mb {
+        args.push("--memory-swap".to_string());
+        args.push(format!("{memory_swap_mb}m"));
+    }
+    if let Some(cpu_quota) = limits.cpu_quota {
+        args.push("--cpus".to_string());
+        args.push(format!("{}", cpu_quota as f64 / 100.0));
+    }
+    if let Some(pids) = limits.pids_limit {
+        args.push("--pids-limit".to_string());
+        args.push(pids.to_string());
+    }
+    if let Some(shm_size_mb) = limits.shm_size_mb {
+        args.push("--shm-size".to_string());
+        args.push(format!("{shm_size_mb}m"));
+    }
+    if let Some(weight) = limits.blkio_weight {
+        args.push("--blkio-weight".to_string());
+        args.push(weight.to_string());
+    }
+    for ulimit in &limits.ulimits {
+        args.push("--ulimit".to_string());
+        args.push(format!("{}={}:{}", ulimit.name, ulimit.soft, ulimit.hard));
+    }
+    // Engines mount tmpfs noexec by default, which breaks build tools running from /tmp
+    for tmpfs in &limits.tmpfs {
+        args.push("--tmpfs".to_string());
+        args.push(format!(
+            "{}:rw,exec,nosuid,nodev,size={}m",
+            tmpfs.target.display(),
+            tmpfs.size_mb
+        ));
+    }
+    args
+}
+
+/// Seccomp profile shipped with jail-ai, denying syscalls development tools do not need
+const SECCOMP_PROFILE: &str = include_str!("../profiles/seccomp.json");
+
+/// Writable tmpfs mounts (target, size in MB) of hardened jails, unless configured in limits
+const HARDENED_TMPFS: &[(&str, u64)] = &[("/tmp", 2048), ("/var/tmp", 512), ("/run", 64)];
+
+/// Capabilities kept by hardened jails running as root inside the container (rootless Docker),
+/// so root can still use the files owned by the image's agent user
+const ROOT_JAIL_CAPABILITIES: &[&str] = &["CHOWN", "DAC_OVERRIDE", "FOWNER", "FSETID"];
+
+/// Location of the seccomp profile on the host
+fn seccomp_profile_path() -> Result<PathBuf> {
+    Ok(crate::jail_setup::get_jail_ai_data_dir()?.join("seccomp.json"))
+}
+
+/// Write the shipped seccomp profile where the engine can read it
+async fn install_seccomp_profile() -> Result<()> {
+    let path = seccomp_profile_path()?;
+    if tokio::fs::read_to_string(&path).await.ok().as_deref() == Some(SECCOMP_PROFILE) {
+        return Ok(());
+    }
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    tokio::fs::write(&path, SECCOMP_PROFILE).await?;
+    debug!("Installed seccomp profile at {}", path.display());
+    Ok(())
+}
+
+/// Hardening arguments for `<engine> run`
+///
+/// `root_user` is set when the jail processes run as root inside the container.
+fn hardening_args(config: &JailConfig, root_user: bool) -> Vec<String> {
+    let mut args = Vec::new();
+    if config.hardened {
+        args.push("--cap-drop=ALL".to_string());
+        if root_user {
+            args.extend(
+                ROOT_JAIL_CAPABILITIES
+                    .iter()
+                    .map(|capability| format!("--cap-add={capability}")),
+            );
+        }
+        args.push("--security-opt=no-new-privileges".to_string());
+        // /home/agent, /nix and bind mounts such as /workspace stay writable
+        args.push("--read-only".to_string());
+        for (target, size_mb) in HARDENED_TMPFS {
+            let configured = config
+                .limits
+                .tmpfs
+                .iter()
+                .any(|tmpfs| tmpfs.target == Path::new(target));
+            if !configured {
+                args.push("--tmpfs".to_string());
+                args.push(format!("{target}:rw,exec,nosuid,nodev,size={size_mb}m"));
+            }
+        }
+    }
+    if config.seccomp {
+        match seccomp_profile_path() {
+            Ok(path) => args.push(format!("--security-opt=seccomp={}", path.display())),
+            Err(e) => warn!("Seccomp profile not applied: {}", e),
+        }
+    }
+    args
+}
+
+/// Size in MB of a tmpfs from its mount options (e.g. `rw,size=2048m`)
+fn tmpfs_size_mb(options: &str) -> Option<u64> {
+    let size = options
+        .split(',')
+        .find_map(|option| option.strip_prefix("size="))?;
+    let (number, unit) = size.split_at(size.find(|c: char| !c.is_ascii_digit())?);
+    let number: u64 = number.parse().ok()?;
+    match unit.to_ascii_lowercase().as_str() {
+        "k" => Some(number / 1024),
+        "m" => Some(number),
+        "g" => Some(number * 1024),
+        _ => None,
+    }
+}
+
+/// Configuration recorded in the labels at creation, with secrets restored from the container
+fn recorded_config(
+    name: &str,
+    container: &serde_json::Value,
+    backend: BackendType,
+) -> Option<JailConfig> {
+    let recorded = container["Config"]["Labels"][CONFIG_LABEL].as_str()?;
+    let mut config = match crate::config_schema::from_json_str(recorded) {
+        Ok(config) => config,
+        Err(e) => {
+            debug!("Ignoring unreadable configuration label of {}: {}", name, e);
+            return None;
+        }
+    };
+    config.name = name.to_string();
+    config.backend = backend;
+
+    let live_env: std::collections::HashMap<&str, &str> = container["Config"]["Env"]
+        .as_array()
+        .map(|env| {
+            env.iter()
+                .filter_map(|entry| entry.as_str()?.split_once('='))
+                .collect()
+        })
+        .unwrap_or_default();
+    config.environment.retain_mut(|(key, value)| {
+        if value != crate::config::REDACTED {
+            return true;
+        }
+        match live_env.get(key.as_str()) {
+            Some(live) => {
+                *value = live.to_string();
+                true
+            }
+            None => false,
+        }
+    });
+    Some(config)
+}
+
+/// Rebuild a jail configuration from `podman inspect` / `docker inspect` output
+fn config_from_inspect(
+    name: &str,
+    container: &serde_json::Value,
+    backend: BackendType,
+) -> JailConfig {
+    if let Some(config) = recorded_config(name, container, backend) {
+        return config;
+    }
+
+    // Jails created before configurations were recorded: reconstruct from the engine state
+    let image = container["Config"]["Image"]
+        .as_str()
+        .unwrap_or(image::DEFAULT_IMAGE_NAME)
+        .to_string();
+
+    // Extract mounts
+    let mut bind_mounts = Vec::new();
+    if let Some(mounts) = container["Mounts"].as_array() {
+        for mount in mounts {
+            if mount["Type"].as_str() == Some("bind") {
+                let source = mount["Source"].as_str().unwrap_or("").to_string();
+                let destination = mount["Destination"].as_str().unwrap_or("").to_string();
+                let readonly = mount["RW"].as_bool().map(|rw| !rw).unwrap_or(false);
+
+                if !source.is_empty() && !destination.is_empty() {
+                    bind_mounts.push(crate::config::BindMount {
+                        source: source.into(),
+                        target: destination.into(),
+                        readonly,
+                        workspace: false,
+                    });
+                }
+            }
+        }
+    }
+
+    // Extract environment variables
+    let mut environment = Vec::new();
+    if let Some(env_array) = container["Config"]["Env"].as_array() {
+        for env in env_array {
+            if let Some(env_str) = env.as_str() {
+                if let Some(pos) = env_str.find('=') {
+                    let key = env_str[..pos].to_string();
+                    let value = env_str[pos + 1..].to_string();
+                    // Skip system environment variables
+                    if !key.starts_with("PATH") && !key.starts_with("HOME") && key != "HOSTNAME" {
+                        environment.push((key, value));
+                    }
+                }
+            }
+        }
+    }
+
+    // Extract network settings
+    let network_mode = container["HostConfig"]["NetworkMode"]
+        .as_str()
+        .unwrap_or("default");
+    let network = crate::config::NetworkConfig {
+        enabled: network_mode != "none",
+        // Check for both "slirp4netns" and "private" for backward compatibility
+        // (older versions incorrectly used "private" which isn't a standard mode)
+        private: network_mode == "slirp4netns"
+            || network_mode == "private"
+            || network_mode == "bridge",
+        host: network_mode == "host",
+    };
+
+    // Extract port mappings
+    let port_mappings = parse_port_bindings(&container["HostConfig"]["PortBindings"]);
+
+    // Extract resource limits
+    let memory_mb = container["HostConfig"]["Memory"]
+        .as_i64()
+        .filter(|&m| m > 0)
+        .map(|m| (m / 1024 / 1024) as u64);
+
+    let cpu_quota = container["HostConfig"]["CpuQuota"]
+        .as_i64()
+        .filter(|&q| q > 0)
+        .and_then(|quota| {
+            container["HostConfig"]["CpuPeriod"]
+                .as_i64()
+                .map(|period| ((quota as f64 / period as f64) * 100.0) as u32)
+        })
+        .or_else(|| {
+            // Docker stores --cpus as NanoCpus instead of a quota/period pair
+            container["HostConfig"]["NanoCpus"]
+                .as_i64()
+                .filter(|&n| n > 0)
+                .map(|nano| (nano / 10_000_000) as u32)
+        });
+
+    let bytes_to_mb = |field: &str| {
+        container["HostConfig"][field]
+            .as_i64()
+            .filter(|&bytes| bytes > 0)
+            .map(|bytes| (bytes / 1024 / 1024) as u64)
+    };
+    let pids_limit = container["HostConfig"]["PidsLimit"]
+        .as_i64()
+        .filter(|&pids| pids > 0)
+        .map(|pids| pids as u32);
+    let blkio_weight = container["HostConfig"]["BlkioWeight"]
+        .as_u64()
+        .filter(|&weight| weight > 0)
+        .map(|weight| weight as u16);
+    let ulimits = container["HostConfig"]["Ulimits"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|ulimit| {
+            // Podman reports names as RLIMIT_NOFILE
+            let name = ulimit["Name"].as_str()?.to_ascii_lowercase();
+            Some(crate::config::Ulimit {
+                name: name.strip_prefix("rlimit_").unwrap_or(&name).to_string(),
+                soft: ulimit["Soft"].as_u64()?,
+                hard: ulimit["Hard"].as_u64()?,
+            })
+        })
+        .collect();
+    let tmpfs = container["HostConfig"]["Tmpfs"]
+        .as_object()
+        .into_iter()
+        .flatten()
+        .filter_map(|(target, options)| {
+            Some(crate::config::TmpfsMount {
+                target: target.into(),
+                size_mb: tmpfs_size_mb(options.as_str()?)?,
+            })
+        })
+        .collect();
+
+    // Extract block_host from label
+    let block_host = container["Config"]["Labels"]["jail-ai.block-host"]
+        .as_str()
+        .map(|s| s == "true")
+        .unwrap_or(false);
+
+    JailConfig {
+        name: name.to_string(),
+        backend,
+        base_image: image,
+        bind_mounts,
+        environment,
+        network,
+        port_mappings,
+        limits: ResourceLimits {
+            memory_mb,
+            cpu_quota,
+            pids_limit,
+            memory_swap_mb: bytes_to_mb("MemorySwap"),
+            shm_size_mb: bytes_to_mb("ShmSize"),
+            blkio_weight,
+            ulimits,
+            tmpfs,
+        },
+        upgrade: false,
+        force_layers: Vec::new(),
+        use_layered_images: true,
+        isolated: false,
+        verbose: false,
+        pre_create_dirs: Vec::new(), // Not persisted in container metadata
+        no_nix: false,
+        block_host,
+        podman_socket: false, // Not persisted in container metadata
+        // Jails without a recorded configuration predate hardening
+        hardened: false,
+        seccomp: false,
+        cow: false,
+        shared_home: false,
+    }
+}
+
+/// Port mappings from a `{"80/tcp": [{"HostIp": "", "HostPort": "8080"}]}` engine binding map
+///
+/// Ranges are listed port by port and an empty address means all interfaces.
+fn parse_port_bindings(bindings: &serde_json::Value) -> Vec<crate::config::PortMapping> {
+    let mut port_mappings = Vec::new();
+    let Some(bindings) = bindings.as_object() else {
+        return port_mappings;
+    };
+    for (container_port_proto, port_bindings) in bindings {
+        // container_port_proto format: "5432/tcp"
+        let Some((container_port, protocol)) = container_port_proto.split_once('/') else {
+            continue;
+        };
+        let Ok(container_port) = container_port.parse::<u16>() else {
+            continue;
+        };
+        for binding in port_bindings.as_array().into_iter().flatten() {
+            let Some(host_port) = binding["HostPort"]
+                .as_str()
+                .and_then(|port| port.parse::<u16>().ok())
+            else {
+                continue;
+            };
+            let host_ip = match binding["HostIp"].as_str() {
+                Some("") | None => "0.0.0.0",
+                Some(ip) => ip,
+            };
+            port_mappings.push(crate::config::PortMapping {
+                host_port,
+                container_port,
+                protocol: protocol.to_string(),
+                host_ip: host_ip.to_string(),
+                count: 1,
+            });
+        }
+    }
+    port_mappings.sort_by_key(|port| (port.host_port, port.container_port));
+    port_mappings
+}
+
+/// Check if an image uses Nix by examining its name/tag
+/// Images with Nix will have "nix" in their layer tag or be the nix base image
+fn image_uses_nix(image: &str) -> bool {
+    // Check if it's the Nix base image
+    if image.contains("jail-ai-nix:") {
+        return true;
+    }
+
+    // Check if it's an agent/project image with nix in the tag
+    // Format: localhost/jail-ai-agent-claude:base-nix-rust or base-rust-nix
+    if let Some(tag_part) = image.split(':').nth(1) {
+        // Check if "nix" appears as a layer component
+        // Valid: "base-nix", "base-nix-rust", "base-rust-nix"
+        // Invalid: "phoenix" (contains "nix" but not as a separate layer)
+        for component in tag_part.split('-') {
+            if component == "nix" {
+                return true;
+            }
+        }
+    }
+
+    false
+}
+
+/// Extract base name by stripping agent suffix
+/// Jail name format: jail__{project}__{hash}__{agent}
+/// Returns: jail__{project}__{hash}
+///
+/// Simply strips the last segment after __ (the agent name), and the session
+/// segment of session jails so that they share the project Nix volume.
+/// If there's no __, returns the name as-is (e.g., test names).
+pub fn extract_base_name(name: &str) -> String {
+    if let Some(pos) = name.rfind("__") {
+        // Strip the last segment (agent name)
+        crate::session::strip_session(&name[..pos]).to_string()
+    } else {
+        // No __ found, return as-is (simple test names like "test")
+        name.to_string()
+    }
+}
+
+/// Arguments of `<engine> exec` running `command` with `options`
+fn engine_exec_args(name: &str, command: &[String], options: &ExecOptions) -> Vec<String> {
+    let mut args = vec!["exec".to_string()];
+
+    if options.stdin {
+        args.push("-i".to_string());
+    }
+    if options.tty {
+        args.push("-t".to_string());
+    }
+    if let Some(user) = &options.user {
+        args.push("--user".to_string());
+        args.push(user.clone());
+    }
+    if let Some(workdir) = &options.workdir {
+        args.push("--workdir".to_string());
+        args.push(workdir.display().to_string());
+    }
+    for (key, value) in &options.env {
+        args.push("-e".to_string());
+        args.push(format!("{key}={value}"));
+    }
+
+    args.push(name.to_string());
+    args.extend(command.iter().cloned());
+    args
+}
+
+/// Exit status of `podman exec`/`docker exec` when the engine itself failed
+const ENGINE_EXEC_FAILURE: i32 = 125;
+
+/// Run `<engine> exec`, attached to the terminal or capturing output
+async fn engine_exec(
+    engine: &str,
+    name: &str,
+    command: &[String],
+    options: &ExecOptions,
+) -> Result<ExecResult> {
+    let mut cmd = Command::new(engine);
+    cmd.args(engine_exec_args(name, command, options));
+    let started = Instant::now();
+
+    let result = if options.is_attached() {
+        // Interactive mode: inherit stdio for direct user interaction
+        cmd.stdin(Stdio::inherit())
+            .stdout(Stdio::inherit())
+            .stderr(Stdio::inherit());
+
+        let status = cmd.status().await.map_err(|e| {
+            JailError::Backend(format!("Failed to execute interactive command: {e}"))
+        })?;
+        ExecResult::new(status, String::new(), String::new(), started)
+    } else {
+        // Non-interactive mode: capture output
+        debug!("Running command: {:?}", cmd);
+        let output = cmd
+            .stdin(Stdio::null())
+            .output()
+            .await
+            .map_err(|e| JailError::Backend(format!("Failed to execute command: {e}")))?;
+        ExecResult::new(
+            output.status,
+            String::from_utf8_lossy(&output.stdout).to_string(),
+            String::from_utf8_lossy(&output.stderr).to_string(),
+            started,
+        )
+    };
+    debug!(
+        "Command exited with {} after {:?}",
+        result.exit_code, result.duration
+    );
+
+    if result.exit_code == ENGINE_EXEC_FAILURE {
+        return Err(JailError::command_failed(
+            format!("{engine} exec {ENGINE_EXEC_FAILURE}"),
+            &result.stderr,
+        ));
+    }
+    Ok(result)
+}
+
+/// Run `<engine> exec` with its output streamed line by line
+fn engine_exec_stream(
+    engine: &str,
+    name: &str,
+    command: &[String],
+    options: &ExecOptions,
+) -> Result<ExecStream> {
+    let detached = ExecOptions {
+        tty: false,
+        stdin: false,
+        ..options.clone()
+    };
+    let mut cmd = Command::new(engine);
+    cmd.args(engine_exec_args(name, command, &detached));
+    ExecStream::spawn(cmd)
+}
+
+/// Summaries of `names` from a single `<engine> inspect` call
+async fn engine_summaries(engine: &str, names: &[String]) -> Result<Vec<JailSummary>> {
+    if names.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut cmd = Command::new(engine);
+    cmd.arg("inspect")
+        .arg("--type")
+        .arg("container")
+        .args(names);
+    let containers: Vec<serde_json::Value> = serde_json::from_str(&run_command(&mut cmd).await?)?;
+
+    let image_ids = engine_image_ids(engine).await.unwrap_or_else(|e| {
+        debug!("Could not list images: {}", e);
+        HashMap::new()
+    });
+    let volumes = engine_volume_mountpoints(engine).await.unwrap_or_else(|e| {
+        debug!("Could not list volumes: {}", e);
+        HashMap::new()
+    });
+
+    let now = chrono::Utc::now();
+    let mut summaries = Vec::new();
+    for container in &containers {
+        let mut summary = summary_from_inspect(container, &image_ids, now);
+        summary.host_blocking = summary.running && host_blocking::loader_active(&summary.name);
+        if let Some(mountpoint) = home_mount(container).and_then(|home| volumes.get(home)) {
+            summary.home_size = dir_size(mountpoint.clone()).await;
+        }
+        summaries.push(summary);
+    }
+    Ok(summaries)
+}
+
+/// Volume mounted on the agent home of a container, shared with the main jail for some sessions
+fn home_mount(container: &serde_json::Value) -> Option<&str> {
+    container["Mounts"]
+        .as_array()?
+        .iter()
+        .find(|mount| mount["Destination"] == "/home/agent")?["Name"]
+        .as_str()
+}
+
+/// Summary of a container from its inspect document (eBPF state and home size left unset)
+fn summary_from_inspect(
+    container: &serde_json::Value,
+    image_ids: &HashMap<String, String>,
+    now: chrono::DateTime<chrono::Utc>,
+) -> JailSummary {
+    let name = container["Name"]
+        .as_str()
+        .unwrap_or_default()
+        .trim_start_matches('/')
+        .to_string();
+    let running = container["State"]["Running"].as_bool().unwrap_or(false);
+    let image = container["Config"]["Image"]
+        .as_str()
+        .unwrap_or_default()
+        .to_string();
+    let image_id = normalize_image_id(container["Image"].as_str().unwrap_or_default());
+    let outdated = image_ids
+        .get(&image)
+        .is_some_and(|current| !image_id.is_empty() && *current != image_id);
+
+    let workspace = container["Config"]["Labels"]["jail-ai.workspace"]
+        .as_str()
+        .map(PathBuf::from)
+        .or_else(|| {
+            container["Mounts"].as_array()?.iter().find_map(|mount| {
+                mount["Destination"]
+                    .as_str()?
+                    .contains("workspace")
+                    .then(|| PathBuf::from(mount["Source"].as_str().unwrap_or_default()))
+            })
+        });
+
+    let uptime_secs = container["State"]["StartedAt"]
+        .as_str()
+        .filter(|_| running)
+        .and_then(|started| chrono::DateTime::parse_from_rfc3339(started).ok())
+        .map(|started| {
+            (now - started.with_timezone(&chrono::Utc))
+                .num_seconds()
+                .max(0) as u64
+        });
+
+    JailSummary {
+        agent: crate::agent_commands::extract_agent_name(&name).to_string(),
+        name,
+        workspace,
+        running,
+        image,
+        outdated,
+        host_blocking: false,
+        uptime_secs,
+        home_size: None,
+    }
+}
+
+/// Image ID without the `sha256:` prefix docker adds
+fn normalize_image_id(id: &str) -> String {
+    let id = id.trim();
+    id.strip_prefix("sha256:").unwrap_or(id).to_string()
+}
+
+/// Current image ID of every local `repository:tag`
+async fn engine_image_ids(engine: &str) -> Result<HashMap<String, String>> {
+    let mut cmd = Command::new(engine);
+    cmd.arg("images")
+        .arg("--no-trunc")
+        .arg("--format")
+        .arg("{{.Repository}}:{{.Tag}}\t{{.ID}}");
+    let output = run_command(&mut cmd).await?;
+    Ok(output
+        .lines()
+        .filter_map(|line| line.split_once('\t'))
+        .map(|(image, id)| (image.to_string(), normalize_image_id(id)))
+        .collect())
+}
+
+/// Host directory of every volume
+async fn engine_volume_mountpoints(engine: &str) -> Result<HashMap<String, PathBuf>> {
+    let mut cmd = Command::new(engine);
+    cmd.arg("volume")
+        .arg("ls")
+        .arg("--format")
+        .arg("{{.Name}}\t{{.Mountpoint}}");
+    let output = run_command(&mut cmd).await?;
+    Ok(output
+        .lines()
+        .filter_map(|line| line.split_once('\t'))
+        .map(|(name, mountpoint)| (name.to_string(), PathBuf::from(mountpoint)))
+        .collect())
+}
+
+/// Whether a volume was created for a jail
+fn is_jail_volume(name: &str) -> bool {
+    name.starts_with("jail__") && (name.ends_with("__home") || name.ends_with("__nix"))
+}
+
+/// Jail volumes of an engine with their size
+async fn engine_list_volumes(engine: &str) -> Result<Vec<VolumeSummary>> {
+    let mut volumes = Vec::new();
+    for (name, mountpoint) in engine_volume_mountpoints(engine).await? {
+        if is_jail_volume(&name) {
+            let size = dir_size(mountpoint).await;
+            volumes.push(VolumeSummary { name, size });
+        }
+    }
+    volumes.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(volumes)
+}
+
+/// Remove a volume with `<engine> volume rm`
+async fn engine_remove_volume(engine: &str, volume: &str) -> Result<()> {
+    let mut cmd = Command::new(engine);
+    cmd.arg("volume").arg("rm").arg(volume);
+    run_command(&mut cmd).await?;
+    Ok(())
+}
+
+/// Image of the throwaway containers reading and writing volume contents
+const VOLUME_HELPER_IMAGE: &str = "docker.io/library/busybox:latest";
+
+/// Shell script deleting the content of a directory except some paths
+///
+/// Arguments: the directory, then the paths to keep relative to it.
+const RESET_VOLUME_SCRIPT: &str = r#"set -e
+dir="$1"
+shift
+keep=$(mktemp -d)
+cd "$dir"
+for path in "$@"; do
+    if [ -e "$path" ]; then
+        mkdir -p "$keep/$(dirname "$path")"
+        cp -a "$path" "$keep/$path"
+    fi
+done
+find . -mindepth 1 -maxdepth 1 -exec rm -rf {} +
+cp -a "$keep/." .
+rm -rf "$keep"
+"#;
+
+/// Fail with [`JailError::NotFound`] unless the volume exists
+async fn engine_require_volume(engine: &str, volume: &str) -> Result<()> {
+    let mut cmd = Command::new(engine);
+    cmd.arg("volume").arg("inspect").arg(volume);
+    run_command(&mut cmd)
+        .await
+        .map(|_| ())
+        .map_err(|_| JailError::NotFound(format!("Volume '{volume}' not found")))
+}
+
+/// `<engine> run` of a helper container with the volume mounted on `/volume`
+fn volume_helper_command(engine: &str, volume: &str, readonly: bool) -> Command {
+    let mount = if readonly {
+        format!("{volume}:/volume:ro")
+    } else {
+        format!("{volume}:/volume")
+    };
+    let mut cmd = Command::new(engine);
+    cmd.arg("run")
+        .arg("--rm")
+        .arg("-i")
+        .arg("--network=none")
+        .arg("-v")
+        .arg(mount)
+        .arg(VOLUME_HELPER_IMAGE);
+    cmd
+}
+
+/// Run a command with its standard input and output redirected, failing on a non-zero exit
+async fn run_redirected(cmd: &mut Command, stdin: Stdio, stdout: Stdio) -> Result<()> {
+    debug!("Running command: {:?}", cmd);
+    let output = cmd
+        .stdin(stdin)
+        .stdout(stdout)
+        .stderr(Stdio::piped())
+        .output()
+        .await
+        .map_err(|e| JailError::Backend(format!("Failed to execute command: {e}")))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(JailError::command_failed(output.status, &stderr));
+    }
+    Ok(())
+}
+
+/// Stream a volume as a tar archive into a host file
+async fn engine_export_volume(engine: &str, volume: &str, archive: &Path) -> Result<()> {
+    engine_require_volume(engine, volume).await?;
+    let file = create_archive(archive)?;
+    let mut cmd = volume_helper_command(engine, volume, true);
+    cmd.args(["tar", "-C", "/volume", "-cf", "-", "."]);
+    run_redirected(&mut cmd, Stdio::null(), Stdio::from(file)).await
+}
+
+/// Create a new archive file readable only by the user, refusing to reuse an existing path
+fn create_archive(archive: &Path) -> Result<std::fs::File> {
+    use std::os::unix::fs::OpenOptionsExt;
+    Ok(std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(archive)?)
+}
+
+/// Stream a host tar archive into a volume, created by the engine when missing
+async fn engine_import_volume(engine: &str, volume: &str, archive: &Path) -> Result<()> {
+    let file = std::fs::File::open(archive)?;
+    let mut cmd = volume_helper_command(engine, volume, false);
+    cmd.args(["tar", "-C", "/volume", "-xf", "-"]);
+    run_redirected(&mut cmd, Stdio::from(file), Stdio::null()).await
+}
+
+/// Delete the content of a volume except the `keep` paths
+async fn engine_reset_volume(engine: &str, volume: &str, keep: &[String]) -> Result<()> {
+    engine_require_volume(engine, volume).await?;
+    let mut cmd = volume_helper_command(engine, volume, false);
+    cmd.args(["sh", "-c", RESET_VOLUME_SCRIPT, "sh", "/volume"])
+        .args(keep);
+    run_command(&mut cmd).await?;
+    Ok(())
+}
+
+/// Images of an engine built by jail-ai, with their size
+async fn engine_list_images(engine: &str) -> Result<Vec<ImageSummary>> {
+    let mut cmd = Command::new(engine);
+    cmd.arg("images")
+        .arg("--format")
+        .arg("{{.Repository}}:{{.Tag}}");
+    let output = run_command(&mut cmd).await?;
+    let mut names: Vec<String> = output
+        .lines()
+        .map(str::trim)
+        .filter(|name| name.starts_with(image::IMAGE_PREFIX))
+        .map(str::to_string)
+        .collect();
+    names.sort();
+    names.dedup();
+    if names.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut cmd = Command::new(engine);
+    cmd.arg("image")
+        .arg("inspect")
+        .arg("--format")
+        .arg("{{.Size}}")
+        .args(&names);
+    let sizes: Vec<Option<u64>> = match run_command(&mut cmd).await {
+        Ok(output) => output
+            .lines()
+            .map(|line| line.trim().parse().ok())
+            .collect(),
+        Err(e) => {
+            debug!("Could not read image sizes: {}", e);
+            Vec::new()
+        }
+    };
+
+    Ok(names
+        .into_iter()
+        .enumerate()
+        .map(|(i, name)| ImageSummary {
+            name,
+            size: sizes.get(i).copied().flatten(),
+        })
+        .collect())
+}
+
+/// Commit a container to an image with `<engine> commit`, adding image labels
+async fn engine_commit(
+    engine: &str,
+    name: &str,
+    image: &str,
+    labels: &[(String, String)],
+) -> Result<()> {
+    let mut cmd = Command::new(engine);
+    cmd.arg("commit");
+    for (key, value) in labels {
+        // Quoted as JSON strings, which the Dockerfile LABEL instruction accepts
+        let value = serde_json::to_string(value)?;
+        cmd.arg("--change").arg(format!("LABEL {key}={value}"));
+    }
+    cmd.arg(name).arg(image);
+    run_command(&mut cmd).await?;
+    crate::image_layers::invalidate_image_cache(image);
+    Ok(())
+}
+
+/// Remove an image with `<engine> rmi`
+async fn engine_remove_image(engine: &str, image: &str) -> Result<()> {
+    engine_action(engine, "rmi", image).await?;
+    crate::image_layers::invalidate_image_cache(image);
+    Ok(())
+}
+
+/// Total size of the files under `path`, `None` when it cannot be read
+async fn dir_size(path: PathBuf) -> Option<u64> {
+    fn walk(path: &Path) -> std::io::Result<u64> {
+        let mut total = 0;
+        for entry in std::fs::read_dir(path)? {
+            let entry = entry?;
+            let metadata = entry.metadata()?;
+            if metadata.is_dir() {
+                // Unreadable subdirectories are skipped rather than failing the whole volume
+                total += walk(&entry.path()).unwrap_or(0);
+            } else {
+                total += metadata.len();
+            }
+        }
+        Ok(total)
+    }
+
+    tokio::task::spawn_blocking(move || walk(&path).ok())
+        .await
+        .ok()
+        .flatten()
+}
+
+/// Run a container action taking only the container name (`stop`, `pause`...)
+async fn engine_action(engine: &str, action: &str, name: &str) -> Result<()> {
+    let mut cmd = Command::new(engine);
+    cmd.arg(action).arg(name);
+    run_command(&mut cmd).await?;
+    Ok(())
+}
+
+/// Stream `<engine> logs` to stdout
+async fn engine_logs(engine: &str, name: &str, options: &LogOptions) -> Result<()> {
+    let mut cmd = Command::new(engine);
+    cmd.arg("logs");
+    if options.follow {
+        cmd.arg("--follow");
+    }
+    if let Some(tail) = options.tail {
+        cmd.arg("--tail").arg(tail.to_string());
+    }
+    cmd.arg(name)
+        .stdin(Stdio::null())
+        .stdout(Stdio::inherit())
+        .stderr(Stdio::inherit());
+
+    let status = cmd
+        .status()
+        .await
+        .map_err(|e| JailError::Backend(format!("Failed to read logs: {e}")))?;
+    if !status.success() {
+        return Err(JailError::ExecutionFailed(format!(
+            "{engine} logs failed with status: {status}"
+        )));
+    }
+    Ok(())
+}
+
+/// Read a single `<engine> stats` sample
+async fn engine_stats(engine: &str, name: &str) -> Result<JailStats> {
+    let mut cmd = Command::new(engine);
+    cmd.arg("stats")
+        .arg("--no-stream")
+        .arg("--format")
+        .arg("{{.CPUPerc}}\t{{.MemUsage}}\t{{.NetIO}}\t{{.BlockIO}}\t{{.PIDs}}")
+        .arg(name);
+
+    let output = run_command(&mut cmd).await?;
+    parse_stats_line(&output)
+        .ok_or_else(|| JailError::Backend(format!("Unexpected stats output: {}", output.trim())))
+}
+
+/// Host ports currently published by a container
+async fn engine_port_mappings(engine: &str, name: &str) -> Result<Vec<crate::config::PortMapping>> {
+    let mut cmd = Command::new(engine);
+    cmd.arg("inspect")
+        .arg("--format")
+        .arg("{{json .NetworkSettings.Ports}}")
+        .arg(name);
+
+    let output = run_command(&mut cmd).await?;
+    let ports: serde_json::Value = serde_json::from_str(output.trim())
+        .map_err(|e| JailError::Backend(format!("Failed to parse inspect output: {e}")))?;
+    Ok(parse_port_bindings(&ports))
+}
+
+/// Count the processes of a container besides its `tini -- sleep infinity` keep-alive
+async fn engine_active_sessions(engine: &str, name: &str) -> Result<usize> {
+    let mut cmd = Command::new(engine);
+    cmd.arg("top").arg(name);
+    let output = run_command(&mut cmd).await?;
+    Ok(count_sessions(&output))
+}
+
+/// Count the non keep-alive processes listed by `<engine> top`, skipping its header
+fn count_sessions(output: &str) -> usize {
+    output
+        .lines()
+        .skip(1)
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.ends_with("sleep infinity"))
+        .count()
+}
+
+/// Parse the tab-separated line produced by `engine_stats`
+fn parse_stats_line(output: &str) -> Option<JailStats> {
+    let line = output.lines().find(|line| !line.trim().is_empty())?;
+    let fields: Vec<&str> = line.split('\t').map(str::trim).collect();
+    let [cpu, memory, net_io, block_io, pids] = fields.as_slice() else {
+        return None;
+    };
+
+    Some(JailStats {
+        cpu: cpu.to_string(),
+        memory: memory.to_string(),
+        net_io: net_io.to_string(),
+        block_io: block_io.to_string(),
+        pids: pids.to_string(),
+    })
+}
+
+/// Copy between the host and a container with `<engine> cp`
+///
+/// `into_jail` selects the direction: host `source` to jail `target`, or the reverse.
+async fn engine_copy(
+    engine: &str,
+    name: &str,
+    source: &Path,
+    target: &Path,
+    into_jail: bool,
+) -> Result<()> {
+    let (from, to) = if into_jail {
+        (
+            source.display().to_string(),
+            format!("{}:{}", name, target.display()),
+        )
+    } else {
+        (
+            format!("{}:{}", name, source.display()),
+            target.display().to_string(),
+        )
+    };
+
+    let mut cmd = Command::new(engine);
+    cmd.arg("cp").arg(&from).arg(&to);
+    run_command(&mut cmd)
+        .await
+        .map_err(|e| JailError::Backend(format!("Failed to copy {from} to {to}: {e}")))?;
+    Ok(())
 }
 
 /// Helper to run a command and capture output
@@ -52,11 +1652,440 @@ async fn run_command(cmd: &mut Command) -> Result<String> {
 
     if !output.status.success() {
         let stderr = String::from_utf8_lossy(&output.stderr);
-        return Err(JailError::ExecutionFailed(format!(
-            "Command failed with status {}: {}",
-            output.status, stderr
-        )));
+        return Err(JailError::command_failed(output.status, &stderr));
     }
 
     Ok(String::from_utf8_lossy(&output.stdout).to_string())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_create_archive_is_private_and_new() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = tempfile::tempdir().unwrap();
+        let archive = dir.path().join("volume.tar");
+        create_archive(&archive).unwrap();
+        let mode = std::fs::metadata(&archive).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        assert!(create_archive(&archive).is_err());
+    }
+
+    #[test]
+    fn test_engine_exec_args() {
+        let command = vec!["ls".to_string(), "-la".to_string()];
+        assert_eq!(
+            engine_exec_args("jail", &command, &ExecOptions::interactive(false)),
+            vec!["exec", "jail", "ls", "-la"]
+        );
+
+        let options = ExecOptions {
+            user: Some("0:0".to_string()),
+            workdir: Some(PathBuf::from("/workspace")),
+            env: vec![("TERM".to_string(), "xterm-256color".to_string())],
+            tty: true,
+            stdin: true,
+        };
+        assert_eq!(
+            engine_exec_args("jail", &command, &options),
+            vec![
+                "exec",
+                "-i",
+                "-t",
+                "--user",
+                "0:0",
+                "--workdir",
+                "/workspace",
+                "-e",
+                "TERM=xterm-256color",
+                "jail",
+                "ls",
+                "-la"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_summary_from_inspect() {
+        let container = serde_json::json!({
+            "Name": "/jail__demo__abc12345__claude",
+            "Image": "sha256:1111",
+            "State": {"Running": true, "StartedAt": "2026-01-01T10:00:00.123456789+01:00"},
+            "Config": {
+                "Image": "localhost/jail-ai-agent-claude:base",
+                "Labels": {"jail-ai.workspace": "/home/user/demo"},
+            },
+        });
+        let image_ids = HashMap::from([(
+            "localhost/jail-ai-agent-claude:base".to_string(),
+            "2222".to_string(),
+        )]);
+        let now = chrono::DateTime::parse_from_rfc3339("2026-01-01T09:01:00.5Z")
+            .unwrap()
+            .with_timezone(&chrono::Utc);
+
+        let summary = summary_from_inspect(&container, &image_ids, now);
+        assert_eq!(summary.name, "jail__demo__abc12345__claude");
+        assert_eq!(summary.agent, "Claude");
+        assert_eq!(summary.workspace, Some(PathBuf::from("/home/user/demo")));
+        assert!(summary.running);
+        assert!(summary.outdated);
+        assert_eq!(summary.uptime_secs, Some(60));
+
+        // Same image ID, stopped, workspace from the mounts of an unlabeled jail
+        let container = serde_json::json!({
+            "Name": "jail__demo__abc12345__claude",
+            "Image": "2222",
+            "State": {"Running": false, "StartedAt": "2026-01-01T10:00:00+01:00"},
+            "Config": {"Image": "localhost/jail-ai-agent-claude:base"},
+            "Mounts": [{"Source": "/src/demo", "Destination": "/workspace"}],
+        });
+        let summary = summary_from_inspect(&container, &image_ids, now);
+        assert!(!summary.outdated);
+        assert_eq!(summary.uptime_secs, None);
+        assert_eq!(summary.workspace, Some(PathBuf::from("/src/demo")));
+    }
+
+    #[tokio::test]
+    async fn test_jail_home_volume_of_shared_session() {
+        let main = "jail__app__12345678__claude";
+        let session = crate::session::session_jail_name("jail__app__12345678", "claude", "2");
+        let own = crate::jail::JailBuilder::new(&session)
+            .build()
+            .config()
+            .clone();
+        let mut shared = own.clone();
+        shared.shared_home = true;
+
+        let fake = fake::FakeBackend::new().with_jail(shared, false);
+        assert_eq!(
+            jail_home_volume(&fake, &session).await,
+            format!("{main}__home")
+        );
+        let fake = fake::FakeBackend::new().with_jail(own, false);
+        assert_eq!(
+            jail_home_volume(&fake, &session).await,
+            format!("{session}__home")
+        );
+        assert_eq!(jail_home_volume(&fake, "gone").await, "gone__home");
+
+        let container = serde_json::json!({"Mounts": [
+            {"Name": "jail__app__12345678__nix", "Destination": "/nix"},
+            {"Name": format!("{main}__home"), "Destination": "/home/agent"},
+        ]});
+        assert_eq!(
+            home_mount(&container),
+            Some(format!("{main}__home").as_str())
+        );
+        assert_eq!(home_mount(&serde_json::json!({})), None);
+    }
+
+    #[tokio::test]
+    async fn test_find_jail_backend_of_docker_jail() {
+        use fake::{FakeBackend, FakeCall};
+        const JAIL: &str = "jail__app__12345678__claude";
+
+        let podman = FakeBackend::new();
+        let docker = FakeBackend::new().with_jail(
+            crate::jail::JailBuilder::new(JAIL)
+                .backend(BackendType::Docker)
+                .build()
+                .config()
+                .clone(),
+            true,
+        );
+        let backends: Vec<(BackendType, Arc<dyn JailBackend>)> = vec![
+            (BackendType::Podman, Arc::new(podman.clone())),
+            (BackendType::Docker, Arc::new(docker.clone())),
+        ];
+
+        let (backend_type, backend) = find_jail_backend(JAIL, &backends).await.unwrap();
+        assert_eq!(backend_type, BackendType::Docker);
+        backend.stop(JAIL).await.unwrap();
+        assert_eq!(docker.calls(), vec![FakeCall::Stop(JAIL.to_string())]);
+        assert!(podman.calls().is_empty());
+
+        assert!(
+            find_jail_backend("jail__other__87654321__claude", &backends)
+                .await
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_inspect_prefers_recorded_config() {
+        let config = JailConfig {
+            name: "jail__demo__abc12345__claude".to_string(),
+            backend: BackendType::Podman,
+            base_image: "localhost/jail-ai-agent-claude:base-rust".to_string(),
+            bind_mounts: vec![crate::config::BindMount {
+                source: PathBuf::from("/home/user/demo"),
+                target: PathBuf::from("/workspace"),
+                readonly: false,
+                workspace: true,
+            }],
+            environment: vec![
+                ("EDITOR".to_string(), "vim".to_string()),
+                ("GITHUB_TOKEN".to_string(), "ghp_secret".to_string()),
+            ],
+            upgrade: true,
+            isolated: true,
+            no_nix: true,
+            force_layers: vec!["rust".to_string()],
+            podman_socket: true,
+            ..Default::default()
+        };
+
+        let labels: serde_json::Map<String, serde_json::Value> = jail_labels(&config)
+            .into_iter()
+            .map(|(key, value)| (key.to_string(), value.into()))
+            .collect();
+        assert_eq!(labels["jail-ai.agent"], "claude");
+        assert_eq!(labels["jail-ai.workspace"], "/home/user/demo");
+        assert!(!labels[CONFIG_LABEL]
+            .as_str()
+            .unwrap()
+            .contains("ghp_secret"));
+
+        let container = serde_json::json!({
+            "Config": {
+                "Image": "localhost/jail-ai-agent-claude:base-rust",
+                "Env": ["PATH=/usr/bin", "EDITOR=vim", "GITHUB_TOKEN=ghp_secret"],
+                "Labels": labels,
+            },
+            "HostConfig": {"NetworkMode": "slirp4netns"},
+        });
+        let inspected = config_from_inspect(&config.name, &container, BackendType::Podman);
+
+        assert_eq!(inspected.name, config.name);
+        assert_eq!(inspected.base_image, config.base_image);
+        assert!(inspected.upgrade && inspected.isolated && inspected.no_nix);
+        assert!(inspected.podman_socket);
+        assert_eq!(inspected.force_layers, vec!["rust"]);
+        assert_eq!(inspected.environment, config.environment);
+        assert_eq!(
+            inspected.bind_mounts[0].source,
+            PathBuf::from("/home/user/demo")
+        );
+    }
+
+    #[test]
+    fn test_seccomp_profile_is_valid() {
+        let profile: serde_json::Value = serde_json::from_str(SECCOMP_PROFILE).unwrap();
+        assert_eq!(profile["defaultAction"], "SCMP_ACT_ALLOW");
+        let denied: Vec<&str> = profile["syscalls"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .flat_map(|rule| rule["names"].as_array().unwrap())
+            .filter_map(|name| name.as_str())
+            .collect();
+        assert!(denied.contains(&"bpf") && denied.contains(&"kexec_load"));
+    }
+
+    #[test]
+    fn test_inspect_reads_extended_limits() {
+        // Jail created before configurations were recorded in labels
+        let container = serde_json::json!({
+            "Config": {"Image": "alpine:latest", "Env": []},
+            "HostConfig": {
+                "NetworkMode": "slirp4netns",
+                "Memory": 1073741824_i64,
+                "MemorySwap": 2147483648_i64,
+                "PidsLimit": 4096,
+                "ShmSize": 268435456_i64,
+                "BlkioWeight": 200,
+                "Ulimits": [{"Name": "RLIMIT_NOFILE", "Soft": 1024, "Hard": 4096}],
+                "Tmpfs": {"/tmp": "rw,exec,nosuid,nodev,size=2048m"},
+            },
+        });
+        let limits = config_from_inspect("legacy", &container, BackendType::Podman).limits;
+
+        assert_eq!(limits.memory_mb, Some(1024));
+        assert_eq!(limits.memory_swap_mb, Some(2048));
+        assert_eq!(limits.pids_limit, Some(4096));
+        assert_eq!(limits.shm_size_mb, Some(256));
+        assert_eq!(limits.blkio_weight, Some(200));
+        assert_eq!(
+            limits.ulimits,
+            vec![crate::config::Ulimit {
+                name: "nofile".to_string(),
+                soft: 1024,
+                hard: 4096,
+            }]
+        );
+        assert_eq!(limits.tmpfs[0].size_mb, 2048);
+        assert_eq!(tmpfs_size_mb("rw,size=1g"), Some(1024));
+        assert_eq!(tmpfs_size_mb("rw"), None);
+    }
+
+    #[tokio::test]
+    async fn test_exec_stream() {
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c")
+            .arg("echo first; echo oops >&2; echo second; exit 3");
+        let mut stream = ExecStream::spawn(cmd).unwrap();
+
+        let mut stdout_lines = Vec::new();
+        while let Some(line) = stream.next_line().await {
+            if let OutputLine::Stdout(text) = line {
+                stdout_lines.push(text);
+                if stdout_lines.len() == 1 {
+                    // The remaining output is still collected by wait()
+                    break;
+                }
+            }
+        }
+        assert_eq!(stdout_lines, vec!["first"]);
+
+        let result = stream.wait().await.unwrap();
+        assert_eq!(result.exit_code, 3);
+        assert_eq!(result.stdout, "first\nsecond\n");
+        assert_eq!(result.stderr, "oops\n");
+        assert!(!result.success());
+
+        match result.into_output() {
+            Err(JailError::CommandExit { code, stderr }) => {
+                assert_eq!(code, 3);
+                assert_eq!(stderr, "oops\n");
+            }
+            other => panic!("Expected CommandExit, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_exec_result_output() {
+        let result = ExecResult {
+            stdout: "ok\n".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(result.into_output().unwrap(), "ok\n");
+
+        let error = ExecResult {
+            exit_code: 42,
+            ..Default::default()
+        }
+        .into_output()
+        .unwrap_err();
+        assert_eq!(error.exit_code(), 42);
+        assert_eq!(error.to_string(), "Command exited with status 42");
+    }
+
+    #[test]
+    fn test_parse_stats_line() {
+        assert_eq!(
+            parse_stats_line("1.25%\t120.5MB / 4.295GB\t1.2kB / 3.4kB\t0B / 8.2MB\t7\n"),
+            Some(JailStats {
+                cpu: "1.25%".to_string(),
+                memory: "120.5MB / 4.295GB".to_string(),
+                net_io: "1.2kB / 3.4kB".to_string(),
+                block_io: "0B / 8.2MB".to_string(),
+                pids: "7".to_string(),
+            })
+        );
+        assert_eq!(parse_stats_line(""), None);
+        assert_eq!(parse_stats_line("1.25%\t120MB"), None);
+    }
+
+    #[test]
+    fn test_parse_port_bindings() {
+        let bindings = serde_json::json!({
+            "3000/tcp": [{ "HostIp": "127.0.0.1", "HostPort": "41234" }],
+            "53/udp": [{ "HostIp": "", "HostPort": "5353" }],
+            "9000/tcp": null,
+        });
+        let ports: Vec<String> = parse_port_bindings(&bindings)
+            .iter()
+            .map(|port| port.to_string())
+            .collect();
+        assert_eq!(
+            ports,
+            vec!["0.0.0.0:5353:53/udp", "127.0.0.1:41234:3000/tcp"]
+        );
+        assert!(parse_port_bindings(&serde_json::Value::Null).is_empty());
+    }
+
+    #[test]
+    fn test_count_sessions() {
+        let idle = "USER  PID  PPID  %CPU  ELAPSED  TTY  TIME  COMMAND\n\
+                    agent 1    0     0.000 2h       ?    0s    tini -- sleep infinity\n\
+                    agent 2    1     0.000 2h       ?    0s    sleep infinity\n";
+        assert_eq!(count_sessions(idle), 0);
+
+        let busy = format!("{idle}agent 3    0     1.000 5m       pts/0 1s   /usr/bin/zsh\n");
+        assert_eq!(count_sessions(&busy), 1);
+        assert_eq!(count_sessions(""), 0);
+    }
+
+    #[test]
+    fn test_image_uses_nix() {
+        // Test Nix base image
+        assert!(image_uses_nix("localhost/jail-ai-nix:latest"));
+
+        // Test agent images with Nix in layer tag
+        assert!(image_uses_nix("localhost/jail-ai-agent-claude:base-nix"));
+        assert!(image_uses_nix(
+            "localhost/jail-ai-agent-claude:base-nix-rust"
+        ));
+        assert!(image_uses_nix(
+            "localhost/jail-ai-agent-claude:base-rust-nix"
+        ));
+        assert!(image_uses_nix(
+            "localhost/jail-ai-agent-jules:base-rust-nix-nodejs"
+        ));
+
+        // Test images without Nix
+        assert!(!image_uses_nix("localhost/jail-ai-agent-claude:base"));
+        assert!(!image_uses_nix("localhost/jail-ai-agent-claude:base-rust"));
+        assert!(!image_uses_nix(
+            "localhost/jail-ai-agent-claude:base-rust-nodejs"
+        ));
+        assert!(!image_uses_nix("alpine:latest"));
+
+        // Test that "nix" substring in other words doesn't trigger false positive
+        assert!(!image_uses_nix("localhost/phoenix-app:latest"));
+        assert!(!image_uses_nix("localhost/jail-ai-agent-claude:base-unix"));
+    }
+
+    #[test]
+    fn test_extract_base_name() {
+        // Test extracting base name from agent-specific jail names
+        // Hash is always 8 hex characters (as per generate_jail_name)
+        // Works for any agent name (future-proof)
+        assert_eq!(
+            extract_base_name("jail__project__abc12345__claude"),
+            "jail__project__abc12345"
+        );
+        assert_eq!(
+            extract_base_name("jail__project__abc12345__copilot"),
+            "jail__project__abc12345"
+        );
+        assert_eq!(
+            extract_base_name("jail__project__def67890__cursor"),
+            "jail__project__def67890"
+        );
+        assert_eq!(
+            extract_base_name("jail__project__12345678__gemini"),
+            "jail__project__12345678"
+        );
+        assert_eq!(
+            extract_base_name("jail__myproject__abcdef12__jules"),
+            "jail__myproject__abcdef12"
+        );
+        assert_eq!(
+            extract_base_name("jail__test__fedcba98__codex"),
+            "jail__test__fedcba98"
+        );
+
+        // Test with any future agent name (strips last segment after __)
+        assert_eq!(
+            extract_base_name("jail__project__abc12345__newagent"),
+            "jail__project__abc12345"
+        );
+
+        // Test simple name without double underscores (test names)
+        assert_eq!(extract_base_name("test"), "test");
+    }
+}
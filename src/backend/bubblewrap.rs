@@ -0,0 +1,1533 @@
+use super::{
+    extract_base_name, image_uses_nix, run_command, ExecOptions, ExecResult, ExecStream,
+    ImageSummary, JailBackend, JailStats, JailSummary, LogOptions, MountMode, VolumeSummary,
+};
+use crate::config::{BackendType, JailConfig, PortMapping, TmpfsMount};
+use crate::cow::CowDirs;
+use crate::error::{JailError, Result};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::Instant;
+use tokio::process::{Child, Command};
+use tracing::{debug, info, warn};
+
+/// Top-level rootfs entries that are replaced by fresh mounts in the sandbox
+const SKIPPED_ROOT_ENTRIES: &[&str] = &["dev", "proc", "sys", "tmp", "run"];
+
+/// Runtime settings of an image that are lost when its filesystem is exported
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct ImageRuntime {
+    env: Vec<String>,
+    working_dir: String,
+}
+
+/// Resources of a running sandbox, released when it is dropped
+struct SandboxSession {
+    /// slirp4netns process, killed on drop
+    _slirp: Option<Child>,
+    /// Session marker used by `is_running`
+    marker: Option<PathBuf>,
+}
+
+impl Drop for SandboxSession {
+    fn drop(&mut self) {
+        if let Some(marker) = &self.marker {
+            let _ = std::fs::remove_file(marker);
+        }
+    }
+}
+
+/// Daemonless backend running jails with bubblewrap user namespaces
+///
+/// State lives under `$XDG_DATA_HOME/jail-ai/bwrap`:
+/// - `rootfs/<image-id>/`: image filesystem, exported once per image
+/// - `jails/<name>/`: `config.json`, `rootfs` link, `resolv.conf` and `run/` session markers
+/// - `volumes/<name>__home/`, `volumes/<base>__nix/`: host directories replacing podman volumes
+///
+/// Jails have no long-running process: every exec starts a new sandbox from the
+/// stored configuration, so "running" means at least one session is active.
+pub struct BubblewrapBackend;
+
+impl BubblewrapBackend {
+    pub fn new() -> Self {
+        Self
+    }
+
+    fn state_dir() -> Result<PathBuf> {
+        Ok(crate::jail_setup::get_jail_ai_data_dir()?.join("bwrap"))
+    }
+
+    fn jail_dir(name: &str) -> Result<PathBuf> {
+        Ok(Self::state_dir()?.join("jails").join(name))
+    }
+
+    fn volume_dir(volume: &str) -> Result<PathBuf> {
+        Ok(Self::state_dir()?.join("volumes").join(volume))
+    }
+
+    fn load_config(name: &str) -> Result<JailConfig> {
+        let path = Self::jail_dir(name)?.join("config.json");
+        let content = std::fs::read_to_string(&path)
+            .map_err(|_| JailError::NotFound(format!("Jail '{name}' not found")))?;
+        serde_json::from_str(&content)
+            .map_err(|e| JailError::Backend(format!("Failed to parse {}: {}", path.display(), e)))
+    }
+
+    /// Export an image filesystem once, keyed by image ID
+    ///
+    /// Returns the rootfs directory; image env and working directory are stored next to it.
+    async fn ensure_rootfs(image: &str) -> Result<PathBuf> {
+        let engine = BackendType::Bubblewrap.image_engine().command();
+
+        let mut id_cmd = Command::new(engine);
+        id_cmd
+            .arg("image")
+            .arg("inspect")
+            .arg("--format")
+            .arg("{{.Id}}")
+            .arg(image);
+        let image_id = run_command(&mut id_cmd).await?;
+        let key = Self::rootfs_key(&image_id);
+
+        let rootfs_root = Self::state_dir()?.join("rootfs");
+        let rootfs = rootfs_root.join(&key);
+        if rootfs.exists() {
+            debug!("Reusing exported rootfs {}", rootfs.display());
+            return Ok(rootfs);
+        }
+
+        info!("Exporting image {} to {}", image, rootfs.display());
+        std::fs::create_dir_all(&rootfs_root)?;
+
+        let mut config_cmd = Command::new(engine);
+        config_cmd
+            .arg("image")
+            .arg("inspect")
+            .arg("--format")
+            .arg("{{json .Config}}")
+            .arg(image);
+        let image_config: serde_json::Value =
+            serde_json::from_str(&run_command(&mut config_cmd).await?)?;
+        let runtime = ImageRuntime {
+            env: image_config["Env"]
+                .as_array()
+                .map(|env| {
+                    env.iter()
+                        .filter_map(|e| e.as_str().map(str::to_string))
+                        .collect()
+                })
+                .unwrap_or_default(),
+            working_dir: image_config["WorkingDir"]
+                .as_str()
+                .filter(|dir| !dir.is_empty())
+                .unwrap_or("/")
+                .to_string(),
+        };
+
+        // Export through a temporary container into a staging directory, then rename
+        let staging = rootfs_root.join(format!("{}.tmp-{}", key, std::process::id()));
+        let tarball = rootfs_root.join(format!("{}.tmp-{}.tar", key, std::process::id()));
+        let export_container = format!("jail-ai-export-{}-{}", key, std::process::id());
+
+        let mut create_cmd = Command::new(engine);
+        create_cmd
+            .arg("create")
+            .arg("--name")
+            .arg(&export_container)
+            .arg(image);
+        run_command(&mut create_cmd).await?;
+
+        let mut export_cmd = Command::new(engine);
+        export_cmd
+            .arg("export")
+            .arg("-o")
+            .arg(&tarball)
+            .arg(&export_container);
+        let export_result = run_command(&mut export_cmd).await;
+
+        let mut rm_cmd = Command::new(engine);
+        rm_cmd.arg("rm").arg("-f").arg(&export_container);
+        if let Err(e) = run_command(&mut rm_cmd).await {
+            debug!("Failed to remove export container: {}", e);
+        }
+        export_result?;
+
+        std::fs::create_dir_all(&staging)?;
+        let mut tar_cmd = Command::new("tar");
+        tar_cmd
+            .arg("-x")
+            .arg("--no-same-owner")
+            // Device nodes cannot be created without privileges, bwrap provides /dev
+            .arg("--exclude=dev/*")
+            .arg("--exclude=./dev/*")
+            .arg("-f")
+            .arg(&tarball)
+            .arg("-C")
+            .arg(&staging);
+        let tar_result = run_command(&mut tar_cmd).await;
+        let _ = std::fs::remove_file(&tarball);
+        if let Err(e) = tar_result {
+            remove_tree(&staging).await;
+            return Err(e);
+        }
+
+        std::fs::write(
+            rootfs_root.join(format!("{key}.json")),
+            serde_json::to_string_pretty(&runtime)?,
+        )?;
+
+        if let Err(e) = std::fs::rename(&staging, &rootfs) {
+            // Another invocation exported the same image concurrently
+            debug!("Rootfs rename failed ({}), using existing export", e);
+            remove_tree(&staging).await;
+        }
+
+        Ok(rootfs)
+    }
+
+    /// Directory name for an exported image (`sha256:` prefix stripped, 12 hex chars)
+    fn rootfs_key(image_id: &str) -> String {
+        let id = image_id.trim();
+        let id = id.strip_prefix("sha256:").unwrap_or(id);
+        id.chars().take(12).collect()
+    }
+
+    fn load_image_runtime(rootfs: &Path) -> ImageRuntime {
+        let path = rootfs.with_extension("json");
+        std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    /// Populate a volume directory from the image on first use, like podman does for volumes
+    async fn seed_volume(volume: &Path, source: &Path) -> Result<()> {
+        if volume.exists() {
+            return Ok(());
+        }
+
+        std::fs::create_dir_all(volume)?;
+        if source.is_dir() {
+            debug!("Seeding {} from {}", volume.display(), source.display());
+            let mut cp_cmd = Command::new("cp");
+            cp_cmd
+                .arg("-a")
+                .arg(format!("{}/.", source.display()))
+                .arg(volume);
+            run_command(&mut cp_cmd).await?;
+        }
+        Ok(())
+    }
+
+    /// Build the bwrap arguments for a sandbox running `command`
+    fn build_bwrap_args(
+        config: &JailConfig,
+        rootfs: &Path,
+        runtime: &ImageRuntime,
+        root_entries: &[(String, Option<PathBuf>)],
+        state_dir: &Path,
+        command: &[String],
+        options: &ExecOptions,
+    ) -> Vec<String> {
+        let mut args = vec![
+            "--die-with-parent".to_string(),
+            // Maps the host UID/GID to the same IDs (keep-id equivalent)
+            "--unshare-user".to_string(),
+            "--unshare-pid".to_string(),
+            "--unshare-ipc".to_string(),
+            "--unshare-uts".to_string(),
+            "--unshare-cgroup-try".to_string(),
+            "--hostname".to_string(),
+            "jail-ai".to_string(),
+        ];
+
+        if !options.tty {
+            // Detach from the controlling terminal (prevents TIOCSTI injection)
+            args.push("--new-session".to_string());
+        }
+
+        // The image filesystem is always read-only and privilege escalation is always
+        // forbidden by bwrap; hardening also drops the capabilities of root in the sandbox
+        if config.hardened {
+            args.push("--cap-drop".to_string());
+            args.push("ALL".to_string());
+        }
+
+        // IDs inside the user namespace (numeric only: there is no user database lookup)
+        if let Some((uid, gid)) = options.user.as_deref().and_then(Self::parse_user) {
+            args.push("--uid".to_string());
+            args.push(uid.to_string());
+            if let Some(gid) = gid {
+                args.push("--gid".to_string());
+                args.push(gid.to_string());
+            }
+        }
+
+        // Network settings: host shares the namespace, everything else gets its own
+        // (private networking is provided by slirp4netns once the sandbox is started)
+        if !config.network.host {
+            args.push("--unshare-net".to_string());
+        }
+
+        // Read-only image filesystem on top of a tmpfs root, so mount points can be created
+        args.push("--tmpfs".to_string());
+        args.push("/".to_string());
+        for (entry, link_target) in root_entries {
+            let target = format!("/{entry}");
+            match link_target {
+                Some(link) => {
+                    args.push("--symlink".to_string());
+                    args.push(link.display().to_string());
+                    args.push(target);
+                }
+                None => {
+                    args.push("--ro-bind".to_string());
+                    args.push(rootfs.join(entry).display().to_string());
+                    args.push(target);
+                }
+            }
+        }
+        args.extend(
+            [
+                "--dev", "/dev", "--proc", "/proc", "--tmpfs", "/tmp", "--tmpfs", "/run",
+                "--tmpfs", "/var/tmp",
+            ]
+            .iter()
+            .map(|s| s.to_string()),
+        );
+
+        // Size-capped tmpfs mounts (shadowing the defaults above) and /dev/shm
+        let shm = config.limits.shm_size_mb.map(|size_mb| TmpfsMount {
+            target: PathBuf::from("/dev/shm"),
+            size_mb,
+        });
+        for tmpfs in config.limits.tmpfs.iter().chain(shm.as_ref()) {
+            args.push("--size".to_string());
+            args.push((tmpfs.size_mb * 1024 * 1024).to_string());
+            args.push("--tmpfs".to_string());
+            args.push(tmpfs.target.display().to_string());
+        }
+
+        // DNS: slirp4netns serves 10.0.2.3, host networking uses the host resolver
+        if config.network.enabled && !config.network.host {
+            args.push("--ro-bind".to_string());
+            args.push(
+                state_dir
+                    .join("jails")
+                    .join(&config.name)
+                    .join("resolv.conf")
+                    .display()
+                    .to_string(),
+            );
+            args.push("/etc/resolv.conf".to_string());
+        } else if config.network.host {
+            args.push("--ro-bind-try".to_string());
+            args.push("/etc/resolv.conf".to_string());
+            args.push("/etc/resolv.conf".to_string());
+        }
+
+        // Persistent /home/agent, agent-specific (not shared across different agents)
+        args.push("--bind".to_string());
+        args.push(
+            state_dir
+                .join("volumes")
+                .join(super::home_volume_name(config))
+                .display()
+                .to_string(),
+        );
+        args.push("/home/agent".to_string());
+
+        // Per-jail Nix store shared across all agents working on the same project
+        if image_uses_nix(&config.base_image) {
+            args.push("--bind".to_string());
+            args.push(
+                state_dir
+                    .join("volumes")
+                    .join(format!("{}__nix", extract_base_name(&config.name)))
+                    .display()
+                    .to_string(),
+            );
+            args.push("/nix".to_string());
+        }
+
+        // Bind mounts
+        for mount in &config.bind_mounts {
+            match super::mount_mode(config, mount) {
+                MountMode::ReadOnly => {
+                    args.push("--ro-bind".to_string());
+                    args.push(mount.source.display().to_string());
+                }
+                MountMode::ReadWrite => {
+                    args.push("--bind".to_string());
+                    args.push(mount.source.display().to_string());
+                }
+                // Every session mounts its own overlay on the same upper directory
+                MountMode::Overlay(dirs) => {
+                    args.push("--overlay-src".to_string());
+                    args.push(mount.source.display().to_string());
+                    args.push("--overlay".to_string());
+                    args.push(dirs.upper.display().to_string());
+                    args.push(dirs.work.display().to_string());
+                }
+            }
+            args.push(mount.target.display().to_string());
+        }
+
+        // Environment: image env, then jail env, like `podman exec`
+        args.push("--clearenv".to_string());
+        let image_env = runtime.env.iter().filter_map(|e| e.split_once('='));
+        let jail_env = config
+            .environment
+            .iter()
+            .map(|(k, v)| (k.as_str(), v.as_str()));
+        for (key, value) in image_env.chain(jail_env) {
+            args.push("--setenv".to_string());
+            args.push(key.to_string());
+            args.push(value.to_string());
+        }
+        args.push("--setenv".to_string());
+        args.push("HOME".to_string());
+        args.push("/home/agent".to_string());
+        for (key, value) in &options.env {
+            args.push("--setenv".to_string());
+            args.push(key.clone());
+            args.push(value.clone());
+        }
+
+        args.push("--chdir".to_string());
+        args.push(
+            options
+                .workdir
+                .as_ref()
+                .map(|dir| dir.display().to_string())
+                .unwrap_or_else(|| runtime.working_dir.clone()),
+        );
+
+        args.push("--".to_string());
+        args.extend(command.iter().cloned());
+        args
+    }
+
+    /// Parse a numeric `UID[:GID]` user specification
+    fn parse_user(user: &str) -> Option<(u32, Option<u32>)> {
+        match user.split_once(':') {
+            Some((uid, gid)) => Some((uid.parse().ok()?, Some(gid.parse().ok()?))),
+            None => Some((user.parse().ok()?, None)),
+        }
+    }
+
+    /// Host location of a path inside the jail
+    ///
+    /// Only bind mounts and the home and Nix volumes are writable host directories;
+    /// the image filesystem itself is read-only.
+    fn host_path(config: &JailConfig, state_dir: &Path, path: &Path) -> Option<(PathBuf, bool)> {
+        let mut mounts: Vec<(PathBuf, PathBuf, bool)> = config
+            .bind_mounts
+            .iter()
+            .map(|m| (m.target.clone(), m.source.clone(), m.readonly))
+            .collect();
+        let volumes = state_dir.join("volumes");
+        mounts.push((
+            PathBuf::from("/home/agent"),
+            volumes.join(super::home_volume_name(config)),
+            false,
+        ));
+        if image_uses_nix(&config.base_image) {
+            mounts.push((
+                PathBuf::from("/nix"),
+                volumes.join(format!("{}__nix", extract_base_name(&config.name))),
+                false,
+            ));
+        }
+
+        // The most specific mount wins (e.g. a file mounted inside /home/agent)
+        mounts
+            .into_iter()
+            .filter_map(|(target, source, readonly)| {
+                let relative = path.strip_prefix(&target).ok()?;
+                let host = if relative.as_os_str().is_empty() {
+                    source
+                } else {
+                    source.join(relative)
+                };
+                Some((target.components().count(), host, readonly))
+            })
+            .max_by_key(|(depth, _, _)| *depth)
+            .map(|(_, host, readonly)| (host, readonly))
+    }
+
+    /// PIDs of the sandboxes currently running for a jail, removing stale markers
+    fn session_pids(name: &str) -> Result<Vec<u32>> {
+        let run_dir = Self::jail_dir(name)?.join("run");
+        let Ok(entries) = std::fs::read_dir(&run_dir) else {
+            return Ok(Vec::new());
+        };
+
+        let mut pids = Vec::new();
+        for entry in entries.flatten() {
+            let file_name = entry.file_name().to_string_lossy().to_string();
+            if let Ok(pid) = file_name.parse::<u32>() {
+                if Path::new(&format!("/proc/{pid}")).exists() {
+                    pids.push(pid);
+                } else {
+                    // Stale marker from a session that was killed
+                    let _ = std::fs::remove_file(entry.path());
+                }
+            }
+        }
+        Ok(pids)
+    }
+
+    fn unsupported(operation: &str) -> JailError {
+        JailError::Backend(format!(
+            "{operation} is not supported by the bubblewrap backend"
+        ))
+    }
+
+    /// Wrap the sandbox in a transient systemd scope when resource limits are set,
+    /// and in prlimit when ulimits are set
+    fn limit_prefix(config: &JailConfig) -> Vec<String> {
+        let limits = &config.limits;
+        let mut properties = Vec::new();
+        if let Some(memory_mb) = limits.memory_mb {
+            properties.push(format!("MemoryMax={memory_mb}M"));
+        }
+        if let Some(memory_swap_mb) = limits.memory_swap_mb {
+            // systemd caps swap alone, while engines cap memory plus swap
+            let swap_mb = memory_swap_mb.saturating_sub(limits.memory_mb.unwrap_or(0));
+            properties.push(format!("MemorySwapMax={swap_mb}M"));
+        }
+        if let Some(cpu_quota) = limits.cpu_quota {
+            properties.push(format!("CPUQuota={cpu_quota}%"));
+        }
+        if let Some(pids) = limits.pids_limit {
+            properties.push(format!("TasksMax={pids}"));
+        }
+        if let Some(weight) = limits.blkio_weight {
+            properties.push(format!("IOWeight={weight}"));
+        }
+
+        let mut prefix = Vec::new();
+        if !properties.is_empty() {
+            if which::which("systemd-run").is_ok() {
+                prefix.extend(
+                    ["systemd-run", "--user", "--scope", "--quiet"]
+                        .iter()
+                        .map(|s| s.to_string()),
+                );
+                for property in properties {
+                    prefix.push("-p".to_string());
+                    prefix.push(property);
+                }
+            } else {
+                warn!(
+                    "systemd-run not found: resource limits are not enforced for bubblewrap jails"
+                );
+            }
+        }
+
+        if !limits.ulimits.is_empty() {
+            if which::which("prlimit").is_ok() {
+                prefix.push("prlimit".to_string());
+                for ulimit in &limits.ulimits {
+                    prefix.push(format!("--{}={}:{}", ulimit.name, ulimit.soft, ulimit.hard));
+                }
+            } else {
+                warn!("prlimit not found: ulimits are not enforced for bubblewrap jails");
+            }
+        }
+        prefix
+    }
+
+    /// List the top-level rootfs entries to expose, with symlink targets
+    fn root_entries(rootfs: &Path) -> Result<Vec<(String, Option<PathBuf>)>> {
+        let mut entries = Vec::new();
+        for entry in std::fs::read_dir(rootfs)? {
+            let entry = entry?;
+            let name = entry.file_name().to_string_lossy().to_string();
+            if SKIPPED_ROOT_ENTRIES.contains(&name.as_str()) {
+                continue;
+            }
+            let link_target = if entry.file_type()?.is_symlink() {
+                Some(std::fs::read_link(entry.path())?)
+            } else {
+                None
+            };
+            entries.push((name, link_target));
+        }
+        entries.sort();
+        Ok(entries)
+    }
+
+    /// Attach slirp4netns to the sandbox network namespace and forward ports
+    async fn start_slirp(
+        config: &JailConfig,
+        child_pid: u32,
+        jail_dir: &Path,
+    ) -> Result<tokio::process::Child> {
+        let api_socket = jail_dir.join("run").join(format!("slirp-{child_pid}.sock"));
+
+        let mut cmd = Command::new("slirp4netns");
+        cmd.arg("--configure")
+            .arg("--mtu=65520")
+            // Keep host services unreachable through 10.0.2.2
+            .arg("--disable-host-loopback")
+            .arg("--api-socket")
+            .arg(&api_socket)
+            .arg(child_pid.to_string())
+            .arg("tap0")
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .kill_on_drop(true);
+
+        let slirp = cmd
+            .spawn()
+            .map_err(|e| JailError::Backend(format!("Failed to start slirp4netns: {e}")))?;
+
+        if !config.port_mappings.is_empty() {
+            // The API socket appears once slirp4netns has configured the interface
+            let mut attempts = 0;
+            while !api_socket.exists() && attempts < 100 {
+                tokio::time::sleep(tokio::time::Duration::from_millis(20)).await;
+                attempts += 1;
+            }
+
+            for port in &config.port_mappings {
+                for (host_port, container_port) in port.ports() {
+                    let request = serde_json::json!({
+                        "execute": "add_hostfwd",
+                        "arguments": {
+                            "proto": port.protocol,
+                            "host_addr": port.host_ip,
+                            "host_port": host_port,
+                            "guest_port": container_port,
+                        }
+                    });
+                    Self::slirp_api_call(&api_socket, &request).await?;
+                    debug!(
+                        "Forwarding host port {}:{} to {}/{}",
+                        port.host_ip, host_port, container_port, port.protocol
+                    );
+                }
+            }
+        }
+
+        Ok(slirp)
+    }
+
+    async fn slirp_api_call(api_socket: &Path, request: &serde_json::Value) -> Result<()> {
+        let api_socket = api_socket.to_path_buf();
+        let request = request.to_string();
+
+        tokio::task::spawn_blocking(move || -> Result<()> {
+            use std::io::{Read, Write};
+            let mut stream = std::os::unix::net::UnixStream::connect(&api_socket).map_err(|e| {
+                JailError::Backend(format!("Failed to connect to slirp4netns API: {e}"))
+            })?;
+            stream.write_all(request.as_bytes())?;
+            stream.shutdown(std::net::Shutdown::Write)?;
+
+            let mut response = String::new();
+            stream.read_to_string(&mut response)?;
+            if response.contains("\"error\"") {
+                return Err(JailError::Backend(format!(
+                    "slirp4netns rejected port forwarding: {}",
+                    response.trim()
+                )));
+            }
+            Ok(())
+        })
+        .await
+        .map_err(|e| JailError::Backend(format!("slirp4netns API task failed: {e}")))?
+    }
+
+    /// Start a sandbox running `command`, with the terminal attached or output piped
+    async fn spawn_sandbox(
+        &self,
+        name: &str,
+        command: &[String],
+        options: &ExecOptions,
+    ) -> Result<(Child, SandboxSession)> {
+        let config = Self::load_config(name)?;
+        let state_dir = Self::state_dir()?;
+        let jail_dir = Self::jail_dir(name)?;
+        let rootfs = std::fs::canonicalize(jail_dir.join("rootfs")).map_err(|e| {
+            JailError::Backend(format!(
+                "Rootfs of jail '{name}' is missing ({e}), recreate it with --upgrade"
+            ))
+        })?;
+        let runtime = Self::load_image_runtime(&rootfs);
+        let root_entries = Self::root_entries(&rootfs)?;
+
+        let private_network = config.network.enabled && !config.network.host;
+        let run_dir = jail_dir.join("run");
+        std::fs::create_dir_all(&run_dir)?;
+
+        let mut bwrap_args = Vec::new();
+        let session_files = if private_network {
+            // bwrap reports the sandbox PID on --info-fd and waits on --block-fd
+            // until slirp4netns has configured the network
+            let info_file = run_dir.join(format!("info-{}", std::process::id()));
+            let block_fifo = run_dir.join(format!("block-{}", std::process::id()));
+            let _ = std::fs::remove_file(&block_fifo);
+            let mut mkfifo = Command::new("mkfifo");
+            mkfifo.arg(&block_fifo);
+            run_command(&mut mkfifo).await?;
+
+            bwrap_args.extend(
+                ["--info-fd", "3", "--block-fd", "4"]
+                    .iter()
+                    .map(|s| s.to_string()),
+            );
+            Some((info_file, block_fifo))
+        } else {
+            None
+        };
+        bwrap_args.extend(Self::build_bwrap_args(
+            &config,
+            &rootfs,
+            &runtime,
+            &root_entries,
+            &state_dir,
+            command,
+            options,
+        ));
+
+        let mut argv = Self::limit_prefix(&config);
+        argv.push("bwrap".to_string());
+        argv.extend(bwrap_args);
+
+        let mut cmd = if let Some((info_file, block_fifo)) = &session_files {
+            // The shell opens the extra descriptors; opening the FIFO blocks until we write to it
+            let mut cmd = Command::new("sh");
+            cmd.arg("-c")
+                .arg("info=$1; block=$2; shift 2; exec \"$@\" 3>\"$info\" 4<\"$block\"")
+                .arg("sh")
+                .arg(info_file)
+                .arg(block_fifo)
+                .args(&argv);
+            cmd
+        } else {
+            let mut cmd = Command::new(&argv[0]);
+            cmd.args(&argv[1..]);
+            cmd
+        };
+
+        let interactive = options.is_attached();
+        if interactive {
+            cmd.stdin(Stdio::inherit())
+                .stdout(Stdio::inherit())
+                .stderr(Stdio::inherit());
+        } else {
+            cmd.stdin(Stdio::null())
+                .stdout(Stdio::piped())
+                .stderr(Stdio::piped());
+        }
+
+        debug!("Running bubblewrap sandbox: {:?}", argv);
+        let child = cmd
+            .spawn()
+            .map_err(|e| JailError::Backend(format!("Failed to start bwrap: {e}")))?;
+
+        // Session marker for is_running
+        let marker = child.id().map(|pid| run_dir.join(pid.to_string()));
+        if let Some(marker) = &marker {
+            std::fs::write(marker, "")?;
+        }
+
+        let mut slirp = None;
+        if let Some((info_file, block_fifo)) = &session_files {
+            // Unblock the shell redirection, then wait for bwrap to report the sandbox PID
+            let fifo = block_fifo.clone();
+            let writer = tokio::task::spawn_blocking(move || {
+                std::fs::OpenOptions::new().write(true).open(fifo)
+            });
+
+            let mut child_pid = None;
+            for _ in 0..250 {
+                if let Some(pid) = std::fs::read_to_string(info_file)
+                    .ok()
+                    .and_then(|info| Self::parse_child_pid(&info))
+                {
+                    child_pid = Some(pid);
+                    break;
+                }
+                tokio::time::sleep(tokio::time::Duration::from_millis(20)).await;
+            }
+
+            let mut writer = writer
+                .await
+                .map_err(|e| JailError::Backend(format!("Failed to open block FIFO: {e}")))??;
+            let child_pid = child_pid.ok_or_else(|| {
+                JailError::Backend("bwrap did not report the sandbox PID".to_string())
+            })?;
+
+            slirp = Some(Self::start_slirp(&config, child_pid, &jail_dir).await?);
+
+            // Release the sandbox: bwrap continues once the block fd is readable
+            use std::io::Write;
+            writer.write_all(b"1")?;
+            drop(writer);
+            let _ = std::fs::remove_file(info_file);
+            let _ = std::fs::remove_file(block_fifo);
+        }
+
+        Ok((
+            child,
+            SandboxSession {
+                _slirp: slirp,
+                marker,
+            },
+        ))
+    }
+
+    /// Run `command` in a new sandbox until it exits
+    async fn run_sandbox(
+        &self,
+        name: &str,
+        command: &[String],
+        options: &ExecOptions,
+    ) -> Result<ExecResult> {
+        let started = Instant::now();
+        let (child, session) = self.spawn_sandbox(name, command, options).await?;
+
+        let result = if options.is_attached() {
+            let mut child = child;
+            let status = child.wait().await.map_err(|e| {
+                JailError::Backend(format!("Failed to execute interactive command: {e}"))
+            })?;
+            ExecResult::new(status, String::new(), String::new(), started)
+        } else {
+            let output = child
+                .wait_with_output()
+                .await
+                .map_err(|e| JailError::Backend(format!("Failed to execute command: {e}")))?;
+            ExecResult::new(
+                output.status,
+                String::from_utf8_lossy(&output.stdout).to_string(),
+                String::from_utf8_lossy(&output.stderr).to_string(),
+                started,
+            )
+        };
+
+        drop(session);
+        Ok(result)
+    }
+
+    /// Reject users bwrap cannot map (names would need the image's passwd database)
+    fn check_user(options: &ExecOptions) -> Result<()> {
+        match &options.user {
+            Some(user) if Self::parse_user(user).is_none() => Err(JailError::Config(format!(
+                "Invalid user '{user}': the bubblewrap backend only accepts numeric UID[:GID]"
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    /// Extract `child-pid` from bwrap's `--info-fd` JSON
+    fn parse_child_pid(info: &str) -> Option<u32> {
+        serde_json::from_str::<serde_json::Value>(info)
+            .ok()?
+            .get("child-pid")?
+            .as_u64()
+            .map(|pid| pid as u32)
+    }
+}
+
+impl Default for BubblewrapBackend {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Remove a directory tree, including read-only directories (e.g. an exported Nix store)
+async fn remove_tree(path: &Path) {
+    if !path.exists() {
+        return;
+    }
+
+    let mut chmod_cmd = Command::new("chmod");
+    chmod_cmd.arg("-R").arg("u+w").arg(path);
+    if let Err(e) = run_command(&mut chmod_cmd).await {
+        debug!("Failed to make {} writable: {}", path.display(), e);
+    }
+
+    match tokio::fs::remove_dir_all(path).await {
+        Ok(()) => debug!("Removed {}", path.display()),
+        Err(e) => debug!("Could not remove {}: {}", path.display(), e),
+    }
+}
+
+#[async_trait]
+impl JailBackend for BubblewrapBackend {
+    async fn create(&self, config: &JailConfig) -> Result<()> {
+        info!("Creating bubblewrap jail: {}", config.name);
+
+        if self.exists(&config.name).await? {
+            if !config.upgrade {
+                return Err(JailError::AlreadyExists(config.name.clone()));
+            }
+            info!(
+                "Upgrade enabled: removing existing jail '{}' (home is kept)",
+                config.name
+            );
+            self.remove(&config.name, false).await?;
+        }
+
+        let private_network = config.network.enabled && !config.network.host;
+        if private_network && which::which("slirp4netns").is_err() {
+            return Err(JailError::Backend(
+                "slirp4netns is required for private networking with the bubblewrap backend"
+                    .to_string(),
+            ));
+        }
+
+        if config.block_host && private_network {
+            warn!("eBPF host blocking needs a container cgroup and is not available with bubblewrap; host loopback stays unreachable through slirp4netns");
+        }
+        if config.podman_socket {
+            warn!("--podman is not supported with the bubblewrap backend");
+        }
+
+        // Build or pull the image with podman/docker, then export it once
+        let actual_image = super::resolve_image(config).await?;
+        if config.seccomp {
+            warn!("bwrap only loads compiled seccomp filters: the seccomp profile is not applied");
+        }
+        if config.cow {
+            crate::cow::check_mounts(config)?;
+            CowDirs::for_jail(&config.name)?.create()?;
+        }
+        let rootfs = Self::ensure_rootfs(&actual_image).await?;
+
+        let jail_dir = Self::jail_dir(&config.name)?;
+        std::fs::create_dir_all(jail_dir.join("run"))?;
+        let rootfs_link = jail_dir.join("rootfs");
+        let _ = std::fs::remove_file(&rootfs_link);
+        std::os::unix::fs::symlink(&rootfs, &rootfs_link)?;
+        std::fs::write(jail_dir.join("resolv.conf"), "nameserver 10.0.2.3\n")?;
+
+        Self::seed_volume(
+            &Self::volume_dir(&super::home_volume_name(config))?,
+            &rootfs.join("home").join("agent"),
+        )
+        .await?;
+        if image_uses_nix(&actual_image) {
+            Self::seed_volume(
+                &Self::volume_dir(&format!("{}__nix", extract_base_name(&config.name)))?,
+                &rootfs.join("nix"),
+            )
+            .await?;
+        }
+
+        let mut stored = config.clone();
+        stored.backend = BackendType::Bubblewrap;
+        stored.base_image = actual_image;
+        stored.upgrade = false;
+        stored.verbose = false;
+        std::fs::write(
+            jail_dir.join("config.json"),
+            serde_json::to_string_pretty(&stored)?,
+        )?;
+
+        // Pre-create directories if needed (for worktree support)
+        for dir in &config.pre_create_dirs {
+            debug!("Creating directory: {}", dir.display());
+            self.exec(
+                &config.name,
+                &[
+                    "mkdir".to_string(),
+                    "-p".to_string(),
+                    dir.display().to_string(),
+                ],
+                false,
+            )
+            .await?;
+        }
+
+        info!("Jail {} created successfully", config.name);
+        Ok(())
+    }
+
+    async fn remove(&self, name: &str, remove_volume: bool) -> Result<()> {
+        info!("Removing bubblewrap jail: {}", name);
+
+        let jail_dir = Self::jail_dir(name)?;
+        if !jail_dir.exists() {
+            return Err(JailError::NotFound(format!("Jail '{name}' not found")));
+        }
+        let home_volume = super::jail_home_volume(self, name).await;
+        remove_tree(&jail_dir).await;
+
+        if remove_volume {
+            let nix_volume = format!("{}__nix", extract_base_name(name));
+            // A home shared with the main jail is kept while a jail still uses it
+            let mut home_in_use = false;
+            for other in self.list_all().await? {
+                if Self::load_config(&other)
+                    .is_ok_and(|config| super::home_volume_name(&config) == home_volume)
+                {
+                    home_in_use = true;
+                    break;
+                }
+            }
+            if !home_in_use {
+                remove_tree(&Self::volume_dir(&home_volume)?).await;
+            }
+
+            // The nix volume is shared by the agents of a project
+            let base = format!("{}__", extract_base_name(name));
+            let nix_in_use = self
+                .list_all()
+                .await?
+                .iter()
+                .any(|other| other.starts_with(&base));
+            if !nix_in_use {
+                remove_tree(&Self::volume_dir(&nix_volume)?).await;
+            }
+
+            info!(
+                "Jail {} removed (attempted to remove volumes {}, {})",
+                name, home_volume, nix_volume
+            );
+        } else {
+            info!("Jail {} removed", name);
+        }
+
+        Ok(())
+    }
+
+    async fn exec_with(
+        &self,
+        name: &str,
+        command: &[String],
+        options: &ExecOptions,
+    ) -> Result<ExecResult> {
+        debug!(
+            "Executing command in jail {}: {:?} ({:?})",
+            name, command, options
+        );
+        Self::check_user(options)?;
+        self.run_sandbox(name, command, options).await
+    }
+
+    async fn exec_stream(
+        &self,
+        name: &str,
+        command: &[String],
+        options: &ExecOptions,
+    ) -> Result<ExecStream> {
+        debug!(
+            "Streaming command in jail {}: {:?} ({:?})",
+            name, command, options
+        );
+        Self::check_user(options)?;
+        let detached = ExecOptions {
+            tty: false,
+            stdin: false,
+            ..options.clone()
+        };
+        let (child, session) = self.spawn_sandbox(name, command, &detached).await?;
+        Ok(ExecStream::from_child(child).with_session(Box::new(session)))
+    }
+
+    fn exec_argv(
+        &self,
+        _name: &str,
+        _command: &[String],
+        _options: &ExecOptions,
+    ) -> Result<Vec<String>> {
+        // Private networking needs slirp4netns attached after bwrap starts,
+        // which a plain command line cannot express
+        Err(Self::unsupported("Running a terminal pane"))
+    }
+
+    async fn exists(&self, name: &str) -> Result<bool> {
+        Ok(Self::jail_dir(name)?.join("config.json").exists())
+    }
+
+    async fn list_all(&self) -> Result<Vec<String>> {
+        let jails_dir = Self::state_dir()?.join("jails");
+        let mut jails = Vec::new();
+
+        if let Ok(entries) = std::fs::read_dir(&jails_dir) {
+            for entry in entries.flatten() {
+                let name = entry.file_name().to_string_lossy().to_string();
+                if name.starts_with("jail__") && entry.path().join("config.json").exists() {
+                    jails.push(name);
+                }
+            }
+        }
+
+        jails.sort();
+        debug!("Found {} bubblewrap jails", jails.len());
+        Ok(jails)
+    }
+
+    async fn list_summaries(&self) -> Result<Vec<JailSummary>> {
+        let names = self.list_all().await?;
+        if names.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let engine = BackendType::Bubblewrap.image_engine().command();
+        let image_ids = super::engine_image_ids(engine).await.unwrap_or_else(|e| {
+            debug!("Could not list images: {}", e);
+            Default::default()
+        });
+
+        let mut summaries = Vec::new();
+        for name in names {
+            let config = Self::load_config(&name)?;
+            // The exported rootfs is keyed by image ID: a different key means the image was rebuilt
+            let outdated = match (
+                std::fs::read_link(Self::jail_dir(&name)?.join("rootfs")),
+                image_ids.get(&config.base_image),
+            ) {
+                (Ok(rootfs), Some(current)) => {
+                    rootfs.file_name() != Some(Self::rootfs_key(current).as_ref())
+                }
+                _ => false,
+            };
+
+            let home_size =
+                super::dir_size(Self::volume_dir(&super::home_volume_name(&config))?).await;
+
+            summaries.push(JailSummary {
+                agent: crate::agent_commands::extract_agent_name(&name).to_string(),
+                workspace: super::workspace_source(&config),
+                running: !Self::session_pids(&name)?.is_empty(),
+                image: config.base_image,
+                outdated,
+                // slirp4netns isolates the host instead of eBPF, and sandboxes have no start time
+                host_blocking: false,
+                uptime_secs: None,
+                home_size,
+                name,
+            });
+        }
+        Ok(summaries)
+    }
+
+    async fn inspect(&self, name: &str) -> Result<JailConfig> {
+        Self::load_config(name)
+    }
+
+    async fn is_running(&self, name: &str) -> Result<bool> {
+        Ok(!Self::session_pids(name)?.is_empty())
+    }
+
+    async fn start(&self, name: &str) -> Result<()> {
+        // Nothing to start: every exec runs its own sandbox
+        if !self.exists(name).await? {
+            return Err(JailError::NotFound(format!("Jail '{name}' not found")));
+        }
+        debug!(
+            "Bubblewrap jail {} has no long-running process to start",
+            name
+        );
+        Ok(())
+    }
+
+    async fn stop(&self, name: &str) -> Result<()> {
+        if !self.exists(name).await? {
+            return Err(JailError::NotFound(format!("Jail '{name}' not found")));
+        }
+
+        // Sandboxes die with bwrap (--die-with-parent)
+        for pid in Self::session_pids(name)? {
+            info!("Stopping bubblewrap session {} of jail {}", pid, name);
+            let mut kill_cmd = Command::new("kill");
+            kill_cmd.arg("-TERM").arg(pid.to_string());
+            if let Err(e) = run_command(&mut kill_cmd).await {
+                debug!("Failed to stop session {}: {}", pid, e);
+            }
+        }
+        Ok(())
+    }
+
+    async fn restart(&self, name: &str) -> Result<()> {
+        // Nothing runs between sessions: the next exec starts a fresh sandbox
+        self.stop(name).await
+    }
+
+    async fn pause(&self, _name: &str) -> Result<()> {
+        Err(Self::unsupported("Pausing a jail"))
+    }
+
+    async fn unpause(&self, _name: &str) -> Result<()> {
+        Err(Self::unsupported("Unpausing a jail"))
+    }
+
+    async fn logs(&self, _name: &str, _options: &LogOptions) -> Result<()> {
+        // Sessions run attached to the caller, there is no main process output to keep
+        Err(Self::unsupported("Reading logs"))
+    }
+
+    async fn stats(&self, _name: &str) -> Result<JailStats> {
+        Err(Self::unsupported("Reading resource usage"))
+    }
+
+    async fn port_mappings(&self, name: &str) -> Result<Vec<PortMapping>> {
+        // slirp4netns forwards the configured ports while a sandbox runs
+        if !self.is_running(name).await? {
+            return Ok(Vec::new());
+        }
+        Ok(self.inspect(name).await?.port_mappings)
+    }
+
+    async fn active_sessions(&self, name: &str) -> Result<usize> {
+        // Every sandbox runs a user command, there is no keep-alive process
+        Ok(Self::session_pids(name)?.len())
+    }
+
+    async fn list_volumes(&self) -> Result<Vec<VolumeSummary>> {
+        let volumes_dir = Self::state_dir()?.join("volumes");
+        let Ok(entries) = std::fs::read_dir(&volumes_dir) else {
+            return Ok(Vec::new());
+        };
+
+        let mut volumes = Vec::new();
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if super::is_jail_volume(&name) {
+                let size = super::dir_size(entry.path()).await;
+                volumes.push(VolumeSummary { name, size });
+            }
+        }
+        volumes.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(volumes)
+    }
+
+    async fn remove_volume(&self, volume: &str) -> Result<()> {
+        let volume_dir = Self::volume_dir(volume)?;
+        if !volume_dir.exists() {
+            return Err(JailError::NotFound(format!("Volume '{volume}' not found")));
+        }
+        remove_tree(&volume_dir).await;
+        Ok(())
+    }
+
+    async fn export_volume(&self, volume: &str, archive: &Path) -> Result<()> {
+        let volume_dir = Self::volume_dir(volume)?;
+        if !volume_dir.exists() {
+            return Err(JailError::NotFound(format!("Volume '{volume}' not found")));
+        }
+        let file = super::create_archive(archive)?;
+        let mut cmd = Command::new("tar");
+        cmd.arg("-C").arg(&volume_dir).args(["-cf", "-", "."]);
+        super::run_redirected(&mut cmd, Stdio::null(), Stdio::from(file)).await
+    }
+
+    async fn import_volume(&self, volume: &str, archive: &Path) -> Result<()> {
+        let volume_dir = Self::volume_dir(volume)?;
+        std::fs::create_dir_all(&volume_dir)?;
+        let mut cmd = Command::new("tar");
+        cmd.arg("-C").arg(&volume_dir).arg("-xf").arg(archive);
+        run_command(&mut cmd).await?;
+        Ok(())
+    }
+
+    async fn reset_volume(&self, volume: &str, keep: &[String]) -> Result<()> {
+        let volume_dir = Self::volume_dir(volume)?;
+        if !volume_dir.exists() {
+            return Err(JailError::NotFound(format!("Volume '{volume}' not found")));
+        }
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c")
+            .arg(super::RESET_VOLUME_SCRIPT)
+            .arg("sh")
+            .arg(&volume_dir)
+            .args(keep);
+        run_command(&mut cmd).await?;
+        Ok(())
+    }
+
+    async fn commit(&self, _name: &str, _image: &str, _labels: &[(String, String)]) -> Result<()> {
+        Err(Self::unsupported("Committing a jail to an image"))
+    }
+
+    async fn list_images(&self) -> Result<Vec<ImageSummary>> {
+        super::engine_list_images(BackendType::Bubblewrap.image_engine().command()).await
+    }
+
+    async fn remove_image(&self, image: &str) -> Result<()> {
+        // Exported root filesystems are kept: jails created from the image still use them
+        super::engine_remove_image(BackendType::Bubblewrap.image_engine().command(), image).await
+    }
+
+    async fn copy_in(&self, name: &str, source: &Path, target: &Path) -> Result<()> {
+        let config = Self::load_config(name)?;
+        let (host_target, readonly) = Self::host_path(&config, &Self::state_dir()?, target)
+            .ok_or_else(|| {
+                JailError::Backend(format!(
+                    "{} is on the read-only image filesystem of the jail",
+                    target.display()
+                ))
+            })?;
+        if readonly {
+            return Err(JailError::Backend(format!(
+                "{} is mounted read-only",
+                target.display()
+            )));
+        }
+
+        let mut cp_cmd = Command::new("cp");
+        cp_cmd.arg("-a").arg(source).arg(&host_target);
+        run_command(&mut cp_cmd).await?;
+        Ok(())
+    }
+
+    async fn copy_out(&self, name: &str, source: &Path, target: &Path) -> Result<()> {
+        let config = Self::load_config(name)?;
+        let state_dir = Self::state_dir()?;
+        let host_source = match Self::host_path(&config, &state_dir, source) {
+            Some((host_source, _)) => host_source,
+            None => {
+                // Read straight from the exported image
+                let rootfs = std::fs::canonicalize(Self::jail_dir(name)?.join("rootfs"))?;
+                rootfs.join(source.strip_prefix("/").unwrap_or(source))
+            }
+        };
+
+        let mut cp_cmd = Command::new("cp");
+        cp_cmd.arg("-a").arg(&host_source).arg(target);
+        run_command(&mut cp_cmd).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_config() -> JailConfig {
+        JailConfig {
+            name: "jail__project__abc12345__claude".to_string(),
+            backend: BackendType::Bubblewrap,
+            base_image: "localhost/jail-ai-agent-claude:base-nix".to_string(),
+            environment: vec![("TEST".to_string(), "value".to_string())],
+            ..Default::default()
+        }
+    }
+
+    fn args_for(config: &JailConfig) -> Vec<String> {
+        let runtime = ImageRuntime {
+            env: vec!["PATH=/usr/bin".to_string()],
+            working_dir: "/workspace".to_string(),
+        };
+        let root_entries = vec![
+            ("bin".to_string(), Some(PathBuf::from("usr/bin"))),
+            ("etc".to_string(), None),
+            ("usr".to_string(), None),
+        ];
+        BubblewrapBackend::build_bwrap_args(
+            config,
+            Path::new("/state/rootfs/abc"),
+            &runtime,
+            &root_entries,
+            Path::new("/state"),
+            &["zsh".to_string()],
+            &ExecOptions::interactive(true),
+        )
+    }
+
+    fn has_triple(args: &[String], a: &str, b: &str, c: &str) -> bool {
+        args.windows(3).any(|w| w[0] == a && w[1] == b && w[2] == c)
+    }
+
+    #[test]
+    fn test_rootfs_key() {
+        assert_eq!(
+            BubblewrapBackend::rootfs_key("sha256:0123456789abcdef\n"),
+            "0123456789ab"
+        );
+        assert_eq!(
+            BubblewrapBackend::rootfs_key("0123456789abcdef"),
+            "0123456789ab"
+        );
+    }
+
+    #[test]
+    fn test_parse_child_pid() {
+        assert_eq!(
+            BubblewrapBackend::parse_child_pid(r#"{ "child-pid": 4242, "cgroup-namespace": 1 }"#),
+            Some(4242)
+        );
+        assert_eq!(BubblewrapBackend::parse_child_pid("{"), None);
+    }
+
+    #[test]
+    fn test_build_bwrap_args_filesystem() {
+        let args = args_for(&test_config());
+
+        assert!(has_triple(
+            &args,
+            "--ro-bind",
+            "/state/rootfs/abc/etc",
+            "/etc"
+        ));
+        assert!(has_triple(&args, "--symlink", "usr/bin", "/bin"));
+        assert!(has_triple(
+            &args,
+            "--bind",
+            "/state/volumes/jail__project__abc12345__claude__home",
+            "/home/agent"
+        ));
+        assert!(has_triple(
+            &args,
+            "--bind",
+            "/state/volumes/jail__project__abc12345__nix",
+            "/nix"
+        ));
+        assert!(has_triple(&args, "--setenv", "PATH", "/usr/bin"));
+        assert!(has_triple(&args, "--setenv", "TEST", "value"));
+        assert!(args
+            .windows(2)
+            .any(|w| w[0] == "--chdir" && w[1] == "/workspace"));
+        assert_eq!(&args[args.len() - 2..], &["--", "zsh"]);
+        // Interactive sessions keep the controlling terminal
+        assert!(!args.contains(&"--new-session".to_string()));
+    }
+
+    #[test]
+    fn test_build_bwrap_args_tmpfs_limits() {
+        let mut config = test_config();
+        config.limits.tmpfs.push(TmpfsMount {
+            target: PathBuf::from("/tmp"),
+            size_mb: 2,
+        });
+        config.limits.shm_size_mb = Some(1);
+
+        let args = args_for(&config);
+        assert!(has_triple(&args, "2097152", "--tmpfs", "/tmp"));
+        assert!(has_triple(&args, "1048576", "--tmpfs", "/dev/shm"));
+    }
+
+    #[test]
+    fn test_build_bwrap_args_network_modes() {
+        let mut config = test_config();
+
+        let args = args_for(&config);
+        assert!(args.contains(&"--unshare-net".to_string()));
+        assert!(has_triple(
+            &args,
+            "--ro-bind",
+            "/state/jails/jail__project__abc12345__claude/resolv.conf",
+            "/etc/resolv.conf"
+        ));
+
+        config.network.host = true;
+        let args = args_for(&config);
+        assert!(!args.contains(&"--unshare-net".to_string()));
+
+        config.network.host = false;
+        config.network.enabled = false;
+        let args = args_for(&config);
+        assert!(args.contains(&"--unshare-net".to_string()));
+        assert!(!args.iter().any(|a| a.ends_with("resolv.conf")));
+    }
+
+    #[test]
+    fn test_build_bwrap_args_exec_options() {
+        let options = ExecOptions {
+            user: Some("0:0".to_string()),
+            workdir: Some(PathBuf::from("/tmp")),
+            env: vec![("TERM".to_string(), "dumb".to_string())],
+            ..Default::default()
+        };
+        let args = BubblewrapBackend::build_bwrap_args(
+            &test_config(),
+            Path::new("/state/rootfs/abc"),
+            &ImageRuntime::default(),
+            &[],
+            Path::new("/state"),
+            &["id".to_string()],
+            &options,
+        );
+
+        assert!(args.contains(&"--new-session".to_string()));
+        assert!(args.windows(2).any(|w| w[0] == "--uid" && w[1] == "0"));
+        assert!(args.windows(2).any(|w| w[0] == "--gid" && w[1] == "0"));
+        assert!(has_triple(&args, "--setenv", "TERM", "dumb"));
+        assert!(args.windows(2).any(|w| w[0] == "--chdir" && w[1] == "/tmp"));
+    }
+
+    #[test]
+    fn test_parse_user() {
+        assert_eq!(BubblewrapBackend::parse_user("0"), Some((0, None)));
+        assert_eq!(
+            BubblewrapBackend::parse_user("1000:100"),
+            Some((1000, Some(100)))
+        );
+        assert_eq!(BubblewrapBackend::parse_user("agent"), None);
+    }
+
+    #[test]
+    fn test_host_path() {
+        let mut config = test_config();
+        config.bind_mounts.push(crate::config::BindMount {
+            source: PathBuf::from("/src/project"),
+            target: PathBuf::from("/workspace"),
+            readonly: false,
+            workspace: true,
+        });
+        config.bind_mounts.push(crate::config::BindMount {
+            source: PathBuf::from("/home/me/.gitconfig"),
+            target: PathBuf::from("/home/agent/.gitconfig"),
+            readonly: true,
+            workspace: false,
+        });
+        let state = Path::new("/state");
+
+        assert_eq!(
+            BubblewrapBackend::host_path(&config, state, Path::new("/workspace/src/main.rs")),
+            Some((PathBuf::from("/src/project/src/main.rs"), false))
+        );
+        assert_eq!(
+            BubblewrapBackend::host_path(&config, state, Path::new("/home/agent/.zshrc")),
+            Some((
+                PathBuf::from("/state/volumes/jail__project__abc12345__claude__home/.zshrc"),
+                false
+            ))
+        );
+        // The file mount is more specific than the home volume
+        assert_eq!(
+            BubblewrapBackend::host_path(&config, state, Path::new("/home/agent/.gitconfig")),
+            Some((PathBuf::from("/home/me/.gitconfig"), true))
+        );
+        assert_eq!(
+            BubblewrapBackend::host_path(&config, state, Path::new("/etc/passwd")),
+            None
+        );
+    }
+
+    #[test]
+    fn test_build_bwrap_args_bind_mounts() {
+        let mut config = test_config();
+        config.bind_mounts.push(crate::config::BindMount {
+            source: PathBuf::from("/src/project"),
+            target: PathBuf::from("/workspace"),
+            readonly: false,
+            workspace: true,
+        });
+        config.bind_mounts.push(crate::config::BindMount {
+            source: PathBuf::from("/home/me/.gitconfig"),
+            target: PathBuf::from("/home/agent/.gitconfig"),
+            readonly: true,
+            workspace: false,
+        });
+
+        let args = args_for(&config);
+        assert!(has_triple(&args, "--bind", "/src/project", "/workspace"));
+        assert!(has_triple(
+            &args,
+            "--ro-bind",
+            "/home/me/.gitconfig",
+            "/home/agent/.gitconfig"
+        ));
+    }
+}
@@ -0,0 +1,772 @@
+use super::{
+    extract_base_name, host_blocking, image_uses_nix, run_command, ExecOptions, ExecResult,
+    ExecStream, ImageSummary, JailBackend, JailStats, JailSummary, LogOptions, VolumeSummary,
+};
+use crate::config::{BackendType, JailConfig, PortMapping};
+use crate::error::{JailError, Result};
+use async_trait::async_trait;
+use std::path::Path;
+use std::sync::OnceLock;
+use tokio::process::Command;
+use tracing::{debug, info, warn};
+
+/// How the Docker daemon maps container users to host users
+///
+/// Docker has no `--userns=keep-id`, so the user the jail runs as depends on the daemon mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DockerUserMode {
+    /// Rootful daemon: run as the host UID/GID so bind mounts keep their ownership
+    Rootful,
+    /// Rootful daemon with `userns-remap`: opt out of the remap, then run as the host UID/GID
+    UsernsRemap,
+    /// Rootless daemon: container root is already the host user
+    Rootless,
+}
+
+impl DockerUserMode {
+    /// Detect the daemon mode from `docker info`
+    fn detect() -> Self {
+        let security_options = std::process::Command::new("docker")
+            .arg("info")
+            .arg("--format")
+            .arg("{{json .SecurityOptions}}")
+            .output()
+            .ok()
+            .filter(|o| o.status.success())
+            .map(|o| String::from_utf8_lossy(&o.stdout).to_string())
+            .unwrap_or_default();
+
+        let mode = Self::from_security_options(&security_options);
+        debug!("Docker user mode: {:?}", mode);
+        mode
+    }
+
+    fn from_security_options(security_options: &str) -> Self {
+        if security_options.contains("name=rootless") {
+            DockerUserMode::Rootless
+        } else if security_options.contains("name=userns") {
+            DockerUserMode::UsernsRemap
+        } else {
+            DockerUserMode::Rootful
+        }
+    }
+}
+
+pub struct DockerBackend {
+    user_mode: OnceLock<DockerUserMode>,
+}
+
+impl DockerBackend {
+    pub fn new() -> Self {
+        Self {
+            user_mode: OnceLock::new(),
+        }
+    }
+
+    /// Create a backend for a known daemon mode (skips `docker info`)
+    #[cfg(test)]
+    fn with_user_mode(user_mode: DockerUserMode) -> Self {
+        Self {
+            user_mode: OnceLock::from(user_mode),
+        }
+    }
+
+    fn user_mode(&self) -> DockerUserMode {
+        *self.user_mode.get_or_init(DockerUserMode::detect)
+    }
+
+    /// Get the host user or group ID (`id -u` / `id -g`)
+    fn host_id(flag: &str) -> String {
+        std::process::Command::new("id")
+            .arg(flag)
+            .output()
+            .ok()
+            .and_then(|o| String::from_utf8(o.stdout).ok())
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+            .unwrap_or_else(|| "1000".to_string())
+    }
+
+    /// Docker socket of the daemon, proxied into jails with `--podman`
+    fn host_socket(&self) -> String {
+        match self.user_mode() {
+            DockerUserMode::Rootless => {
+                format!("/run/user/{}/docker.sock", Self::host_id("-u"))
+            }
+            _ => "/var/run/docker.sock".to_string(),
+        }
+    }
+
+    /// Arguments that give the jail the same file ownership as podman's `--userns=keep-id`
+    fn user_args(&self) -> Vec<String> {
+        let host_user = || format!("{}:{}", Self::host_id("-u"), Self::host_id("-g"));
+
+        let mut args = match self.user_mode() {
+            DockerUserMode::Rootful => vec!["--user".to_string(), host_user()],
+            DockerUserMode::UsernsRemap => vec![
+                "--userns=host".to_string(),
+                "--user".to_string(),
+                host_user(),
+            ],
+            DockerUserMode::Rootless => vec!["--user".to_string(), "0:0".to_string()],
+        };
+
+        // The runtime user may have no passwd entry, keep the agent home
+        args.push("-e".to_string());
+        args.push("HOME=/home/agent".to_string());
+        args
+    }
+
+    fn build_run_args(&self, config: &JailConfig) -> Vec<String> {
+        let mut args = vec![
+            "run".to_string(),
+            "-d".to_string(),
+            "--name".to_string(),
+            config.name.clone(),
+        ];
+
+        // Preserve user ID mapping from host to avoid permission issues with bind mounts
+        args.extend(self.user_args());
+
+        // Record the configuration, and whether eBPF host blocking must be reattached after reboots
+        args.extend(super::label_args(config));
+
+        // Persistent volume for /home/agent to preserve data across upgrades
+        // Agent-specific (not shared across different agents)
+        let home_volume = super::home_volume_name(config);
+        args.push("-v".to_string());
+        args.push(format!("{home_volume}:/home/agent"));
+
+        // Per-jail Nix store volume for containers using Nix
+        // Shared across all agents working on the same project
+        if image_uses_nix(&config.base_image) {
+            let nix_volume = format!("{}__nix", extract_base_name(&config.name));
+            debug!(
+                "Detected Nix in image, mounting per-jail Nix store volume: {}",
+                nix_volume
+            );
+            args.push("-v".to_string());
+            args.push(format!("{nix_volume}:/nix"));
+        }
+
+        // Docker-in-Docker: mount the filtered proxy of the host's Docker socket
+        if config.podman_socket {
+            let socket_path = self.host_socket();
+
+            if std::path::Path::new(&socket_path).exists() {
+                let proxy_dir = crate::socket_proxy::socket_dir(&config.name);
+                debug!("Mounting Docker socket proxy: {}", proxy_dir.display());
+                args.push("-v".to_string());
+                args.push(format!("{}:/run/docker", proxy_dir.display()));
+
+                args.push("-e".to_string());
+                args.push("DOCKER_HOST=unix:///run/docker/docker.sock".to_string());
+            } else {
+                warn!(
+                    "Docker socket not found at {}. Docker-in-Docker may not work.",
+                    socket_path
+                );
+            }
+        }
+
+        // Network settings
+        // Docker has no slirp4netns mode: private networking uses the default bridge,
+        // which is isolated from the host's loopback (host IPs are blocked with eBPF)
+        if !config.network.enabled {
+            args.push("--network=none".to_string());
+        } else if config.network.host {
+            // Used for OAuth authentication to allow callbacks to localhost
+            args.push("--network=host".to_string());
+        } else {
+            args.push("--network=bridge".to_string());
+        }
+
+        // Port mappings (requires network to be enabled)
+        if config.network.enabled {
+            for port_mapping in &config.port_mappings {
+                args.push("-p".to_string());
+                args.push(port_mapping.publish_arg());
+            }
+        }
+
+        // Bind mounts
+        for mount in &config.bind_mounts {
+            let bind_arg = if mount.readonly {
+                format!("{}:{}:ro", mount.source.display(), mount.target.display())
+            } else {
+                format!("{}:{}", mount.source.display(), mount.target.display())
+            };
+            args.push("-v".to_string());
+            args.push(bind_arg);
+        }
+
+        // Environment variables
+        for (key, value) in &config.environment {
+            args.push("-e".to_string());
+            args.push(format!("{key}={value}"));
+        }
+
+        // Resource limits
+        args.extend(super::limit_args(&config.limits));
+
+        // Hardening (rootless mode runs the jail as root inside the container)
+        args.extend(super::hardening_args(
+            config,
+            self.user_mode() == DockerUserMode::Rootless,
+        ));
+
+        // Base image
+        args.push(config.base_image.clone());
+
+        // Keep container running with tini as PID 1 to reap zombie processes
+        args.push("tini".to_string());
+        args.push("--".to_string());
+        args.push("sleep".to_string());
+        args.push("infinity".to_string());
+
+        args
+    }
+
+    /// Start the container if it is stopped and make sure host blocking is attached
+    async fn prepare_exec(&self, name: &str) -> Result<()> {
+        if !self.exists(name).await? {
+            return Err(JailError::NotFound(format!("Jail '{name}' does not exist")));
+        }
+        crate::socket_proxy::ensure_running(name).await?;
+
+        // Start the container if it is stopped
+        if let Ok(state) = self.container_state(name).await {
+            if state == "exited" || state == "created" {
+                info!("Container {} is {}, starting it...", name, state);
+                self.start(name).await?;
+
+                // Wait a bit for cgroup to be fully initialized
+                tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
+            }
+        }
+
+        // Re-attach eBPF after a restart, or when the loader died (e.g. system reboot)
+        host_blocking::reattach_if_needed("docker", name).await
+    }
+
+    async fn container_state(&self, name: &str) -> Result<String> {
+        let mut cmd = Command::new("docker");
+        cmd.arg("inspect")
+            .arg(name)
+            .arg("--format")
+            .arg("{{.State.Status}}");
+
+        Ok(run_command(&mut cmd).await?.trim().to_string())
+    }
+}
+
+impl Default for DockerBackend {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl JailBackend for DockerBackend {
+    async fn create(&self, config: &JailConfig) -> Result<()> {
+        info!("Creating docker jail: {}", config.name);
+
+        if config.cow {
+            return Err(JailError::Config(
+                "Copy-on-write workspaces (--cow) need podman or bubblewrap: the docker daemon cannot mount overlays of host directories".to_string(),
+            ));
+        }
+
+        // If upgrade is true, stop and remove existing container first
+        if config.upgrade && self.exists(&config.name).await? {
+            info!(
+                "Upgrade enabled: stopping and removing existing container '{}'",
+                config.name
+            );
+
+            let mut rm_cmd = Command::new("docker");
+            rm_cmd.arg("rm").arg("-f").arg(&config.name);
+
+            if let Err(e) = run_command(&mut rm_cmd).await {
+                // Log warning but continue - the container might already be gone
+                debug!("Failed to remove existing container (may not exist): {}", e);
+            }
+        } else if !config.upgrade && self.exists(&config.name).await? {
+            return Err(JailError::AlreadyExists(config.name.clone()));
+        }
+
+        // Determine which image to use
+        let actual_image = super::resolve_image(config).await?;
+        if config.seccomp {
+            super::install_seccomp_profile().await?;
+        }
+        let socket_path = self.host_socket();
+        if config.podman_socket && std::path::Path::new(&socket_path).exists() {
+            crate::socket_proxy::prepare(config, "docker", std::path::Path::new(&socket_path))
+                .await?;
+        }
+
+        // Create and start the container with the determined image
+        let mut modified_config = config.clone();
+        modified_config.base_image = actual_image;
+        let args = self.build_run_args(&modified_config);
+        let mut cmd = Command::new("docker");
+        cmd.args(&args);
+
+        debug!("Creating container with args: {:?}", args);
+        run_command(&mut cmd).await?;
+
+        // Apply eBPF host blocking if requested
+        // Skip eBPF when using host networking (container shares host's network namespace)
+        if config.block_host {
+            if config.network.host {
+                info!(
+                    "Skipping eBPF host blocking for container '{}' (using --network=host)",
+                    config.name
+                );
+            } else {
+                info!(
+                    "Applying eBPF host blocking for container '{}'",
+                    config.name
+                );
+                // Propagate eBPF loading errors - container creation must fail if eBPF fails
+                host_blocking::apply("docker", &config.name).await?;
+                info!("✓ eBPF host blocking applied successfully");
+            }
+        }
+
+        // Pre-create directories if needed (for worktree support)
+        super::pre_create_dirs(config).await?;
+
+        info!("Jail {} created successfully", config.name);
+        Ok(())
+    }
+
+    async fn remove(&self, name: &str, remove_volume: bool) -> Result<()> {
+        info!("Removing docker jail: {}", name);
+        let home_volume = super::jail_home_volume(self, name).await;
+
+        // Remove eBPF blocker if it exists
+        host_blocking::release(name);
+        crate::socket_proxy::remove(name);
+
+        // Remove container (with force flag to stop if running)
+        let mut cmd = Command::new("docker");
+        cmd.arg("rm").arg("-f").arg(name);
+
+        run_command(&mut cmd).await?;
+
+        if remove_volume {
+            let nix_volume = format!("{}__nix", extract_base_name(name));
+
+            // Attempt removal but ignore errors (volumes may not exist, the nix
+            // volume may still be in use by other agents)
+            for volume in [&home_volume, &nix_volume] {
+                let mut vol_cmd = Command::new("docker");
+                vol_cmd.arg("volume").arg("rm").arg(volume);
+
+                match run_command(&mut vol_cmd).await {
+                    Ok(_) => debug!("Volume {} removed", volume),
+                    Err(e) => debug!("Could not remove volume {}: {}", volume, e),
+                }
+            }
+
+            info!(
+                "Jail {} removed (attempted to remove volumes {}, {})",
+                name, home_volume, nix_volume
+            );
+        } else {
+            info!("Jail {} removed", name);
+        }
+
+        Ok(())
+    }
+
+    async fn exec_with(
+        &self,
+        name: &str,
+        command: &[String],
+        options: &ExecOptions,
+    ) -> Result<ExecResult> {
+        debug!(
+            "Executing command in jail {}: {:?} ({:?})",
+            name, command, options
+        );
+        self.prepare_exec(name).await?;
+        super::engine_exec("docker", name, command, options).await
+    }
+
+    async fn exec_stream(
+        &self,
+        name: &str,
+        command: &[String],
+        options: &ExecOptions,
+    ) -> Result<ExecStream> {
+        debug!(
+            "Streaming command in jail {}: {:?} ({:?})",
+            name, command, options
+        );
+        self.prepare_exec(name).await?;
+        super::engine_exec_stream("docker", name, command, options)
+    }
+
+    fn exec_argv(
+        &self,
+        name: &str,
+        command: &[String],
+        options: &ExecOptions,
+    ) -> Result<Vec<String>> {
+        let mut argv = vec!["docker".to_string()];
+        argv.extend(super::engine_exec_args(name, command, options));
+        Ok(argv)
+    }
+
+    async fn exists(&self, name: &str) -> Result<bool> {
+        // Docker name filters are unanchored regexes
+        let mut cmd = Command::new("docker");
+        cmd.arg("ps")
+            .arg("-a")
+            .arg("--filter")
+            .arg(format!("name=^{name}$"))
+            .arg("--format")
+            .arg("{{.Names}}");
+
+        match run_command(&mut cmd).await {
+            Ok(output) => Ok(output.lines().any(|line| line.trim() == name)),
+            Err(_) => Ok(false),
+        }
+    }
+
+    async fn list_all(&self) -> Result<Vec<String>> {
+        debug!("Listing all jail-ai containers");
+
+        let mut cmd = Command::new("docker");
+        cmd.arg("ps").arg("-a").arg("--format").arg("{{.Names}}");
+
+        let output = run_command(&mut cmd).await?;
+
+        // Filter containers that start with "jail__"
+        let jails: Vec<String> = output
+            .lines()
+            .filter(|line| line.starts_with("jail__"))
+            .map(|line| line.to_string())
+            .collect();
+
+        debug!("Found {} jail-ai containers", jails.len());
+        Ok(jails)
+    }
+
+    async fn is_running(&self, name: &str) -> Result<bool> {
+        match self.container_state(name).await {
+            Ok(state) => Ok(state == "running"),
+            Err(_) => Ok(false),
+        }
+    }
+
+    async fn start(&self, name: &str) -> Result<()> {
+        info!("Starting container: {}", name);
+        crate::socket_proxy::ensure_running(name).await?;
+
+        let mut cmd = Command::new("docker");
+        cmd.arg("start").arg(name);
+
+        run_command(&mut cmd).await?;
+        info!("Container {} started successfully", name);
+        Ok(())
+    }
+
+    async fn list_summaries(&self) -> Result<Vec<JailSummary>> {
+        let names = self.list_all().await?;
+        super::engine_summaries("docker", &names).await
+    }
+
+    async fn inspect(&self, name: &str) -> Result<JailConfig> {
+        debug!("Inspecting jail: {}", name);
+
+        if !self.exists(name).await? {
+            return Err(JailError::NotFound(format!("Jail '{name}' not found")));
+        }
+
+        let mut cmd = Command::new("docker");
+        cmd.arg("inspect").arg(name);
+
+        let output = run_command(&mut cmd).await?;
+        let inspect_data: serde_json::Value = serde_json::from_str(&output)
+            .map_err(|e| JailError::Backend(format!("Failed to parse inspect output: {e}")))?;
+
+        let container = inspect_data
+            .as_array()
+            .and_then(|arr| arr.first())
+            .ok_or_else(|| JailError::Backend("Empty inspect output".to_string()))?;
+
+        Ok(super::config_from_inspect(
+            name,
+            container,
+            BackendType::Docker,
+        ))
+    }
+
+    async fn stop(&self, name: &str) -> Result<()> {
+        info!("Stopping container: {}", name);
+        super::engine_action("docker", "stop", name).await?;
+        // The eBPF programs go away with the container cgroup
+        host_blocking::release(name);
+        crate::socket_proxy::stop(name);
+        Ok(())
+    }
+
+    async fn restart(&self, name: &str) -> Result<()> {
+        info!("Restarting container: {}", name);
+        crate::socket_proxy::ensure_running(name).await?;
+        super::engine_action("docker", "restart", name).await?;
+        host_blocking::release(name);
+
+        // Wait a bit for cgroup to be fully initialized, then re-attach eBPF
+        tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
+        host_blocking::reattach_if_needed("docker", name).await
+    }
+
+    async fn pause(&self, name: &str) -> Result<()> {
+        super::engine_action("docker", "pause", name).await
+    }
+
+    async fn unpause(&self, name: &str) -> Result<()> {
+        super::engine_action("docker", "unpause", name).await
+    }
+
+    async fn logs(&self, name: &str, options: &LogOptions) -> Result<()> {
+        super::engine_logs("docker", name, options).await
+    }
+
+    async fn stats(&self, name: &str) -> Result<JailStats> {
+        super::engine_stats("docker", name).await
+    }
+
+    async fn port_mappings(&self, name: &str) -> Result<Vec<PortMapping>> {
+        super::engine_port_mappings("docker", name).await
+    }
+
+    async fn active_sessions(&self, name: &str) -> Result<usize> {
+        super::engine_active_sessions("docker", name).await
+    }
+
+    async fn list_volumes(&self) -> Result<Vec<VolumeSummary>> {
+        super::engine_list_volumes("docker").await
+    }
+
+    async fn remove_volume(&self, volume: &str) -> Result<()> {
+        super::engine_remove_volume("docker", volume).await
+    }
+
+    async fn export_volume(&self, volume: &str, archive: &Path) -> Result<()> {
+        super::engine_export_volume("docker", volume, archive).await
+    }
+
+    async fn import_volume(&self, volume: &str, archive: &Path) -> Result<()> {
+        super::engine_import_volume("docker", volume, archive).await
+    }
+
+    async fn reset_volume(&self, volume: &str, keep: &[String]) -> Result<()> {
+        super::engine_reset_volume("docker", volume, keep).await
+    }
+
+    async fn commit(&self, name: &str, image: &str, labels: &[(String, String)]) -> Result<()> {
+        super::engine_commit("docker", name, image, labels).await
+    }
+
+    async fn list_images(&self) -> Result<Vec<ImageSummary>> {
+        super::engine_list_images("docker").await
+    }
+
+    async fn remove_image(&self, image: &str) -> Result<()> {
+        super::engine_remove_image("docker", image).await
+    }
+
+    async fn copy_in(&self, name: &str, source: &Path, target: &Path) -> Result<()> {
+        super::engine_copy("docker", name, source, target, true).await
+    }
+
+    async fn copy_out(&self, name: &str, source: &Path, target: &Path) -> Result<()> {
+        super::engine_copy("docker", name, source, target, false).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_config(name: &str) -> JailConfig {
+        JailConfig {
+            name: name.to_string(),
+            backend: BackendType::Docker,
+            base_image: "alpine:latest".to_string(),
+            environment: vec![("TEST".to_string(), "value".to_string())],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_user_mode_from_security_options() {
+        assert_eq!(
+            DockerUserMode::from_security_options(
+                r#"["name=seccomp,profile=builtin","name=rootless","name=cgroupns"]"#
+            ),
+            DockerUserMode::Rootless
+        );
+        assert_eq!(
+            DockerUserMode::from_security_options(
+                r#"["name=seccomp,profile=builtin","name=userns"]"#
+            ),
+            DockerUserMode::UsernsRemap
+        );
+        assert_eq!(
+            DockerUserMode::from_security_options(
+                r#"["name=apparmor","name=seccomp,profile=builtin"]"#
+            ),
+            DockerUserMode::Rootful
+        );
+    }
+
+    #[test]
+    fn test_build_run_args_user_mapping() {
+        let config = test_config("test");
+
+        let rootful = DockerBackend::with_user_mode(DockerUserMode::Rootful);
+        let args = rootful.build_run_args(&config);
+        let user = format!(
+            "{}:{}",
+            DockerBackend::host_id("-u"),
+            DockerBackend::host_id("-g")
+        );
+        assert!(args.windows(2).any(|w| w[0] == "--user" && w[1] == user));
+        assert!(!args.contains(&"--userns=host".to_string()));
+        assert!(args.contains(&"HOME=/home/agent".to_string()));
+
+        let remap = DockerBackend::with_user_mode(DockerUserMode::UsernsRemap);
+        let args = remap.build_run_args(&config);
+        assert!(args.contains(&"--userns=host".to_string()));
+        assert!(args.windows(2).any(|w| w[0] == "--user" && w[1] == user));
+
+        let rootless = DockerBackend::with_user_mode(DockerUserMode::Rootless);
+        let args = rootless.build_run_args(&config);
+        assert!(args.windows(2).any(|w| w[0] == "--user" && w[1] == "0:0"));
+
+        // Podman-only flags must never reach docker
+        assert!(!args.iter().any(|a| a.starts_with("--userns=keep-id")));
+        assert!(!args.contains(&"--network=private".to_string()));
+    }
+
+    #[test]
+    fn test_build_run_args_volumes_and_labels() {
+        let backend = DockerBackend::with_user_mode(DockerUserMode::Rootful);
+        let config = JailConfig {
+            base_image: "localhost/jail-ai-agent-claude:base-nix-rust".to_string(),
+            block_host: true,
+            ..test_config("jail__project__abc12345__claude")
+        };
+
+        let args = backend.build_run_args(&config);
+
+        assert!(args.contains(&"jail__project__abc12345__claude__home:/home/agent".to_string()));
+        assert!(args.contains(&"jail__project__abc12345__nix:/nix".to_string()));
+        assert!(args.contains(&"jail-ai.block-host=true".to_string()));
+        assert!(args.contains(&"TEST=value".to_string()));
+        assert_eq!(
+            &args[args.len() - 5..],
+            &[
+                "localhost/jail-ai-agent-claude:base-nix-rust",
+                "tini",
+                "--",
+                "sleep",
+                "infinity"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_build_run_args_hardened() {
+        let mut config = test_config("test");
+        let rootful = DockerBackend::with_user_mode(DockerUserMode::Rootful);
+        assert!(!rootful
+            .build_run_args(&config)
+            .contains(&"--read-only".to_string()));
+
+        config.hardened = true;
+        config.limits.tmpfs.push(crate::config::TmpfsMount {
+            target: "/tmp".into(),
+            size_mb: 100,
+        });
+        let args = rootful.build_run_args(&config);
+        assert!(args.contains(&"--cap-drop=ALL".to_string()));
+        assert!(args.contains(&"--security-opt=no-new-privileges".to_string()));
+        assert!(args.contains(&"--read-only".to_string()));
+        assert!(!args.iter().any(|a| a.starts_with("--cap-add")));
+        // The configured /tmp wins over the hardening default
+        let tmpfs: Vec<&String> = args
+            .windows(2)
+            .filter(|w| w[0] == "--tmpfs")
+            .map(|w| &w[1])
+            .collect();
+        assert_eq!(tmpfs.len(), 3, "{tmpfs:?}");
+        assert!(tmpfs.contains(&&"/tmp:rw,exec,nosuid,nodev,size=100m".to_string()));
+
+        // Root inside the container keeps access to files owned by the agent user
+        let rootless = DockerBackend::with_user_mode(DockerUserMode::Rootless);
+        let args = rootless.build_run_args(&config);
+        assert!(args.contains(&"--cap-add=DAC_OVERRIDE".to_string()));
+    }
+
+    #[test]
+    fn test_build_run_args_network_modes() {
+        let backend = DockerBackend::with_user_mode(DockerUserMode::Rootful);
+        let mut config = test_config("test");
+        config
+            .port_mappings
+            .push(crate::cli::Commands::parse_port("8080:80").unwrap());
+
+        let args = backend.build_run_args(&config);
+        assert!(args.contains(&"--network=bridge".to_string()));
+        assert!(args.contains(&"127.0.0.1:8080:80/tcp".to_string()));
+
+        config.network.host = true;
+        let args = backend.build_run_args(&config);
+        assert!(args.contains(&"--network=host".to_string()));
+
+        config.network.enabled = false;
+        let args = backend.build_run_args(&config);
+        assert!(args.contains(&"--network=none".to_string()));
+        assert!(!args.contains(&"-p".to_string()));
+    }
+
+    #[test]
+    fn test_build_run_args_limits() {
+        let backend = DockerBackend::with_user_mode(DockerUserMode::Rootful);
+        let mut config = test_config("test");
+        config.limits.memory_mb = Some(512);
+        config.limits.cpu_quota = Some(50);
+        config.limits.pids_limit = Some(4096);
+        config.limits.memory_swap_mb = Some(512);
+        config.limits.shm_size_mb = Some(256);
+        config.limits.blkio_weight = Some(200);
+        config.limits.ulimits.push(crate::config::Ulimit {
+            name: "nofile".to_string(),
+            soft: 1024,
+            hard: 4096,
+        });
+        config.limits.tmpfs.push(crate::config::TmpfsMount {
+            target: "/tmp".into(),
+            size_mb: 2048,
+        });
+
+        let args = backend.build_run_args(&config);
+        let has = |flag: &str, value: &str| args.windows(2).any(|w| w[0] == flag && w[1] == value);
+        assert!(has("-m", "512m"));
+        assert!(has("--cpus", "0.5"));
+        assert!(has("--pids-limit", "4096"));
+        assert!(has("--memory-swap", "512m"));
+        assert!(has("--shm-size", "256m"));
+        assert!(has("--blkio-weight", "200"));
+        assert!(has("--ulimit", "nofile=1024:4096"));
+        assert!(has("--tmpfs", "/tmp:rw,exec,nosuid,nodev,size=2048m"));
+    }
+}
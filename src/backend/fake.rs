@@ -0,0 +1,407 @@
+//! In-memory backend for testing jail lifecycle logic without a container engine
+
+use super::{
+    ExecOptions, ExecResult, ExecStream, ImageSummary, JailBackend, JailStats, JailSummary,
+    LogOptions, VolumeSummary,
+};
+use crate::config::{JailConfig, PortMapping};
+use crate::error::{JailError, Result};
+use async_trait::async_trait;
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// Backend call recorded by [`FakeBackend`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FakeCall {
+    Create(String),
+    Remove {
+        name: String,
+        remove_volume: bool,
+    },
+    Exec {
+        name: String,
+        command: Vec<String>,
+    },
+    Start(String),
+    Stop(String),
+    Restart(String),
+    Pause(String),
+    Unpause(String),
+    Logs(String),
+    RemoveVolume(String),
+    ExportVolume {
+        volume: String,
+        archive: PathBuf,
+    },
+    ImportVolume {
+        volume: String,
+        archive: PathBuf,
+    },
+    ResetVolume {
+        volume: String,
+        keep: Vec<String>,
+    },
+    Commit {
+        name: String,
+        image: String,
+    },
+    RemoveImage(String),
+    CopyIn {
+        name: String,
+        source: PathBuf,
+        target: PathBuf,
+    },
+    CopyOut {
+        name: String,
+        source: PathBuf,
+        target: PathBuf,
+    },
+}
+
+#[derive(Debug, Clone)]
+struct FakeJail {
+    config: JailConfig,
+    running: bool,
+}
+
+#[derive(Default)]
+struct FakeState {
+    jails: BTreeMap<String, FakeJail>,
+    calls: Vec<FakeCall>,
+    failing_creates: HashSet<String>,
+    sessions: BTreeMap<String, usize>,
+    volumes: Vec<VolumeSummary>,
+    images: Vec<ImageSummary>,
+}
+
+/// Backend simulating containers in memory and recording every mutating call
+///
+/// Clones share the same state, so a test can keep a handle while the code under
+/// test owns another one.
+#[derive(Clone, Default)]
+pub struct FakeBackend {
+    state: Arc<Mutex<FakeState>>,
+}
+
+impl FakeBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, FakeState> {
+        self.state.lock().expect("fake backend state poisoned")
+    }
+
+    /// Add an existing jail, as if it had been created earlier
+    pub fn with_jail(self, config: JailConfig, running: bool) -> Self {
+        self.state()
+            .jails
+            .insert(config.name.clone(), FakeJail { config, running });
+        self
+    }
+
+    /// Make `create` fail for this jail name
+    pub fn fail_create(self, name: &str) -> Self {
+        self.state().failing_creates.insert(name.to_string());
+        self
+    }
+
+    /// Report commands currently running in this jail
+    pub fn with_sessions(self, name: &str, sessions: usize) -> Self {
+        self.state().sessions.insert(name.to_string(), sessions);
+        self
+    }
+
+    /// Add an existing jail volume
+    pub fn with_volume(self, name: &str, size: u64) -> Self {
+        self.state().volumes.push(VolumeSummary {
+            name: name.to_string(),
+            size: Some(size),
+        });
+        self
+    }
+
+    /// Add an existing jail-ai image
+    pub fn with_image(self, name: &str, size: u64) -> Self {
+        self.state().images.push(ImageSummary {
+            name: name.to_string(),
+            size: Some(size),
+        });
+        self
+    }
+
+    /// Calls received so far, in order
+    pub fn calls(&self) -> Vec<FakeCall> {
+        self.state().calls.clone()
+    }
+
+    /// Configuration of a simulated jail
+    pub fn jail(&self, name: &str) -> Option<JailConfig> {
+        self.state().jails.get(name).map(|jail| jail.config.clone())
+    }
+
+    fn not_found(name: &str) -> JailError {
+        JailError::NotFound(format!("Jail '{name}' not found"))
+    }
+
+    /// Record a call on an existing jail and update its running state
+    fn record(&self, name: &str, call: FakeCall, running: Option<bool>) -> Result<()> {
+        let mut state = self.state();
+        state.calls.push(call);
+        let jail = state
+            .jails
+            .get_mut(name)
+            .ok_or_else(|| Self::not_found(name))?;
+        if let Some(running) = running {
+            jail.running = running;
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl JailBackend for FakeBackend {
+    async fn create(&self, config: &JailConfig) -> Result<()> {
+        let mut state = self.state();
+        state.calls.push(FakeCall::Create(config.name.clone()));
+
+        if state.failing_creates.contains(&config.name) {
+            return Err(JailError::Backend(format!(
+                "Failed to create '{}'",
+                config.name
+            )));
+        }
+        if state.jails.contains_key(&config.name) {
+            if !config.upgrade {
+                return Err(JailError::AlreadyExists(config.name.clone()));
+            }
+            state.calls.push(FakeCall::Remove {
+                name: config.name.clone(),
+                remove_volume: false,
+            });
+        }
+
+        state.jails.insert(
+            config.name.clone(),
+            FakeJail {
+                config: config.clone(),
+                running: true,
+            },
+        );
+        Ok(())
+    }
+
+    async fn remove(&self, name: &str, remove_volume: bool) -> Result<()> {
+        let mut state = self.state();
+        state.calls.push(FakeCall::Remove {
+            name: name.to_string(),
+            remove_volume,
+        });
+        let jail = state
+            .jails
+            .remove(name)
+            .ok_or_else(|| Self::not_found(name))?;
+        let home = super::home_volume_name(&jail.config);
+        // Like the engines, keep a volume another jail still mounts
+        let in_use = state
+            .jails
+            .values()
+            .any(|other| super::home_volume_name(&other.config) == home);
+        if remove_volume && !in_use {
+            state.volumes.retain(|v| v.name != home);
+        }
+        Ok(())
+    }
+
+    async fn exec_with(
+        &self,
+        name: &str,
+        command: &[String],
+        _options: &ExecOptions,
+    ) -> Result<ExecResult> {
+        let call = FakeCall::Exec {
+            name: name.to_string(),
+            command: command.to_vec(),
+        };
+        self.record(name, call, Some(true))?;
+        Ok(ExecResult::default())
+    }
+
+    async fn exec_stream(
+        &self,
+        name: &str,
+        command: &[String],
+        options: &ExecOptions,
+    ) -> Result<ExecStream> {
+        self.exec_with(name, command, options).await?;
+        // A real process without output stands in for the jailed command
+        ExecStream::spawn(tokio::process::Command::new("true"))
+    }
+
+    fn exec_argv(
+        &self,
+        name: &str,
+        command: &[String],
+        _options: &ExecOptions,
+    ) -> Result<Vec<String>> {
+        let mut argv = vec!["fake".to_string(), "exec".to_string(), name.to_string()];
+        argv.extend(command.iter().cloned());
+        Ok(argv)
+    }
+
+    async fn exists(&self, name: &str) -> Result<bool> {
+        Ok(self.state().jails.contains_key(name))
+    }
+
+    async fn list_all(&self) -> Result<Vec<String>> {
+        Ok(self.state().jails.keys().cloned().collect())
+    }
+
+    async fn list_summaries(&self) -> Result<Vec<JailSummary>> {
+        Ok(self
+            .state()
+            .jails
+            .values()
+            .map(|jail| JailSummary {
+                name: jail.config.name.clone(),
+                agent: crate::agent_commands::extract_agent_name(&jail.config.name).to_string(),
+                workspace: super::workspace_source(&jail.config),
+                running: jail.running,
+                image: jail.config.base_image.clone(),
+                ..Default::default()
+            })
+            .collect())
+    }
+
+    async fn inspect(&self, name: &str) -> Result<JailConfig> {
+        self.jail(name).ok_or_else(|| Self::not_found(name))
+    }
+
+    async fn is_running(&self, name: &str) -> Result<bool> {
+        self.state()
+            .jails
+            .get(name)
+            .map(|jail| jail.running)
+            .ok_or_else(|| Self::not_found(name))
+    }
+
+    async fn start(&self, name: &str) -> Result<()> {
+        self.record(name, FakeCall::Start(name.to_string()), Some(true))
+    }
+
+    async fn stop(&self, name: &str) -> Result<()> {
+        self.record(name, FakeCall::Stop(name.to_string()), Some(false))
+    }
+
+    async fn restart(&self, name: &str) -> Result<()> {
+        self.record(name, FakeCall::Restart(name.to_string()), Some(true))
+    }
+
+    async fn pause(&self, name: &str) -> Result<()> {
+        self.record(name, FakeCall::Pause(name.to_string()), None)
+    }
+
+    async fn unpause(&self, name: &str) -> Result<()> {
+        self.record(name, FakeCall::Unpause(name.to_string()), None)
+    }
+
+    async fn logs(&self, name: &str, _options: &LogOptions) -> Result<()> {
+        self.record(name, FakeCall::Logs(name.to_string()), None)
+    }
+
+    async fn stats(&self, name: &str) -> Result<JailStats> {
+        if !self.is_running(name).await? {
+            return Err(JailError::Backend(format!("Jail '{name}' is not running")));
+        }
+        Ok(JailStats::default())
+    }
+
+    async fn port_mappings(&self, name: &str) -> Result<Vec<PortMapping>> {
+        if !self.is_running(name).await? {
+            return Ok(Vec::new());
+        }
+        Ok(self.inspect(name).await?.port_mappings)
+    }
+
+    async fn active_sessions(&self, name: &str) -> Result<usize> {
+        Ok(self.state().sessions.get(name).copied().unwrap_or(0))
+    }
+
+    async fn list_volumes(&self) -> Result<Vec<VolumeSummary>> {
+        Ok(self.state().volumes.clone())
+    }
+
+    async fn remove_volume(&self, volume: &str) -> Result<()> {
+        let mut state = self.state();
+        state.calls.push(FakeCall::RemoveVolume(volume.to_string()));
+        state.volumes.retain(|v| v.name != volume);
+        Ok(())
+    }
+
+    async fn export_volume(&self, volume: &str, archive: &Path) -> Result<()> {
+        self.state().calls.push(FakeCall::ExportVolume {
+            volume: volume.to_string(),
+            archive: archive.to_path_buf(),
+        });
+        Ok(())
+    }
+
+    async fn import_volume(&self, volume: &str, archive: &Path) -> Result<()> {
+        self.state().calls.push(FakeCall::ImportVolume {
+            volume: volume.to_string(),
+            archive: archive.to_path_buf(),
+        });
+        Ok(())
+    }
+
+    async fn reset_volume(&self, volume: &str, keep: &[String]) -> Result<()> {
+        self.state().calls.push(FakeCall::ResetVolume {
+            volume: volume.to_string(),
+            keep: keep.to_vec(),
+        });
+        Ok(())
+    }
+
+    async fn commit(&self, name: &str, image: &str, _labels: &[(String, String)]) -> Result<()> {
+        let call = FakeCall::Commit {
+            name: name.to_string(),
+            image: image.to_string(),
+        };
+        self.record(name, call, None)?;
+        self.state().images.push(ImageSummary {
+            name: image.to_string(),
+            size: None,
+        });
+        Ok(())
+    }
+
+    async fn list_images(&self) -> Result<Vec<ImageSummary>> {
+        Ok(self.state().images.clone())
+    }
+
+    async fn remove_image(&self, image: &str) -> Result<()> {
+        let mut state = self.state();
+        state.calls.push(FakeCall::RemoveImage(image.to_string()));
+        state.images.retain(|i| i.name != image);
+        Ok(())
+    }
+
+    async fn copy_in(&self, name: &str, source: &Path, target: &Path) -> Result<()> {
+        let call = FakeCall::CopyIn {
+            name: name.to_string(),
+            source: source.to_path_buf(),
+            target: target.to_path_buf(),
+        };
+        self.record(name, call, None)
+    }
+
+    async fn copy_out(&self, name: &str, source: &Path, target: &Path) -> Result<()> {
+        let call = FakeCall::CopyOut {
+            name: name.to_string(),
+            source: source.to_path_buf(),
+            target: target.to_path_buf(),
+        };
+        self.record(name, call, None)
+    }
+}
@@ -0,0 +1,321 @@
+//! eBPF host blocking shared by the container backends
+//!
+//! Podman and Docker expose the same inspect template fields, so the cgroup
+//! lookup and loader bookkeeping only differ by the engine command.
+
+use super::run_command;
+use crate::error::{JailError, Result};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+use tokio::process::Command;
+use tracing::{debug, info, warn};
+
+// Global registry to store eBPF blockers for active containers
+// This prevents them from being dropped (which would detach the eBPF programs)
+static EBPF_BLOCKERS: OnceLock<Arc<Mutex<HashMap<String, crate::ebpf::EbpfHostBlocker>>>> =
+    OnceLock::new();
+
+fn ebpf_blockers() -> &'static Arc<Mutex<HashMap<String, crate::ebpf::EbpfHostBlocker>>> {
+    EBPF_BLOCKERS.get_or_init(|| Arc::new(Mutex::new(HashMap::new())))
+}
+
+/// Check if eBPF loader is running for this container and reattach if needed
+/// This handles cases where the system rebooted but the container auto-started
+pub(super) async fn reattach_if_needed(engine: &str, name: &str) -> Result<()> {
+    // Check if this container has eBPF blocking enabled by checking for the label
+    // If the label doesn't exist or is false, skip eBPF reattachment
+    let mut label_cmd = Command::new(engine);
+    label_cmd
+        .arg("inspect")
+        .arg(name)
+        .arg("--format")
+        .arg("{{index .Config.Labels \"jail-ai.block-host\"}}");
+
+    let block_host_label = match run_command(&mut label_cmd).await {
+        Ok(output) => output.trim() == "true",
+        Err(_) => {
+            // Label not found or inspect failed, assume no eBPF needed
+            debug!(
+                "No block-host label found for container {}, skipping eBPF check",
+                name
+            );
+            return Ok(());
+        }
+    };
+
+    if !block_host_label {
+        debug!("Container {} does not have eBPF blocking enabled", name);
+        return Ok(());
+    }
+
+    // Check if container is using host networking
+    let mut network_cmd = Command::new(engine);
+    network_cmd
+        .arg("inspect")
+        .arg(name)
+        .arg("--format")
+        .arg("{{.HostConfig.NetworkMode}}");
+
+    if let Ok(network_mode) = run_command(&mut network_cmd).await {
+        if network_mode.trim() == "host" {
+            debug!("Container {} uses host networking, skipping eBPF", name);
+            return Ok(());
+        }
+    }
+
+    // Check if eBPF loader is already running for this container
+    if is_loader_running(name).await {
+        debug!("eBPF loader already running for container {}", name);
+        return Ok(());
+    }
+
+    // Loader not running but should be - reattach eBPF
+    info!(
+        "⚠️  eBPF loader not running for container {} (likely due to system reboot)",
+        name
+    );
+    info!("Reattaching eBPF host blocking...");
+
+    // Get the actual cgroup path from the container
+    // This is more reliable than constructing it manually
+    let cgroup_path = match container_cgroup_path(engine, name).await {
+        Ok(path) => path,
+        Err(e) => {
+            warn!("Failed to get cgroup path for container {}: {}", name, e);
+            warn!("eBPF host blocking will not be reattached");
+            return Ok(());
+        }
+    };
+
+    debug!("Found cgroup path for container {}: {}", name, cgroup_path);
+
+    // Verify cgroup exists
+    if !std::path::Path::new(&cgroup_path).exists() {
+        warn!("Cgroup path does not exist: {}", cgroup_path);
+        warn!("eBPF host blocking will not be reattached");
+        return Ok(());
+    }
+
+    attach(name, &cgroup_path).await?;
+
+    info!("✓ eBPF host blocking reattached for container {}", name);
+    Ok(())
+}
+
+/// Whether a loader process holds the lock for this container (works across jail-ai invocations)
+pub(super) fn loader_active(name: &str) -> bool {
+    std::fs::read_to_string(format!("/tmp/jail-ai-ebpf-{name}.lock"))
+        .ok()
+        .and_then(|pid| pid.trim().parse::<u32>().ok())
+        .is_some_and(|pid| std::path::Path::new(&format!("/proc/{pid}")).exists())
+}
+
+/// Check if eBPF loader process is running for a specific container
+/// by checking if we have a blocker in memory AND a loader process is running
+async fn is_loader_running(container_name: &str) -> bool {
+    // ONLY trust our in-memory map - if we don't have a blocker stored,
+    // then we don't know if eBPF is active for this container
+
+    let has_blocker_in_memory = {
+        let blockers = ebpf_blockers();
+        if let Ok(blockers_map) = blockers.lock() {
+            blockers_map.contains_key(container_name)
+        } else {
+            false
+        }
+    }; // Lock is dropped here before any await
+
+    if !has_blocker_in_memory {
+        debug!(
+            "No eBPF blocker in memory for container {} - needs reattach",
+            container_name
+        );
+        return false;
+    }
+
+    // We have a blocker in memory - verify the loader process is actually running
+    debug!(
+        "Found eBPF blocker in memory for container {}, checking if loader is running",
+        container_name
+    );
+
+    let mut ps_cmd = Command::new("ps");
+    ps_cmd.args(["aux"]);
+
+    if let Ok(output) = run_command(&mut ps_cmd).await {
+        for line in output.lines() {
+            if line.contains("jail-ai-ebpf-loader") && !line.contains("grep") {
+                debug!("eBPF loader process is running");
+                return true;
+            }
+        }
+    }
+
+    // Blocker in memory but no loader process - stale entry
+    debug!("Blocker in memory but loader not running - needs reattach");
+    false
+}
+
+/// Get the PID of the container's main process
+///
+/// # Arguments
+/// * `engine` - Container engine command (`podman` or `docker`)
+/// * `name` - Name of the container
+///
+/// # Returns
+/// Container PID (process ID on the host)
+///
+/// # Errors
+/// Returns Err if container doesn't exist or PID cannot be retrieved
+pub(super) async fn container_pid(engine: &str, name: &str) -> Result<u32> {
+    let api_pid = match super::podman_api::PodmanApi::shared() {
+        Some(api) if engine == "podman" => match api.container_inspect(name).await {
+            Ok(Some(container)) => container["State"]["Pid"].as_u64().map(|pid| pid as u32),
+            _ => None,
+        },
+        _ => None,
+    };
+
+    let pid = match api_pid {
+        Some(pid) => pid,
+        None => {
+            let mut cmd = Command::new(engine);
+            cmd.arg("inspect")
+                .arg(name)
+                .arg("--format")
+                .arg("{{.State.Pid}}");
+
+            let output = run_command(&mut cmd).await?;
+            output
+                .trim()
+                .parse::<u32>()
+                .map_err(|e| JailError::Backend(format!("Failed to parse PID: {}", e)))?
+        }
+    };
+
+    if pid == 0 {
+        return Err(JailError::Backend(format!(
+            "Container '{}' is not running (PID is 0)",
+            name
+        )));
+    }
+
+    debug!("Container '{}' PID: {}", name, pid);
+    Ok(pid)
+}
+
+/// Get the cgroup path for the container
+///
+/// This reads /proc/<pid>/cgroup to find the cgroup path.
+/// Supports both cgroup v1 and v2.
+///
+/// # Returns
+/// Cgroup path (e.g., "/sys/fs/cgroup/user.slice/...")
+///
+/// # Errors
+/// Returns Err if container doesn't exist, is not running, or cgroup path cannot be determined
+pub(super) async fn container_cgroup_path(engine: &str, name: &str) -> Result<String> {
+    // Get container PID first
+    let pid = container_pid(engine, name).await?;
+
+    // Read /proc/<pid>/cgroup
+    let cgroup_file = format!("/proc/{}/cgroup", pid);
+    let content = tokio::fs::read_to_string(&cgroup_file)
+        .await
+        .map_err(|e| JailError::Backend(format!("Failed to read {}: {}", cgroup_file, e)))?;
+
+    let full_path = parse_cgroup_file(&content)
+        .ok_or_else(|| JailError::Backend(format!("Invalid cgroup file for PID {}", pid)))?;
+
+    debug!("Container '{}' cgroup path: {}", name, full_path);
+    Ok(full_path)
+}
+
+/// Turn the first line of /proc/<pid>/cgroup into a path under /sys/fs/cgroup
+fn parse_cgroup_file(content: &str) -> Option<String> {
+    // Format (cgroup v2): "0::/system.slice/containerd.service"
+    // Format (cgroup v1): "12:memory:/user.slice/user-1000.slice/session-1.scope"
+    let cgroup_line = content.lines().next()?;
+
+    // Extract the path part (after the second colon for v1, or after :: for v2)
+    let path_part = if cgroup_line.contains("::") {
+        cgroup_line.split("::").nth(1)?
+    } else {
+        cgroup_line.split(':').nth(2)?
+    };
+
+    // For cgroup v2, the base is /sys/fs/cgroup
+    // For cgroup v1, it's /sys/fs/cgroup/<subsystem>
+    // We'll use v2 path format for now
+    Some(format!("/sys/fs/cgroup{}", path_part))
+}
+
+/// Apply eBPF host blocking to a container
+///
+/// This method:
+/// 1. Gets the container's cgroup path
+/// 2. Detects host IPs to block
+/// 3. Attaches an eBPF program to intercept connect() syscalls
+/// 4. Stores the blocker instance to prevent early detachment
+///
+/// # Errors
+/// Returns Err if container is not running, cgroup path cannot be determined,
+/// or eBPF program cannot be attached
+pub(super) async fn apply(engine: &str, name: &str) -> Result<()> {
+    // Get container's cgroup path
+    let cgroup_path = container_cgroup_path(engine, name).await?;
+    attach(name, &cgroup_path).await
+}
+
+async fn attach(name: &str, cgroup_path: &str) -> Result<()> {
+    // Get host IPs to block
+    let host_ips = crate::ebpf::get_host_ips()?;
+    info!("Detected {} host IPs to block", host_ips.len());
+
+    // Create eBPF blocker and attach to cgroup
+    let mut blocker = crate::ebpf::EbpfHostBlocker::new();
+    blocker.attach_to_cgroup(cgroup_path, &host_ips).await?;
+
+    // Store the blocker instance to prevent it from being dropped
+    // When dropped, the eBPF programs would be detached
+    let blockers = ebpf_blockers();
+    let mut blockers_map = blockers
+        .lock()
+        .map_err(|e| JailError::Backend(format!("Failed to lock eBPF blockers registry: {}", e)))?;
+    blockers_map.insert(name.to_string(), blocker);
+    debug!("Stored eBPF blocker for container '{}' in registry", name);
+
+    Ok(())
+}
+
+/// Drop the eBPF blocker of a removed container
+pub(super) fn release(name: &str) {
+    let blockers = ebpf_blockers();
+    if let Ok(mut blockers_map) = blockers.lock() {
+        if blockers_map.remove(name).is_some() {
+            debug!(
+                "Removed eBPF blocker for container '{}' from registry",
+                name
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_cgroup_file() {
+        assert_eq!(
+            parse_cgroup_file("0::/system.slice/docker-abc123.scope\n").as_deref(),
+            Some("/sys/fs/cgroup/system.slice/docker-abc123.scope")
+        );
+        assert_eq!(
+            parse_cgroup_file("12:memory:/user.slice/libpod-abc123.scope").as_deref(),
+            Some("/sys/fs/cgroup/user.slice/libpod-abc123.scope")
+        );
+        assert_eq!(parse_cgroup_file(""), None);
+        assert_eq!(parse_cgroup_file("garbage"), None);
+    }
+}
@@ -1,22 +1,17 @@
-use super::{run_command, JailBackend};
-use crate::config::JailConfig;
+use super::podman_api::PodmanApi;
+use super::{
+    extract_base_name, host_blocking, image_uses_nix, run_command, ExecOptions, ExecResult,
+    ExecStream, ImageSummary, JailBackend, JailStats, JailSummary, LogOptions, MountMode,
+    VolumeSummary,
+};
+use crate::config::{BackendType, JailConfig, PortMapping};
+use crate::cow::CowDirs;
 use crate::error::{JailError, Result};
-use crate::image;
 use async_trait::async_trait;
-use std::collections::HashMap;
-use std::sync::{Arc, Mutex, OnceLock};
+use std::path::Path;
 use tokio::process::Command;
 use tracing::{debug, info, warn};
 
-// Global registry to store eBPF blockers for active containers
-// This prevents them from being dropped (which would detach the eBPF programs)
-static EBPF_BLOCKERS: OnceLock<Arc<Mutex<HashMap<String, crate::ebpf::EbpfHostBlocker>>>> =
-    OnceLock::new();
-
-fn ebpf_blockers() -> &'static Arc<Mutex<HashMap<String, crate::ebpf::EbpfHostBlocker>>> {
-    EBPF_BLOCKERS.get_or_init(|| Arc::new(Mutex::new(HashMap::new())))
-}
-
 pub struct PodmanBackend;
 
 impl PodmanBackend {
@@ -24,209 +19,77 @@ impl PodmanBackend {
         Self
     }
 
-    /// Check if eBPF loader is running for this container and reattach if needed
-    /// This handles cases where the system rebooted but the container auto-started
-    async fn reattach_ebpf_if_needed(&self, name: &str) -> Result<()> {
-        // Check if this container has eBPF blocking enabled by checking for the label
-        // If the label doesn't exist or is false, skip eBPF reattachment
-        let mut label_cmd = Command::new("podman");
-        label_cmd
-            .arg("inspect")
-            .arg(name)
-            .arg("--format")
-            .arg("{{index .Config.Labels \"jail-ai.block-host\"}}");
-
-        let block_host_label = match run_command(&mut label_cmd).await {
-            Ok(output) => output.trim() == "true",
-            Err(_) => {
-                // Label not found or inspect failed, assume no eBPF needed
-                debug!(
-                    "No block-host label found for container {}, skipping eBPF check",
-                    name
-                );
-                return Ok(());
-            }
-        };
-
-        if !block_host_label {
-            debug!("Container {} does not have eBPF blocking enabled", name);
-            return Ok(());
-        }
-
-        // Check if container is using host networking
-        let mut network_cmd = Command::new("podman");
-        network_cmd
-            .arg("inspect")
-            .arg(name)
-            .arg("--format")
-            .arg("{{.HostConfig.NetworkMode}}");
-
-        if let Ok(network_mode) = run_command(&mut network_cmd).await {
-            if network_mode.trim() == "host" {
-                debug!("Container {} uses host networking, skipping eBPF", name);
-                return Ok(());
-            }
-        }
-
-        // Check if eBPF loader is already running for this container
-        let loader_running = self.is_ebpf_loader_running(name).await;
-
-        if loader_running {
-            debug!("eBPF loader already running for container {}", name);
-            return Ok(());
-        }
-
-        // Loader not running but should be - reattach eBPF
-        info!(
-            "⚠️  eBPF loader not running for container {} (likely due to system reboot)",
-            name
-        );
-        info!("Reattaching eBPF host blocking...");
-
-        // Get the actual cgroup path from the container
-        // This is more reliable than constructing it manually
-        let cgroup_path = match self.get_container_cgroup_path(name).await {
-            Ok(path) => path,
-            Err(e) => {
-                warn!("Failed to get cgroup path for container {}: {}", name, e);
-                warn!("eBPF host blocking will not be reattached");
-                return Ok(());
-            }
-        };
-
-        debug!("Found cgroup path for container {}: {}", name, cgroup_path);
-
-        // Verify cgroup exists
-        if !std::path::Path::new(&cgroup_path).exists() {
-            warn!("Cgroup path does not exist: {}", cgroup_path);
-            warn!("eBPF host blocking will not be reattached");
-            return Ok(());
-        }
-
-        // Get host IPs to block
-        let host_ips = crate::ebpf::get_host_ips()?;
-
-        // Create eBPF blocker and attach to cgroup
-        let mut blocker = crate::ebpf::EbpfHostBlocker::new();
-        blocker.attach_to_cgroup(&cgroup_path, &host_ips).await?;
-
-        // Store the blocker instance
-        let blockers = ebpf_blockers();
-        let mut blockers_map = blockers
-            .lock()
-            .map_err(|e| JailError::Backend(format!("Failed to lock eBPF blockers map: {}", e)))?;
-        blockers_map.insert(name.to_string(), blocker);
-
-        info!("✓ eBPF host blocking reattached for container {}", name);
-        Ok(())
+    /// Podman socket of the user, proxied into jails with `--podman`
+    fn host_socket() -> String {
+        // Get the user's UID for the socket path
+        let uid = std::process::Command::new("id")
+            .arg("-u")
+            .output()
+            .ok()
+            .and_then(|o| String::from_utf8(o.stdout).ok())
+            .map(|s| s.trim().to_string())
+            .unwrap_or_else(|| "1000".to_string());
+
+        format!("/run/user/{}/podman/podman.sock", uid)
     }
 
-    /// Check if eBPF loader process is running for a specific container  
-    /// by checking if we have a blocker in memory AND a loader process is running
-    async fn is_ebpf_loader_running(&self, container_name: &str) -> bool {
-        // ONLY trust our in-memory map - if we don't have a blocker stored,
-        // then we don't know if eBPF is active for this container
-
-        let has_blocker_in_memory = {
-            let blockers = ebpf_blockers();
-            if let Ok(blockers_map) = blockers.lock() {
-                blockers_map.contains_key(container_name)
-            } else {
-                false
-            }
-        }; // Lock is dropped here before any await
-
-        if !has_blocker_in_memory {
-            debug!(
-                "No eBPF blocker in memory for container {} - needs reattach",
-                container_name
-            );
-            return false;
+    /// Start the container if it is stopped and make sure host blocking is attached
+    async fn prepare_exec(&self, name: &str) -> Result<()> {
+        if !self.exists(name).await? {
+            return Err(JailError::NotFound(format!("Jail '{name}' does not exist")));
         }
+        crate::socket_proxy::ensure_running(name).await?;
 
-        // We have a blocker in memory - verify the loader process is actually running
-        debug!(
-            "Found eBPF blocker in memory for container {}, checking if loader is running",
-            container_name
-        );
-
-        let mut ps_cmd = Command::new("ps");
-        ps_cmd.args(["aux"]);
-
-        if let Ok(output) = run_command(&mut ps_cmd).await {
-            for line in output.lines() {
-                if line.contains("jail-ai-ebpf-loader") && !line.contains("grep") {
-                    debug!("eBPF loader process is running");
-                    return true;
-                }
+        let mut was_stopped = false;
+        // Check container state
+        if let Ok(state) = self.container_state(name).await {
+            let state = state.as_str();
+            was_stopped = state == "exited" || state == "stopped" || state == "created";
+            if was_stopped {
+                info!("Container {} is {}, starting it...", name, state);
+                let mut start_cmd = Command::new("podman");
+                start_cmd.arg("start").arg(name);
+                run_command(&mut start_cmd).await?;
+                info!("Container {} started successfully", name);
+
+                // Wait a bit for cgroup to be fully initialized
+                tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
+
+                // Re-attach eBPF if needed after container restart
+                host_blocking::reattach_if_needed("podman", name).await?;
             }
         }
 
-        // Blocker in memory but no loader process - stale entry
-        debug!("Blocker in memory but loader not running - needs reattach");
-        false
-    }
-
-    async fn image_exists(&self, image: &str) -> Result<bool> {
-        let mut cmd = Command::new("podman");
-        cmd.arg("image").arg("exists").arg(image);
-
-        match cmd.output().await {
-            Ok(output) => Ok(output.status.success()),
-            Err(_) => Ok(false),
+        // Always check eBPF status when entering an existing running container
+        // (in case of system reboot where container auto-starts but loader doesn't)
+        if !was_stopped {
+            host_blocking::reattach_if_needed("podman", name).await?;
         }
+        Ok(())
     }
 
-    /// Check if an image uses Nix by examining its name/tag
-    /// Images with Nix will have "nix" in their layer tag or be the nix base image
-    fn image_uses_nix(image: &str) -> bool {
-        // Check if it's the Nix base image
-        if image.contains("jail-ai-nix:") {
-            return true;
-        }
-
-        // Check if it's an agent/project image with nix in the tag
-        // Format: localhost/jail-ai-agent-claude:base-nix-rust or base-rust-nix
-        if let Some(tag_part) = image.split(':').nth(1) {
-            // Check if "nix" appears as a layer component
-            // Valid: "base-nix", "base-nix-rust", "base-rust-nix"
-            // Invalid: "phoenix" (contains "nix" but not as a separate layer)
-            for component in tag_part.split('-') {
-                if component == "nix" {
-                    return true;
+    /// Container status (`running`, `exited`, `created`...)
+    async fn container_state(&self, name: &str) -> Result<String> {
+        if let Some(api) = PodmanApi::shared() {
+            match api.container_inspect(name).await {
+                Ok(Some(container)) => {
+                    return Ok(container["State"]["Status"]
+                        .as_str()
+                        .unwrap_or_default()
+                        .to_string())
                 }
+                Ok(None) => return Err(JailError::NotFound(format!("Jail '{name}' not found"))),
+                Err(e) => debug!("{}, falling back to the podman CLI", e),
             }
         }
 
-        false
-    }
-
-    /// Get the image currently used by a container
-    pub async fn get_container_image(&self, name: &str) -> Result<String> {
-        let mut cmd = Command::new("podman");
-        cmd.arg("inspect")
+        let mut state_cmd = Command::new("podman");
+        state_cmd
+            .arg("inspect")
             .arg(name)
             .arg("--format")
-            .arg("{{.Config.Image}}");
-
-        let output = run_command(&mut cmd).await?;
-        Ok(output.trim().to_string())
-    }
-
-    /// Extract base name by stripping agent suffix
-    /// Jail name format: jail__{project}__{hash}__{agent}
-    /// Returns: jail__{project}__{hash}
-    ///
-    /// Simply strips the last segment after __ (the agent name).
-    /// If there's no __, returns the name as-is (e.g., test names).
-    fn extract_base_name(name: &str) -> String {
-        if let Some(pos) = name.rfind("__") {
-            // Strip the last segment (agent name)
-            name[..pos].to_string()
-        } else {
-            // No __ found, return as-is (simple test names like "test")
-            name.to_string()
-        }
+            .arg("{{.State.Status}}");
+        Ok(run_command(&mut state_cmd).await?.trim().to_string())
     }
 
     fn build_run_args(&self, config: &JailConfig) -> Vec<String> {
@@ -245,24 +108,22 @@ impl PodmanBackend {
             // "--cap-add=CAP_NET_ADMIN".to_string(),
         ]);
 
-        // Add label to track if eBPF host blocking is enabled
-        // This allows us to reattach eBPF after system reboots
-        args.push("--label".to_string());
-        args.push(format!("jail-ai.block-host={}", config.block_host));
+        // Record the configuration, and whether eBPF host blocking must be reattached after reboots
+        args.extend(super::label_args(config));
 
         // Persistent volume for /home/agent to preserve data across upgrades
         // Agent-specific (not shared across different agents)
-        let home_volume = format!("{}__home", config.name);
+        let home_volume = super::home_volume_name(config);
         args.push("-v".to_string());
         args.push(format!("{home_volume}:/home/agent"));
 
         // Extract base name (strip agent suffix if present) for nix volume
         // jail__project__abc123__claude -> jail__project__abc123
-        let base_name = Self::extract_base_name(&config.name);
+        let base_name = extract_base_name(&config.name);
 
         // Per-jail Nix store volume for containers using Nix
         // Shared across all agents working on the same project
-        if Self::image_uses_nix(&config.base_image) {
+        if image_uses_nix(&config.base_image) {
             let nix_volume = format!("{}__nix", base_name);
             debug!(
                 "Detected Nix in image, mounting per-jail Nix store volume: {}",
@@ -272,25 +133,17 @@ impl PodmanBackend {
             args.push(format!("{nix_volume}:/nix"));
         }
 
-        // Podman-in-Podman: mount host's Podman socket
+        // Podman-in-Podman: mount the filtered proxy of the host's Podman socket
         // This allows running containers inside the jail (useful for MCP agents)
         if config.podman_socket {
-            // Get the user's UID for the socket path
-            let uid = std::process::Command::new("id")
-                .arg("-u")
-                .output()
-                .ok()
-                .and_then(|o| String::from_utf8(o.stdout).ok())
-                .map(|s| s.trim().to_string())
-                .unwrap_or_else(|| "1000".to_string());
-
-            let socket_path = format!("/run/user/{}/podman/podman.sock", uid);
+            let socket_path = Self::host_socket();
 
             // Check if the socket exists
             if std::path::Path::new(&socket_path).exists() {
-                debug!("Mounting Podman socket: {}", socket_path);
+                let proxy_dir = crate::socket_proxy::socket_dir(&config.name);
+                debug!("Mounting Podman socket proxy: {}", proxy_dir.display());
                 args.push("-v".to_string());
-                args.push(format!("{}:/run/podman/podman.sock", socket_path));
+                args.push(format!("{}:/run/podman", proxy_dir.display()));
 
                 // Set CONTAINER_HOST environment variable for podman remote
                 args.push("-e".to_string());
@@ -325,19 +178,26 @@ impl PodmanBackend {
         if config.network.enabled {
             for port_mapping in &config.port_mappings {
                 args.push("-p".to_string());
-                args.push(format!(
-                    "{}:{}/{}",
-                    port_mapping.host_port, port_mapping.container_port, port_mapping.protocol
-                ));
+                args.push(port_mapping.publish_arg());
             }
         }
 
         // Bind mounts
         for mount in &config.bind_mounts {
-            let bind_arg = if mount.readonly {
-                format!("{}:{}:ro", mount.source.display(), mount.target.display())
-            } else {
-                format!("{}:{}", mount.source.display(), mount.target.display())
+            let bind_arg = match super::mount_mode(config, mount) {
+                MountMode::ReadOnly => {
+                    format!("{}:{}:ro", mount.source.display(), mount.target.display())
+                }
+                MountMode::ReadWrite => {
+                    format!("{}:{}", mount.source.display(), mount.target.display())
+                }
+                MountMode::Overlay(dirs) => format!(
+                    "{}:{}:O,upperdir={},workdir={}",
+                    mount.source.display(),
+                    mount.target.display(),
+                    dirs.upper.display(),
+                    dirs.work.display()
+                ),
             };
             args.push("-v".to_string());
             args.push(bind_arg);
@@ -350,14 +210,10 @@ impl PodmanBackend {
         }
 
         // Resource limits
-        if let Some(memory_mb) = config.limits.memory_mb {
-            args.push("-m".to_string());
-            args.push(format!("{memory_mb}m"));
-        }
-        if let Some(cpu_quota) = config.limits.cpu_quota {
-            args.push("--cpus".to_string());
-            args.push(format!("{}", cpu_quota as f64 / 100.0));
-        }
+        args.extend(super::limit_args(&config.limits));
+
+        // Hardening (keep-id runs the jail as the host user, never as root)
+        args.extend(super::hardening_args(config, false));
 
         // Base image
         args.push(config.base_image.clone());
@@ -370,137 +226,6 @@ impl PodmanBackend {
 
         args
     }
-
-    /// Get the PID of the container's main process
-    ///
-    /// # Arguments
-    /// * `name` - Name of the container
-    ///
-    /// # Returns
-    /// Container PID (process ID on the host)
-    ///
-    /// # Errors
-    /// Returns Err if container doesn't exist or PID cannot be retrieved
-    pub async fn get_container_pid(&self, name: &str) -> Result<u32> {
-        let mut cmd = Command::new("podman");
-        cmd.arg("inspect")
-            .arg(name)
-            .arg("--format")
-            .arg("{{.State.Pid}}");
-
-        let output = run_command(&mut cmd).await?;
-        let pid = output
-            .trim()
-            .parse::<u32>()
-            .map_err(|e| JailError::Backend(format!("Failed to parse PID: {}", e)))?;
-
-        if pid == 0 {
-            return Err(JailError::Backend(format!(
-                "Container '{}' is not running (PID is 0)",
-                name
-            )));
-        }
-
-        debug!("Container '{}' PID: {}", name, pid);
-        Ok(pid)
-    }
-
-    /// Get the cgroup path for the container
-    ///
-    /// This reads /proc/<pid>/cgroup to find the cgroup path.
-    /// Supports both cgroup v1 and v2.
-    ///
-    /// # Arguments
-    /// * `name` - Name of the container
-    ///
-    /// # Returns
-    /// Cgroup path (e.g., "/sys/fs/cgroup/user.slice/...")
-    ///
-    /// # Errors
-    /// Returns Err if container doesn't exist, is not running, or cgroup path cannot be determined
-    pub async fn get_container_cgroup_path(&self, name: &str) -> Result<String> {
-        // Get container PID first
-        let pid = self.get_container_pid(name).await?;
-
-        // Read /proc/<pid>/cgroup
-        let cgroup_file = format!("/proc/{}/cgroup", pid);
-        let content = tokio::fs::read_to_string(&cgroup_file)
-            .await
-            .map_err(|e| JailError::Backend(format!("Failed to read {}: {}", cgroup_file, e)))?;
-
-        // Parse cgroup file
-        // Format (cgroup v2): "0::/system.slice/containerd.service"
-        // Format (cgroup v1): "12:memory:/user.slice/user-1000.slice/session-1.scope"
-
-        let cgroup_path = content
-            .lines()
-            .next()
-            .ok_or_else(|| JailError::Backend(format!("Empty cgroup file for PID {}", pid)))?;
-
-        // Extract the path part (after the second colon for v1, or after :: for v2)
-        let path_part = if cgroup_path.contains("::") {
-            // cgroup v2: "0::/path"
-            cgroup_path
-                .split("::")
-                .nth(1)
-                .ok_or_else(|| JailError::Backend("Invalid cgroup v2 format".to_string()))?
-        } else {
-            // cgroup v1: "12:subsystem:/path"
-            cgroup_path
-                .split(':')
-                .nth(2)
-                .ok_or_else(|| JailError::Backend("Invalid cgroup v1 format".to_string()))?
-        };
-
-        // For cgroup v2, the base is /sys/fs/cgroup
-        // For cgroup v1, it's /sys/fs/cgroup/<subsystem>
-        // We'll use v2 path format for now
-        let full_path = format!("/sys/fs/cgroup{}", path_part);
-
-        debug!("Container '{}' cgroup path: {}", name, full_path);
-        Ok(full_path)
-    }
-
-    /// Apply eBPF host blocking to a container
-    ///
-    /// This method:
-    /// 1. Gets the container's cgroup path
-    /// 2. Detects host IPs to block
-    /// 3. Attaches an eBPF program to intercept connect() syscalls
-    /// 4. Stores the blocker instance to prevent early detachment
-    ///
-    /// # Arguments
-    /// * `name` - Name of the container
-    ///
-    /// # Returns
-    /// Ok(()) if successful
-    ///
-    /// # Errors
-    /// Returns Err if container is not running, cgroup path cannot be determined,
-    /// or eBPF program cannot be attached
-    async fn apply_ebpf_host_blocking(&self, name: &str) -> Result<()> {
-        // Get container's cgroup path
-        let cgroup_path = self.get_container_cgroup_path(name).await?;
-
-        // Get host IPs to block
-        let host_ips = crate::ebpf::get_host_ips()?;
-        info!("Detected {} host IPs to block", host_ips.len());
-
-        // Create eBPF blocker and attach to cgroup
-        let mut blocker = crate::ebpf::EbpfHostBlocker::new();
-        blocker.attach_to_cgroup(&cgroup_path, &host_ips).await?;
-
-        // Store the blocker instance to prevent it from being dropped
-        // When dropped, the eBPF programs would be detached
-        let blockers = ebpf_blockers();
-        let mut blockers_map = blockers.lock().map_err(|e| {
-            JailError::Backend(format!("Failed to lock eBPF blockers registry: {}", e))
-        })?;
-        blockers_map.insert(name.to_string(), blocker);
-        debug!("Stored eBPF blocker for container '{}' in registry", name);
-
-        Ok(())
-    }
 }
 
 impl Default for PodmanBackend {
@@ -534,76 +259,19 @@ impl JailBackend for PodmanBackend {
         }
 
         // Determine which image to use
-        let actual_image = if config.base_image == image::DEFAULT_IMAGE_NAME
-            && config.use_layered_images
-        {
-            // Use layered image system with auto-detection
-            info!("Using layered image system with auto-detection");
-
-            // Try to find workspace path from bind mounts
-            let workspace_path = config
-                .bind_mounts
-                .iter()
-                .find(|m| {
-                    m.target
-                        .to_str()
-                        .map(|s| s.contains("workspace"))
-                        .unwrap_or(false)
-                })
-                .map(|m| m.source.clone())
-                .unwrap_or_else(|| {
-                    std::env::current_dir().unwrap_or_else(|_| std::path::PathBuf::from("."))
-                });
-
-            // Try to detect agent from jail name (format: jail__{project}__{hash}__{agent})
-            let agent_name = config
-                .name
-                .rsplit("__")
-                .next()
-                .and_then(|suffix| match suffix {
-                    "claude" | "claude-code-router" | "copilot" | "cursor" | "gemini" | "jules"
-                    | "codex" | "pi" => Some(suffix),
-                    _ => None,
-                });
-
-            debug!(
-                "Workspace path: {:?}, Agent: {:?}",
-                workspace_path, agent_name
-            );
-
-            // Build the appropriate layered image
-            crate::image_layers::ensure_layered_image_available(
-                &workspace_path,
-                agent_name,
-                config.upgrade,
-                &config.force_layers,
-                config.isolated,
-                config.verbose,
-                config.no_nix,
-            )
-            .await?
-        } else if config.base_image == image::DEFAULT_IMAGE_NAME {
-            // Default image should use layered system
-            // If use_layered_images is false, it's likely a configuration error
-            return Err(JailError::Backend(
-                    "Default image requires layered images to be enabled. Please set use_layered_images to true.".to_string()
-                ));
-        } else {
-            // For custom images, check if they exist and pull if needed
-            let image_exists = self.image_exists(&config.base_image).await?;
-            if !image_exists {
-                debug!("Image {} not found locally, pulling...", config.base_image);
-                let mut pull_cmd = Command::new("podman");
-                pull_cmd.arg("pull").arg(&config.base_image);
-
-                run_command(&mut pull_cmd)
-                    .await
-                    .map_err(|e| JailError::Backend(format!("Failed to pull image: {e}")))?;
-            } else {
-                debug!("Using local image: {}", config.base_image);
-            }
-            config.base_image.clone()
-        };
+        let actual_image = super::resolve_image(config).await?;
+        if config.seccomp {
+            super::install_seccomp_profile().await?;
+        }
+        if config.cow {
+            crate::cow::check_mounts(config)?;
+            CowDirs::for_jail(&config.name)?.create()?;
+        }
+        let socket_path = Self::host_socket();
+        if config.podman_socket && std::path::Path::new(&socket_path).exists() {
+            crate::socket_proxy::prepare(config, "podman", std::path::Path::new(&socket_path))
+                .await?;
+        }
 
         // Create and start the container with the determined image
         let mut modified_config = config.clone();
@@ -630,42 +298,13 @@ impl JailBackend for PodmanBackend {
                     config.name
                 );
                 // Propagate eBPF loading errors - container creation must fail if eBPF fails
-                self.apply_ebpf_host_blocking(&config.name).await?;
+                host_blocking::apply("podman", &config.name).await?;
                 info!("✓ eBPF host blocking applied successfully");
             }
         }
 
         // Pre-create directories if needed (for worktree support)
-        if !config.pre_create_dirs.is_empty() {
-            info!(
-                "Pre-creating {} directories in container for worktree support",
-                config.pre_create_dirs.len()
-            );
-
-            for dir in &config.pre_create_dirs {
-                debug!("Creating directory: {}", dir.display());
-
-                let mut mkdir_cmd = Command::new("podman");
-                mkdir_cmd
-                    .arg("exec")
-                    .arg(&config.name)
-                    .arg("mkdir")
-                    .arg("-p")
-                    .arg(dir.to_str().ok_or_else(|| {
-                        JailError::Backend(format!("Invalid directory path: {}", dir.display()))
-                    })?);
-
-                run_command(&mut mkdir_cmd).await.map_err(|e| {
-                    JailError::Backend(format!(
-                        "Failed to create directory {} in container: {}",
-                        dir.display(),
-                        e
-                    ))
-                })?;
-            }
-
-            info!("Pre-created directories successfully");
-        }
+        super::pre_create_dirs(config).await?;
 
         info!("Jail {} created successfully", config.name);
         Ok(())
@@ -673,17 +312,11 @@ impl JailBackend for PodmanBackend {
 
     async fn remove(&self, name: &str, remove_volume: bool) -> Result<()> {
         info!("Removing podman jail: {}", name);
+        let home_volume = super::jail_home_volume(self, name).await;
 
         // Remove eBPF blocker if it exists
-        let blockers = ebpf_blockers();
-        if let Ok(mut blockers_map) = blockers.lock() {
-            if blockers_map.remove(name).is_some() {
-                debug!(
-                    "Removed eBPF blocker for container '{}' from registry",
-                    name
-                );
-            }
-        }
+        host_blocking::release(name);
+        crate::socket_proxy::remove(name);
 
         // Remove container (with force flag to stop if running)
         let mut cmd = Command::new("podman");
@@ -692,8 +325,7 @@ impl JailBackend for PodmanBackend {
         run_command(&mut cmd).await?;
 
         if remove_volume {
-            // Remove associated agent-specific home volume
-            let home_volume = format!("{name}__home");
+            // Remove the home volume, refused by podman while another jail shares it
             let mut vol_cmd = Command::new("podman");
             vol_cmd.arg("volume").arg("rm").arg(&home_volume);
 
@@ -707,7 +339,7 @@ impl JailBackend for PodmanBackend {
             }
 
             // Extract base name (strip agent suffix if present) for nix volume
-            let base_name = Self::extract_base_name(name);
+            let base_name = extract_base_name(name);
 
             // Remove associated nix volume (if it exists)
             let nix_volume = format!("{base_name}__nix");
@@ -734,88 +366,53 @@ impl JailBackend for PodmanBackend {
         Ok(())
     }
 
-    async fn exec(&self, name: &str, command: &[String], interactive: bool) -> Result<String> {
+    async fn exec_with(
+        &self,
+        name: &str,
+        command: &[String],
+        options: &ExecOptions,
+    ) -> Result<ExecResult> {
         debug!(
-            "Executing command in jail {}: {:?} (interactive: {})",
-            name, command, interactive
+            "Executing command in jail {}: {:?} ({:?})",
+            name, command, options
         );
+        self.prepare_exec(name).await?;
+        super::engine_exec("podman", name, command, options).await
+    }
 
-        let mut was_stopped = false;
-        // Check if container exists and is stopped
-        if self.exists(name).await? {
-            // Check container state
-            let mut state_cmd = Command::new("podman");
-            state_cmd
-                .arg("inspect")
-                .arg(name)
-                .arg("--format")
-                .arg("{{.State.Status}}");
-
-            if let Ok(state) = run_command(&mut state_cmd).await {
-                let state = state.trim();
-                was_stopped = state == "exited" || state == "stopped" || state == "created";
-                if was_stopped {
-                    info!("Container {} is {}, starting it...", name, state);
-                    let mut start_cmd = Command::new("podman");
-                    start_cmd.arg("start").arg(name);
-                    run_command(&mut start_cmd).await?;
-                    info!("Container {} started successfully", name);
-
-                    // Wait a bit for cgroup to be fully initialized
-                    tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
-
-                    // Re-attach eBPF if needed after container restart
-                    self.reattach_ebpf_if_needed(name).await?;
-                }
-            }
-        }
-
-        // Always check eBPF status when entering an existing running container
-        // (in case of system reboot where container auto-starts but loader doesn't)
-        if !was_stopped {
-            self.reattach_ebpf_if_needed(name).await?;
-        }
-
-        let mut cmd = Command::new("podman");
-        cmd.arg("exec");
-
-        if interactive {
-            cmd.arg("-it");
-        }
-
-        cmd.arg(name);
+    async fn exec_stream(
+        &self,
+        name: &str,
+        command: &[String],
+        options: &ExecOptions,
+    ) -> Result<ExecStream> {
+        debug!(
+            "Streaming command in jail {}: {:?} ({:?})",
+            name, command, options
+        );
+        self.prepare_exec(name).await?;
+        super::engine_exec_stream("podman", name, command, options)
+    }
 
-        for arg in command {
-            cmd.arg(arg);
-        }
+    fn exec_argv(
+        &self,
+        name: &str,
+        command: &[String],
+        options: &ExecOptions,
+    ) -> Result<Vec<String>> {
+        let mut argv = vec!["podman".to_string()];
+        argv.extend(super::engine_exec_args(name, command, options));
+        Ok(argv)
+    }
 
-        if interactive {
-            // Interactive mode: inherit stdio for direct user interaction
-            use std::process::Stdio;
-            cmd.stdin(Stdio::inherit())
-                .stdout(Stdio::inherit())
-                .stderr(Stdio::inherit());
-
-            let status = cmd.status().await.map_err(|e| {
-                JailError::Backend(format!("Failed to execute interactive command: {e}"))
-            })?;
-
-            if !status.success() {
-                return Err(JailError::ExecutionFailed(format!(
-                    "Interactive command failed with status: {status}"
-                )));
+    async fn exists(&self, name: &str) -> Result<bool> {
+        if let Some(api) = PodmanApi::shared() {
+            match api.container_exists(name).await {
+                Ok(exists) => return Ok(exists),
+                Err(e) => debug!("{}, falling back to the podman CLI", e),
             }
-
-            Ok(String::new()) // No output to capture in interactive mode
-        } else {
-            // Non-interactive mode: capture output
-            let output = run_command(&mut cmd).await?;
-            debug!("Command output: {}", output);
-            Ok(output)
         }
-    }
 
-    async fn exists(&self, name: &str) -> Result<bool> {
         let mut cmd = Command::new("podman");
         cmd.arg("ps")
             .arg("-a")
@@ -833,16 +430,25 @@ impl JailBackend for PodmanBackend {
     async fn list_all(&self) -> Result<Vec<String>> {
         debug!("Listing all jail-ai containers");
 
-        let mut cmd = Command::new("podman");
-        cmd.arg("ps").arg("-a").arg("--format").arg("{{.Names}}");
-
-        let output = run_command(&mut cmd).await?;
+        let names = match PodmanApi::shared() {
+            Some(api) => api.container_names().await.ok(),
+            None => None,
+        };
+        let names = match names {
+            Some(names) => names,
+            None => {
+                let mut cmd = Command::new("podman");
+                cmd.arg("ps").arg("-a").arg("--format").arg("{{.Names}}");
+
+                let output = run_command(&mut cmd).await?;
+                output.lines().map(|line| line.to_string()).collect()
+            }
+        };
 
         // Filter containers that start with "jail__"
-        let jails: Vec<String> = output
-            .lines()
-            .filter(|line| line.starts_with("jail__"))
-            .map(|line| line.to_string())
+        let jails: Vec<String> = names
+            .into_iter()
+            .filter(|name| name.starts_with("jail__"))
             .collect();
 
         debug!("Found {} jail-ai containers", jails.len());
@@ -850,6 +456,17 @@ impl JailBackend for PodmanBackend {
     }
 
     async fn is_running(&self, name: &str) -> Result<bool> {
+        if let Some(api) = PodmanApi::shared() {
+            match api.container_inspect(name).await {
+                Ok(container) => {
+                    return Ok(container
+                        .map(|c| c["State"]["Running"].as_bool().unwrap_or(false))
+                        .unwrap_or(false))
+                }
+                Err(e) => debug!("{}, falling back to the podman CLI", e),
+            }
+        }
+
         let mut cmd = Command::new("podman");
         cmd.arg("ps")
             .arg("--filter")
@@ -865,6 +482,7 @@ impl JailBackend for PodmanBackend {
 
     async fn start(&self, name: &str) -> Result<()> {
         info!("Starting container: {}", name);
+        crate::socket_proxy::ensure_running(name).await?;
 
         let mut cmd = Command::new("podman");
         cmd.arg("start").arg(name);
@@ -874,9 +492,28 @@ impl JailBackend for PodmanBackend {
         Ok(())
     }
 
+    async fn list_summaries(&self) -> Result<Vec<JailSummary>> {
+        let names = self.list_all().await?;
+        super::engine_summaries("podman", &names).await
+    }
+
     async fn inspect(&self, name: &str) -> Result<JailConfig> {
         debug!("Inspecting jail: {}", name);
 
+        if let Some(api) = PodmanApi::shared() {
+            match api.container_inspect(name).await {
+                Ok(Some(container)) => {
+                    return Ok(super::config_from_inspect(
+                        name,
+                        &container,
+                        BackendType::Podman,
+                    ))
+                }
+                Ok(None) => return Err(JailError::NotFound(format!("Jail '{name}' not found"))),
+                Err(e) => debug!("{}, falling back to the podman CLI", e),
+            }
+        }
+
         if !self.exists(name).await? {
             return Err(JailError::NotFound(format!("Jail '{name}' not found")));
         }
@@ -894,141 +531,102 @@ impl JailBackend for PodmanBackend {
             .and_then(|arr| arr.first())
             .ok_or_else(|| JailError::Backend("Empty inspect output".to_string()))?;
 
-        // Extract configuration
-        let image = container["Config"]["Image"]
-            .as_str()
-            .unwrap_or(image::DEFAULT_IMAGE_NAME)
-            .to_string();
-
-        // Extract mounts
-        let mut bind_mounts = Vec::new();
-        if let Some(mounts) = container["Mounts"].as_array() {
-            for mount in mounts {
-                if mount["Type"].as_str() == Some("bind") {
-                    let source = mount["Source"].as_str().unwrap_or("").to_string();
-                    let destination = mount["Destination"].as_str().unwrap_or("").to_string();
-                    let readonly = mount["RW"].as_bool().map(|rw| !rw).unwrap_or(false);
-
-                    if !source.is_empty() && !destination.is_empty() {
-                        bind_mounts.push(crate::config::BindMount {
-                            source: source.into(),
-                            target: destination.into(),
-                            readonly,
-                        });
-                    }
-                }
-            }
-        }
+        Ok(super::config_from_inspect(
+            name,
+            container,
+            BackendType::Podman,
+        ))
+    }
 
-        // Extract environment variables
-        let mut environment = Vec::new();
-        if let Some(env_array) = container["Config"]["Env"].as_array() {
-            for env in env_array {
-                if let Some(env_str) = env.as_str() {
-                    if let Some(pos) = env_str.find('=') {
-                        let key = env_str[..pos].to_string();
-                        let value = env_str[pos + 1..].to_string();
-                        // Skip system environment variables
-                        if !key.starts_with("PATH") && !key.starts_with("HOME") && key != "HOSTNAME"
-                        {
-                            environment.push((key, value));
-                        }
-                    }
-                }
-            }
-        }
+    async fn stop(&self, name: &str) -> Result<()> {
+        info!("Stopping container: {}", name);
+        super::engine_action("podman", "stop", name).await?;
+        // The eBPF programs go away with the container cgroup
+        host_blocking::release(name);
+        crate::socket_proxy::stop(name);
+        Ok(())
+    }
 
-        // Extract network settings
-        let network_mode = container["HostConfig"]["NetworkMode"]
-            .as_str()
-            .unwrap_or("default");
-        let network = crate::config::NetworkConfig {
-            enabled: network_mode != "none",
-            // Check for both "slirp4netns" and "private" for backward compatibility
-            // (older versions incorrectly used "private" which isn't a standard mode)
-            private: network_mode == "slirp4netns"
-                || network_mode == "private"
-                || network_mode == "bridge",
-            host: network_mode == "host",
-        };
+    async fn restart(&self, name: &str) -> Result<()> {
+        info!("Restarting container: {}", name);
+        crate::socket_proxy::ensure_running(name).await?;
+        super::engine_action("podman", "restart", name).await?;
+        host_blocking::release(name);
 
-        // Extract port mappings
-        let mut port_mappings = Vec::new();
-        if let Some(port_bindings) = container["HostConfig"]["PortBindings"].as_object() {
-            for (container_port_proto, bindings) in port_bindings {
-                // container_port_proto format: "5432/tcp"
-                let parts: Vec<&str> = container_port_proto.split('/').collect();
-                if parts.len() == 2 {
-                    if let Ok(container_port) = parts[0].parse::<u16>() {
-                        let protocol = parts[1].to_string();
-                        // bindings is an array of host port bindings
-                        if let Some(bindings_array) = bindings.as_array() {
-                            for binding in bindings_array {
-                                if let Some(host_port_str) = binding["HostPort"].as_str() {
-                                    if let Ok(host_port) = host_port_str.parse::<u16>() {
-                                        port_mappings.push(crate::config::PortMapping {
-                                            host_port,
-                                            container_port,
-                                            protocol: protocol.clone(),
-                                        });
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
-            }
-        }
+        // Wait a bit for cgroup to be fully initialized, then re-attach eBPF
+        tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
+        host_blocking::reattach_if_needed("podman", name).await
+    }
 
-        // Extract resource limits
-        let memory_mb = container["HostConfig"]["Memory"]
-            .as_i64()
-            .filter(|&m| m > 0)
-            .map(|m| (m / 1024 / 1024) as u64);
-
-        let cpu_quota = container["HostConfig"]["CpuQuota"]
-            .as_i64()
-            .filter(|&q| q > 0)
-            .and_then(|quota| {
-                container["HostConfig"]["CpuPeriod"]
-                    .as_i64()
-                    .map(|period| ((quota as f64 / period as f64) * 100.0) as u32)
-            });
-
-        // Extract block_host from label
-        let block_host = container["Config"]["Labels"]["jail-ai.block-host"]
-            .as_str()
-            .map(|s| s == "true")
-            .unwrap_or(false);
-
-        Ok(JailConfig {
-            name: name.to_string(),
-            backend: crate::config::BackendType::Podman,
-            base_image: image,
-            bind_mounts,
-            environment,
-            network,
-            port_mappings,
-            limits: crate::config::ResourceLimits {
-                memory_mb,
-                cpu_quota,
-            },
-            upgrade: false,
-            force_layers: Vec::new(),
-            use_layered_images: true,
-            isolated: false,
-            verbose: false,
-            pre_create_dirs: Vec::new(), // Not persisted in container metadata
-            no_nix: false,
-            block_host,
-            podman_socket: false, // Not persisted in container metadata
-        })
+    async fn pause(&self, name: &str) -> Result<()> {
+        super::engine_action("podman", "pause", name).await
+    }
+
+    async fn unpause(&self, name: &str) -> Result<()> {
+        super::engine_action("podman", "unpause", name).await
+    }
+
+    async fn logs(&self, name: &str, options: &LogOptions) -> Result<()> {
+        super::engine_logs("podman", name, options).await
+    }
+
+    async fn stats(&self, name: &str) -> Result<JailStats> {
+        super::engine_stats("podman", name).await
+    }
+
+    async fn port_mappings(&self, name: &str) -> Result<Vec<PortMapping>> {
+        super::engine_port_mappings("podman", name).await
+    }
+
+    async fn active_sessions(&self, name: &str) -> Result<usize> {
+        super::engine_active_sessions("podman", name).await
+    }
+
+    async fn list_volumes(&self) -> Result<Vec<VolumeSummary>> {
+        super::engine_list_volumes("podman").await
+    }
+
+    async fn remove_volume(&self, volume: &str) -> Result<()> {
+        super::engine_remove_volume("podman", volume).await
+    }
+
+    async fn export_volume(&self, volume: &str, archive: &Path) -> Result<()> {
+        super::engine_export_volume("podman", volume, archive).await
+    }
+
+    async fn import_volume(&self, volume: &str, archive: &Path) -> Result<()> {
+        super::engine_import_volume("podman", volume, archive).await
+    }
+
+    async fn reset_volume(&self, volume: &str, keep: &[String]) -> Result<()> {
+        super::engine_reset_volume("podman", volume, keep).await
+    }
+
+    async fn commit(&self, name: &str, image: &str, labels: &[(String, String)]) -> Result<()> {
+        super::engine_commit("podman", name, image, labels).await
+    }
+
+    async fn list_images(&self) -> Result<Vec<ImageSummary>> {
+        super::engine_list_images("podman").await
+    }
+
+    async fn remove_image(&self, image: &str) -> Result<()> {
+        super::engine_remove_image("podman", image).await
+    }
+
+    async fn copy_in(&self, name: &str, source: &Path, target: &Path) -> Result<()> {
+        super::engine_copy("podman", name, source, target, true).await
+    }
+
+    async fn copy_out(&self, name: &str, source: &Path, target: &Path) -> Result<()> {
+        super::engine_copy("podman", name, source, target, false).await
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::cli::Commands;
 
     #[test]
     fn test_build_run_args() {
@@ -1050,6 +648,7 @@ mod tests {
             limits: crate::config::ResourceLimits {
                 memory_mb: Some(512),
                 cpu_quota: Some(50),
+                ..Default::default()
             },
             upgrade: false,
             force_layers: Vec::new(),
@@ -1060,6 +659,10 @@ mod tests {
             no_nix: false,
             block_host: false,
             podman_socket: false,
+            hardened: false,
+            seccomp: false,
+            cow: false,
+            shared_home: false,
         };
 
         let args = backend.build_run_args(&config);
@@ -1117,20 +720,15 @@ mod tests {
                 host: false,
             },
             port_mappings: vec![
-                crate::config::PortMapping {
-                    host_port: 8080,
-                    container_port: 80,
-                    protocol: "tcp".to_string(),
-                },
-                crate::config::PortMapping {
-                    host_port: 5432,
-                    container_port: 5432,
-                    protocol: "tcp".to_string(),
-                },
+                Commands::parse_port("8080:80").unwrap(),
+                Commands::parse_port("0.0.0.0:5432:5432").unwrap(),
+                Commands::parse_port("8000-8010:9000-9010/udp").unwrap(),
+                Commands::parse_port("0:3000").unwrap(),
             ],
             limits: crate::config::ResourceLimits {
                 memory_mb: None,
                 cpu_quota: None,
+                ..Default::default()
             },
             upgrade: false,
             force_layers: Vec::new(),
@@ -1141,14 +739,20 @@ mod tests {
             pre_create_dirs: Vec::new(),
             block_host: false,
             podman_socket: false,
+            hardened: false,
+            seccomp: false,
+            cow: false,
+            shared_home: false,
         };
 
         let args = backend.build_run_args(&config);
 
         // Verify port mappings are included
         assert!(args.contains(&"-p".to_string()));
-        assert!(args.contains(&"8080:80/tcp".to_string()));
-        assert!(args.contains(&"5432:5432/tcp".to_string()));
+        assert!(args.contains(&"127.0.0.1:8080:80/tcp".to_string()));
+        assert!(args.contains(&"0.0.0.0:5432:5432/tcp".to_string()));
+        assert!(args.contains(&"127.0.0.1:8000-8010:9000-9010/udp".to_string()));
+        assert!(args.contains(&"127.0.0.1::3000/tcp".to_string()));
     }
 
     #[test]
@@ -1165,14 +769,11 @@ mod tests {
                 private: true,
                 host: false,
             },
-            port_mappings: vec![crate::config::PortMapping {
-                host_port: 8080,
-                container_port: 80,
-                protocol: "tcp".to_string(),
-            }],
+            port_mappings: vec![Commands::parse_port("8080:80").unwrap()],
             limits: crate::config::ResourceLimits {
                 memory_mb: None,
                 cpu_quota: None,
+                ..Default::default()
             },
             upgrade: false,
             force_layers: Vec::new(),
@@ -1183,6 +784,10 @@ mod tests {
             pre_create_dirs: Vec::new(),
             block_host: false,
             podman_socket: false,
+            hardened: false,
+            seccomp: false,
+            cow: false,
+            shared_home: false,
         };
 
         let args = backend.build_run_args(&config);
@@ -1213,88 +818,6 @@ mod tests {
         assert!(filtered.contains(&"jail__another__xyz12345__copilot".to_string()));
     }
 
-    #[test]
-    fn test_image_uses_nix() {
-        // Test Nix base image
-        assert!(PodmanBackend::image_uses_nix(
-            "localhost/jail-ai-nix:latest"
-        ));
-
-        // Test agent images with Nix in layer tag
-        assert!(PodmanBackend::image_uses_nix(
-            "localhost/jail-ai-agent-claude:base-nix"
-        ));
-        assert!(PodmanBackend::image_uses_nix(
-            "localhost/jail-ai-agent-claude:base-nix-rust"
-        ));
-        assert!(PodmanBackend::image_uses_nix(
-            "localhost/jail-ai-agent-claude:base-rust-nix"
-        ));
-        assert!(PodmanBackend::image_uses_nix(
-            "localhost/jail-ai-agent-jules:base-rust-nix-nodejs"
-        ));
-
-        // Test images without Nix
-        assert!(!PodmanBackend::image_uses_nix(
-            "localhost/jail-ai-agent-claude:base"
-        ));
-        assert!(!PodmanBackend::image_uses_nix(
-            "localhost/jail-ai-agent-claude:base-rust"
-        ));
-        assert!(!PodmanBackend::image_uses_nix(
-            "localhost/jail-ai-agent-claude:base-rust-nodejs"
-        ));
-        assert!(!PodmanBackend::image_uses_nix("alpine:latest"));
-
-        // Test that "nix" substring in other words doesn't trigger false positive
-        assert!(!PodmanBackend::image_uses_nix(
-            "localhost/phoenix-app:latest"
-        ));
-        assert!(!PodmanBackend::image_uses_nix(
-            "localhost/jail-ai-agent-claude:base-unix"
-        ));
-    }
-
-    #[test]
-    fn test_extract_base_name() {
-        // Test extracting base name from agent-specific jail names
-        // Hash is always 8 hex characters (as per generate_jail_name)
-        // Works for any agent name (future-proof)
-        assert_eq!(
-            PodmanBackend::extract_base_name("jail__project__abc12345__claude"),
-            "jail__project__abc12345"
-        );
-        assert_eq!(
-            PodmanBackend::extract_base_name("jail__project__abc12345__copilot"),
-            "jail__project__abc12345"
-        );
-        assert_eq!(
-            PodmanBackend::extract_base_name("jail__project__def67890__cursor"),
-            "jail__project__def67890"
-        );
-        assert_eq!(
-            PodmanBackend::extract_base_name("jail__project__12345678__gemini"),
-            "jail__project__12345678"
-        );
-        assert_eq!(
-            PodmanBackend::extract_base_name("jail__myproject__abcdef12__jules"),
-            "jail__myproject__abcdef12"
-        );
-        assert_eq!(
-            PodmanBackend::extract_base_name("jail__test__fedcba98__codex"),
-            "jail__test__fedcba98"
-        );
-
-        // Test with any future agent name (strips last segment after __)
-        assert_eq!(
-            PodmanBackend::extract_base_name("jail__project__abc12345__newagent"),
-            "jail__project__abc12345"
-        );
-
-        // Test simple name without double underscores (test names)
-        assert_eq!(PodmanBackend::extract_base_name("test"), "test");
-    }
-
     #[test]
     fn test_build_run_args_with_nix_volume() {
         let backend = PodmanBackend::new();
@@ -1315,6 +838,7 @@ mod tests {
             limits: crate::config::ResourceLimits {
                 memory_mb: None,
                 cpu_quota: None,
+                ..Default::default()
             },
             upgrade: false,
             force_layers: Vec::new(),
@@ -1325,6 +849,10 @@ mod tests {
             pre_create_dirs: Vec::new(),
             block_host: false,
             podman_socket: false,
+            hardened: false,
+            seccomp: false,
+            cow: false,
+            shared_home: false,
         };
 
         let args = backend.build_run_args(&config_with_nix);
@@ -1365,4 +893,26 @@ mod tests {
         // Verify the old shared volume name is NOT used
         assert!(!args.iter().any(|arg| arg.contains("jail-ai-nix-store")));
     }
+
+    #[test]
+    fn test_build_run_args_cow_workspace() {
+        let backend = PodmanBackend::new();
+        let config = crate::jail::JailBuilder::new("jail__app__12345678__claude")
+            .bind_mount("/src/app", "/workspace", false)
+            .bind_mount("/src/data", "/data", false)
+            .cow(true)
+            .build()
+            .config()
+            .clone();
+
+        let args = backend.build_run_args(&config);
+        let dirs = CowDirs::for_jail(&config.name).unwrap();
+        assert!(args.contains(&format!(
+            "/src/app:/workspace:O,upperdir={},workdir={}",
+            dirs.upper.display(),
+            dirs.work.display()
+        )));
+        // Other mounts stay plain bind mounts
+        assert!(args.contains(&"/src/data:/data".to_string()));
+    }
 }
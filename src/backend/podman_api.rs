@@ -0,0 +1,456 @@
+//! Minimal client for the libpod REST API over the user's podman socket
+//!
+//! Read-only queries (exists, inspect, list, image lookups) go through a single
+//! keep-alive connection instead of spawning one `podman` process per query.
+//! Callers fall back to the CLI whenever a request fails.
+
+use crate::error::{JailError, Result};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::UnixStream;
+use tokio::sync::Mutex;
+use tracing::debug;
+
+/// API version prefix understood by podman 4 and later
+const API_PREFIX: &str = "/v4.0.0/libpod";
+
+static SHARED_API: OnceLock<Option<PodmanApi>> = OnceLock::new();
+
+/// HTTP response from the podman service
+#[derive(Debug)]
+pub(crate) struct Response {
+    pub(crate) status: u16,
+    pub(crate) body: Vec<u8>,
+}
+
+impl Response {
+    fn json(&self) -> Result<serde_json::Value> {
+        serde_json::from_slice(&self.body)
+            .map_err(|e| JailError::Backend(format!("Invalid podman API response: {e}")))
+    }
+
+    fn unexpected(&self, what: &str) -> JailError {
+        JailError::Backend(format!(
+            "Podman API {} failed with status {}: {}",
+            what,
+            self.status,
+            String::from_utf8_lossy(&self.body).trim()
+        ))
+    }
+}
+
+/// libpod REST client reusing one connection for the whole invocation
+pub struct PodmanApi {
+    socket: PathBuf,
+    connection: Mutex<Option<BufReader<UnixStream>>>,
+    unavailable: AtomicBool,
+}
+
+impl PodmanApi {
+    pub fn new(socket: impl Into<PathBuf>) -> Self {
+        Self {
+            socket: socket.into(),
+            connection: Mutex::new(None),
+            unavailable: AtomicBool::new(false),
+        }
+    }
+
+    /// Client for the user's podman socket, `None` when the socket does not exist
+    pub fn shared() -> Option<&'static PodmanApi> {
+        SHARED_API
+            .get_or_init(|| {
+                let socket = default_socket()?;
+                if !socket.exists() {
+                    debug!(
+                        "Podman socket {} not found, using the podman CLI",
+                        socket.display()
+                    );
+                    return None;
+                }
+                debug!("Using podman REST API at {}", socket.display());
+                Some(PodmanApi::new(socket))
+            })
+            .as_ref()
+    }
+
+    /// Check whether a container exists
+    pub async fn container_exists(&self, name: &str) -> Result<bool> {
+        let path = format!("/containers/{}/exists", encode_path_segment(name));
+        let response = self.get(&path).await?;
+        match response.status {
+            204 => Ok(true),
+            404 => Ok(false),
+            _ => Err(response.unexpected("container exists")),
+        }
+    }
+
+    /// Inspect a container, `None` when it does not exist
+    ///
+    /// The document has the same layout as one element of `podman inspect` output.
+    pub async fn container_inspect(&self, name: &str) -> Result<Option<serde_json::Value>> {
+        let path = format!("/containers/{}/json", encode_path_segment(name));
+        let response = self.get(&path).await?;
+        match response.status {
+            200 => response.json().map(Some),
+            404 => Ok(None),
+            _ => Err(response.unexpected("container inspect")),
+        }
+    }
+
+    /// Names of all containers, running or not
+    pub async fn container_names(&self) -> Result<Vec<String>> {
+        let response = self.get("/containers/json?all=true").await?;
+        if response.status != 200 {
+            return Err(response.unexpected("container list"));
+        }
+
+        let containers = response.json()?;
+        Ok(containers
+            .as_array()
+            .map(|containers| {
+                containers
+                    .iter()
+                    .filter_map(|c| c["Names"].as_array()?.first()?.as_str())
+                    .map(|name| name.trim_start_matches('/').to_string())
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+
+    /// Check whether an image exists locally
+    pub async fn image_exists(&self, image: &str) -> Result<bool> {
+        let path = format!("/images/{}/exists", encode_path_segment(image));
+        let response = self.get(&path).await?;
+        match response.status {
+            204 => Ok(true),
+            404 => Ok(false),
+            _ => Err(response.unexpected("image exists")),
+        }
+    }
+
+    async fn get(&self, path: &str) -> Result<Response> {
+        if self.unavailable.load(Ordering::Relaxed) {
+            return Err(JailError::Backend("Podman API is unavailable".to_string()));
+        }
+
+        let mut connection = self.connection.lock().await;
+
+        // A kept-alive connection may have been closed by the service: retry once on a new one
+        let reused = connection.is_some();
+        match self.send(&mut connection, path).await {
+            Err(e) if reused => {
+                debug!("Podman API connection lost ({}), reconnecting", e);
+                self.send(&mut connection, path).await
+            }
+            result => result,
+        }
+    }
+
+    async fn send(
+        &self,
+        connection: &mut Option<BufReader<UnixStream>>,
+        path: &str,
+    ) -> Result<Response> {
+        let mut stream = match connection.take() {
+            Some(stream) => stream,
+            None => {
+                let stream = UnixStream::connect(&self.socket).await.map_err(|e| {
+                    self.unavailable.store(true, Ordering::Relaxed);
+                    JailError::Backend(format!(
+                        "Failed to connect to podman socket {}: {}",
+                        self.socket.display(),
+                        e
+                    ))
+                })?;
+                BufReader::new(stream)
+            }
+        };
+
+        debug!("Podman API GET {}{}", API_PREFIX, path);
+        let request = format!(
+            "GET {API_PREFIX}{path} HTTP/1.1\r\nHost: d\r\nAccept: application/json\r\n\r\n"
+        );
+        stream
+            .get_mut()
+            .write_all(request.as_bytes())
+            .await
+            .map_err(|e| JailError::Backend(format!("Podman API request failed: {e}")))?;
+        let (response, keep_alive) = read_response(&mut stream)
+            .await
+            .map_err(|e| JailError::Backend(format!("Podman API request failed: {e}")))?;
+
+        // Keep the connection for the next request unless the service closes it
+        if keep_alive {
+            *connection = Some(stream);
+        }
+        Ok(response)
+    }
+}
+
+/// Default podman socket: `$XDG_RUNTIME_DIR/podman/podman.sock`, else `/run/user/UID/podman/podman.sock`
+fn default_socket() -> Option<PathBuf> {
+    if let Ok(runtime_dir) = std::env::var("XDG_RUNTIME_DIR") {
+        if !runtime_dir.is_empty() {
+            return Some(Path::new(&runtime_dir).join("podman/podman.sock"));
+        }
+    }
+
+    use std::os::unix::fs::MetadataExt;
+    let uid = std::fs::metadata("/proc/self").ok()?.uid();
+    Some(PathBuf::from(format!("/run/user/{uid}/podman/podman.sock")))
+}
+
+/// Percent-encode a URL path segment (image names contain `/` and `:`)
+pub(crate) fn encode_path_segment(segment: &str) -> String {
+    let mut encoded = String::with_capacity(segment.len());
+    for byte in segment.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    encoded
+}
+
+fn invalid_data(message: impl Into<String>) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, message.into())
+}
+
+/// Read one HTTP/1.1 response, returning it with whether the connection can be reused
+pub(crate) async fn read_response(
+    stream: &mut BufReader<UnixStream>,
+) -> std::io::Result<(Response, bool)> {
+    let mut status_line = String::new();
+    if stream.read_line(&mut status_line).await? == 0 {
+        return Err(invalid_data("connection closed"));
+    }
+    let status = status_line
+        .split_whitespace()
+        .nth(1)
+        .and_then(|code| code.parse::<u16>().ok())
+        .ok_or_else(|| invalid_data(format!("invalid status line: {}", status_line.trim())))?;
+
+    let mut content_length = None;
+    let mut chunked = false;
+    let mut keep_alive = true;
+    loop {
+        let mut line = String::new();
+        if stream.read_line(&mut line).await? == 0 {
+            return Err(invalid_data("connection closed in headers"));
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+        match name.trim().to_ascii_lowercase().as_str() {
+            "content-length" => {
+                content_length = Some(
+                    value
+                        .parse::<usize>()
+                        .map_err(|_| invalid_data("invalid Content-Length"))?,
+                )
+            }
+            "transfer-encoding" => chunked = value.eq_ignore_ascii_case("chunked"),
+            "connection" => keep_alive = !value.eq_ignore_ascii_case("close"),
+            _ => {}
+        }
+    }
+
+    let body = if chunked {
+        read_chunked_body(stream).await?
+    } else if let Some(length) = content_length {
+        let mut body = vec![0; length];
+        stream.read_exact(&mut body).await?;
+        body
+    } else if status == 204 || status == 304 || (100..200).contains(&status) {
+        Vec::new()
+    } else {
+        // Body delimited by the end of the connection
+        keep_alive = false;
+        let mut body = Vec::new();
+        stream.read_to_end(&mut body).await?;
+        body
+    };
+
+    Ok((Response { status, body }, keep_alive))
+}
+
+pub(crate) async fn read_chunked_body(
+    stream: &mut BufReader<UnixStream>,
+) -> std::io::Result<Vec<u8>> {
+    let mut body = Vec::new();
+    loop {
+        let mut size_line = String::new();
+        stream.read_line(&mut size_line).await?;
+        let size = size_line
+            .trim()
+            .split(';')
+            .next()
+            .and_then(|size| usize::from_str_radix(size, 16).ok())
+            .ok_or_else(|| invalid_data("invalid chunk size"))?;
+
+        if size == 0 {
+            // Skip trailers up to the final empty line
+            loop {
+                let mut trailer = String::new();
+                if stream.read_line(&mut trailer).await? == 0 || trailer.trim().is_empty() {
+                    return Ok(body);
+                }
+            }
+        }
+
+        let start = body.len();
+        body.resize(start + size, 0);
+        stream.read_exact(&mut body[start..]).await?;
+
+        let mut crlf = [0; 2];
+        stream.read_exact(&mut crlf).await?;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::Arc;
+    use tokio::net::UnixListener;
+
+    /// Stand-in podman service answering canned responses, counting connections
+    fn spawn_server(listener: UnixListener, connections: Arc<AtomicUsize>) {
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                connections.fetch_add(1, Ordering::SeqCst);
+                tokio::spawn(async move {
+                    let mut stream = BufReader::new(stream);
+                    loop {
+                        let mut request_line = String::new();
+                        if stream.read_line(&mut request_line).await.unwrap_or(0) == 0 {
+                            return;
+                        }
+                        loop {
+                            let mut header = String::new();
+                            stream.read_line(&mut header).await.unwrap();
+                            if header.trim().is_empty() {
+                                break;
+                            }
+                        }
+
+                        let path = request_line.split_whitespace().nth(1).unwrap().to_string();
+                        let response = match path.as_str() {
+                            "/v4.0.0/libpod/containers/jail__demo/exists" => {
+                                "HTTP/1.1 204 No Content\r\n\r\n".to_string()
+                            }
+                            "/v4.0.0/libpod/containers/jail__demo/json" => {
+                                let body = r#"{"Name":"jail__demo","State":{"Status":"running","Running":true,"Pid":4242},"Config":{"Image":"localhost/jail-ai-agent-claude:base","Env":["FOO=bar"],"Labels":{"jail-ai.block-host":"true"}},"HostConfig":{"NetworkMode":"slirp4netns","Memory":0,"CpuQuota":0}}"#;
+                                // Split in two chunks, like Go's net/http does for large bodies
+                                let (first, second) = body.split_at(20);
+                                format!(
+                                    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nTransfer-Encoding: chunked\r\n\r\n{:x}\r\n{}\r\n{:x}\r\n{}\r\n0\r\n\r\n",
+                                    first.len(),
+                                    first,
+                                    second.len(),
+                                    second
+                                )
+                            }
+                            "/v4.0.0/libpod/containers/json?all=true" => {
+                                let body = r#"[{"Names":["jail__demo"]},{"Names":["other"]}]"#;
+                                format!(
+                                    "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n{}",
+                                    body.len(),
+                                    body
+                                )
+                            }
+                            "/v4.0.0/libpod/images/localhost%2Fjail-ai-base%3Alatest/exists" => {
+                                "HTTP/1.1 204 No Content\r\n\r\n".to_string()
+                            }
+                            _ => {
+                                let body = r#"{"cause":"no such container","response":404}"#;
+                                format!(
+                                    "HTTP/1.1 404 Not Found\r\nContent-Length: {}\r\n\r\n{}",
+                                    body.len(),
+                                    body
+                                )
+                            }
+                        };
+                        stream
+                            .get_mut()
+                            .write_all(response.as_bytes())
+                            .await
+                            .unwrap();
+                    }
+                });
+            }
+        });
+    }
+
+    #[test]
+    fn test_encode_path_segment() {
+        assert_eq!(encode_path_segment("jail__demo"), "jail__demo");
+        assert_eq!(
+            encode_path_segment("localhost/jail-ai-base:latest"),
+            "localhost%2Fjail-ai-base%3Alatest"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_api_against_stand_in_server() {
+        let dir = tempfile::tempdir().unwrap();
+        let socket = dir.path().join("podman.sock");
+        let connections = Arc::new(AtomicUsize::new(0));
+        spawn_server(UnixListener::bind(&socket).unwrap(), connections.clone());
+
+        let api = PodmanApi::new(&socket);
+
+        assert!(api.container_exists("jail__demo").await.unwrap());
+        assert!(!api.container_exists("jail__missing").await.unwrap());
+
+        let inspect = api.container_inspect("jail__demo").await.unwrap().unwrap();
+        assert_eq!(inspect["State"]["Pid"], 4242);
+        assert!(api
+            .container_inspect("jail__missing")
+            .await
+            .unwrap()
+            .is_none());
+
+        assert_eq!(
+            api.container_names().await.unwrap(),
+            vec!["jail__demo".to_string(), "other".to_string()]
+        );
+        assert!(api
+            .image_exists("localhost/jail-ai-base:latest")
+            .await
+            .unwrap());
+        assert!(!api.image_exists("alpine:latest").await.unwrap());
+
+        // Every request went through the same kept-alive connection
+        assert_eq!(connections.load(Ordering::SeqCst), 1);
+
+        // The inspect document maps to the same configuration as the CLI output
+        let config = super::super::config_from_inspect(
+            "jail__demo",
+            &inspect,
+            crate::config::BackendType::Podman,
+        );
+        assert_eq!(config.base_image, "localhost/jail-ai-agent-claude:base");
+        assert!(config.block_host);
+    }
+
+    #[tokio::test]
+    async fn test_api_unavailable_socket() {
+        let dir = tempfile::tempdir().unwrap();
+        let api = PodmanApi::new(dir.path().join("missing.sock"));
+
+        assert!(api.container_exists("jail__demo").await.is_err());
+        // Later calls fail fast so callers go straight to the CLI
+        assert!(api.unavailable.load(Ordering::Relaxed));
+        assert!(api.image_exists("alpine").await.is_err());
+    }
+}
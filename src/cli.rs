@@ -5,10 +5,44 @@ use std::path::PathBuf;
 /// Default image name
 pub const DEFAULT_IMAGE: &str = "localhost/jail-ai-env:latest";
 
+/// Default workspace path inside the jail
+pub const DEFAULT_WORKSPACE_PATH: &str = "/workspace";
+
+/// Size in MB of a `--tmpfs` mount given without a size
+pub const DEFAULT_TMPFS_SIZE_MB: u64 = 64;
+
+/// Extended resource limits shared by `create` and the agent commands
+#[derive(Args, Debug, Clone, Default)]
+pub struct ResourceLimitOptions {
+    /// Maximum number of processes in the jail (agent jails default to 4096)
+    #[arg(long)]
+    pub pids_limit: Option<u32>,
+
+    /// Memory plus swap limit in MB (needs a memory limit; equal to it disables swap)
+    #[arg(long)]
+    pub memory_swap: Option<u64>,
+
+    /// Size of /dev/shm in MB
+    #[arg(long)]
+    pub shm_size: Option<u64>,
+
+    /// Block IO weight (10-1000)
+    #[arg(long, value_parser = clap::value_parser!(u16).range(10..=1000))]
+    pub blkio_weight: Option<u16>,
+
+    /// Process resource limit (format: name=soft[:hard], e.g. nofile=1024:4096)
+    #[arg(long)]
+    pub ulimit: Vec<String>,
+
+    /// Size-capped tmpfs mount (format: path[:size_mb]; agent jails mount /tmp with 2048 MB)
+    #[arg(long)]
+    pub tmpfs: Vec<String>,
+}
+
 /// Common options for AI agent commands
 #[derive(Args, Debug)]
 pub struct AgentCommandOptions {
-    /// Backend type (only 'podman' is supported, kept for compatibility)
+    /// Backend type (podman, docker or bubblewrap; auto-detects podman or docker by default)
     #[arg(short, long)]
     pub backend: Option<String>,
 
@@ -20,7 +54,7 @@ pub struct AgentCommandOptions {
     #[arg(short = 'm', long)]
     pub mount: Vec<String>,
 
-    /// Port mapping from host to container (format: host_port:container_port or host_port:container_port/protocol)
+    /// Port mapping from host to container (format: [address:]host_port:container_port[/protocol], host port 0 for a free one)
     #[arg(short = 'p', long)]
     pub port: Vec<String>,
 
@@ -45,12 +79,15 @@ pub struct AgentCommandOptions {
     #[arg(long)]
     pub cpu: Option<u32>,
 
+    #[command(flatten)]
+    pub limits: ResourceLimitOptions,
+
     /// Skip auto-mounting current working directory to /workspace
     #[arg(long)]
     pub no_workspace: bool,
 
     /// Custom workspace path inside jail (default: /workspace)
-    #[arg(long, default_value = "/workspace")]
+    #[arg(long, default_value = DEFAULT_WORKSPACE_PATH)]
     pub workspace_path: String,
 
     /// Mount entire ~/.claude directory (default: only .claude/.credentials.json)
@@ -129,14 +166,47 @@ pub struct AgentCommandOptions {
     #[arg(long)]
     pub no_block_host: bool,
 
-    /// Enable Podman-in-Podman by mounting the host's Podman socket
+    /// Enable Podman-in-Podman through a filtered proxy of the host's Podman socket (Docker socket with --backend docker)
     /// This allows running containers inside the jail (useful for MCP agents)
     #[arg(long)]
     pub podman: bool,
 
+    /// Drop all capabilities, forbid privilege escalation (sudo) and make the root filesystem read-only
+    #[arg(long)]
+    pub hardened: bool,
+
+    /// Apply the seccomp profile shipped with jail-ai (podman and docker)
+    #[arg(long)]
+    pub seccomp: bool,
+
+    /// Mount the workspace copy-on-write: changes stay apart until `jail-ai apply` (podman and bubblewrap)
+    #[arg(long)]
+    pub cow: bool,
+
+    /// Run in a throwaway jail, removed with its home volume and session images on exit
+    #[arg(long)]
+    pub ephemeral: bool,
+
+    /// Run a parallel session in its own jail (next free numbered session without a name)
+    #[arg(long, num_args = 0..=1, default_missing_value = "", conflicts_with = "ephemeral")]
+    pub session: Option<String>,
+
+    /// Share the home volume of the agent's main jail with the session
+    #[arg(long, requires = "session")]
+    pub shared_home: bool,
+
+    /// Recreate an existing jail without asking when its configuration differs from the request
+    /// (data in /home/agent is preserved)
+    #[arg(long)]
+    pub auto_recreate: bool,
+
     /// Launch the TUI with a tab for the agent and a tab for an interactive shell
     #[arg(long)]
     pub tui: bool,
+
+    /// Apply a named profile from ~/.config/jail-ai/config.toml
+    #[arg(long)]
+    pub profile: Option<String>,
 }
 
 #[derive(Parser, Debug)]
@@ -162,7 +232,7 @@ pub enum Commands {
         /// Name of the jail (auto-generated from current directory if not provided)
         name: Option<String>,
 
-        /// Backend type (only 'podman' is supported, kept for compatibility)
+        /// Backend type (podman, docker or bubblewrap; auto-detects podman or docker by default)
         #[arg(short, long)]
         backend: Option<String>,
 
@@ -174,7 +244,7 @@ pub enum Commands {
         #[arg(short = 'm', long)]
         mount: Vec<String>,
 
-        /// Port mapping from host to container (format: host_port:container_port or host_port:container_port/protocol)
+        /// Port mapping from host to container (format: [address:]host_port:container_port[/protocol], host port 0 for a free one)
         #[arg(short = 'p', long)]
         port: Vec<String>,
 
@@ -199,6 +269,9 @@ pub enum Commands {
         #[arg(long)]
         cpu: Option<u32>,
 
+        #[command(flatten)]
+        limits: ResourceLimitOptions,
+
         /// Load configuration from file
         #[arg(short, long)]
         config: Option<PathBuf>,
@@ -208,7 +281,7 @@ pub enum Commands {
         no_workspace: bool,
 
         /// Custom workspace path inside jail (default: /workspace)
-        #[arg(long, default_value = "/workspace")]
+        #[arg(long, default_value = DEFAULT_WORKSPACE_PATH)]
         workspace_path: String,
 
         /// Mount entire ~/.claude directory (default: only .claude/.credentials.json)
@@ -275,10 +348,26 @@ pub enum Commands {
         #[arg(long)]
         no_block_host: bool,
 
-        /// Enable Podman-in-Podman by mounting the host's Podman socket
+        /// Enable Podman-in-Podman through a filtered proxy of the host's Podman socket (Docker socket with --backend docker)
         /// This allows running containers inside the jail (useful for MCP agents)
         #[arg(long)]
         podman: bool,
+
+        /// Drop all capabilities, forbid privilege escalation (sudo) and make the root filesystem read-only
+        #[arg(long)]
+        hardened: bool,
+
+        /// Apply the seccomp profile shipped with jail-ai (podman and docker)
+        #[arg(long)]
+        seccomp: bool,
+
+        /// Mount the workspace copy-on-write: changes stay apart until `jail-ai apply` (podman and bubblewrap)
+        #[arg(long)]
+        cow: bool,
+
+        /// Apply a named profile from ~/.config/jail-ai/config.toml
+        #[arg(long, conflicts_with = "config")]
+        profile: Option<String>,
     },
 
     /// Remove a jail
@@ -301,6 +390,61 @@ pub enum Commands {
         name: Option<String>,
     },
 
+    /// Stop a jail without removing it
+    Stop {
+        /// Name of the jail (auto-detected from current directory if not provided)
+        name: Option<String>,
+    },
+
+    /// Restart a jail
+    Restart {
+        /// Name of the jail (auto-detected from current directory if not provided)
+        name: Option<String>,
+    },
+
+    /// Freeze all processes of a jail
+    Pause {
+        /// Name of the jail (auto-detected from current directory if not provided)
+        name: Option<String>,
+    },
+
+    /// Resume a paused jail
+    Unpause {
+        /// Name of the jail (auto-detected from current directory if not provided)
+        name: Option<String>,
+    },
+
+    /// Show the host ports published by a running jail
+    Port {
+        /// Name of the jail (auto-detected from current directory if not provided)
+        name: Option<String>,
+    },
+
+    /// Show the output of a jail
+    Logs {
+        /// Name of the jail (auto-detected from current directory if not provided)
+        name: Option<String>,
+
+        /// Keep streaming new output
+        #[arg(short, long)]
+        follow: bool,
+
+        /// Only show the last N lines
+        #[arg(long)]
+        tail: Option<usize>,
+    },
+
+    /// Copy files between the host and a jail
+    /// Use JAIL:PATH for the jail side, or :PATH for the jail of the current directory
+    /// Example: jail-ai cp ./notes.md :/workspace/notes.md
+    Cp {
+        /// Source (host path, JAIL:PATH or :PATH)
+        source: String,
+
+        /// Target (host path, JAIL:PATH or :PATH)
+        target: String,
+    },
+
     /// Save jail configuration to file
     Save {
         /// Name of the jail (auto-detected from current directory if not provided)
@@ -426,14 +570,30 @@ pub enum Commands {
         #[arg(short, long)]
         current: bool,
 
-        /// Backend type (only 'podman' is supported, kept for compatibility)
+        /// Backend type (podman, docker or bubblewrap; auto-detects podman or docker by default)
         #[arg(short, long)]
         backend: Option<String>,
+
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+
+        /// Show only jails of this agent (e.g., claude, copilot)
+        #[arg(long)]
+        agent: Option<String>,
+
+        /// Show only running jails
+        #[arg(long)]
+        running: bool,
+
+        /// Show only jails whose image has been rebuilt since they were created
+        #[arg(long)]
+        stale: bool,
     },
 
     /// Stop and remove all jail-ai containers
     CleanAll {
-        /// Backend type (only 'podman' is supported, kept for compatibility)
+        /// Backend type (podman, docker or bubblewrap; auto-detects podman or docker by default)
         #[arg(short, long)]
         backend: Option<String>,
 
@@ -446,6 +606,36 @@ pub enum Commands {
         volume: bool,
     },
 
+    /// Stop running jails left idle, keeping them for later use
+    Reap {
+        /// Stop jails without activity for this long (e.g. 30m, 2h, 1d, 1h30m)
+        #[arg(long, default_value = "2h")]
+        idle: String,
+
+        /// Backend type (podman, docker or bubblewrap; auto-detects podman or docker by default)
+        #[arg(short, long)]
+        backend: Option<String>,
+
+        /// Only report the jails that would be stopped
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Remove jails of deleted or moved projects, unused volumes and unreferenced images
+    Gc {
+        /// Backend type (podman, docker or bubblewrap; auto-detects podman or docker by default)
+        #[arg(short, long)]
+        backend: Option<String>,
+
+        /// Only show what would be removed and the space it would free
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Remove without confirmation
+        #[arg(short, long)]
+        force: bool,
+    },
+
     /// Upgrade jail by recreating it with the latest image
     Upgrade {
         /// Name of the jail (auto-detected from current directory if not provided)
@@ -463,18 +653,263 @@ pub enum Commands {
         #[arg(long)]
         all: bool,
     },
+
+    /// Inspect jail-ai configuration
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommands,
+    },
+
+    /// Manage jail home and Nix volumes
+    Volume {
+        #[command(subcommand)]
+        command: VolumeCommands,
+    },
+
+    /// Save the state of a jail and roll back to it
+    Snapshot {
+        #[command(subcommand)]
+        command: SnapshotCommands,
+    },
+
+    /// List the workspace changes of a copy-on-write jail
+    Changes {
+        /// Name of the jail (auto-detected from current directory if not provided)
+        name: Option<String>,
+
+        /// Show the content changes as a unified diff
+        #[arg(long)]
+        diff: bool,
+    },
+
+    /// Copy the changes of a copy-on-write jail onto the workspace (stops the jail)
+    Apply {
+        /// Name of the jail (auto-detected from current directory if not provided)
+        name: Option<String>,
+
+        /// Apply without confirmation
+        #[arg(short, long)]
+        force: bool,
+    },
+
+    /// Drop the changes of a copy-on-write jail (stops the jail)
+    Discard {
+        /// Name of the jail (auto-detected from current directory if not provided)
+        name: Option<String>,
+
+        /// Discard without confirmation
+        #[arg(short, long)]
+        force: bool,
+    },
+
+    /// Serve the filtered podman socket of a jail (started by jail-ai)
+    #[command(hide = true)]
+    SocketProxy {
+        /// Name of the jail
+        jail: String,
+    },
+}
+
+/// Configuration subcommands
+#[derive(Subcommand, Debug)]
+pub enum ConfigCommands {
+    /// Show the configuration and where each value comes from
+    Show {
+        /// Compute the full jail configuration an agent command would use (nothing is created)
+        #[arg(long)]
+        effective: bool,
+
+        /// Agent to compute the configuration for
+        #[arg(long, default_value = "claude")]
+        agent: String,
+
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+
+        #[command(flatten)]
+        common: Box<AgentCommandOptions>,
+    },
+
+    /// Print the JSON Schema of jail configuration files (as written by `save`)
+    Schema,
+
+    /// Validate a jail configuration file and report all problems
+    Validate {
+        /// Configuration file to validate
+        file: PathBuf,
+    },
+}
+
+/// Volume subcommands
+///
+/// Volumes are given by name, or by jail name for the jail home volume.
+#[derive(Subcommand, Debug)]
+pub enum VolumeCommands {
+    /// List jail volumes with their size and owner
+    Ls {
+        /// Backend type (podman, docker or bubblewrap; auto-detects podman or docker by default)
+        #[arg(short, long)]
+        backend: Option<String>,
+    },
+
+    /// Back up a volume to a tar archive
+    Export {
+        /// Volume (or jail) to back up
+        volume: String,
+
+        /// Archive to write
+        archive: PathBuf,
+
+        /// Backend type (podman, docker or bubblewrap; auto-detects podman or docker by default)
+        #[arg(short, long)]
+        backend: Option<String>,
+    },
+
+    /// Restore a tar archive into a volume, creating it when missing
+    Import {
+        /// Volume (or jail) to restore into
+        volume: String,
+
+        /// Archive to read
+        archive: PathBuf,
+
+        /// Backend type (podman, docker or bubblewrap; auto-detects podman or docker by default)
+        #[arg(short, long)]
+        backend: Option<String>,
+    },
+
+    /// Copy the content of a volume into another one (e.g. a configured home into a new jail)
+    Clone {
+        /// Volume (or jail) to copy from
+        source: String,
+
+        /// Volume (or jail) to copy into
+        target: String,
+
+        /// Backend type (podman, docker or bubblewrap; auto-detects podman or docker by default)
+        #[arg(short, long)]
+        backend: Option<String>,
+    },
+
+    /// Wipe a volume, keeping the agent credentials of home volumes
+    Reset {
+        /// Volume (or jail) to wipe
+        volume: String,
+
+        /// Also delete the agent credentials
+        #[arg(long)]
+        all: bool,
+
+        /// Wipe without confirmation
+        #[arg(short, long)]
+        force: bool,
+
+        /// Backend type (podman, docker or bubblewrap; auto-detects podman or docker by default)
+        #[arg(short, long)]
+        backend: Option<String>,
+    },
+}
+
+/// Snapshot subcommands
+#[derive(Subcommand, Debug)]
+pub enum SnapshotCommands {
+    /// Commit the jail filesystem to an image and back up its home volume
+    Create {
+        /// Name of the jail (auto-detected from current directory if not provided)
+        name: Option<String>,
+
+        /// Description of the snapshot
+        #[arg(short, long)]
+        label: Option<String>,
+    },
+
+    /// List snapshots
+    List {
+        /// Only show the snapshots of this jail
+        name: Option<String>,
+    },
+
+    /// Recreate the jail from a snapshot, keeping its name and mounts
+    Restore {
+        /// Snapshot identifier (see `snapshot list`)
+        id: String,
+
+        /// Restore without confirmation
+        #[arg(short, long)]
+        force: bool,
+    },
+
+    /// Delete a snapshot image and home backup
+    Delete {
+        /// Snapshot identifier (see `snapshot list`)
+        id: String,
+    },
+}
+
+/// One side of a `cp` command
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CopyPath {
+    /// Path on the host
+    Host(PathBuf),
+    /// Absolute path inside a jail (`None`: the jail of the current directory)
+    Jail { name: Option<String>, path: PathBuf },
 }
 
 impl Commands {
+    /// Parse a `cp` argument: `JAIL:/path` or `:/path` inside a jail, anything else on the host
+    pub fn parse_copy_path(arg: &str) -> CopyPath {
+        if let Some((name, path)) = arg.split_once(':') {
+            if !name.contains('/') && path.starts_with('/') {
+                return CopyPath::Jail {
+                    name: (!name.is_empty()).then(|| name.to_string()),
+                    path: PathBuf::from(path),
+                };
+            }
+        }
+        CopyPath::Host(PathBuf::from(arg))
+    }
+
     pub fn parse_backend(backend: &str) -> Result<crate::config::BackendType, String> {
         match backend.to_lowercase().as_str() {
             "podman" | "pod" => Ok(crate::config::BackendType::Podman),
+            "docker" => Ok(crate::config::BackendType::Docker),
+            "bubblewrap" | "bwrap" => Ok(crate::config::BackendType::Bubblewrap),
             _ => Err(format!(
-                "Invalid backend '{backend}'. Only 'podman' is supported"
+                "Invalid backend '{backend}'. Supported backends: podman, docker, bubblewrap"
             )),
         }
     }
 
+    /// Backend given with `--backend`, if the command takes one
+    pub fn backend(&self) -> Option<&str> {
+        let backend = match self {
+            Commands::Create { backend, .. }
+            | Commands::List { backend, .. }
+            | Commands::CleanAll { backend, .. }
+            | Commands::Reap { backend, .. }
+            | Commands::Gc { backend, .. } => backend,
+            Commands::Claude { common, .. }
+            | Commands::ClaudeCodeRouter { common, .. }
+            | Commands::Copilot { common, .. }
+            | Commands::Cursor { common, .. }
+            | Commands::Gemini { common, .. }
+            | Commands::CodeRabbit { common, .. }
+            | Commands::Codex { common, .. }
+            | Commands::Jules { common, .. }
+            | Commands::Pi { common, .. } => &common.backend,
+            Commands::Volume { command } => match command {
+                VolumeCommands::Ls { backend }
+                | VolumeCommands::Export { backend, .. }
+                | VolumeCommands::Import { backend, .. }
+                | VolumeCommands::Clone { backend, .. }
+                | VolumeCommands::Reset { backend, .. } => backend,
+            },
+            _ => return None,
+        };
+        backend.as_deref()
+    }
+
     pub fn parse_mount(mount_str: &str) -> Result<crate::config::BindMount, String> {
         let parts: Vec<&str> = mount_str.split(':').collect();
         if parts.len() < 2 {
@@ -494,6 +929,7 @@ impl Commands {
             source,
             target,
             readonly,
+            workspace: false,
         })
     }
 
@@ -508,52 +944,174 @@ impl Commands {
         Ok((parts[0].to_string(), parts[1].to_string()))
     }
 
+    /// Parse a port mapping (format: [address:]host:container[/protocol])
+    ///
+    /// Ports may be ranges of the same length (`8000-8010:8000-8010`), a host port of 0
+    /// picks a free one, and the address defaults to loopback.
     pub fn parse_port(port_str: &str) -> Result<crate::config::PortMapping, String> {
         // Split by '/' to extract protocol if present
-        let parts: Vec<&str> = port_str.split('/').collect();
-        let port_part = parts[0];
-        let protocol = if parts.len() > 1 {
-            let proto = parts[1].to_lowercase();
-            if proto != "tcp" && proto != "udp" {
-                return Err(format!(
-                    "Invalid protocol '{}'. Expected: tcp or udp",
-                    parts[1]
-                ));
+        let (spec, protocol) = match port_str.rsplit_once('/') {
+            Some((spec, proto)) => {
+                let proto = proto.to_lowercase();
+                if proto != "tcp" && proto != "udp" {
+                    return Err(format!("Invalid protocol '{proto}'. Expected: tcp or udp"));
+                }
+                (spec, proto)
             }
-            proto
-        } else {
-            "tcp".to_string()
+            None => (port_str, "tcp".to_string()),
         };
 
-        // Split by ':' to extract host and container ports
-        let port_parts: Vec<&str> = port_part.split(':').collect();
-        if port_parts.len() != 2 {
-            return Err(format!(
-                "Invalid port mapping format '{port_str}'. Expected: host_port:container_port or host_port:container_port/protocol"
-            ));
-        }
-
-        let host_port = port_parts[0].parse::<u16>().map_err(|_| {
+        let format_error = || {
             format!(
-                "Invalid host port '{}'. Expected a number between 1 and 65535",
-                port_parts[0]
+                "Invalid port mapping format '{port_str}'. Expected: [address:]host_port:container_port[/protocol]"
             )
-        })?;
+        };
 
-        let container_port = port_parts[1].parse::<u16>().map_err(|_| {
-            format!(
-                "Invalid container port '{}'. Expected a number between 1 and 65535",
-                port_parts[1]
-            )
-        })?;
+        // Optional bind address, bracketed for IPv6 ([::1]:8080:80)
+        let (host_ip, ports) = if let Some(rest) = spec.strip_prefix('[') {
+            rest.split_once("]:").ok_or_else(format_error)?
+        } else if spec.matches(':').count() == 2 {
+            spec.split_once(':').ok_or_else(format_error)?
+        } else {
+            (crate::config::DEFAULT_HOST_IP, spec)
+        };
+        host_ip
+            .parse::<std::net::IpAddr>()
+            .map_err(|_| format!("Invalid bind address '{host_ip}'. Expected an IP address"))?;
+
+        let (host, container) = ports.split_once(':').ok_or_else(format_error)?;
+        let (host_port, host_count) = Self::parse_port_range(host, "host")?;
+        let (container_port, count) = Self::parse_port_range(container, "container")?;
+        if container_port == 0 {
+            return Err("Invalid container port '0'. Expected a number between 1 and 65535".into());
+        }
+        if host_port == 0 && count > 1 {
+            return Err(format!(
+                "Invalid port mapping '{port_str}'. A free host port is only picked for a single port"
+            ));
+        }
+        if host_port != 0 && host_count != count {
+            return Err(format!(
+                "Invalid port mapping '{port_str}'. Host and container port ranges must have the same length"
+            ));
+        }
 
         Ok(crate::config::PortMapping {
             host_port,
             container_port,
             protocol,
+            host_ip: host_ip.to_string(),
+            count,
         })
     }
 
+    /// Parse a port or port range (first-last), returning the first port and the number of ports
+    fn parse_port_range(range: &str, which: &str) -> Result<(u16, u16), String> {
+        let port = |value: &str| {
+            value.parse::<u16>().map_err(|_| {
+                format!("Invalid {which} port '{value}'. Expected a number between 1 and 65535")
+            })
+        };
+        let Some((first, last)) = range.split_once('-') else {
+            return Ok((port(range)?, 1));
+        };
+        let (first, last) = (port(first)?, port(last)?);
+        if first == 0 || last < first {
+            return Err(format!(
+                "Invalid {which} port range '{range}'. Expected first-last with 1 <= first <= last"
+            ));
+        }
+        Ok((first, last - first + 1))
+    }
+
+    /// Parse a ulimit (format: name=soft[:hard], the hard limit defaults to the soft one)
+    pub fn parse_ulimit(ulimit_str: &str) -> Result<crate::config::Ulimit, String> {
+        let invalid =
+            || format!("Invalid ulimit format '{ulimit_str}'. Expected: name=soft[:hard]");
+        let (name, values) = ulimit_str.split_once('=').ok_or_else(invalid)?;
+        if !crate::config::ULIMIT_NAMES.contains(&name) {
+            return Err(format!(
+                "Unknown ulimit '{name}'. Expected one of: {}",
+                crate::config::ULIMIT_NAMES.join(", ")
+            ));
+        }
+
+        let (soft, hard) = values.split_once(':').unwrap_or((values, values));
+        let soft = soft.parse::<u64>().map_err(|_| invalid())?;
+        let hard = hard.parse::<u64>().map_err(|_| invalid())?;
+        if soft > hard {
+            return Err(format!(
+                "Invalid ulimit '{ulimit_str}': soft limit exceeds hard limit"
+            ));
+        }
+
+        Ok(crate::config::Ulimit {
+            name: name.to_string(),
+            soft,
+            hard,
+        })
+    }
+
+    /// Parse a tmpfs mount (format: path[:size_mb], 64 MB when no size is given)
+    pub fn parse_tmpfs(tmpfs_str: &str) -> Result<crate::config::TmpfsMount, String> {
+        let (target, size) = match tmpfs_str.split_once(':') {
+            Some((target, size)) => {
+                let size_mb = size
+                    .parse::<u64>()
+                    .ok()
+                    .filter(|&mb| mb > 0)
+                    .ok_or_else(|| {
+                        format!("Invalid tmpfs size '{size}'. Expected a size in MB greater than 0")
+                    })?;
+                (target, size_mb)
+            }
+            None => (tmpfs_str, DEFAULT_TMPFS_SIZE_MB),
+        };
+
+        if !target.starts_with('/') {
+            return Err(format!(
+                "Invalid tmpfs path '{target}'. Expected an absolute path"
+            ));
+        }
+
+        Ok(crate::config::TmpfsMount {
+            target: PathBuf::from(target),
+            size_mb: size,
+        })
+    }
+
+    /// Parse a duration made of `<number><unit>` parts with units s, m, h and d (e.g. `1h30m`)
+    pub fn parse_duration(duration_str: &str) -> Result<std::time::Duration, String> {
+        let invalid = || {
+            format!("Invalid duration '{duration_str}'. Expected e.g. 90s, 30m, 2h, 1d or 1h30m")
+        };
+        let mut total = 0u64;
+        let mut digits = String::new();
+        for c in duration_str.trim().chars() {
+            if c.is_ascii_digit() {
+                digits.push(c);
+                continue;
+            }
+            let unit = match c {
+                's' => 1,
+                'm' => 60,
+                'h' => 3_600,
+                'd' => 86_400,
+                _ => return Err(invalid()),
+            };
+            let value = digits.parse::<u64>().map_err(|_| invalid())?;
+            total = value
+                .checked_mul(unit)
+                .and_then(|secs| total.checked_add(secs))
+                .ok_or_else(invalid)?;
+            digits.clear();
+        }
+        if !digits.is_empty() || total == 0 {
+            return Err(invalid());
+        }
+        Ok(std::time::Duration::from_secs(total))
+    }
+
     /// Sanitize a jail name to match podman requirements
     /// Names must match [a-zA-Z0-9][a-zA-Z0-9_.-]*
     pub fn sanitize_jail_name(name: &str) -> String {
@@ -627,6 +1185,59 @@ impl Commands {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_parse_copy_path() {
+        assert_eq!(
+            Commands::parse_copy_path("jail__demo__abc12345__claude:/workspace/a.txt"),
+            CopyPath::Jail {
+                name: Some("jail__demo__abc12345__claude".to_string()),
+                path: PathBuf::from("/workspace/a.txt"),
+            }
+        );
+        assert_eq!(
+            Commands::parse_copy_path(":/home/agent"),
+            CopyPath::Jail {
+                name: None,
+                path: PathBuf::from("/home/agent"),
+            }
+        );
+        assert_eq!(
+            Commands::parse_copy_path("./notes.md"),
+            CopyPath::Host(PathBuf::from("./notes.md"))
+        );
+        // Colons in host paths do not make them jail paths
+        assert_eq!(
+            Commands::parse_copy_path("./data/a:b"),
+            CopyPath::Host(PathBuf::from("./data/a:b"))
+        );
+        assert_eq!(
+            Commands::parse_copy_path("c:relative"),
+            CopyPath::Host(PathBuf::from("c:relative"))
+        );
+    }
+
+    #[test]
+    fn test_cp_command_parsing() {
+        let cli = Cli::parse_from(["jail-ai", "cp", "./a.txt", ":/workspace/a.txt"]);
+        match cli.command {
+            Some(Commands::Cp { source, target }) => {
+                assert_eq!(source, "./a.txt");
+                assert_eq!(target, ":/workspace/a.txt");
+            }
+            _ => panic!("Expected Cp command"),
+        }
+
+        let cli = Cli::parse_from(["jail-ai", "logs", "-f", "--tail", "50"]);
+        match cli.command {
+            Some(Commands::Logs { name, follow, tail }) => {
+                assert_eq!(name, None);
+                assert!(follow);
+                assert_eq!(tail, Some(50));
+            }
+            _ => panic!("Expected Logs command"),
+        }
+    }
+
     #[test]
     fn test_parse_backend() {
         assert!(matches!(
@@ -637,9 +1248,29 @@ mod tests {
             Commands::parse_backend("pod"),
             Ok(crate::config::BackendType::Podman)
         ));
+        assert!(matches!(
+            Commands::parse_backend("Docker"),
+            Ok(crate::config::BackendType::Docker)
+        ));
+        assert!(matches!(
+            Commands::parse_backend("bwrap"),
+            Ok(crate::config::BackendType::Bubblewrap)
+        ));
         assert!(Commands::parse_backend("invalid").is_err());
     }
 
+    #[test]
+    fn test_command_backend() {
+        let cli = Cli::parse_from(["jail-ai", "claude", "--backend", "docker"]);
+        assert_eq!(cli.command.unwrap().backend(), Some("docker"));
+
+        let cli = Cli::parse_from(["jail-ai", "volume", "ls", "-b", "bwrap"]);
+        assert_eq!(cli.command.unwrap().backend(), Some("bwrap"));
+
+        let cli = Cli::parse_from(["jail-ai", "list"]);
+        assert_eq!(cli.command.unwrap().backend(), None);
+    }
+
     #[test]
     fn test_parse_mount() {
         // Test with a path that exists
@@ -771,6 +1402,21 @@ mod tests {
         assert!(Cli::try_parse_from(args).is_err());
     }
 
+    #[test]
+    fn test_profile_conflicts_with_config_file() {
+        // A configuration file is used as is, a profile would be ignored
+        let args = vec![
+            "jail-ai",
+            "create",
+            "test-jail",
+            "--config",
+            "jail.json",
+            "--profile",
+            "strict",
+        ];
+        assert!(Cli::try_parse_from(args).is_err());
+    }
+
     #[test]
     fn test_host_network_flag_create_command() {
         // Test that --host-network works with the create command
@@ -790,6 +1436,83 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_profile_flag_parsing() {
+        let args = vec!["jail-ai", "claude", "--profile", "strict"];
+        let cli = Cli::try_parse_from(args).unwrap();
+        match cli.command {
+            Some(Commands::Claude { common, .. }) => {
+                assert_eq!(common.profile.as_deref(), Some("strict"));
+            }
+            _ => panic!("Expected Claude command"),
+        }
+
+        let args = vec!["jail-ai", "create", "test-jail", "--profile", "dev"];
+        let cli = Cli::try_parse_from(args).unwrap();
+        match cli.command {
+            Some(Commands::Create { profile, .. }) => {
+                assert_eq!(profile.as_deref(), Some("dev"));
+            }
+            _ => panic!("Expected Create command"),
+        }
+    }
+
+    #[test]
+    fn test_config_show_parsing() {
+        let args = vec![
+            "jail-ai",
+            "config",
+            "show",
+            "--effective",
+            "--agent",
+            "codex",
+            "--json",
+            "--profile",
+            "strict",
+            "--memory",
+            "1024",
+        ];
+        let cli = Cli::try_parse_from(args).unwrap();
+        match cli.command {
+            Some(Commands::Config {
+                command:
+                    ConfigCommands::Show {
+                        effective,
+                        agent,
+                        json,
+                        common,
+                    },
+            }) => {
+                assert!(effective);
+                assert!(json);
+                assert_eq!(agent, "codex");
+                assert_eq!(common.profile.as_deref(), Some("strict"));
+                assert_eq!(common.memory, Some(1024));
+            }
+            _ => panic!("Expected Config Show command"),
+        }
+    }
+
+    #[test]
+    fn test_config_validate_parsing() {
+        let args = vec!["jail-ai", "config", "validate", "jail.json"];
+        let cli = Cli::try_parse_from(args).unwrap();
+        match cli.command {
+            Some(Commands::Config {
+                command: ConfigCommands::Validate { file },
+            }) => assert_eq!(file, PathBuf::from("jail.json")),
+            _ => panic!("Expected Config Validate command"),
+        }
+
+        let args = vec!["jail-ai", "config", "schema"];
+        assert!(matches!(
+            Cli::try_parse_from(args).unwrap().command,
+            Some(Commands::Config {
+                command: ConfigCommands::Schema
+            })
+        ));
+    }
+
     #[test]
     fn test_auth_flag_parsing() {
         // Test that the --auth flag is properly parsed
@@ -820,6 +1543,63 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parse_ulimit() {
+        let ulimit = Commands::parse_ulimit("nofile=1024:4096").unwrap();
+        assert_eq!(ulimit.name, "nofile");
+        assert_eq!((ulimit.soft, ulimit.hard), (1024, 4096));
+
+        let ulimit = Commands::parse_ulimit("nproc=512").unwrap();
+        assert_eq!((ulimit.soft, ulimit.hard), (512, 512));
+
+        assert!(Commands::parse_ulimit("nofile").is_err());
+        assert!(Commands::parse_ulimit("files=10").is_err());
+        assert!(Commands::parse_ulimit("nofile=4096:1024").is_err());
+        assert!(Commands::parse_ulimit("nofile=many").is_err());
+    }
+
+    #[test]
+    fn test_parse_tmpfs() {
+        let tmpfs = Commands::parse_tmpfs("/tmp:512").unwrap();
+        assert_eq!(tmpfs.target, PathBuf::from("/tmp"));
+        assert_eq!(tmpfs.size_mb, 512);
+
+        let tmpfs = Commands::parse_tmpfs("/scratch").unwrap();
+        assert_eq!(tmpfs.size_mb, DEFAULT_TMPFS_SIZE_MB);
+
+        assert!(Commands::parse_tmpfs("tmp:512").is_err());
+        assert!(Commands::parse_tmpfs("/tmp:0").is_err());
+        assert!(Commands::parse_tmpfs("/tmp:big").is_err());
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        use std::time::Duration;
+
+        assert_eq!(
+            Commands::parse_duration("90s").unwrap(),
+            Duration::from_secs(90)
+        );
+        assert_eq!(
+            Commands::parse_duration("2h").unwrap(),
+            Duration::from_secs(7200)
+        );
+        assert_eq!(
+            Commands::parse_duration("1h30m").unwrap(),
+            Duration::from_secs(5400)
+        );
+        assert_eq!(
+            Commands::parse_duration("1d").unwrap(),
+            Duration::from_secs(86_400)
+        );
+
+        assert!(Commands::parse_duration("").is_err());
+        assert!(Commands::parse_duration("2").is_err());
+        assert!(Commands::parse_duration("0m").is_err());
+        assert!(Commands::parse_duration("h").is_err());
+        assert!(Commands::parse_duration("2w").is_err());
+    }
+
     #[test]
     fn test_parse_port() {
         // Test basic port mapping
@@ -858,5 +1638,46 @@ mod tests {
         assert!(Commands::parse_port("invalid:80").is_err());
         assert!(Commands::parse_port("8080:invalid").is_err());
         assert!(Commands::parse_port("70000:80").is_err());
+        assert!(Commands::parse_port("8080:0").is_err());
+    }
+
+    #[test]
+    fn test_parse_port_ranges_addresses_and_free_ports() {
+        // Loopback unless an address is given
+        let port = Commands::parse_port("8080:80").unwrap();
+        assert_eq!(port.host_ip, "127.0.0.1");
+        assert_eq!(port.to_string(), "127.0.0.1:8080:80/tcp");
+
+        let port = Commands::parse_port("0.0.0.0:8080:80/udp").unwrap();
+        assert_eq!(port.host_ip, "0.0.0.0");
+        assert_eq!(port.publish_arg(), "0.0.0.0:8080:80/udp");
+
+        let port = Commands::parse_port("[::1]:8080:80").unwrap();
+        assert_eq!(port.host_ip, "::1");
+        assert_eq!(port.to_string(), "[::1]:8080:80/tcp");
+
+        let port = Commands::parse_port("8000-8010:9000-9010").unwrap();
+        assert_eq!(
+            (port.host_port, port.container_port, port.count),
+            (8000, 9000, 11)
+        );
+        assert_eq!(port.to_string(), "127.0.0.1:8000-8010:9000-9010/tcp");
+        assert_eq!(port.ports().last(), Some((8010, 9010)));
+
+        // Host port 0 picks a free port
+        let port = Commands::parse_port("0:3000").unwrap();
+        assert_eq!(port.host_port, 0);
+        assert_eq!(port.publish_arg(), "127.0.0.1::3000/tcp");
+
+        // Every canonical form parses back to the same mapping
+        for spec in ["127.0.0.1:0:3000/tcp", "[::1]:8000-8010:8000-8010/udp"] {
+            assert_eq!(Commands::parse_port(spec).unwrap().to_string(), spec);
+        }
+
+        assert!(Commands::parse_port("8000-8010:8000-8005").is_err());
+        assert!(Commands::parse_port("0:8000-8010").is_err());
+        assert!(Commands::parse_port("8010-8000:8010-8000").is_err());
+        assert!(Commands::parse_port("localhost:8080:80").is_err());
+        assert!(Commands::parse_port("[::1:8080:80").is_err());
     }
 }
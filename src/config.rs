@@ -1,13 +1,15 @@
 use crate::image;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
+use std::sync::OnceLock;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct JailConfig {
     /// Name of the jail
     pub name: String,
 
-    /// Backend type (podman)
+    /// Backend type (podman, docker or bubblewrap)
     pub backend: BackendType,
 
     /// Base image or directory for the jail
@@ -63,26 +65,47 @@ pub struct JailConfig {
     /// Enable Podman-in-Podman by mounting the host's Podman socket
     #[serde(default)]
     pub podman_socket: bool,
+
+    /// Drop all capabilities, forbid privilege escalation and make the root filesystem read-only
+    #[serde(default)]
+    pub hardened: bool,
+
+    /// Apply the seccomp profile shipped with jail-ai
+    #[serde(default)]
+    pub seccomp: bool,
+
+    /// Mount the workspace as an overlay keeping changes apart until applied
+    #[serde(default)]
+    pub cow: bool,
+
+    /// Session jail using the home volume of the main jail of its agent
+    #[serde(default)]
+    pub shared_home: bool,
 }
 
 fn default_true() -> bool {
     true
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
 #[serde(rename_all = "lowercase")]
 pub enum BackendType {
     Podman,
+    Docker,
+    Bubblewrap,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct BindMount {
     pub source: PathBuf,
     pub target: PathBuf,
     pub readonly: bool,
+    /// The project workspace, overlaid in copy-on-write jails
+    #[serde(default)]
+    pub workspace: bool,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct NetworkConfig {
     /// Enable network access (if false, uses --network=none for complete isolation)
     pub enabled: bool,
@@ -96,50 +119,267 @@ pub struct NetworkConfig {
     pub host: bool,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct PortMapping {
+    /// Host port, 0 to pick a free one when the jail is created
     pub host_port: u16,
     pub container_port: u16,
     /// Protocol (tcp or udp)
     #[serde(default = "default_tcp")]
     pub protocol: String,
+    /// Host address the port is bound on
+    #[serde(default = "default_host_ip")]
+    pub host_ip: String,
+    /// Number of consecutive ports mapped, for port ranges
+    #[serde(default = "default_port_count")]
+    pub count: u16,
 }
 
 fn default_tcp() -> String {
     "tcp".to_string()
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Ports are only reachable from the host unless bound on another address
+pub const DEFAULT_HOST_IP: &str = "127.0.0.1";
+
+fn default_host_ip() -> String {
+    DEFAULT_HOST_IP.to_string()
+}
+
+fn default_port_count() -> u16 {
+    1
+}
+
+impl PortMapping {
+    fn range(first: u16, count: u16) -> String {
+        if count > 1 {
+            format!("{}-{}", first, first.saturating_add(count - 1))
+        } else {
+            first.to_string()
+        }
+    }
+
+    /// Host address, bracketed when it is an IPv6 address
+    fn host_addr(&self) -> String {
+        if self.host_ip.contains(':') {
+            format!("[{}]", self.host_ip)
+        } else {
+            self.host_ip.clone()
+        }
+    }
+
+    /// `--publish` value of podman and docker, an empty host port letting the engine choose
+    pub fn publish_arg(&self) -> String {
+        let host = if self.host_port == 0 {
+            String::new()
+        } else {
+            Self::range(self.host_port, self.count)
+        };
+        format!(
+            "{}:{}:{}/{}",
+            self.host_addr(),
+            host,
+            Self::range(self.container_port, self.count),
+            self.protocol
+        )
+    }
+
+    /// Mapping of each port of a range
+    pub fn ports(&self) -> impl Iterator<Item = (u16, u16)> + '_ {
+        (0..self.count).map(|i| {
+            (
+                self.host_port.saturating_add(i),
+                self.container_port.saturating_add(i),
+            )
+        })
+    }
+}
+
+/// Canonical `[ADDRESS:]HOST:CONTAINER[/PROTOCOL]` form, as accepted by `--port`
+impl std::fmt::Display for PortMapping {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}:{}:{}/{}",
+            self.host_addr(),
+            Self::range(self.host_port, self.count),
+            Self::range(self.container_port, self.count),
+            self.protocol
+        )
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
 pub struct ResourceLimits {
     pub memory_mb: Option<u64>,
     pub cpu_quota: Option<u32>,
+    /// Maximum number of processes
+    #[serde(default)]
+    pub pids_limit: Option<u32>,
+    /// Memory plus swap limit in MB (equal to `memory_mb` disables swap)
+    #[serde(default)]
+    pub memory_swap_mb: Option<u64>,
+    /// Size of /dev/shm in MB
+    #[serde(default)]
+    pub shm_size_mb: Option<u64>,
+    /// Block IO weight (10-1000)
+    #[serde(default)]
+    pub blkio_weight: Option<u16>,
+    /// Process resource limits (nofile, nproc...)
+    #[serde(default)]
+    pub ulimits: Vec<Ulimit>,
+    /// tmpfs mounts with a size cap
+    #[serde(default)]
+    pub tmpfs: Vec<TmpfsMount>,
+}
+
+/// Names accepted for ulimits, as understood by podman, docker and prlimit
+pub const ULIMIT_NAMES: &[&str] = &[
+    "core",
+    "cpu",
+    "data",
+    "fsize",
+    "locks",
+    "memlock",
+    "msgqueue",
+    "nice",
+    "nofile",
+    "nproc",
+    "rss",
+    "rtprio",
+    "rttime",
+    "sigpending",
+    "stack",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct Ulimit {
+    /// Resource name (e.g. nofile, nproc)
+    pub name: String,
+    pub soft: u64,
+    pub hard: u64,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct TmpfsMount {
+    pub target: PathBuf,
+    pub size_mb: u64,
+}
+
+/// Backend given on the command line, set once by `main` (see `BackendType::select`)
+static SELECTED_BACKEND: OnceLock<BackendType> = OnceLock::new();
+
+/// Backend found on the system, detected once per process
+static DETECTED_BACKEND: OnceLock<BackendType> = OnceLock::new();
+
 impl BackendType {
-    /// Check if this backend is available on the system
-    pub fn is_available(&self) -> bool {
-        std::process::Command::new("podman")
+    /// Name of the container engine command
+    pub fn command(&self) -> &'static str {
+        match self {
+            BackendType::Podman => "podman",
+            BackendType::Docker => "docker",
+            BackendType::Bubblewrap => "bwrap",
+        }
+    }
+
+    /// Backend whose engine builds and stores images for this backend
+    ///
+    /// Bubblewrap has no image store: images are built with podman (or docker) and exported.
+    pub fn image_engine(&self) -> Self {
+        match self {
+            BackendType::Bubblewrap => {
+                if !BackendType::Podman.command_available()
+                    && BackendType::Docker.command_available()
+                {
+                    BackendType::Docker
+                } else {
+                    BackendType::Podman
+                }
+            }
+            other => *other,
+        }
+    }
+
+    fn command_available(&self) -> bool {
+        std::process::Command::new(self.command())
             .arg("--version")
             .output()
             .map(|o| o.status.success())
             .unwrap_or(false)
     }
 
+    /// Check if this backend is available on the system
+    pub fn is_available(&self) -> bool {
+        self.command_available() && self.image_engine().command_available()
+    }
+
     /// Get all available backends on the system
     pub fn all_available() -> Vec<Self> {
-        if BackendType::Podman.is_available() {
-            vec![BackendType::Podman]
-        } else {
-            vec![]
-        }
+        [
+            BackendType::Podman,
+            BackendType::Docker,
+            BackendType::Bubblewrap,
+        ]
+        .into_iter()
+        .filter(|backend| backend.is_available())
+        .collect()
     }
 
-    /// Always returns Podman (only supported backend)
+    /// Return the selected backend, otherwise Podman when installed and Docker as a fallback
     pub fn detect() -> Self {
-        BackendType::Podman
+        if let Some(backend) = SELECTED_BACKEND.get() {
+            return *backend;
+        }
+
+        *DETECTED_BACKEND.get_or_init(|| {
+            if !BackendType::Podman.command_available() && BackendType::Docker.command_available() {
+                BackendType::Docker
+            } else {
+                BackendType::Podman
+            }
+        })
+    }
+
+    /// Use this backend for the jails detected in the rest of the process
+    ///
+    /// Called once by `main` with the `--backend` of the command, later calls are ignored.
+    pub fn select(self) {
+        let _ = SELECTED_BACKEND.set(self);
     }
 }
 
+/// Placeholder replacing secret values in recorded configurations
+pub const REDACTED: &str = "<redacted>";
+
+impl JailConfig {
+    /// Copy of the configuration with secret-looking environment values redacted
+    pub fn without_secrets(&self) -> JailConfig {
+        let mut config = self.clone();
+        for (key, value) in &mut config.environment {
+            if is_secret_env(key) {
+                *value = REDACTED.to_string();
+            }
+        }
+        config
+    }
+}
+
+/// Environment variables whose values must not be recorded (tokens, keys, passwords)
+pub fn is_secret_env(key: &str) -> bool {
+    let key = key.to_ascii_uppercase();
+    [
+        "TOKEN",
+        "SECRET",
+        "PASSWORD",
+        "PASSWD",
+        "KEY",
+        "CREDENTIAL",
+        "COOKIE",
+    ]
+    .iter()
+    .any(|pattern| key.contains(pattern))
+}
+
 impl Default for JailConfig {
     fn default() -> Self {
         Self {
@@ -154,10 +394,7 @@ impl Default for JailConfig {
                 host: false,
             },
             port_mappings: Vec::new(),
-            limits: ResourceLimits {
-                memory_mb: None,
-                cpu_quota: None,
-            },
+            limits: ResourceLimits::default(),
             upgrade: false,
             force_layers: Vec::new(),
             use_layered_images: true,
@@ -167,6 +404,10 @@ impl Default for JailConfig {
             no_nix: false,
             block_host: true,
             podman_socket: false,
+            hardened: false,
+            seccomp: false,
+            cow: false,
+            shared_home: false,
         }
     }
 }
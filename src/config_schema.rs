@@ -0,0 +1,606 @@
+use crate::config::JailConfig;
+use crate::error::{JailError, Result};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::path::Path;
+
+/// Current version of the serialized `JailConfig` format
+pub const SCHEMA_VERSION: u32 = 2;
+
+/// Serialized form of a jail configuration file
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct VersionedJailConfig {
+    /// Version of the configuration format (files without it are treated as version 0)
+    pub schema_version: u32,
+
+    #[serde(flatten)]
+    pub config: JailConfig,
+}
+
+/// A migration upgrading a configuration from version `n` to `n + 1`
+type Migration = fn(&mut Map<String, Value>);
+
+/// Migrations indexed by the version they upgrade from
+const MIGRATIONS: &[Migration] = &[migrate_v0_to_v1, migrate_v1_to_v2];
+
+/// Version 0 files predate private/host networking and always had resource limits optional
+fn migrate_v0_to_v1(config: &mut Map<String, Value>) {
+    if let Some(Value::Object(network)) = config.get_mut("network") {
+        network.entry("private").or_insert(Value::Bool(true));
+        network.entry("host").or_insert(Value::Bool(false));
+    }
+    config.entry("limits").or_insert_with(|| {
+        serde_json::json!({
+            "memory_mb": null,
+            "cpu_quota": null,
+        })
+    });
+}
+
+/// Version 1 ports had no host address and were published on all interfaces, keep them so
+fn migrate_v1_to_v2(config: &mut Map<String, Value>) {
+    if let Some(Value::Array(ports)) = config.get_mut("port_mappings") {
+        for port in ports {
+            if let Value::Object(port) = port {
+                port.entry("host_ip")
+                    .or_insert_with(|| Value::from("0.0.0.0"));
+            }
+        }
+    }
+}
+
+/// Serialize a configuration to JSON, tagged with the current schema version
+pub fn to_json_string(config: &JailConfig) -> Result<String> {
+    Ok(serde_json::to_string_pretty(&VersionedJailConfig {
+        schema_version: SCHEMA_VERSION,
+        config: config.clone(),
+    })?)
+}
+
+/// Serialize a configuration to single-line JSON (for container labels)
+pub fn to_json_line(config: &JailConfig) -> Result<String> {
+    Ok(serde_json::to_string(&VersionedJailConfig {
+        schema_version: SCHEMA_VERSION,
+        config: config.clone(),
+    })?)
+}
+
+/// Parse a configuration file, migrating older versions
+pub fn from_json_str(content: &str) -> Result<JailConfig> {
+    let value: Value = serde_json::from_str(content)?;
+    let migrated = migrate(value)?;
+    let versioned: VersionedJailConfig = serde_json::from_value(migrated)?;
+    Ok(versioned.config)
+}
+
+/// Load a configuration file, migrating older versions
+pub fn load(path: &Path) -> Result<JailConfig> {
+    let content = std::fs::read_to_string(path)?;
+    from_json_str(&content).map_err(|e| {
+        JailError::Config(format!(
+            "Invalid configuration file {}: {}",
+            path.display(),
+            e
+        ))
+    })
+}
+
+/// Upgrade a configuration value to the current schema version
+pub fn migrate(value: Value) -> Result<Value> {
+    let Value::Object(mut config) = value else {
+        return Err(JailError::Config(
+            "Configuration must be a JSON object".to_string(),
+        ));
+    };
+
+    let version = match config.get("schema_version") {
+        None => 0,
+        Some(v) => v
+            .as_u64()
+            .and_then(|v| u32::try_from(v).ok())
+            .ok_or_else(|| {
+                JailError::Config(format!(
+                    "Invalid schema_version {v}, expected a non-negative integer"
+                ))
+            })?,
+    };
+
+    if version > SCHEMA_VERSION {
+        return Err(JailError::Config(format!(
+            "Configuration schema_version {version} is newer than supported version {SCHEMA_VERSION}, please upgrade jail-ai"
+        )));
+    }
+
+    for migration in &MIGRATIONS[version as usize..] {
+        migration(&mut config);
+    }
+    config.insert("schema_version".to_string(), Value::from(SCHEMA_VERSION));
+
+    Ok(Value::Object(config))
+}
+
+/// JSON Schema of the configuration file format
+pub fn json_schema() -> Value {
+    serde_json::to_value(schemars::schema_for!(VersionedJailConfig))
+        .expect("JSON Schema serialization cannot fail")
+}
+
+/// Validate a configuration file, returning every problem found
+pub fn validate(content: &str) -> Vec<String> {
+    let value: Value = match serde_json::from_str(content) {
+        Ok(value) => value,
+        Err(e) => return vec![format!("invalid JSON: {e}")],
+    };
+
+    let value = match migrate(value) {
+        Ok(value) => value,
+        Err(e) => return vec![e.to_string()],
+    };
+
+    let schema = json_schema();
+    let mut problems = Vec::new();
+    check_schema(&value, &schema, &schema, "", &mut problems);
+
+    if problems.is_empty() {
+        match serde_json::from_value::<VersionedJailConfig>(value) {
+            Ok(versioned) => check_semantics(&versioned.config, &mut problems),
+            Err(e) => problems.push(e.to_string()),
+        }
+    }
+
+    problems
+}
+
+/// Checks beyond the structure: values the backend would reject
+fn check_semantics(config: &JailConfig, problems: &mut Vec<String>) {
+    let valid_name = config
+        .name
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphanumeric())
+        && config
+            .name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "_.-".contains(c));
+    if !valid_name {
+        problems.push(format!(
+            "name: '{}' must match [a-zA-Z0-9][a-zA-Z0-9_.-]*",
+            config.name
+        ));
+    }
+
+    if config.network.host && !config.network.enabled {
+        problems.push("network: 'host' requires 'enabled' to be true".to_string());
+    }
+
+    for (i, mount) in config.bind_mounts.iter().enumerate() {
+        if !mount.target.is_absolute() {
+            problems.push(format!(
+                "bind_mounts[{i}].target: '{}' must be an absolute path",
+                mount.target.display()
+            ));
+        }
+        if !mount.source.is_absolute() {
+            problems.push(format!(
+                "bind_mounts[{i}].source: '{}' must be an absolute path",
+                mount.source.display()
+            ));
+        }
+    }
+
+    for (i, (key, _)) in config.environment.iter().enumerate() {
+        if key.is_empty() || key.contains('=') {
+            problems.push(format!("environment[{i}]: invalid variable name '{key}'"));
+        }
+    }
+
+    for (i, port) in config.port_mappings.iter().enumerate() {
+        if port.container_port == 0 {
+            problems.push(format!("port_mappings[{i}].container_port: must not be 0"));
+        }
+        if port.count == 0 {
+            problems.push(format!("port_mappings[{i}].count: must not be 0"));
+        } else if port.host_port == 0 && port.count > 1 {
+            problems.push(format!(
+                "port_mappings[{i}].host_port: a free port is only picked for a single port"
+            ));
+        }
+        let last_port = port.host_port.max(port.container_port);
+        if last_port
+            .checked_add(port.count.saturating_sub(1))
+            .is_none()
+        {
+            problems.push(format!(
+                "port_mappings[{i}].count: range exceeds port 65535"
+            ));
+        }
+        if port.host_ip.parse::<std::net::IpAddr>().is_err() {
+            problems.push(format!(
+                "port_mappings[{i}].host_ip: '{}' is not an IP address",
+                port.host_ip
+            ));
+        }
+        if port.protocol != "tcp" && port.protocol != "udp" {
+            problems.push(format!(
+                "port_mappings[{i}].protocol: '{}' must be tcp or udp",
+                port.protocol
+            ));
+        }
+    }
+
+    if let Some(cpu) = config.limits.cpu_quota {
+        if cpu == 0 || cpu > 100 {
+            problems.push(format!(
+                "limits.cpu_quota: {cpu} must be a percentage between 1 and 100"
+            ));
+        }
+    }
+    if config.limits.memory_mb == Some(0) {
+        problems.push("limits.memory_mb: must be greater than 0".to_string());
+    }
+    if let Some(swap) = config.limits.memory_swap_mb {
+        match config.limits.memory_mb {
+            None => problems.push("limits.memory_swap_mb: requires limits.memory_mb".to_string()),
+            Some(memory) if swap < memory => problems.push(format!(
+                "limits.memory_swap_mb: {swap} must not be lower than limits.memory_mb ({memory})"
+            )),
+            Some(_) => {}
+        }
+    }
+    if config.limits.pids_limit == Some(0) {
+        problems.push("limits.pids_limit: must be greater than 0".to_string());
+    }
+    if let Some(weight) = config.limits.blkio_weight {
+        if !(10..=1000).contains(&weight) {
+            problems.push(format!(
+                "limits.blkio_weight: {weight} must be between 10 and 1000"
+            ));
+        }
+    }
+    for (i, ulimit) in config.limits.ulimits.iter().enumerate() {
+        if !crate::config::ULIMIT_NAMES.contains(&ulimit.name.as_str()) {
+            problems.push(format!(
+                "limits.ulimits[{i}].name: unknown resource '{}'",
+                ulimit.name
+            ));
+        }
+        if ulimit.soft > ulimit.hard {
+            problems.push(format!(
+                "limits.ulimits[{i}]: soft limit {} exceeds hard limit {}",
+                ulimit.soft, ulimit.hard
+            ));
+        }
+    }
+    for (i, tmpfs) in config.limits.tmpfs.iter().enumerate() {
+        if !tmpfs.target.is_absolute() {
+            problems.push(format!(
+                "limits.tmpfs[{i}].target: '{}' must be an absolute path",
+                tmpfs.target.display()
+            ));
+        }
+        if tmpfs.size_mb == 0 {
+            problems.push(format!("limits.tmpfs[{i}].size_mb: must be greater than 0"));
+        }
+    }
+}
+
+/// Minimal JSON Schema checker covering the subset emitted by schemars for `JailConfig`
+fn check_schema(
+    value: &Value,
+    schema: &Value,
+    root: &Value,
+    path: &str,
+    problems: &mut Vec<String>,
+) {
+    let field = if path.is_empty() { "<root>" } else { path };
+
+    if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+        let name = reference.trim_start_matches("#/definitions/");
+        match root.get("definitions").and_then(|d| d.get(name)) {
+            Some(target) => check_schema(value, target, root, path, problems),
+            None => problems.push(format!("{field}: unresolved schema reference {reference}")),
+        }
+        return;
+    }
+
+    if let Some(all_of) = schema.get("allOf").and_then(Value::as_array) {
+        for sub in all_of {
+            check_schema(value, sub, root, path, problems);
+        }
+    }
+
+    if let Some(any_of) = schema.get("anyOf").and_then(Value::as_array) {
+        let matches = any_of.iter().any(|sub| {
+            let mut sub_problems = Vec::new();
+            check_schema(value, sub, root, path, &mut sub_problems);
+            sub_problems.is_empty()
+        });
+        if !matches {
+            problems.push(format!("{field}: {value} does not match any allowed type"));
+        }
+        return;
+    }
+
+    if let Some(allowed) = schema.get("enum").and_then(Value::as_array) {
+        if !allowed.contains(value) {
+            let allowed: Vec<String> = allowed.iter().map(Value::to_string).collect();
+            problems.push(format!(
+                "{field}: {value} is not one of {}",
+                allowed.join(", ")
+            ));
+            return;
+        }
+    }
+
+    if let Some(expected) = schema.get("type") {
+        let types: Vec<&str> = match expected {
+            Value::String(t) => vec![t.as_str()],
+            Value::Array(ts) => ts.iter().filter_map(Value::as_str).collect(),
+            _ => Vec::new(),
+        };
+        if !types.is_empty() && !types.iter().any(|t| type_matches(value, t)) {
+            problems.push(format!(
+                "{field}: expected {}, found {}",
+                types.join(" or "),
+                type_name(value)
+            ));
+            return;
+        }
+    }
+
+    if let Some(n) = value.as_f64() {
+        // schemars describes unsigned integers by format only, without an upper bound
+        let format_max = match schema.get("format").and_then(Value::as_str) {
+            Some("uint8") => Some(f64::from(u8::MAX)),
+            Some("uint16") => Some(f64::from(u16::MAX)),
+            Some("uint32") => Some(f64::from(u32::MAX)),
+            _ => None,
+        };
+        if let Some(max) = format_max {
+            if n > max {
+                problems.push(format!("{field}: {value} is above the maximum of {max}"));
+            }
+        }
+        if let Some(min) = schema.get("minimum").and_then(Value::as_f64) {
+            if n < min {
+                problems.push(format!("{field}: {value} is below the minimum of {min}"));
+            }
+        }
+        if let Some(max) = schema.get("maximum").and_then(Value::as_f64) {
+            if n > max {
+                problems.push(format!("{field}: {value} is above the maximum of {max}"));
+            }
+        }
+    }
+
+    if let Value::Object(object) = value {
+        let properties = schema.get("properties").and_then(Value::as_object);
+        if let Some(required) = schema.get("required").and_then(Value::as_array) {
+            for key in required.iter().filter_map(Value::as_str) {
+                if !object.contains_key(key) {
+                    problems.push(format!("{}: missing required field", join_path(path, key)));
+                }
+            }
+        }
+        if let Some(properties) = properties {
+            for (key, child) in object {
+                match properties.get(key) {
+                    Some(child_schema) => {
+                        check_schema(child, child_schema, root, &join_path(path, key), problems)
+                    }
+                    None => problems.push(format!("{}: unknown field", join_path(path, key))),
+                }
+            }
+        }
+    }
+
+    if let Value::Array(items) = value {
+        match schema.get("items") {
+            Some(Value::Array(tuple)) => {
+                if items.len() != tuple.len() {
+                    problems.push(format!(
+                        "{field}: expected {} elements, found {}",
+                        tuple.len(),
+                        items.len()
+                    ));
+                }
+                for (i, (item, item_schema)) in items.iter().zip(tuple).enumerate() {
+                    check_schema(item, item_schema, root, &format!("{path}[{i}]"), problems);
+                }
+            }
+            Some(item_schema) => {
+                for (i, item) in items.iter().enumerate() {
+                    check_schema(item, item_schema, root, &format!("{path}[{i}]"), problems);
+                }
+            }
+            None => {}
+        }
+    }
+}
+
+fn join_path(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.to_string()
+    } else {
+        format!("{path}.{key}")
+    }
+}
+
+fn type_matches(value: &Value, expected: &str) -> bool {
+    match expected {
+        "null" => value.is_null(),
+        "boolean" => value.is_boolean(),
+        "string" => value.is_string(),
+        "array" => value.is_array(),
+        "object" => value.is_object(),
+        "integer" => value.is_i64() || value.is_u64(),
+        "number" => value.is_number(),
+        _ => true,
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip_includes_version() {
+        let config = JailConfig::default();
+        let json = to_json_string(&config).unwrap();
+        let value: Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["schema_version"], SCHEMA_VERSION);
+
+        let loaded = from_json_str(&json).unwrap();
+        assert_eq!(loaded.name, config.name);
+        assert!(validate(&json).is_empty(), "{:?}", validate(&json));
+    }
+
+    #[test]
+    fn test_migrate_legacy_file() {
+        // Version 0: no schema_version, no private/host networking, no limits
+        let legacy = r#"{
+            "name": "legacy",
+            "backend": "podman",
+            "base_image": "alpine:latest",
+            "bind_mounts": [],
+            "environment": [["FOO", "bar"]],
+            "network": { "enabled": true }
+        }"#;
+
+        let config = from_json_str(legacy).unwrap();
+        assert_eq!(config.name, "legacy");
+        assert!(config.network.private);
+        assert!(!config.network.host);
+        assert!(config.limits.memory_mb.is_none());
+        assert_eq!(config.environment, vec![("FOO".into(), "bar".into())]);
+    }
+
+    #[test]
+    fn test_migrate_v1_ports_keep_all_interfaces() {
+        let v1 = r#"{
+            "schema_version": 1,
+            "name": "ports",
+            "backend": "podman",
+            "base_image": "alpine:latest",
+            "bind_mounts": [],
+            "environment": [],
+            "network": { "enabled": true, "private": true, "host": false },
+            "limits": { "memory_mb": null, "cpu_quota": null },
+            "port_mappings": [
+                { "host_port": 8080, "container_port": 80 },
+                { "host_port": 9090, "container_port": 90, "host_ip": "127.0.0.1" }
+            ]
+        }"#;
+
+        let config = from_json_str(v1).unwrap();
+        assert_eq!(config.port_mappings[0].host_ip, "0.0.0.0");
+        assert_eq!(config.port_mappings[1].host_ip, "127.0.0.1");
+
+        // New files default to the loopback address
+        let v2 = v1.replace(r#""schema_version": 1"#, r#""schema_version": 2"#);
+        let config = from_json_str(&v2).unwrap();
+        assert_eq!(
+            config.port_mappings[0].host_ip,
+            crate::config::DEFAULT_HOST_IP
+        );
+    }
+
+    #[test]
+    fn test_rejects_newer_version() {
+        let err = from_json_str(r#"{"schema_version": 999}"#).unwrap_err();
+        assert!(err.to_string().contains("newer"), "{err}");
+    }
+
+    #[test]
+    fn test_validate_reports_all_problems() {
+        let content = r#"{
+            "schema_version": 1,
+            "name": "-bad name",
+            "backend": "lxc",
+            "base_image": 42,
+            "bind_mounts": [{ "source": "/src", "target": "relative", "readonly": false }],
+            "environment": [],
+            "network": { "enabled": true, "private": true, "host": false },
+            "port_mappings": [{ "host_port": 70000, "container_port": 80 }],
+            "limits": { "memory_mb": null, "cpu_quota": null },
+            "unexpected": true
+        }"#;
+
+        let problems = validate(content);
+        let joined = problems.join("\n");
+        assert!(joined.contains("backend"), "{joined}");
+        assert!(joined.contains("base_image: expected string"), "{joined}");
+        assert!(joined.contains("port_mappings[0].host_port"), "{joined}");
+        assert!(joined.contains("unexpected: unknown field"), "{joined}");
+        assert!(problems.len() >= 4, "{joined}");
+    }
+
+    #[test]
+    fn test_validate_semantic_problems() {
+        let mut config = JailConfig {
+            name: "-bad".to_string(),
+            ..Default::default()
+        };
+        config.limits.cpu_quota = Some(150);
+        config.bind_mounts.push(crate::config::BindMount {
+            source: "/src".into(),
+            target: "relative".into(),
+            readonly: false,
+            workspace: false,
+        });
+        let problems = validate(&to_json_string(&config).unwrap());
+        assert_eq!(problems.len(), 3, "{problems:?}");
+    }
+
+    #[test]
+    fn test_validate_extended_limits() {
+        let mut config = JailConfig {
+            name: "limits".to_string(),
+            ..Default::default()
+        };
+        config.limits.memory_swap_mb = Some(1024);
+        config.limits.blkio_weight = Some(5);
+        config.limits.ulimits.push(crate::config::Ulimit {
+            name: "nofile".to_string(),
+            soft: 4096,
+            hard: 1024,
+        });
+        config.limits.tmpfs.push(crate::config::TmpfsMount {
+            target: "tmp".into(),
+            size_mb: 0,
+        });
+
+        let problems = validate(&to_json_string(&config).unwrap());
+        assert_eq!(problems.len(), 5, "{problems:?}");
+        assert!(
+            problems[0].contains("requires limits.memory_mb"),
+            "{problems:?}"
+        );
+    }
+
+    #[test]
+    fn test_validate_invalid_json() {
+        let problems = validate("{ not json");
+        assert_eq!(problems.len(), 1);
+        assert!(problems[0].starts_with("invalid JSON"));
+    }
+
+    #[test]
+    fn test_schema_describes_version() {
+        let schema = json_schema();
+        assert!(schema["properties"]["schema_version"].is_object());
+        assert!(schema["properties"]["bind_mounts"].is_object());
+    }
+}
@@ -0,0 +1,427 @@
+use crate::agent_commands::{self, AgentCommandParams};
+use crate::config::{BindMount, JailConfig};
+use crate::error::Result;
+use crate::settings::{JailSettings, SettingsLayers, Source};
+use serde::Serialize;
+
+/// A single configuration value and where it came from
+#[derive(Debug, Clone, Serialize)]
+pub struct FieldOrigin {
+    pub field: String,
+    pub value: String,
+    pub source: Source,
+}
+
+/// The configuration an agent command would use, with per-field provenance
+#[derive(Debug, Serialize)]
+pub struct EffectiveConfig {
+    pub config: JailConfig,
+    pub provenance: Vec<FieldOrigin>,
+}
+
+/// Agent config directory settings, in the order they are reported
+const AGENT_DIR_KEYS: &[&str] = &[
+    "agent-configs",
+    "claude-dir",
+    "claude-code-router-dir",
+    "copilot-dir",
+    "cursor-dir",
+    "gemini-dir",
+    "coderabbit-dir",
+    "codex-dir",
+    "jules-dir",
+    "pi-dir",
+];
+
+/// Show the settings merged from the configuration files
+pub fn show_file_settings(profile: Option<&str>, json: bool) -> Result<()> {
+    let layers = SettingsLayers::load(profile, agent_commands::get_git_root().as_deref())?;
+    let merged = layers.merged();
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&merged)?);
+        return Ok(());
+    }
+
+    let mut rows = Vec::new();
+    if let serde_json::Value::Object(map) = serde_json::to_value(&merged)? {
+        for (key, value) in map {
+            let source = layers.source_of(&key).unwrap_or(Source::Default);
+            rows.push(FieldOrigin {
+                field: key,
+                value: match value {
+                    serde_json::Value::String(s) => s,
+                    other => other.to_string(),
+                },
+                source,
+            });
+        }
+    }
+
+    if rows.is_empty() {
+        println!("No settings found in configuration files");
+    } else {
+        print_table(&rows);
+    }
+    Ok(())
+}
+
+/// Compute the configuration `run_ai_agent_command` would use, without creating anything
+pub async fn compute_effective_config(
+    agent_command: &str,
+    mut params: AgentCommandParams,
+) -> Result<EffectiveConfig> {
+    let cwd = std::env::current_dir()?;
+    let git_root = agent_commands::get_git_root();
+
+    // Same layering as run_ai_agent_command; the CLI layer is only recorded for provenance
+    let mut layers = SettingsLayers::load(params.profile.as_deref(), git_root.as_deref())?;
+    let cli_settings = JailSettings::from_params(&params);
+    layers.merged().apply_to_params(&mut params);
+    layers.push(Source::Cli, cli_settings);
+
+    let auth_source = if params.auth {
+        Some(Source::Cli)
+    } else if agent_commands::first_run_auth_needed(agent_command, &params) {
+        params.auth = true;
+        Some(Source::AutoDetect)
+    } else {
+        None
+    };
+
+    let base_name = agent_commands::auto_detect_jail_name()?;
+    let jail_name = agent_commands::agent_jail_name(&base_name, agent_command);
+    let backend_type = agent_commands::resolve_backend_type(params.backend.as_deref())?;
+
+    let build =
+        |params: &AgentCommandParams| -> Result<JailConfig> {
+            Ok(agent_commands::build_agent_jail(
+                agent_command,
+                &jail_name,
+                backend_type,
+                params,
+                &cwd,
+            )?
+            .build()
+            .config()
+            .clone())
+        };
+
+    let mut config = build(&params)?;
+
+    // Rebuild without the git/GPG and agent config options to attribute their mounts
+    let without_gpg = build(&AgentCommandParams {
+        git_gpg: false,
+        ..params.clone()
+    })?;
+    let without_dirs = build(&AgentCommandParams {
+        claude_dir: false,
+        claude_code_router_dir: false,
+        copilot_dir: false,
+        cursor_dir: false,
+        gemini_dir: false,
+        coderabbit_dir: false,
+        codex_dir: false,
+        jules_dir: false,
+        pi_dir: false,
+        agent_configs: false,
+        ..params.clone()
+    })?;
+
+    // Resolve the layered image the same way the backend would, without building it
+    let image_source = if config.base_image == crate::image::DEFAULT_IMAGE_NAME {
+        let workspace_dir = git_root.clone().unwrap_or_else(|| cwd.clone());
+        config.base_image = crate::image_layers::get_expected_image_name(
+            &workspace_dir,
+            Some(agent_commands::normalize_agent_name(agent_command)),
+            params.isolated,
+            params.no_nix,
+        )
+        .await?;
+        Source::AutoDetect
+    } else {
+        layers.source_of("image").unwrap_or(Source::Default)
+    };
+
+    let source_or_default = |key: &str| layers.source_of(key).unwrap_or(Source::Default);
+    let gpg_source = source_or_default("git-gpg");
+    let merged = serde_json::to_value(layers.merged())?;
+    let dirs_source = AGENT_DIR_KEYS
+        .iter()
+        .filter(|key| merged.get(**key) == Some(&serde_json::Value::Bool(true)))
+        .find_map(|key| layers.source_of(key))
+        .unwrap_or(Source::Default);
+
+    let mut provenance = Vec::new();
+    let mut record = |field: &str, value: String, source: Source| {
+        provenance.push(FieldOrigin {
+            field: field.to_string(),
+            value,
+            source,
+        });
+    };
+
+    record("name", config.name.clone(), Source::AutoDetect);
+    record(
+        "backend",
+        format!("{:?}", config.backend).to_lowercase(),
+        layers.source_of("backend").unwrap_or(Source::AutoDetect),
+    );
+    record("base_image", config.base_image.clone(), image_source);
+
+    // Either network key of a layer overrides the mode set by the layers below
+    let mode_source = layers
+        .source_of_any(&["no-network", "host-network"])
+        .unwrap_or(Source::Default);
+    let (network, network_source) = if config.network.host {
+        (
+            "host".to_string(),
+            auth_source.clone().unwrap_or(mode_source),
+        )
+    } else if !config.network.enabled {
+        ("none".to_string(), mode_source)
+    } else {
+        ("private".to_string(), mode_source)
+    };
+    record("network", network, network_source);
+
+    for mount in &config.bind_mounts {
+        let source = if !contains_mount(&without_gpg.bind_mounts, mount) {
+            gpg_source.clone()
+        } else if !contains_mount(&without_dirs.bind_mounts, mount) {
+            dirs_source.clone()
+        } else if let Some(entry) = params.mount.iter().find(|e| mount_entry_matches(e, mount)) {
+            layers
+                .source_of_entry("mounts", entry)
+                .unwrap_or(Source::Cli)
+        } else {
+            // Workspace, worktree and auto-mounted credentials
+            Source::AutoDetect
+        };
+        record("bind_mounts", format_mount(mount), source);
+    }
+
+    for (key, value) in &config.environment {
+        let entry = format!("{key}={value}");
+        let source = if params.env.contains(&entry) {
+            layers.source_of_entry("env", &entry).unwrap_or(Source::Cli)
+        } else if !without_gpg.environment.iter().any(|(k, _)| k == key) {
+            gpg_source.clone()
+        } else if key == "EDITOR" {
+            Source::Default
+        } else {
+            // TZ and TERM are inherited from the host
+            Source::AutoDetect
+        };
+        record("environment", entry, source);
+    }
+
+    for port in &config.port_mappings {
+        let value = port.to_string();
+        let source = params
+            .port
+            .iter()
+            .find(|entry| {
+                crate::cli::Commands::parse_port(entry).is_ok_and(|p| p.to_string() == value)
+            })
+            .and_then(|entry| layers.source_of_entry("ports", entry))
+            .unwrap_or(Source::Cli);
+        record("port_mappings", value, source);
+    }
+
+    record(
+        "limits.memory_mb",
+        option_to_string(config.limits.memory_mb),
+        source_or_default("memory"),
+    );
+    record(
+        "limits.cpu_quota",
+        option_to_string(config.limits.cpu_quota),
+        source_or_default("cpu"),
+    );
+    let cli_or_default = |given: bool| if given { Source::Cli } else { Source::Default };
+    record(
+        "limits.pids_limit",
+        option_to_string(config.limits.pids_limit),
+        cli_or_default(params.limits.pids_limit.is_some()),
+    );
+    for tmpfs in &config.limits.tmpfs {
+        let given = params.limits.tmpfs.iter().any(|entry| {
+            crate::cli::Commands::parse_tmpfs(entry).is_ok_and(|t| t.target == tmpfs.target)
+        });
+        record(
+            "limits.tmpfs",
+            format!("{} ({} MB)", tmpfs.target.display(), tmpfs.size_mb),
+            cli_or_default(given),
+        );
+    }
+    record(
+        "force_layers",
+        config.force_layers.join(","),
+        layers
+            .source_of("layers")
+            .or_else(|| layers.source_of("cloud"))
+            .unwrap_or(Source::Default),
+    );
+    record(
+        "upgrade",
+        config.upgrade.to_string(),
+        if config.upgrade {
+            Source::Cli
+        } else {
+            Source::Default
+        },
+    );
+    record(
+        "isolated",
+        config.isolated.to_string(),
+        source_or_default("isolated"),
+    );
+    record(
+        "no_nix",
+        config.no_nix.to_string(),
+        source_or_default("no-nix"),
+    );
+    record(
+        "block_host",
+        config.block_host.to_string(),
+        source_or_default("no-block-host"),
+    );
+    record(
+        "podman_socket",
+        config.podman_socket.to_string(),
+        source_or_default("podman"),
+    );
+    record(
+        "hardened",
+        config.hardened.to_string(),
+        source_or_default("hardened"),
+    );
+    record(
+        "seccomp",
+        config.seccomp.to_string(),
+        source_or_default("seccomp"),
+    );
+    record("cow", config.cow.to_string(), source_or_default("cow"));
+    for dir in &config.pre_create_dirs {
+        record(
+            "pre_create_dirs",
+            dir.display().to_string(),
+            Source::AutoDetect,
+        );
+    }
+
+    Ok(EffectiveConfig { config, provenance })
+}
+
+/// Print the effective configuration as a table or JSON
+pub fn print_effective_config(effective: &EffectiveConfig, json: bool) -> Result<()> {
+    if json {
+        println!("{}", serde_json::to_string_pretty(effective)?);
+    } else {
+        print_table(&effective.provenance);
+    }
+    Ok(())
+}
+
+fn print_table(rows: &[FieldOrigin]) {
+    let field_width = rows
+        .iter()
+        .map(|r| r.field.len())
+        .max()
+        .unwrap_or(0)
+        .max("FIELD".len());
+    let value_width = rows
+        .iter()
+        .map(|r| r.value.len())
+        .max()
+        .unwrap_or(0)
+        .max("VALUE".len());
+
+    println!(
+        "{:<field_width$}  {:<value_width$}  SOURCE",
+        "FIELD", "VALUE"
+    );
+    for row in rows {
+        println!(
+            "{:<field_width$}  {:<value_width$}  {}",
+            row.field, row.value, row.source
+        );
+    }
+}
+
+fn option_to_string<T: std::fmt::Display>(value: Option<T>) -> String {
+    value.map_or_else(|| "-".to_string(), |v| v.to_string())
+}
+
+fn format_mount(mount: &BindMount) -> String {
+    format!(
+        "{}:{}{}",
+        mount.source.display(),
+        mount.target.display(),
+        if mount.readonly { ":ro" } else { "" }
+    )
+}
+
+fn contains_mount(mounts: &[BindMount], mount: &BindMount) -> bool {
+    mounts
+        .iter()
+        .any(|m| m.source == mount.source && m.target == mount.target)
+}
+
+fn mount_entry_matches(entry: &str, mount: &BindMount) -> bool {
+    let parts: Vec<&str> = entry.split(':').collect();
+    parts.len() >= 2
+        && std::path::Path::new(parts[0]) == mount.source
+        && std::path::Path::new(parts[1]) == mount.target
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn mount(source: &str, target: &str) -> BindMount {
+        BindMount {
+            source: PathBuf::from(source),
+            target: PathBuf::from(target),
+            readonly: false,
+            workspace: false,
+        }
+    }
+
+    #[test]
+    fn test_mount_entry_matches() {
+        let m = mount("/srv/data", "/data");
+        assert!(mount_entry_matches("/srv/data:/data", &m));
+        assert!(mount_entry_matches("/srv/data:/data:ro", &m));
+        assert!(!mount_entry_matches("/srv/data:/other", &m));
+        assert!(!mount_entry_matches("/srv/data", &m));
+    }
+
+    #[test]
+    fn test_contains_mount() {
+        let mounts = vec![mount("/a", "/b")];
+        assert!(contains_mount(&mounts, &mount("/a", "/b")));
+        assert!(!contains_mount(&mounts, &mount("/a", "/c")));
+    }
+
+    #[test]
+    fn test_format_mount() {
+        let mut m = mount("/a", "/b");
+        assert_eq!(format_mount(&m), "/a:/b");
+        m.readonly = true;
+        assert_eq!(format_mount(&m), "/a:/b:ro");
+    }
+
+    #[test]
+    fn test_field_origin_serializes_source() {
+        let origin = FieldOrigin {
+            field: "limits.memory_mb".to_string(),
+            value: "2048".to_string(),
+            source: Source::Profile("strict".to_string()),
+        };
+        let json = serde_json::to_value(&origin).unwrap();
+        assert_eq!(json["source"], "profile:strict");
+    }
+}
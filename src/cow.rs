@@ -0,0 +1,515 @@
+//! Copy-on-write workspaces: the host tree is the lower layer of an overlay whose
+//! upper layer keeps what the agent changed until it is applied or discarded
+//!
+//! Upper and work directories live under `$XDG_DATA_HOME/jail-ai/cow/<jail>`.
+
+use crate::backend::JailBackend;
+use crate::config::JailConfig;
+use crate::error::{JailError, Result};
+use std::fmt;
+use std::os::unix::fs::{FileTypeExt, MetadataExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use tokio::process::Command;
+use tracing::{debug, info};
+
+/// Whiteout file of fuse-overlayfs when it cannot create device nodes
+const WHITEOUT_PREFIX: &str = ".wh.";
+/// Marker of an opaque directory hiding the lower one, written by fuse-overlayfs
+const OPAQUE_MARKER: &str = ".wh..wh..opq";
+/// Extended attributes marking an opaque directory, set by kernel overlayfs (`user.` when rootless)
+const OPAQUE_XATTRS: [&str; 2] = ["trusted.overlay.opaque", "user.overlay.opaque"];
+
+/// Overlay directories of a copy-on-write jail
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CowDirs {
+    /// Changes made in the jail
+    pub upper: PathBuf,
+    /// Scratch directory of the overlay, on the same filesystem as `upper`
+    pub work: PathBuf,
+}
+
+impl CowDirs {
+    pub fn for_jail(jail: &str) -> Result<Self> {
+        let dir = crate::jail_setup::get_jail_ai_data_dir()?
+            .join("cow")
+            .join(jail);
+        Ok(Self {
+            upper: dir.join("upper"),
+            work: dir.join("work"),
+        })
+    }
+
+    pub fn create(&self) -> Result<()> {
+        std::fs::create_dir_all(&self.upper)?;
+        std::fs::create_dir_all(&self.work)?;
+        Ok(())
+    }
+
+    /// Drop every change, leaving empty overlay directories
+    pub fn clear(&self) -> Result<()> {
+        for dir in [&self.upper, &self.work] {
+            remove_path(dir)?;
+        }
+        self.create()
+    }
+
+    /// Remove the overlay directories of a jail
+    pub fn remove(&self) -> Result<()> {
+        if let Some(dir) = self.upper.parent() {
+            remove_path(dir)?;
+        }
+        Ok(())
+    }
+}
+
+/// Change of the workspace, relative to its root
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Change {
+    Added(PathBuf),
+    Modified(PathBuf),
+    Deleted(PathBuf),
+}
+
+impl Change {
+    pub fn path(&self) -> &Path {
+        match self {
+            Self::Added(path) | Self::Modified(path) | Self::Deleted(path) => path,
+        }
+    }
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let marker = match self {
+            Self::Added(_) => 'A',
+            Self::Modified(_) => 'M',
+            Self::Deleted(_) => 'D',
+        };
+        write!(f, "{} {}", marker, self.path().display())
+    }
+}
+
+/// Host workspace of a copy-on-write jail, the lower layer of its overlay
+pub fn cow_workspace(config: &JailConfig) -> Result<PathBuf> {
+    if !config.cow {
+        return Err(JailError::Config(format!(
+            "Jail '{}' does not use a copy-on-write workspace (--cow)",
+            config.name
+        )));
+    }
+    crate::backend::workspace_mount(config)
+        .map(|mount| mount.source.clone())
+        .ok_or_else(|| JailError::Config(format!("Jail '{}' has no workspace mount", config.name)))
+}
+
+/// Refuse a copy-on-write jail whose workspace would be writable around its overlay
+///
+/// Read-write mounts of the workspace, of a directory containing it or of the repository
+/// of a worktree would write straight to the host.
+pub fn check_mounts(config: &JailConfig) -> Result<()> {
+    let canonical = |path: &Path| path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    let workspace = canonical(&cow_workspace(config)?);
+    let mut protected = vec![workspace.clone()];
+    if let Ok(Some(worktree)) = crate::worktree::detect_worktree(&workspace) {
+        protected.push(worktree.main_git_dir);
+    }
+
+    let overlaid = crate::backend::workspace_mount(config);
+    for mount in &config.bind_mounts {
+        if mount.readonly || overlaid.is_some_and(|w| std::ptr::eq(w, mount)) {
+            continue;
+        }
+        let source = canonical(&mount.source);
+        if protected
+            .iter()
+            .any(|path| source.starts_with(path) || path.starts_with(&source))
+        {
+            return Err(JailError::Config(format!(
+                "Copy-on-write workspace of jail '{}': {} is also mounted read-write at {}, mount it read-only or drop --cow",
+                config.name,
+                mount.source.display(),
+                mount.target.display()
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Overlay whiteout: a character device with device number 0/0
+fn is_whiteout(metadata: &std::fs::Metadata) -> bool {
+    metadata.file_type().is_char_device() && metadata.rdev() == 0
+}
+
+/// Upper directory marked opaque through an extended attribute
+fn has_opaque_xattr(dir: &Path) -> bool {
+    OPAQUE_XATTRS.iter().any(|name| {
+        let mut value = [0u8; 1];
+        matches!(rustix::fs::lgetxattr(dir, *name, &mut value), Ok(1) if value[0] == b'y')
+    })
+}
+
+/// Changes recorded in the upper directory, parents before their content
+pub fn list_changes(upper: &Path, lower: &Path) -> Result<Vec<Change>> {
+    let mut changes = Vec::new();
+    if upper.is_dir() {
+        scan_upper(upper, lower, Path::new(""), &mut changes)?;
+    }
+    Ok(changes)
+}
+
+fn scan_upper(
+    upper: &Path,
+    lower: &Path,
+    relative: &Path,
+    changes: &mut Vec<Change>,
+) -> Result<()> {
+    let mut entries =
+        std::fs::read_dir(upper.join(relative))?.collect::<std::io::Result<Vec<_>>>()?;
+    entries.sort_by_key(|entry| entry.file_name());
+
+    let opaque = entries
+        .iter()
+        .any(|entry| entry.file_name() == OPAQUE_MARKER)
+        || has_opaque_xattr(&upper.join(relative));
+    if opaque {
+        // Everything of the lower directory not copied up was deleted
+        let mut hidden: Vec<_> = std::fs::read_dir(lower.join(relative))
+            .into_iter()
+            .flatten()
+            .flatten()
+            .map(|entry| entry.file_name())
+            .filter(|name| !entries.iter().any(|entry| entry.file_name() == *name))
+            .collect();
+        hidden.sort();
+        changes.extend(
+            hidden
+                .into_iter()
+                .map(|name| Change::Deleted(relative.join(name))),
+        );
+    }
+
+    for entry in entries {
+        let name = entry.file_name();
+        if name == OPAQUE_MARKER {
+            continue;
+        }
+        if let Some(deleted) = name.to_str().and_then(|n| n.strip_prefix(WHITEOUT_PREFIX)) {
+            changes.push(Change::Deleted(relative.join(deleted)));
+            continue;
+        }
+
+        let path = relative.join(&name);
+        let metadata = entry.metadata()?;
+        if is_whiteout(&metadata) {
+            changes.push(Change::Deleted(path));
+            continue;
+        }
+
+        let original = lower.join(&path).symlink_metadata().ok();
+        if metadata.is_dir() {
+            // Directories copied up only to hold changed files are not changes themselves
+            if !original.is_some_and(|original| original.is_dir()) {
+                changes.push(Change::Added(path.clone()));
+            }
+            scan_upper(upper, lower, &path, changes)?;
+        } else if original.is_some() {
+            changes.push(Change::Modified(path));
+        } else {
+            changes.push(Change::Added(path));
+        }
+    }
+    Ok(())
+}
+
+/// Remove a file, symlink or directory tree, including directories without write permission
+fn remove_path(path: &Path) -> Result<()> {
+    let Ok(metadata) = path.symlink_metadata() else {
+        return Ok(());
+    };
+    if !metadata.is_dir() {
+        std::fs::remove_file(path)?;
+        return Ok(());
+    }
+
+    // Overlay work directories are created without any permission
+    if metadata.permissions().mode() & 0o700 != 0o700 {
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o700))?;
+    }
+    for entry in std::fs::read_dir(path)? {
+        remove_path(&entry?.path())?;
+    }
+    std::fs::remove_dir(path)?;
+    Ok(())
+}
+
+/// Copy the changes of the upper directory onto the workspace, deletions included
+pub fn apply_changes(upper: &Path, lower: &Path, changes: &[Change]) -> Result<()> {
+    for change in changes {
+        let target = lower.join(change.path());
+        let source = upper.join(change.path());
+        if let Change::Deleted(_) = change {
+            remove_path(&target)?;
+            continue;
+        }
+
+        let metadata = source.symlink_metadata()?;
+        // Never write through a symlink, nor over an entry of another type
+        let replaced = target
+            .symlink_metadata()
+            .is_ok_and(|existing| existing.is_symlink() || existing.is_dir() != metadata.is_dir());
+        if replaced {
+            remove_path(&target)?;
+        }
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        if metadata.is_dir() {
+            std::fs::create_dir_all(&target)?;
+            std::fs::set_permissions(&target, metadata.permissions())?;
+        } else if metadata.is_symlink() {
+            std::os::unix::fs::symlink(std::fs::read_link(&source)?, &target)?;
+        } else {
+            std::fs::copy(&source, &target)?;
+        }
+        debug!("Applied {}", change);
+    }
+    Ok(())
+}
+
+/// Stop the jail so that its overlay is not mounted while the layers change
+async fn stop_if_running(backend: &dyn JailBackend, jail: &str) -> Result<()> {
+    if backend.is_running(jail).await? {
+        info!("Stopping jail {} to update its workspace layers", jail);
+        backend.stop(jail).await?;
+    }
+    Ok(())
+}
+
+/// Apply the changes of a jail to its workspace and start over from the result
+pub async fn apply(
+    backend: &dyn JailBackend,
+    jail: &str,
+    dirs: &CowDirs,
+    workspace: &Path,
+    changes: &[Change],
+) -> Result<()> {
+    stop_if_running(backend, jail).await?;
+    apply_changes(&dirs.upper, workspace, changes)?;
+    dirs.clear()
+}
+
+/// Drop the changes of a jail
+pub async fn discard(backend: &dyn JailBackend, jail: &str, dirs: &CowDirs) -> Result<()> {
+    stop_if_running(backend, jail).await?;
+    dirs.clear()
+}
+
+/// Print a unified diff of each changed file with `diff -u`
+pub async fn print_diff(upper: &Path, lower: &Path, changes: &[Change]) -> Result<()> {
+    let null = Path::new("/dev/null");
+    for change in changes {
+        let path = change.path();
+        let (old, new) = match change {
+            Change::Added(_) => (null.to_path_buf(), upper.join(path)),
+            Change::Modified(_) => (lower.join(path), upper.join(path)),
+            Change::Deleted(_) => (lower.join(path), null.to_path_buf()),
+        };
+        // Directories and special files have no content to compare
+        if [&old, &new]
+            .iter()
+            .any(|file| *file != null && !file.symlink_metadata().is_ok_and(|m| m.is_file()))
+        {
+            println!("{change}");
+            continue;
+        }
+
+        let status = Command::new("diff")
+            .arg("-u")
+            .arg("--label")
+            .arg(Path::new("a").join(path))
+            .arg("--label")
+            .arg(Path::new("b").join(path))
+            .arg(&old)
+            .arg(&new)
+            .status()
+            .await
+            .map_err(|e| JailError::ExecutionFailed(format!("Failed to run diff: {e}")))?;
+        // diff exits with 1 when the files differ
+        if status.code().is_none_or(|code| code > 1) {
+            return Err(JailError::ExecutionFailed(format!(
+                "diff failed on {}",
+                path.display()
+            )));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::fake::{FakeBackend, FakeCall};
+    use crate::jail::JailBuilder;
+
+    fn write(root: &Path, path: &str, content: &str) {
+        let path = root.join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, content).unwrap();
+    }
+
+    fn layers() -> (tempfile::TempDir, tempfile::TempDir) {
+        let lower = tempfile::tempdir().unwrap();
+        write(lower.path(), "README.md", "readme");
+        write(lower.path(), "src/main.rs", "fn main() {}");
+        write(lower.path(), "src/old.rs", "old");
+        write(lower.path(), "build/out.o", "obj");
+
+        let upper = tempfile::tempdir().unwrap();
+        write(upper.path(), "src/main.rs", "fn main() { run() }");
+        write(upper.path(), "src/new/mod.rs", "new");
+        write(upper.path(), "src/.wh.old.rs", "");
+        write(upper.path(), "build/.wh..wh..opq", "");
+        write(upper.path(), "build/fresh.o", "fresh");
+        (lower, upper)
+    }
+
+    #[test]
+    fn test_list_changes() {
+        let (lower, upper) = layers();
+        let changes = list_changes(upper.path(), lower.path()).unwrap();
+        assert_eq!(
+            changes,
+            vec![
+                Change::Deleted("build/out.o".into()),
+                Change::Added("build/fresh.o".into()),
+                Change::Deleted("src/old.rs".into()),
+                Change::Modified("src/main.rs".into()),
+                Change::Added("src/new".into()),
+                Change::Added("src/new/mod.rs".into()),
+            ]
+        );
+        assert_eq!(changes[0].to_string(), "D build/out.o");
+        assert!(list_changes(&upper.path().join("missing"), lower.path())
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_list_changes_of_opaque_xattr_directory() {
+        let (lower, upper) = layers();
+        std::fs::remove_file(upper.path().join("build/.wh..wh..opq")).unwrap();
+        let build = upper.path().join("build");
+        if let Err(e) = rustix::fs::lsetxattr(
+            &build,
+            "user.overlay.opaque",
+            b"y",
+            rustix::fs::XattrFlags::empty(),
+        ) {
+            eprintln!("Skipping, no user extended attributes here: {e}");
+            return;
+        }
+
+        let changes = list_changes(upper.path(), lower.path()).unwrap();
+        assert!(changes.contains(&Change::Deleted("build/out.o".into())));
+        assert!(changes.contains(&Change::Added("build/fresh.o".into())));
+    }
+
+    #[test]
+    fn test_apply_changes() {
+        let (lower, upper) = layers();
+        let changes = list_changes(upper.path(), lower.path()).unwrap();
+        apply_changes(upper.path(), lower.path(), &changes).unwrap();
+
+        let read = |path: &str| std::fs::read_to_string(lower.path().join(path)).ok();
+        assert_eq!(read("src/main.rs").as_deref(), Some("fn main() { run() }"));
+        assert_eq!(read("src/new/mod.rs").as_deref(), Some("new"));
+        assert_eq!(read("build/fresh.o").as_deref(), Some("fresh"));
+        assert_eq!(read("README.md").as_deref(), Some("readme"));
+        assert!(read("src/old.rs").is_none());
+        assert!(read("build/out.o").is_none());
+        assert!(!lower.path().join("src/.wh.old.rs").exists());
+    }
+
+    #[tokio::test]
+    async fn test_discard_stops_jail_and_clears_upper() {
+        let root = tempfile::tempdir().unwrap();
+        let dirs = CowDirs {
+            upper: root.path().join("upper"),
+            work: root.path().join("work"),
+        };
+        dirs.create().unwrap();
+        write(&dirs.upper, "src/main.rs", "changed");
+        let locked = dirs.work.join("work");
+        std::fs::create_dir(&locked).unwrap();
+        std::fs::set_permissions(&locked, std::fs::Permissions::from_mode(0o000)).unwrap();
+
+        let config = JailBuilder::new("jail__app__12345678__claude")
+            .cow(true)
+            .build()
+            .config()
+            .clone();
+        let fake = FakeBackend::new().with_jail(config, true);
+        discard(&fake, "jail__app__12345678__claude", &dirs)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            fake.calls(),
+            vec![FakeCall::Stop("jail__app__12345678__claude".to_string())]
+        );
+        assert_eq!(std::fs::read_dir(&dirs.upper).unwrap().count(), 0);
+        assert_eq!(std::fs::read_dir(&dirs.work).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_check_mounts() {
+        let root = tempfile::tempdir().unwrap();
+        let project = root.path().join("project");
+        std::fs::create_dir(&project).unwrap();
+        let jail = |builder: JailBuilder| builder.cow(true).build().config().clone();
+
+        // The marked mount is overlaid whatever its target
+        let config = jail(
+            JailBuilder::new("jail__project__12345678__claude")
+                .bind_mount("/data", "/workspace-data", false)
+                .workspace_mount(&project, "/code", false)
+                .bind_mount(&project, "/mirror", true),
+        );
+        assert_eq!(cow_workspace(&config).unwrap(), project);
+        check_mounts(&config).unwrap();
+
+        let no_workspace = jail(JailBuilder::new("jail__project__12345678__claude"));
+        assert!(check_mounts(&no_workspace).is_err());
+
+        let parent_writable = jail(
+            JailBuilder::new("jail__project__12345678__claude")
+                .workspace_mount(&project, "/code", false)
+                .bind_mount(root.path(), "/projects", false),
+        );
+        assert!(check_mounts(&parent_writable).is_err());
+    }
+
+    #[test]
+    fn test_check_mounts_refuses_writable_worktree_repository() {
+        let root = tempfile::tempdir().unwrap();
+        let git_dir = root.path().join("main/.git");
+        write(&git_dir, "config", "");
+        std::fs::create_dir_all(git_dir.join("worktrees/feature")).unwrap();
+        let worktree = root.path().join("feature");
+        write(
+            &worktree,
+            ".git",
+            &format!("gitdir: {}", git_dir.join("worktrees/feature").display()),
+        );
+
+        let config = JailBuilder::new("jail__feature__12345678__claude")
+            .workspace_mount(&worktree, "/workspace", false)
+            .bind_mount(&git_dir, &git_dir, false)
+            .cow(true)
+            .build()
+            .config()
+            .clone();
+        assert!(check_mounts(&config).is_err());
+    }
+}
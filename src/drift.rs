@@ -0,0 +1,496 @@
+//! Differences between an existing jail and the configuration requested for it
+
+use crate::config::{
+    is_secret_env, BindMount, JailConfig, NetworkConfig, PortMapping, ResourceLimits, TmpfsMount,
+    Ulimit, REDACTED,
+};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// Variables describing the user's terminal session rather than the jail
+const SESSION_ENV: &[&str] = &["TERM", "TZ"];
+
+/// How a setting differs between the existing jail and the requested configuration
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChangeKind {
+    /// Requested but missing from the jail
+    Added(String),
+    /// Present in the jail but no longer requested
+    Removed(String),
+    /// Present on both sides with different values
+    Changed { from: String, to: String },
+}
+
+/// One setting of an existing jail that differs from the requested configuration
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigChange {
+    pub setting: &'static str,
+    pub kind: ChangeKind,
+}
+
+impl ConfigChange {
+    fn new(setting: &'static str, kind: ChangeKind) -> Self {
+        Self { setting, kind }
+    }
+
+    fn changed(setting: &'static str, from: String, to: String) -> Self {
+        Self::new(setting, ChangeKind::Changed { from, to })
+    }
+
+    /// Whether the change weakens or restores the jail's isolation from the host
+    pub fn affects_isolation(&self) -> bool {
+        matches!(
+            self.setting,
+            "network" | "block-host" | "hardened" | "seccomp" | "cow"
+        )
+    }
+}
+
+impl fmt::Display for ConfigChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            ChangeKind::Added(value) => write!(f, "+ {} {}", self.setting, value),
+            ChangeKind::Removed(value) => write!(f, "- {} {}", self.setting, value),
+            ChangeKind::Changed { from, to } => write!(f, "~ {}: {} → {}", self.setting, from, to),
+        }
+    }
+}
+
+/// Compare an existing jail's configuration with the requested one
+///
+/// Environment variables only present in the jail are ignored: images and
+/// engines add their own, so only requested variables are checked.
+pub fn config_drift(current: &JailConfig, desired: &JailConfig) -> Vec<ConfigChange> {
+    let mut changes = Vec::new();
+
+    if network_mode(&current.network) != network_mode(&desired.network) {
+        changes.push(ConfigChange::changed(
+            "network",
+            network_mode(&current.network).to_string(),
+            network_mode(&desired.network).to_string(),
+        ));
+    }
+    if current.block_host != desired.block_host {
+        changes.push(ConfigChange::changed(
+            "block-host",
+            current.block_host.to_string(),
+            desired.block_host.to_string(),
+        ));
+    }
+
+    for (setting, current, desired) in [
+        ("hardened", current.hardened, desired.hardened),
+        ("seccomp", current.seccomp, desired.seccomp),
+        ("cow", current.cow, desired.cow),
+        ("shared-home", current.shared_home, desired.shared_home),
+    ] {
+        if current != desired {
+            changes.push(ConfigChange::changed(
+                setting,
+                current.to_string(),
+                desired.to_string(),
+            ));
+        }
+    }
+
+    changes.extend(mount_drift(&current.bind_mounts, &desired.bind_mounts));
+    changes.extend(port_drift(&current.port_mappings, &desired.port_mappings));
+    changes.extend(env_drift(&current.environment, &desired.environment));
+
+    if current.limits.memory_mb != desired.limits.memory_mb {
+        changes.push(ConfigChange::changed(
+            "memory",
+            limit(current.limits.memory_mb, "MB"),
+            limit(desired.limits.memory_mb, "MB"),
+        ));
+    }
+    if current.limits.cpu_quota != desired.limits.cpu_quota {
+        changes.push(ConfigChange::changed(
+            "cpu",
+            limit(current.limits.cpu_quota, "%"),
+            limit(desired.limits.cpu_quota, "%"),
+        ));
+    }
+    changes.extend(extended_limit_drift(&current.limits, &desired.limits));
+    if current.podman_socket != desired.podman_socket {
+        changes.push(ConfigChange::changed(
+            "podman-socket",
+            current.podman_socket.to_string(),
+            desired.podman_socket.to_string(),
+        ));
+    }
+
+    // The default image is resolved to a layered image; upgrades of those are checked separately
+    if desired.base_image != crate::image::DEFAULT_IMAGE_NAME
+        && current.base_image != desired.base_image
+    {
+        changes.push(ConfigChange::changed(
+            "image",
+            current.base_image.clone(),
+            desired.base_image.clone(),
+        ));
+    }
+
+    changes
+}
+
+fn network_mode(network: &NetworkConfig) -> &'static str {
+    if !network.enabled {
+        "none"
+    } else if network.host {
+        "host"
+    } else if network.private {
+        "private"
+    } else {
+        "shared"
+    }
+}
+
+fn limit<T: fmt::Display>(value: Option<T>, unit: &str) -> String {
+    value.map_or_else(|| "none".to_string(), |value| format!("{value}{unit}"))
+}
+
+/// Resolve symlinks so equivalent host paths compare equal
+fn canonical(path: &Path) -> PathBuf {
+    std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+fn mount_spec(mount: &BindMount) -> String {
+    let spec = format!("{}:{}", mount.source.display(), mount.target.display());
+    if mount.readonly {
+        format!("{spec}:ro")
+    } else {
+        spec
+    }
+}
+
+/// Mounts keyed by their target inside the jail
+fn mounts_by_target(mounts: &[BindMount]) -> BTreeMap<PathBuf, String> {
+    mounts
+        .iter()
+        .map(|mount| {
+            let canonical_mount = BindMount {
+                source: canonical(&mount.source),
+                ..mount.clone()
+            };
+            (mount.target.clone(), mount_spec(&canonical_mount))
+        })
+        .collect()
+}
+
+fn mount_drift(current: &[BindMount], desired: &[BindMount]) -> Vec<ConfigChange> {
+    let current = mounts_by_target(current);
+    let desired = mounts_by_target(desired);
+    let mut changes = Vec::new();
+
+    for (target, spec) in &desired {
+        match current.get(target) {
+            None => changes.push(ConfigChange::new("mount", ChangeKind::Added(spec.clone()))),
+            Some(existing) if existing != spec => changes.push(ConfigChange::changed(
+                "mount",
+                existing.clone(),
+                spec.clone(),
+            )),
+            Some(_) => {}
+        }
+    }
+    for (target, spec) in &current {
+        if !desired.contains_key(target) {
+            changes.push(ConfigChange::new(
+                "mount",
+                ChangeKind::Removed(spec.clone()),
+            ));
+        }
+    }
+
+    changes
+}
+
+fn extended_limit_drift(current: &ResourceLimits, desired: &ResourceLimits) -> Vec<ConfigChange> {
+    let mut changes = Vec::new();
+    let mut compare = |setting, from: String, to: String| {
+        if from != to {
+            changes.push(ConfigChange::changed(setting, from, to));
+        }
+    };
+    compare(
+        "pids",
+        limit(current.pids_limit, ""),
+        limit(desired.pids_limit, ""),
+    );
+    compare(
+        "memory-swap",
+        limit(current.memory_swap_mb, "MB"),
+        limit(desired.memory_swap_mb, "MB"),
+    );
+    compare(
+        "shm",
+        limit(current.shm_size_mb, "MB"),
+        limit(desired.shm_size_mb, "MB"),
+    );
+    compare(
+        "blkio-weight",
+        limit(current.blkio_weight, ""),
+        limit(desired.blkio_weight, ""),
+    );
+
+    let ulimit = |ulimit: &Ulimit| {
+        (
+            ulimit.name.clone(),
+            format!("{}:{}", ulimit.soft, ulimit.hard),
+        )
+    };
+    changes.extend(keyed_drift(
+        "ulimit",
+        current.ulimits.iter().map(ulimit).collect(),
+        desired.ulimits.iter().map(ulimit).collect(),
+    ));
+    let tmpfs = |tmpfs: &TmpfsMount| {
+        (
+            tmpfs.target.display().to_string(),
+            format!("{}MB", tmpfs.size_mb),
+        )
+    };
+    changes.extend(keyed_drift(
+        "tmpfs",
+        current.tmpfs.iter().map(tmpfs).collect(),
+        desired.tmpfs.iter().map(tmpfs).collect(),
+    ));
+    changes
+}
+
+/// Drift of settings identified by a key (ulimit name, tmpfs target)
+fn keyed_drift(
+    setting: &'static str,
+    current: BTreeMap<String, String>,
+    desired: BTreeMap<String, String>,
+) -> Vec<ConfigChange> {
+    let mut changes = Vec::new();
+    for (key, value) in &desired {
+        match current.get(key) {
+            None => changes.push(ConfigChange::new(
+                setting,
+                ChangeKind::Added(format!("{key}={value}")),
+            )),
+            Some(existing) if existing != value => changes.push(ConfigChange::changed(
+                setting,
+                format!("{key}={existing}"),
+                format!("{key}={value}"),
+            )),
+            Some(_) => {}
+        }
+    }
+    for (key, value) in &current {
+        if !desired.contains_key(key) {
+            changes.push(ConfigChange::new(
+                setting,
+                ChangeKind::Removed(format!("{key}={value}")),
+            ));
+        }
+    }
+    changes
+}
+
+/// Whether a jail port matches a requested one, a requested host port of 0 matching any
+fn port_matches(current: &PortMapping, desired: &PortMapping) -> bool {
+    let free = PortMapping {
+        host_port: 0,
+        ..current.clone()
+    };
+    current.to_string() == desired.to_string()
+        || (desired.host_port == 0 && free.to_string() == desired.to_string())
+}
+
+fn port_drift(current: &[PortMapping], desired: &[PortMapping]) -> Vec<ConfigChange> {
+    let added = desired
+        .iter()
+        .filter(|port| !current.iter().any(|c| port_matches(c, port)))
+        .map(|port| ConfigChange::new("port", ChangeKind::Added(port.to_string())));
+    let removed = current
+        .iter()
+        .filter(|port| !desired.iter().any(|d| port_matches(port, d)))
+        .map(|port| ConfigChange::new("port", ChangeKind::Removed(port.to_string())));
+    added.chain(removed).collect()
+}
+
+fn env_value(key: &str, value: &str) -> String {
+    if is_secret_env(key) {
+        REDACTED.to_string()
+    } else {
+        value.to_string()
+    }
+}
+
+fn env_drift(current: &[(String, String)], desired: &[(String, String)]) -> Vec<ConfigChange> {
+    let current: BTreeMap<&str, &str> = current
+        .iter()
+        .map(|(key, value)| (key.as_str(), value.as_str()))
+        .collect();
+
+    desired
+        .iter()
+        .filter(|(key, _)| !SESSION_ENV.contains(&key.as_str()))
+        .filter_map(|(key, value)| match current.get(key.as_str()) {
+            None => Some(ConfigChange::new(
+                "env",
+                ChangeKind::Added(format!("{key}={}", env_value(key, value))),
+            )),
+            Some(existing) if existing != value => Some(ConfigChange::changed(
+                "env",
+                format!("{key}={}", env_value(key, existing)),
+                format!("{key}={}", env_value(key, value)),
+            )),
+            Some(_) => None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::Commands;
+    use crate::jail::JailBuilder;
+
+    fn config(builder: JailBuilder) -> JailConfig {
+        builder.build().config().clone()
+    }
+
+    fn base() -> JailBuilder {
+        JailBuilder::new("test-jail")
+            .bind_mount("/tmp", "/workspace", false)
+            .env("EDITOR", "vim")
+            .env("TERM", "xterm")
+    }
+
+    #[test]
+    fn test_identical_configs_have_no_drift() {
+        assert!(config_drift(&config(base()), &config(base())).is_empty());
+    }
+
+    #[test]
+    fn test_added_settings() {
+        let current = config(base());
+        let desired = config(
+            base()
+                .bind_mount("/tmp", "/data", true)
+                .port_mapping(Commands::parse_port("8080:80").unwrap())
+                .memory_limit(4096)
+                .podman_socket(true),
+        );
+
+        let changes = config_drift(&current, &desired);
+        let rendered: Vec<String> = changes.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            rendered,
+            vec![
+                "+ mount /tmp:/data:ro",
+                "+ port 127.0.0.1:8080:80/tcp",
+                "~ memory: none → 4096MB",
+                "~ podman-socket: false → true",
+            ]
+        );
+        assert!(!changes.iter().any(ConfigChange::affects_isolation));
+    }
+
+    #[test]
+    fn test_removed_and_changed_mounts_and_ports() {
+        let current = config(base().port_mapping(Commands::parse_port("3000:3000").unwrap()));
+        let desired = config(JailBuilder::new("test-jail").bind_mount("/tmp", "/workspace", true));
+
+        let changes = config_drift(&current, &desired);
+        assert!(changes.contains(&ConfigChange::changed(
+            "mount",
+            "/tmp:/workspace".to_string(),
+            "/tmp:/workspace:ro".to_string(),
+        )));
+        assert!(changes.contains(&ConfigChange::new(
+            "port",
+            ChangeKind::Removed("127.0.0.1:3000:3000/tcp".to_string()),
+        )));
+    }
+
+    #[test]
+    fn test_free_host_port_matches_allocated_port() {
+        let current = config(base().port_mapping(Commands::parse_port("41234:3000").unwrap()));
+        let desired = config(base().port_mapping(Commands::parse_port("0:3000").unwrap()));
+        assert!(config_drift(&current, &desired).is_empty());
+
+        let desired = config(base().port_mapping(Commands::parse_port("0.0.0.0:0:3000").unwrap()));
+        assert_eq!(config_drift(&current, &desired).len(), 2);
+    }
+
+    #[test]
+    fn test_env_drift_ignores_session_and_jail_only_variables() {
+        let current = config(base().env("LANG", "C.UTF-8"));
+        let desired = config(
+            JailBuilder::new("test-jail")
+                .bind_mount("/tmp", "/workspace", false)
+                .env("EDITOR", "nvim")
+                .env("TERM", "tmux-256color")
+                .env("API_TOKEN", "s3cr3t"),
+        );
+
+        let rendered: Vec<String> = config_drift(&current, &desired)
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(
+            rendered,
+            vec![
+                "~ env: EDITOR=vim → EDITOR=nvim",
+                "+ env API_TOKEN=<redacted>",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_extended_limit_drift() {
+        let current = config(base().pids_limit(2048).tmpfs("/tmp", 2048));
+        let desired = config(base().pids_limit(4096).tmpfs("/tmp", 512).ulimit(Ulimit {
+            name: "nofile".to_string(),
+            soft: 1024,
+            hard: 4096,
+        }));
+
+        let rendered: Vec<String> = config_drift(&current, &desired)
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(
+            rendered,
+            vec![
+                "~ pids: 2048 → 4096",
+                "+ ulimit nofile=1024:4096",
+                "~ tmpfs: /tmp=2048MB → /tmp=512MB",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_isolation_changes() {
+        let current = config(base());
+        let desired = config(
+            base()
+                .host_network(true)
+                .block_host(false)
+                .hardened(true)
+                .seccomp(true),
+        );
+
+        let changes = config_drift(&current, &desired);
+        assert_eq!(changes.len(), 4);
+        assert!(changes.iter().all(ConfigChange::affects_isolation));
+        assert_eq!(changes[0].to_string(), "~ network: private → host");
+    }
+
+    #[test]
+    fn test_default_image_is_not_compared() {
+        let current = config(base().base_image("localhost/jail-ai-agent-claude:latest"));
+        let desired = config(base().base_image(crate::image::DEFAULT_IMAGE_NAME));
+        assert!(config_drift(&current, &desired).is_empty());
+
+        let desired = config(base().base_image("alpine:latest"));
+        assert_eq!(config_drift(&current, &desired).len(), 1);
+    }
+}
@@ -113,6 +113,15 @@ pub async fn load_ebpf_via_helper(
     .map_err(|e| JailError::Backend(format!("Failed to join stdout reader task: {}", e)))?
     .map_err(|e| JailError::Backend(format!("Failed to read loader output: {}", e)))?;
 
+    // The loader checks its capabilities first and exits without answering when they are missing
+    if response_str.trim().is_empty() {
+        let _ = child.wait();
+        return Err(JailError::LoaderCapabilities(format!(
+            "{} exited without a response (CAP_BPF and CAP_NET_ADMIN are required)",
+            loader_path.display()
+        )));
+    }
+
     // Parse response
     let response: LoadResponse = serde_json::from_str(response_str.trim()).map_err(|e| {
         JailError::Backend(format!(
@@ -130,6 +139,10 @@ pub async fn load_ebpf_via_helper(
     } else {
         // On failure, wait for child to exit
         let _ = child.wait();
+        let message = response.message.to_lowercase();
+        if message.contains("operation not permitted") || message.contains("permission denied") {
+            return Err(JailError::LoaderCapabilities(response.message));
+        }
         Err(JailError::Backend(format!(
             "eBPF loader failed: {}",
             response.message
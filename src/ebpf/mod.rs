@@ -81,14 +81,7 @@ impl EbpfHostBlocker {
             blocked_ips.len()
         );
 
-        // Extract container name from cgroup path
-        // Format: /sys/fs/cgroup/.../libpod-CONTAINER_NAME.scope/...
-        let container_name = cgroup_path
-            .split('/')
-            .find(|s| s.starts_with("libpod-") && s.ends_with(".scope"))
-            .and_then(|s| s.strip_prefix("libpod-"))
-            .and_then(|s| s.strip_suffix(".scope"))
-            .unwrap_or("unknown");
+        let container_name = container_id_from_cgroup(cgroup_path).unwrap_or("unknown");
 
         debug!(
             "Extracted container name: {} from cgroup path: {}",
@@ -150,10 +143,58 @@ impl Drop for EbpfHostBlocker {
     }
 }
 
+/// Extract the container ID from a container cgroup path
+///
+/// Supported layouts:
+/// - Podman: `/sys/fs/cgroup/.../libpod-CONTAINER_ID.scope/...`
+/// - Docker (systemd driver): `/sys/fs/cgroup/.../docker-CONTAINER_ID.scope`
+/// - Docker (cgroupfs driver): `/sys/fs/cgroup/.../docker/CONTAINER_ID`
+fn container_id_from_cgroup(cgroup_path: &str) -> Option<&str> {
+    let segments: Vec<&str> = cgroup_path.split('/').collect();
+
+    segments
+        .iter()
+        .find_map(|s| {
+            s.strip_suffix(".scope").and_then(|scope| {
+                scope
+                    .strip_prefix("libpod-")
+                    .or_else(|| scope.strip_prefix("docker-"))
+            })
+        })
+        .or_else(|| {
+            segments
+                .windows(2)
+                .find(|w| w[0] == "docker" && !w[1].is_empty())
+                .map(|w| w[1])
+        })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_container_id_from_cgroup() {
+        assert_eq!(
+            container_id_from_cgroup(
+                "/sys/fs/cgroup/user.slice/user-1000.slice/user@1000.service/user.slice/libpod-abc123.scope/container"
+            ),
+            Some("abc123")
+        );
+        assert_eq!(
+            container_id_from_cgroup("/sys/fs/cgroup/system.slice/docker-def456.scope"),
+            Some("def456")
+        );
+        assert_eq!(
+            container_id_from_cgroup("/sys/fs/cgroup/docker/0123abcd"),
+            Some("0123abcd")
+        );
+        assert_eq!(
+            container_id_from_cgroup("/sys/fs/cgroup/user.slice/session-1.scope"),
+            None
+        );
+    }
+
     #[tokio::test]
     async fn test_ebpf_blocker_creation() {
         let blocker = EbpfHostBlocker::new();
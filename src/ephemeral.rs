@@ -0,0 +1,174 @@
+//! Throwaway jails removed with their volumes and images when the session ends
+//!
+//! Ephemeral jails are named `jail__{project}__{hash}__ephemeral-{session}__{agent}`, so
+//! neither they nor their volumes collide with the persistent jail of the project.
+
+use crate::error::{JailError, Result};
+use crate::jail::JailManager;
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use tracing::{debug, info, warn};
+
+const EPHEMERAL_SEGMENT: &str = "__ephemeral-";
+
+/// Name of a new ephemeral jail for an agent, unique to this session
+pub fn ephemeral_jail_name(base_name: &str, agent_command: &str) -> String {
+    let seed = format!(
+        "{}-{}",
+        std::process::id(),
+        chrono::Utc::now().timestamp_nanos_opt().unwrap_or_default()
+    );
+    let session = hex::encode(Sha256::digest(seed.as_bytes()));
+    crate::agent_commands::agent_jail_name(
+        &format!("{base_name}{EPHEMERAL_SEGMENT}{}", &session[..6]),
+        agent_command,
+    )
+}
+
+pub fn is_ephemeral(name: &str) -> bool {
+    name.contains(EPHEMERAL_SEGMENT)
+}
+
+/// Images the session created that no remaining jail uses
+///
+/// Shared `:latest` layers are kept, as they are reused by every jail.
+fn session_images(created: Vec<String>, in_use: &HashSet<String>) -> Vec<String> {
+    created
+        .into_iter()
+        .filter(|name| !in_use.contains(name) && !name.ends_with(":latest"))
+        .collect()
+}
+
+/// Resolve once the session is interrupted (Ctrl-C, terminal closed or SIGTERM)
+pub async fn interrupted() -> Result<()> {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut terminate = signal(SignalKind::terminate())?;
+    let mut hangup = signal(SignalKind::hangup())?;
+    tokio::select! {
+        result = tokio::signal::ctrl_c() => result?,
+        _ = terminate.recv() => {}
+        _ = hangup.recv() => {}
+    }
+    Ok(())
+}
+
+/// Run an agent session in an ephemeral jail, then remove everything it created
+///
+/// Cleanup also runs when the session fails or is interrupted.
+pub async fn run_session<F>(jail: &JailManager, session: F) -> Result<()>
+where
+    F: std::future::Future<Output = Result<()>>,
+{
+    let result = tokio::select! {
+        result = session => result,
+        interrupted = interrupted() => {
+            interrupted?;
+            warn!("Interrupted, removing ephemeral jail {}", jail.config().name);
+            Err(JailError::CommandExit {
+                code: 130,
+                stderr: String::new(),
+            })
+        }
+    };
+
+    remove_session(jail, crate::image_layers::take_created_images()).await;
+    result
+}
+
+/// Remove the jail, its home and Nix volumes and the images built for it, best effort
+async fn remove_session(jail: &JailManager, created_images: Vec<String>) {
+    let name = &jail.config().name;
+    let backend = jail.backend();
+    if backend.exists(name).await.unwrap_or(true) {
+        match jail.remove(true).await {
+            Ok(()) => info!("Removed ephemeral jail {}", name),
+            Err(e) => warn!("Failed to remove ephemeral jail {}: {}", name, e),
+        }
+    }
+
+    let in_use = match backend.list_summaries().await {
+        Ok(jails) => jails.into_iter().map(|jail| jail.image).collect(),
+        Err(e) => {
+            // Without knowing which images are in use, keep them all
+            debug!("Could not list jails: {}", e);
+            return;
+        }
+    };
+    for image in session_images(created_images, &in_use) {
+        match backend.remove_image(&image).await {
+            Ok(()) => info!("Removed image {}", image),
+            Err(e) => warn!("Failed to remove image {}: {}", image, e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::fake::{FakeBackend, FakeCall};
+    use crate::backend::JailBackend;
+    use crate::jail::JailBuilder;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_ephemeral_jail_name() {
+        let name = ephemeral_jail_name("jail__app__12345678", "claude");
+        assert!(name.starts_with("jail__app__12345678__ephemeral-"));
+        assert!(name.ends_with("__claude"));
+        assert!(is_ephemeral(&name));
+        assert!(!is_ephemeral("jail__app__12345678__claude"));
+        assert_eq!(
+            crate::backend::extract_base_name(&name),
+            name.strip_suffix("__claude").unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_run_session_removes_jail_and_session_images() {
+        const JAIL: &str = "jail__app__12345678__ephemeral-abcdef__claude";
+        let config = JailBuilder::new(JAIL).build().config().clone();
+        let fake = Arc::new(
+            FakeBackend::new()
+                .with_jail(config.clone(), true)
+                .with_image("localhost/jail-ai-agent-claude:base-claude", 0),
+        );
+        let jail = JailManager::with_backend(config, fake.clone());
+
+        let session = async {
+            // Images built while the session runs, and one by another process
+            for image in [
+                "localhost/jail-ai-agent-claude:0a1b2c3d",
+                "localhost/jail-ai-base:latest",
+            ] {
+                fake.commit(JAIL, image, &[]).await?;
+                crate::image_layers::record_created_image(image);
+            }
+            fake.commit(JAIL, "localhost/jail-ai-agent-codex:9f8e7d6c", &[])
+                .await?;
+            Err(JailError::CommandExit {
+                code: 1,
+                stderr: String::new(),
+            })
+        };
+        let result = run_session(&jail, session).await;
+        assert!(matches!(
+            result,
+            Err(JailError::CommandExit { code: 1, .. })
+        ));
+
+        let calls = fake.calls();
+        assert!(calls.contains(&FakeCall::Remove {
+            name: JAIL.to_string(),
+            remove_volume: true,
+        }));
+        let removed: Vec<_> = calls
+            .iter()
+            .filter_map(|call| match call {
+                FakeCall::RemoveImage(image) => Some(image.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(removed, vec!["localhost/jail-ai-agent-claude:0a1b2c3d"]);
+    }
+}
@@ -23,11 +23,221 @@ pub enum JailError {
     #[error("Command execution failed: {0}")]
     ExecutionFailed(String),
 
+    #[error("Command exited with status {code}{}", exit_detail(.stderr))]
+    CommandExit { code: i32, stderr: String },
+
     #[error("Unsafe workspace directory: {0}")]
     UnsafeWorkspace(String),
 
     #[error("Unsafe mount source: {0}")]
     UnsafeMount(String),
+
+    #[error("Image not found: {0}")]
+    ImageNotFound(String),
+
+    #[error("Out of memory: {0}")]
+    OutOfMemory(String),
+
+    #[error("Permission denied on container engine socket: {0}")]
+    SocketPermission(String),
+
+    #[error("Cgroup controller unavailable: {0}")]
+    CgroupMissing(String),
+
+    #[error("eBPF loader is missing capabilities: {0}")]
+    LoaderCapabilities(String),
+
+    #[error("Rootless container setup is incomplete: {0}")]
+    RootlessSetup(String),
+}
+
+impl JailError {
+    /// Classify a failed container engine command from its stderr
+    ///
+    /// Unrecognized failures become [`JailError::ExecutionFailed`].
+    pub fn command_failed(status: impl std::fmt::Display, stderr: &str) -> Self {
+        let message = stderr.trim().to_string();
+        let stderr = stderr.to_lowercase();
+        let has = |patterns: &[&str]| patterns.iter().any(|p| stderr.contains(p));
+
+        // Rootless errors come first: they also mention "operation not permitted"
+        if has(&[
+            "newuidmap",
+            "newgidmap",
+            "/etc/subuid",
+            "/etc/subgid",
+            "not enough ids",
+            "insufficient uids or gids",
+            "cannot find uid/gid",
+        ]) {
+            JailError::RootlessSetup(message)
+        } else if has(&["already in use"]) {
+            JailError::AlreadyExists(message)
+        } else if has(&[
+            "image not known",
+            "no such image",
+            "manifest unknown",
+            "unable to find image",
+            "pull access denied",
+            "repository does not exist",
+        ]) {
+            JailError::ImageNotFound(message)
+        } else if has(&["oomkilled", "oom-kill", "out of memory"]) {
+            JailError::OutOfMemory(message)
+        } else if has(&["permission denied"]) && has(&[".sock", "dial unix", "daemon socket"]) {
+            JailError::SocketPermission(message)
+        } else if has(&["cgroup", "controller"])
+            && has(&[
+                "not available",
+                "no such file",
+                "not supported",
+                "not mounted",
+            ])
+        {
+            JailError::CgroupMissing(message)
+        } else {
+            JailError::ExecutionFailed(format!("Command failed with status {status}: {message}"))
+        }
+    }
+
+    /// Remediation hint shown below the error
+    pub fn hint(&self) -> Option<&'static str> {
+        match self {
+            JailError::AlreadyExists(_) => {
+                Some("Remove it with `jail-ai remove <name>`, or pass --upgrade to recreate it")
+            }
+            JailError::NotFound(_) => Some("Run `jail-ai list` to see existing jails"),
+            JailError::ImageNotFound(_) => Some(
+                "Check the image name and your registry login, or omit --image to use the jail-ai images",
+            ),
+            JailError::OutOfMemory(_) => {
+                Some("Raise the memory limit with --memory <MB>, or remove it")
+            }
+            JailError::SocketPermission(_) => Some(
+                "Enable your user's podman socket with `systemctl --user enable --now podman.socket`, \
+                 or add yourself to the docker group",
+            ),
+            JailError::CgroupMissing(_) => Some(
+                "Resource limits need cgroups v2 with controllers delegated to your user; \
+                 drop --memory/--cpu or enable delegation in systemd",
+            ),
+            JailError::LoaderCapabilities(_) => Some(
+                "Run `sudo setcap cap_bpf,cap_net_admin+ep $(which jail-ai-ebpf-loader)`, \
+                 or pass --no-block-host",
+            ),
+            JailError::RootlessSetup(_) => Some(
+                "Add subordinate IDs with `sudo usermod --add-subuids 100000-165535 \
+                 --add-subgids 100000-165535 $USER`, then run `podman system migrate`",
+            ),
+            _ => None,
+        }
+    }
+
+    /// Process exit code to report for this error
+    ///
+    /// These codes are stable so scripts can react to specific failures.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            JailError::CommandExit { code, .. } => *code,
+            JailError::Config(_) => 2,
+            JailError::NotFound(_) => 3,
+            JailError::AlreadyExists(_) => 4,
+            JailError::ImageNotFound(_) => 5,
+            JailError::OutOfMemory(_) => 6,
+            JailError::SocketPermission(_) => 7,
+            JailError::CgroupMissing(_) => 8,
+            JailError::LoaderCapabilities(_) => 9,
+            JailError::RootlessSetup(_) => 10,
+            JailError::UnsafeWorkspace(_) | JailError::UnsafeMount(_) => 11,
+            JailError::Backend(_)
+            | JailError::Io(_)
+            | JailError::Json(_)
+            | JailError::ExecutionFailed(_) => 1,
+        }
+    }
+}
+
+fn exit_detail(stderr: &str) -> String {
+    let stderr = stderr.trim();
+    if stderr.is_empty() {
+        String::new()
+    } else {
+        format!(": {stderr}")
+    }
 }
 
 pub type Result<T> = std::result::Result<T, JailError>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn classify(stderr: &str) -> JailError {
+        JailError::command_failed("exit status: 125", stderr)
+    }
+
+    #[test]
+    fn test_classify_podman_errors() {
+        assert!(matches!(
+            classify("Error: localhost/jail-ai-base:latest: image not known"),
+            JailError::ImageNotFound(_)
+        ));
+        assert!(matches!(
+            classify("Error: creating container storage: the container name \"jail__demo\" is already in use by abc123"),
+            JailError::AlreadyExists(_)
+        ));
+        assert!(matches!(
+            classify("Error: cannot set up namespace using \"/usr/bin/newuidmap\": exit status 1"),
+            JailError::RootlessSetup(_)
+        ));
+        assert!(matches!(
+            classify("Error: OCI runtime error: crun: the requested cgroup controller `cpu` is not available"),
+            JailError::CgroupMissing(_)
+        ));
+        assert!(matches!(
+            classify("Error: container jail__demo was OOMKilled"),
+            JailError::OutOfMemory(_)
+        ));
+    }
+
+    #[test]
+    fn test_classify_docker_errors() {
+        assert!(matches!(
+            classify("permission denied while trying to connect to the Docker daemon socket at unix:///var/run/docker.sock"),
+            JailError::SocketPermission(_)
+        ));
+        assert!(matches!(
+            classify("Unable to find image 'nope:latest' locally\nmanifest unknown"),
+            JailError::ImageNotFound(_)
+        ));
+    }
+
+    #[test]
+    fn test_unclassified_failure() {
+        let error = JailError::command_failed("exit status: 1", "something odd\n");
+        assert_eq!(
+            error.to_string(),
+            "Command execution failed: Command failed with status exit status: 1: something odd"
+        );
+        assert_eq!(error.exit_code(), 1);
+        assert!(error.hint().is_none());
+    }
+
+    #[test]
+    fn test_exit_codes_are_distinct() {
+        let errors = [
+            JailError::Config(String::new()),
+            JailError::NotFound(String::new()),
+            JailError::AlreadyExists(String::new()),
+            JailError::ImageNotFound(String::new()),
+            JailError::OutOfMemory(String::new()),
+            JailError::SocketPermission(String::new()),
+            JailError::CgroupMissing(String::new()),
+            JailError::LoaderCapabilities(String::new()),
+            JailError::RootlessSetup(String::new()),
+        ];
+        let codes: std::collections::HashSet<i32> = errors.iter().map(|e| e.exit_code()).collect();
+        assert_eq!(codes.len(), errors.len());
+        assert!(!codes.contains(&1));
+    }
+}
@@ -0,0 +1,362 @@
+//! Garbage collection of jails, volumes and images left behind by moved or deleted projects
+
+use crate::backend::{extract_base_name, ImageSummary, JailBackend, JailSummary, VolumeSummary};
+use crate::config::JailConfig;
+use crate::error::Result;
+use crate::jail::JailManager;
+use std::collections::HashSet;
+use std::path::Path;
+use std::sync::Arc;
+use tracing::{debug, error};
+
+/// Everything `jail-ai gc` would remove
+#[derive(Debug, Default)]
+pub struct GcPlan {
+    /// Jails whose workspace directory no longer exists, and stopped ephemeral jails
+    pub jails: Vec<JailSummary>,
+    /// Volumes not used by any remaining jail
+    pub volumes: Vec<VolumeSummary>,
+    /// Images neither used by nor built for a remaining jail, a shared `:latest` layer nor a snapshot
+    pub images: Vec<ImageSummary>,
+}
+
+impl GcPlan {
+    pub fn is_empty(&self) -> bool {
+        self.jails.is_empty() && self.volumes.is_empty() && self.images.is_empty()
+    }
+
+    /// Bytes freed by removing the volumes and images whose size is known
+    ///
+    /// Image layers shared with kept images are counted too, so this is an upper bound.
+    pub fn reclaimable_bytes(&self) -> u64 {
+        let volumes = self.volumes.iter().filter_map(|volume| volume.size);
+        let images = self.images.iter().filter_map(|image| image.size);
+        volumes.chain(images).sum()
+    }
+}
+
+/// Select what to remove from the current jails, volumes and images
+///
+/// `images_in_use` lists the images to keep besides those of the remaining jails: the layer
+/// images their workspaces resolve to now, and images of other backends sharing the store.
+fn plan_gc(
+    jails: Vec<JailSummary>,
+    volumes: Vec<VolumeSummary>,
+    images: Vec<ImageSummary>,
+    images_in_use: &HashSet<String>,
+    workspace_exists: impl Fn(&Path) -> bool,
+) -> GcPlan {
+    // Stopped ephemeral jails were left by sessions that could not clean up
+    let (orphaned, kept): (Vec<_>, Vec<_>) = jails.into_iter().partition(|jail| {
+        let left_over = crate::ephemeral::is_ephemeral(&jail.name) && !jail.running;
+        left_over
+            || jail
+                .workspace
+                .as_deref()
+                .is_some_and(|workspace| !workspace_exists(workspace))
+    });
+
+    let used_volumes: HashSet<String> = kept
+        .iter()
+        .flat_map(|jail| {
+            // Sessions may share the home volume of their main jail
+            [
+                format!("{}__home", jail.name),
+                format!("{}__home", crate::session::main_jail_name(&jail.name)),
+                format!("{}__nix", extract_base_name(&jail.name)),
+            ]
+        })
+        .collect();
+    let used_images: HashSet<&str> = kept.iter().map(|jail| jail.image.as_str()).collect();
+
+    GcPlan {
+        volumes: volumes
+            .into_iter()
+            .filter(|volume| !used_volumes.contains(&volume.name))
+            .collect(),
+        images: images
+            .into_iter()
+            .filter(|image| {
+                !used_images.contains(image.name.as_str())
+                    && !images_in_use.contains(&image.name)
+                    && !image.name.ends_with(":latest")
+                    && !crate::image::is_snapshot_image(&image.name)
+            })
+            .collect(),
+        jails: orphaned,
+    }
+}
+
+/// Find orphaned jails, unused volumes and unreferenced images of a backend
+pub async fn find_garbage(
+    backend: &dyn JailBackend,
+    images_in_use: &HashSet<String>,
+) -> Result<GcPlan> {
+    let jails = backend.list_summaries().await?;
+    let volumes = backend.list_volumes().await?;
+    let images = backend.list_images().await?;
+
+    // Shared agent and custom layer images are rebuilt unless kept for the projects using them
+    let mut images_in_use = images_in_use.clone();
+    for jail in &jails {
+        if !jail.workspace.as_deref().is_some_and(Path::exists) {
+            continue;
+        }
+        match backend.inspect(&jail.name).await {
+            Ok(config) => images_in_use.extend(crate::backend::layer_images(&config)),
+            Err(e) => debug!("Failed to inspect jail {}: {}", jail.name, e),
+        }
+    }
+
+    Ok(plan_gc(
+        jails,
+        volumes,
+        images,
+        &images_in_use,
+        Path::exists,
+    ))
+}
+
+/// Remove everything in the plan, continuing past failures
+///
+/// Jails are removed like `jail-ai remove --volume` does, with their pending workspace
+/// changes and socket proxy. Returns the number of items that could not be removed.
+pub async fn remove_garbage(backend: Arc<dyn JailBackend>, plan: &GcPlan) -> usize {
+    let mut failures = 0;
+
+    // Containers first: they hold references to their volumes and image
+    for jail in &plan.jails {
+        let config = JailConfig {
+            name: jail.name.clone(),
+            ..Default::default()
+        };
+        match JailManager::with_backend(config, Arc::clone(&backend))
+            .remove(true)
+            .await
+        {
+            Ok(()) => println!("✓ Removed jail {}", jail.name),
+            Err(e) => {
+                error!("Failed to remove jail {}: {}", jail.name, e);
+                failures += 1;
+            }
+        }
+    }
+
+    // Volumes removed along with their jail are no longer listed
+    let remaining: Option<HashSet<String>> = match backend.list_volumes().await {
+        Ok(volumes) => Some(volumes.into_iter().map(|volume| volume.name).collect()),
+        Err(e) => {
+            debug!("Failed to list volumes: {}", e);
+            None
+        }
+    };
+    for volume in &plan.volumes {
+        if remaining
+            .as_ref()
+            .is_some_and(|remaining| !remaining.contains(&volume.name))
+        {
+            println!("✓ Removed volume {}", volume.name);
+            continue;
+        }
+        match backend.remove_volume(&volume.name).await {
+            Ok(()) => println!("✓ Removed volume {}", volume.name),
+            Err(e) => {
+                error!("Failed to remove volume {}: {}", volume.name, e);
+                failures += 1;
+            }
+        }
+    }
+    for image in &plan.images {
+        match backend.remove_image(&image.name).await {
+            Ok(()) => println!("✓ Removed image {}", image.name),
+            Err(e) => {
+                error!("Failed to remove image {}: {}", image.name, e);
+                failures += 1;
+            }
+        }
+    }
+
+    failures
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::fake::{FakeBackend, FakeCall};
+    use std::path::PathBuf;
+
+    fn summary(name: &str, workspace: Option<&str>, image: &str) -> JailSummary {
+        JailSummary {
+            name: name.to_string(),
+            workspace: workspace.map(PathBuf::from),
+            image: image.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn volume(name: &str, size: u64) -> VolumeSummary {
+        VolumeSummary {
+            name: name.to_string(),
+            size: Some(size),
+        }
+    }
+
+    fn image(name: &str, size: Option<u64>) -> ImageSummary {
+        ImageSummary {
+            name: name.to_string(),
+            size,
+        }
+    }
+
+    #[test]
+    fn test_plan_gc() {
+        let jails = vec![
+            summary(
+                "jail__gone__11111111__claude",
+                Some("/projects/gone"),
+                "localhost/jail-ai-agent-claude:11111111",
+            ),
+            summary(
+                "jail__kept__22222222__claude",
+                Some("/projects/kept"),
+                "localhost/jail-ai-agent-claude:base-rust-claude",
+            ),
+            summary("jail__scratch__33333333__default", None, "alpine:latest"),
+            summary(
+                "jail__kept__22222222__ephemeral-abcdef__claude",
+                Some("/projects/kept"),
+                "localhost/jail-ai-agent-claude:base-rust-claude",
+            ),
+        ];
+        let volumes = vec![
+            volume("jail__gone__11111111__claude__home", 100),
+            volume("jail__gone__11111111__nix", 1000),
+            volume("jail__kept__22222222__claude__home", 100),
+            volume("jail__kept__22222222__nix", 1000),
+            volume("jail__old__44444444__copilot__home", 10),
+        ];
+        let images = vec![
+            image("localhost/jail-ai-agent-claude:11111111", Some(5000)),
+            image(
+                "localhost/jail-ai-agent-claude:base-rust-claude",
+                Some(5000),
+            ),
+            image("localhost/jail-ai-agent-copilot:base-copilot", None),
+            image("localhost/jail-ai-rust:latest", Some(3000)),
+            image(
+                "localhost/jail-ai-snapshot:20260101-120000-11111111",
+                Some(3000),
+            ),
+        ];
+
+        let bwrap_images =
+            HashSet::from(["localhost/jail-ai-agent-gemini:base-gemini".to_string()]);
+        let images = [
+            images,
+            vec![image(
+                "localhost/jail-ai-agent-gemini:base-gemini",
+                Some(5000),
+            )],
+        ]
+        .concat();
+        let plan = plan_gc(jails, volumes, images, &bwrap_images, |path| {
+            path == Path::new("/projects/kept")
+        });
+
+        let names = |items: Vec<&str>| items.into_iter().map(str::to_string).collect::<Vec<_>>();
+        assert_eq!(
+            plan.jails
+                .iter()
+                .map(|j| j.name.clone())
+                .collect::<Vec<_>>(),
+            names(vec![
+                "jail__gone__11111111__claude",
+                "jail__kept__22222222__ephemeral-abcdef__claude",
+            ])
+        );
+        assert_eq!(
+            plan.volumes
+                .iter()
+                .map(|v| v.name.clone())
+                .collect::<Vec<_>>(),
+            names(vec![
+                "jail__gone__11111111__claude__home",
+                "jail__gone__11111111__nix",
+                "jail__old__44444444__copilot__home",
+            ])
+        );
+        assert_eq!(
+            plan.images
+                .iter()
+                .map(|i| i.name.clone())
+                .collect::<Vec<_>>(),
+            names(vec![
+                "localhost/jail-ai-agent-claude:11111111",
+                "localhost/jail-ai-agent-copilot:base-copilot",
+            ])
+        );
+        assert_eq!(plan.reclaimable_bytes(), 100 + 1000 + 10 + 5000);
+    }
+
+    #[tokio::test]
+    async fn test_find_garbage_keeps_current_layer_images() {
+        let workspace = tempfile::tempdir().unwrap();
+        let config = crate::jail::JailBuilder::new("jail__kept__22222222__claude")
+            .bind_mount(workspace.path(), "/workspace", false)
+            .build()
+            .config()
+            .clone();
+        let current = crate::backend::layer_images(&config);
+        assert_eq!(current.len(), 1);
+
+        let fake = FakeBackend::new()
+            .with_jail(config, false)
+            .with_image(&current[0], 5000)
+            .with_image("localhost/jail-ai-agent-claude:base-old-claude", 5000);
+
+        let plan = find_garbage(&fake, &HashSet::new()).await.unwrap();
+        assert_eq!(
+            plan.images
+                .iter()
+                .map(|i| i.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["localhost/jail-ai-agent-claude:base-old-claude"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_remove_garbage_removes_jails_before_volumes_and_images() {
+        let fake = FakeBackend::new()
+            .with_jail(
+                crate::jail::JailBuilder::new("jail__gone__11111111__claude")
+                    .bind_mount("/nonexistent/jail-ai-gc-test", "/workspace", false)
+                    .base_image("localhost/jail-ai-agent-claude:11111111")
+                    .build()
+                    .config()
+                    .clone(),
+                false,
+            )
+            .with_volume("jail__gone__11111111__claude__home", 100)
+            .with_volume("jail__gone__11111111__nix", 1000)
+            .with_image("localhost/jail-ai-agent-claude:11111111", 5000);
+
+        let plan = find_garbage(&fake, &HashSet::new()).await.unwrap();
+        assert_eq!(remove_garbage(Arc::new(fake.clone()), &plan).await, 0);
+        // The home volume goes with its jail
+        assert_eq!(
+            fake.calls(),
+            vec![
+                FakeCall::Remove {
+                    name: "jail__gone__11111111__claude".to_string(),
+                    remove_volume: true,
+                },
+                FakeCall::RemoveVolume("jail__gone__11111111__nix".to_string()),
+                FakeCall::RemoveImage("localhost/jail-ai-agent-claude:11111111".to_string()),
+            ]
+        );
+        assert!(find_garbage(&fake, &HashSet::new())
+            .await
+            .unwrap()
+            .is_empty());
+    }
+}
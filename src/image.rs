@@ -2,3 +2,16 @@ use crate::cli::DEFAULT_IMAGE;
 
 /// Default image name for jail-ai
 pub const DEFAULT_IMAGE_NAME: &str = DEFAULT_IMAGE;
+
+/// Repository prefix of every image built by jail-ai
+pub const IMAGE_PREFIX: &str = "localhost/jail-ai-";
+
+/// Repository of the images saved by `jail-ai snapshot create`
+pub const SNAPSHOT_REPOSITORY: &str = "localhost/jail-ai-snapshot";
+
+/// Whether an image is a jail snapshot
+pub fn is_snapshot_image(image: &str) -> bool {
+    image
+        .strip_prefix(SNAPSHOT_REPOSITORY)
+        .is_some_and(|tag| tag.starts_with(':'))
+}
@@ -1,3 +1,4 @@
+use crate::config::{BackendType, JailConfig};
 use crate::error::{JailError, Result};
 use crate::project_detection::{
     detect_project_type_with_options, has_custom_containerfile, ProjectType,
@@ -252,11 +253,19 @@ fn hash_containerfile(content: &str) -> String {
 
 // ========== Performance Optimization: Image Existence Cache ==========
 
+/// Command for the container engine storing the images of a backend (podman or docker)
+fn engine_command(backend: BackendType) -> Command {
+    Command::new(backend.image_engine().command())
+}
+
 /// Global LRU cache for image existence checks
-/// This prevents repeated `podman image exists` calls for the same image
-static IMAGE_EXISTS_CACHE: OnceLock<Arc<Mutex<LruCache<String, bool>>>> = OnceLock::new();
+/// This prevents repeated `image inspect` calls for the same image
+/// (keyed by engine command and image name)
+static IMAGE_EXISTS_CACHE: OnceLock<Arc<Mutex<ImageCache>>> = OnceLock::new();
 
-fn image_cache() -> &'static Arc<Mutex<LruCache<String, bool>>> {
+type ImageCache = LruCache<(&'static str, String), bool>;
+
+fn image_cache() -> &'static Arc<Mutex<ImageCache>> {
     IMAGE_EXISTS_CACHE.get_or_init(|| {
         // Cache up to 1000 images (more than enough for typical usage)
         Arc::new(Mutex::new(LruCache::new(NonZeroUsize::new(1000).unwrap())))
@@ -264,45 +273,82 @@ fn image_cache() -> &'static Arc<Mutex<LruCache<String, bool>>> {
 }
 
 /// Invalidate cache entry for an image (call after building/removing an image)
-fn invalidate_image_cache(image_name: &str) {
+pub fn invalidate_image_cache(image_name: &str) {
     let cache = image_cache();
     if let Ok(mut cache_guard) = cache.lock() {
-        cache_guard.pop(image_name);
+        for engine in [BackendType::Podman, BackendType::Docker] {
+            cache_guard.pop(&(engine.command(), image_name.to_string()));
+        }
         debug!("🗑️  Invalidated cache for image: {}", image_name);
     }
 }
 
+/// Project and agent images this process created under a new name
+static CREATED_IMAGES: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+pub fn record_created_image(image_name: &str) {
+    if let Ok(mut created) = CREATED_IMAGES.lock() {
+        created.push(image_name.to_string());
+    }
+}
+
+/// Images created by this process since the last call, so that an ephemeral session
+/// removes only its own
+pub fn take_created_images() -> Vec<String> {
+    CREATED_IMAGES
+        .lock()
+        .map(|mut created| std::mem::take(&mut *created))
+        .unwrap_or_default()
+}
+
 /// Check if an image exists locally (with LRU caching)
 ///
-/// Performance optimization: Caches results to avoid repeated `podman image exists` calls.
+/// Performance optimization: Caches results to avoid repeated `image inspect` calls.
 /// Cache is automatically invalidated when images are built or removed.
-pub async fn image_exists(image_name: &str) -> Result<bool> {
+pub async fn image_exists(backend: BackendType, image_name: &str) -> Result<bool> {
+    let key = (backend.image_engine().command(), image_name.to_string());
+
     // Check cache first
     {
         let cache = image_cache();
         if let Ok(mut cache_guard) = cache.lock() {
-            if let Some(&exists) = cache_guard.get(image_name) {
+            if let Some(&exists) = cache_guard.get(&key) {
                 debug!("✅ Cache hit for image existence: {}", image_name);
                 return Ok(exists);
             }
         }
     }
 
-    // Cache miss: query podman
+    // Cache miss: query the container engine
     debug!("🔍 Cache miss, checking image existence: {}", image_name);
-    let mut cmd = Command::new("podman");
-    cmd.arg("image").arg("exists").arg(image_name);
+    let uses_podman = backend.image_engine() == BackendType::Podman;
+    let api_exists = match crate::backend::podman_api::PodmanApi::shared() {
+        Some(api) if uses_podman => api.image_exists(image_name).await.ok(),
+        _ => None,
+    };
 
-    let exists = match cmd.output().await {
-        Ok(output) => output.status.success(),
-        Err(_) => false,
+    let exists = match api_exists {
+        Some(exists) => exists,
+        None => {
+            let mut cmd = engine_command(backend);
+            cmd.arg("image")
+                .arg("inspect")
+                .arg("--format")
+                .arg("{{.Id}}")
+                .arg(image_name);
+
+            match cmd.output().await {
+                Ok(output) => output.status.success(),
+                Err(_) => false,
+            }
+        }
     };
 
     // Update cache
     {
         let cache = image_cache();
         if let Ok(mut cache_guard) = cache.lock() {
-            cache_guard.put(image_name.to_string(), exists);
+            cache_guard.put(key, exists);
         }
     }
 
@@ -349,13 +395,16 @@ pub async fn get_expected_image_name(
 }
 
 /// Get the containerfile hash label from an image
-async fn get_image_containerfile_hash(image_name: &str) -> Result<Option<String>> {
-    let mut cmd = Command::new("podman");
+async fn get_image_containerfile_hash(
+    backend: BackendType,
+    image_name: &str,
+) -> Result<Option<String>> {
+    let mut cmd = engine_command(backend);
     cmd.arg("image")
         .arg("inspect")
         .arg(image_name)
         .arg("--format")
-        .arg("{{index .Labels \"ai.jail.containerfile.hash\"}}");
+        .arg("{{index .Config.Labels \"ai.jail.containerfile.hash\"}}");
 
     match cmd.output().await {
         Ok(output) if output.status.success() => {
@@ -372,13 +421,14 @@ async fn get_image_containerfile_hash(image_name: &str) -> Result<Option<String>
 
 // ========== Performance Optimization: Batch Image Checks ==========
 
-/// Check multiple images for rebuild in a single podman inspect call
+/// Check multiple images for rebuild in a single image inspect call
 ///
 /// Performance optimization: Groups multiple image inspections into a single
-/// podman command, reducing syscall overhead significantly.
+/// engine command, reducing syscall overhead significantly.
 ///
 /// Returns a Vec of bools indicating which images need rebuild (same order as input)
 async fn batch_check_images_need_rebuild(
+    backend: BackendType,
     images: &[(String, String)], // (image_name, layer_name)
 ) -> Result<Vec<bool>> {
     if images.is_empty() {
@@ -393,7 +443,7 @@ async fn batch_check_images_need_rebuild(
     let mut existing_indices = Vec::new();
 
     for (idx, (image_name, layer_name)) in images.iter().enumerate() {
-        if !image_exists(image_name).await? {
+        if !image_exists(backend, image_name).await? {
             debug!("Image {} doesn't exist, needs rebuild", image_name);
             needs_rebuild.push(true);
         } else {
@@ -408,17 +458,18 @@ async fn batch_check_images_need_rebuild(
         return Ok(needs_rebuild);
     }
 
-    // Build single podman inspect command for all existing images
+    // Build single inspect command for all existing images
+    // (.Config.Labels is populated by both podman and docker)
     let image_names: Vec<&str> = existing_images
         .iter()
         .map(|(name, _)| name.as_str())
         .collect();
 
-    let mut cmd = Command::new("podman");
+    let mut cmd = engine_command(backend);
     cmd.arg("image")
         .arg("inspect")
         .arg("--format")
-        .arg("{{.Id}}\t{{index .Labels \"ai.jail.containerfile.hash\"}}");
+        .arg("{{.Id}}\t{{index .Config.Labels \"ai.jail.containerfile.hash\"}}");
 
     for name in &image_names {
         cmd.arg(name);
@@ -495,9 +546,13 @@ async fn batch_check_images_need_rebuild(
 }
 
 /// Check if an image needs to be rebuilt based on Containerfile changes
-async fn image_needs_rebuild(image_name: &str, layer_name: &str) -> Result<bool> {
+async fn image_needs_rebuild(
+    backend: BackendType,
+    image_name: &str,
+    layer_name: &str,
+) -> Result<bool> {
     // If image doesn't exist, it needs to be built
-    if !image_exists(image_name).await? {
+    if !image_exists(backend, image_name).await? {
         return Ok(true);
     }
 
@@ -511,7 +566,7 @@ async fn image_needs_rebuild(image_name: &str, layer_name: &str) -> Result<bool>
     let current_hash = hash_containerfile(containerfile_content);
 
     // Get the hash from the image
-    let image_hash = get_image_containerfile_hash(image_name).await?;
+    let image_hash = get_image_containerfile_hash(backend, image_name).await?;
 
     // Rebuild if hashes don't match
     match image_hash {
@@ -532,6 +587,7 @@ async fn image_needs_rebuild(image_name: &str, layer_name: &str) -> Result<bool>
 ///
 /// Performance optimization: Uses batch checking to inspect all images in a single call
 pub async fn check_layers_need_rebuild(
+    backend: BackendType,
     workspace_path: &Path,
     agent_name: Option<&str>,
     no_nix: bool,
@@ -585,7 +641,7 @@ pub async fn check_layers_need_rebuild(
     }
 
     // 🚀 Batch check all images at once
-    let needs_rebuild = batch_check_images_need_rebuild(&images_to_check).await?;
+    let needs_rebuild = batch_check_images_need_rebuild(backend, &images_to_check).await?;
 
     // Collect outdated layers
     let outdated_layers: Vec<String> = images_to_check
@@ -611,6 +667,7 @@ pub async fn check_layers_need_rebuild(
 
 /// Build a custom layer from project's jail-ai.Containerfile
 async fn build_custom_layer(
+    backend: BackendType,
     workspace_path: &Path,
     base_image: &str,
     image_tag: &str,
@@ -653,7 +710,7 @@ async fn build_custom_layer(
     let containerfile_hash = hash_containerfile(&containerfile_content);
 
     // Build command
-    let mut cmd = Command::new("podman");
+    let mut cmd = engine_command(backend);
     cmd.arg("build").arg("-t").arg(image_tag);
 
     if no_cache {
@@ -717,6 +774,7 @@ async fn build_custom_layer(
 ///
 /// This function is public to allow parallel building from image_parallel module
 pub async fn build_shared_layer(
+    backend: BackendType,
     layer_name: &str,
     base_image: Option<&str>,
     verbose: bool,
@@ -751,7 +809,7 @@ pub async fn build_shared_layer(
     };
 
     // Check if image needs to be rebuilt (doesn't exist or Containerfile changed)
-    if !force_rebuild && !image_needs_rebuild(&image_name, layer_name).await? {
+    if !force_rebuild && !image_needs_rebuild(backend, &image_name, layer_name).await? {
         if verbose {
             info!("Shared layer {} is up to date, skipping", layer_name);
         } else {
@@ -764,12 +822,20 @@ pub async fn build_shared_layer(
         info!("Force rebuilding layer: {}", layer_name);
     }
 
-    build_image_from_containerfile(layer_name, base_image, &image_name, verbose, force_rebuild)
-        .await
+    build_image_from_containerfile(
+        backend,
+        layer_name,
+        base_image,
+        &image_name,
+        verbose,
+        force_rebuild,
+    )
+    .await
 }
 
 /// Internal function to build an image from a Containerfile
 async fn build_image_from_containerfile(
+    backend: BackendType,
     layer_name: &str,
     base_image: Option<&str>,
     image_tag: &str,
@@ -810,7 +876,7 @@ async fn build_image_from_containerfile(
         .map_err(|e| JailError::Backend(format!("Failed to write Containerfile: {}", e)))?;
 
     // Build command
-    let mut cmd = Command::new("podman");
+    let mut cmd = engine_command(backend);
     cmd.arg("build").arg("-t").arg(image_tag);
 
     if no_cache {
@@ -868,16 +934,122 @@ async fn build_image_from_containerfile(
     Ok(image_tag.to_string())
 }
 
-/// Build the complete image stack for a project
+/// Project type of a workspace, or the one made of the layers forced with `--layers`
+fn project_type_for(workspace_path: &Path, force_layers: &[String], no_nix: bool) -> ProjectType {
+    if force_layers.is_empty() {
+        return detect_project_type_with_options(workspace_path, no_nix);
+    }
+
+    // Build a synthetic ProjectType from specified layers
+    let mut lang_types = Vec::new();
+    for layer in force_layers {
+        match layer.as_str() {
+            "rust" => lang_types.push(ProjectType::Rust),
+            "golang" => lang_types.push(ProjectType::Golang),
+            "python" => lang_types.push(ProjectType::Python),
+            "nodejs" => lang_types.push(ProjectType::NodeJS),
+            "java" => lang_types.push(ProjectType::Java),
+            "nix" => lang_types.push(ProjectType::Nix),
+            "php" => lang_types.push(ProjectType::Php),
+            "cpp" => lang_types.push(ProjectType::Cpp),
+            "csharp" => lang_types.push(ProjectType::CSharp),
+            "terraform" => lang_types.push(ProjectType::Terraform),
+            "kubernetes" => lang_types.push(ProjectType::Kubernetes),
+            "aws" => lang_types.push(ProjectType::Aws),
+            "gcp" => lang_types.push(ProjectType::Gcp),
+            "base" => {}                                         // base is implicit
+            "custom" => {}                                       // custom is handled separately
+            layer_name if layer_name.starts_with("agent-") => {} // ignore agent layers
+            _ => debug!("Unknown layer '{}' in force_layers, ignoring", layer),
+        }
+    }
+
+    match lang_types.len() {
+        0 => ProjectType::Generic,
+        1 => lang_types[0].clone(),
+        _ => ProjectType::Multi(lang_types),
+    }
+}
+
+/// Image of the project's jail-ai.Containerfile layer
+fn custom_image_name(project_type: &ProjectType, project_hash: &str, isolated: bool) -> String {
+    let custom_layer_tag = if isolated {
+        format!("{}-custom", project_hash)
+    } else {
+        generate_layer_tag(project_type, true, None)
+    };
+    format!("localhost/jail-ai-custom:{}", custom_layer_tag)
+}
+
+/// Final agent (or project) image: tagged with the workspace hash in isolated mode,
+/// with the layer composition otherwise
+fn final_image_name(
+    project_type: &ProjectType,
+    project_hash: &str,
+    has_custom: bool,
+    isolated: bool,
+    agent_name: Option<&str>,
+) -> String {
+    let image_tag = if isolated {
+        project_hash.to_string()
+    } else {
+        generate_layer_tag(project_type, has_custom, agent_name)
+    };
+    match agent_name {
+        Some(agent) => get_agent_project_image_name(agent, &image_tag),
+        None => get_project_image_name(project_type.language_layer(), &image_tag),
+    }
+}
+
+fn log_image_mode(isolated: bool, image_name: &str) {
+    if isolated {
+        info!("Using isolated mode: workspace-specific image");
+    } else {
+        info!("Using shared mode: layer-based image ({})", image_name);
+    }
+}
+
+/// Non-`:latest` images a jail resolves to now: its custom layer, if any, and its final image
+pub fn layer_images(
+    config: &JailConfig,
+    workspace_path: &Path,
+    agent_name: Option<&str>,
+) -> Vec<String> {
+    let project_hash = generate_project_hash(workspace_path);
+    let project_type = project_type_for(workspace_path, &config.force_layers, config.no_nix);
+    let has_custom = has_custom_containerfile(workspace_path);
+
+    let mut images = Vec::new();
+    if has_custom {
+        images.push(custom_image_name(
+            &project_type,
+            &project_hash,
+            config.isolated,
+        ));
+    }
+    images.push(final_image_name(
+        &project_type,
+        &project_hash,
+        has_custom,
+        config.isolated,
+        agent_name,
+    ));
+    images
+}
+
+/// Build the complete image stack for a project, with the engine and options of a jail
 pub async fn build_project_image(
+    config: &JailConfig,
     workspace_path: &Path,
     agent_name: Option<&str>,
-    upgrade: bool,
-    force_layers: &[String],
-    isolated: bool,
-    verbose: bool,
-    no_nix: bool,
 ) -> Result<String> {
+    let backend = config.backend;
+    let upgrade = config.upgrade;
+    let force_layers = config.force_layers.as_slice();
+    let isolated = config.isolated;
+    let verbose = config.verbose;
+    let no_nix = config.no_nix;
+
     // Generate project-specific identifier (for isolated mode)
     let project_hash = generate_project_hash(workspace_path);
     if isolated {
@@ -885,58 +1057,25 @@ pub async fn build_project_image(
     }
 
     // Detect project type (skip autodetection if force_layers is specified)
-    let project_type = if !force_layers.is_empty() {
-        // Bypass autodetection: use only the specified layers
+    let project_type = project_type_for(workspace_path, force_layers, no_nix);
+    if !force_layers.is_empty() {
         info!(
             "Bypassing autodetection: using specified layers: {:?}",
             force_layers
         );
-
-        // Build a synthetic ProjectType from specified layers
-        let mut lang_types = Vec::new();
-        for layer in force_layers {
-            match layer.as_str() {
-                "rust" => lang_types.push(ProjectType::Rust),
-                "golang" => lang_types.push(ProjectType::Golang),
-                "python" => lang_types.push(ProjectType::Python),
-                "nodejs" => lang_types.push(ProjectType::NodeJS),
-                "java" => lang_types.push(ProjectType::Java),
-                "nix" => lang_types.push(ProjectType::Nix),
-                "php" => lang_types.push(ProjectType::Php),
-                "cpp" => lang_types.push(ProjectType::Cpp),
-                "csharp" => lang_types.push(ProjectType::CSharp),
-                "terraform" => lang_types.push(ProjectType::Terraform),
-                "kubernetes" => lang_types.push(ProjectType::Kubernetes),
-                "aws" => lang_types.push(ProjectType::Aws),
-                "gcp" => lang_types.push(ProjectType::Gcp),
-                "base" => {}                                         // base is implicit
-                "custom" => {}                                       // custom is handled separately
-                layer_name if layer_name.starts_with("agent-") => {} // ignore agent layers
-                _ => debug!("Unknown layer '{}' in force_layers, ignoring", layer),
-            }
-        }
-
-        match lang_types.len() {
-            0 => ProjectType::Generic,
-            1 => lang_types[0].clone(),
-            _ => ProjectType::Multi(lang_types),
-        }
     } else {
-        // Auto-detect project type
-        let detected = detect_project_type_with_options(workspace_path, no_nix);
-        info!("Detected project type: {:?}", detected);
-        detected
-    };
+        info!("Detected project type: {:?}", project_type);
+    }
 
     // Step 1: Build base layer (shared :latest)
     let force_base = upgrade || force_layers.contains(&"base".to_string());
-    let should_rebuild_base = force_base || !image_exists(BASE_IMAGE_NAME).await?;
+    let should_rebuild_base = force_base || !image_exists(backend, BASE_IMAGE_NAME).await?;
 
     let base_image = if should_rebuild_base {
         if verbose {
             info!("Building base layer...");
         }
-        build_shared_layer("base", None, verbose, force_base).await?
+        build_shared_layer(backend, "base", None, verbose, force_base).await?
     } else {
         if verbose {
             info!("Base layer already exists, skipping");
@@ -964,6 +1103,7 @@ pub async fn build_project_image(
 
                 // Use parallel building
                 let results = crate::image_parallel::build_language_layers_parallel(
+                    backend,
                     &base_image,
                     types,
                     force_layers,
@@ -991,10 +1131,11 @@ pub async fn build_project_image(
                     let should_force_lang =
                         upgrade || force_layers.contains(&layer_name.to_string());
                     let should_rebuild_lang =
-                        should_force_lang || !image_exists(lang_image_name).await?;
+                        should_force_lang || !image_exists(backend, lang_image_name).await?;
 
                     current_image = if should_rebuild_lang {
                         build_shared_layer(
+                            backend,
                             layer_name,
                             Some(&current_image),
                             verbose,
@@ -1015,11 +1156,18 @@ pub async fn build_project_image(
             let layer_name = project_type.language_layer();
             let lang_image_name = get_language_image_name(&project_type);
             let should_force_lang = upgrade || force_layers.contains(&layer_name.to_string());
-            let should_rebuild_lang = should_force_lang || !image_exists(lang_image_name).await?;
+            let should_rebuild_lang =
+                should_force_lang || !image_exists(backend, lang_image_name).await?;
 
             if should_rebuild_lang {
-                build_shared_layer(layer_name, Some(&base_image), verbose, should_force_lang)
-                    .await?
+                build_shared_layer(
+                    backend,
+                    layer_name,
+                    Some(&base_image),
+                    verbose,
+                    should_force_lang,
+                )
+                .await?
             } else {
                 if verbose {
                     info!("Language layer {} already exists, skipping", layer_name);
@@ -1036,31 +1184,29 @@ pub async fn build_project_image(
     // Step 2.5: Build custom layer if present
     let has_custom = has_custom_containerfile(workspace_path);
     let custom_image = if has_custom {
-        // Generate image name for custom layer
-        let custom_layer_tag = if isolated {
-            format!("{}-custom", project_hash)
-        } else {
-            generate_layer_tag(&project_type, true, None)
-        };
-
-        let custom_image_name = format!("localhost/jail-ai-custom:{}", custom_layer_tag);
+        let custom_image_name = custom_image_name(&project_type, &project_hash, isolated);
 
         let should_force_custom = upgrade || force_layers.contains(&"custom".to_string());
-        let should_rebuild_custom =
-            should_force_custom || !image_exists(&custom_image_name).await?;
+        let custom_exists = image_exists(backend, &custom_image_name).await?;
+        let should_rebuild_custom = should_force_custom || !custom_exists;
 
         if should_rebuild_custom {
             if verbose {
                 info!("Building custom layer: {}", custom_image_name);
             }
-            build_custom_layer(
+            let custom_image = build_custom_layer(
+                backend,
                 workspace_path,
                 &language_image,
                 &custom_image_name,
                 verbose,
                 should_force_custom,
             )
-            .await?
+            .await?;
+            if !custom_exists {
+                record_created_image(&custom_image);
+            }
+            custom_image
         } else {
             if verbose {
                 info!("Custom layer already exists, skipping");
@@ -1081,28 +1227,24 @@ pub async fn build_project_image(
         // This ensures agent has all language tooling (rust, nix, etc.)
 
         let agent_layer = format!("agent-{}", agent);
-
-        // Determine the final image tag based on isolation mode
-        let image_tag = if isolated {
-            // Isolated mode: Use workspace hash
-            info!("Using isolated mode: workspace-specific image");
-            project_hash.clone()
-        } else {
-            // Shared mode: Use layer composition
-            let layer_tag = generate_layer_tag(&project_type, has_custom, Some(agent));
-            info!("Using shared mode: layer-based image ({})", layer_tag);
-            layer_tag
-        };
-
-        let final_image_name = get_agent_project_image_name(agent, &image_tag);
+        let final_image_name = final_image_name(
+            &project_type,
+            &project_hash,
+            has_custom,
+            isolated,
+            Some(agent),
+        );
+        log_image_mode(isolated, &final_image_name);
         let should_force_agent = upgrade || force_layers.contains(&agent_layer);
-        let should_rebuild_agent = should_force_agent || !image_exists(&final_image_name).await?;
+        let agent_exists = image_exists(backend, &final_image_name).await?;
+        let should_rebuild_agent = should_force_agent || !agent_exists;
 
         if should_rebuild_agent {
             if verbose {
                 info!("Building agent image: {}", final_image_name);
             }
             build_image_from_containerfile(
+                backend,
                 &agent_layer,
                 Some(&custom_image),
                 &final_image_name,
@@ -1110,6 +1252,9 @@ pub async fn build_project_image(
                 should_force_agent,
             )
             .await?;
+            if !agent_exists {
+                record_created_image(&final_image_name);
+            }
         } else if verbose {
             info!("Agent image already exists, skipping");
         } else {
@@ -1120,26 +1265,15 @@ pub async fn build_project_image(
         Ok(final_image_name)
     } else {
         // No agent: just tag custom/language image
-        let layer_type = project_type.language_layer();
+        let final_image_name =
+            final_image_name(&project_type, &project_hash, has_custom, isolated, None);
+        log_image_mode(isolated, &final_image_name);
 
-        // Determine the final image tag based on isolation mode
-        let image_tag = if isolated {
-            // Isolated mode: Use workspace hash
-            info!("Using isolated mode: workspace-specific image");
-            project_hash.clone()
-        } else {
-            // Shared mode: Use layer composition
-            let layer_tag = generate_layer_tag(&project_type, has_custom, None);
-            info!("Using shared mode: layer-based image ({})", layer_tag);
-            layer_tag
-        };
-
-        let final_image_name = get_project_image_name(layer_type, &image_tag);
-
-        if upgrade || !image_exists(&final_image_name).await? {
+        let final_exists = image_exists(backend, &final_image_name).await?;
+        if upgrade || !final_exists {
             info!("Tagging custom/language image: {}", final_image_name);
 
-            let mut cmd = Command::new("podman");
+            let mut cmd = engine_command(backend);
             cmd.arg("tag").arg(&custom_image).arg(&final_image_name);
 
             let status = cmd
@@ -1155,6 +1289,9 @@ pub async fn build_project_image(
             }
 
             info!("Tagged {} as {}", custom_image, final_image_name);
+            if !final_exists {
+                record_created_image(&final_image_name);
+            }
         } else {
             debug!("Image already exists: {}", final_image_name);
         }
@@ -1166,24 +1303,11 @@ pub async fn build_project_image(
 
 /// Ensure the appropriate image is available for the workspace and agent
 pub async fn ensure_layered_image_available(
+    config: &JailConfig,
     workspace_path: &Path,
     agent_name: Option<&str>,
-    upgrade: bool,
-    force_layers: &[String],
-    isolated: bool,
-    verbose: bool,
-    no_nix: bool,
 ) -> Result<String> {
-    build_project_image(
-        workspace_path,
-        agent_name,
-        upgrade,
-        force_layers,
-        isolated,
-        verbose,
-        no_nix,
-    )
-    .await
+    build_project_image(config, workspace_path, agent_name).await
 }
 
 #[cfg(test)]
@@ -6,6 +6,7 @@
 ///
 /// For projects with multiple language stacks (e.g., Rust + Node.js + Python),
 /// layers can be built concurrently instead of sequentially.
+use crate::config::BackendType;
 use crate::error::Result;
 use crate::image_layers::{build_shared_layer, image_exists};
 use crate::project_detection::{detect_project_type_with_options, ProjectType};
@@ -31,6 +32,7 @@ use tracing::{debug, info};
 /// # Returns
 /// HashMap mapping layer names to their built image names
 pub async fn build_language_layers_parallel(
+    backend: BackendType,
     base_image: &str,
     lang_types: &[ProjectType],
     _force_layers: &[String],
@@ -57,7 +59,8 @@ pub async fn build_language_layers_parallel(
 
         join_set.spawn(async move {
             let result =
-                build_shared_layer(&layer_name, Some(&base_image), verbose, upgrade).await?;
+                build_shared_layer(backend, &layer_name, Some(&base_image), verbose, upgrade)
+                    .await?;
             Ok::<_, crate::error::JailError>((layer_name, result))
         });
     }
@@ -112,7 +115,10 @@ pub async fn build_language_layers_parallel(
 /// # Returns
 /// A JoinHandle that can be awaited if you want to wait for prefetching to complete,
 /// or just dropped to let it run in the background.
-pub fn prefetch_common_layers(workspace_path: &Path) -> tokio::task::JoinHandle<()> {
+pub fn prefetch_common_layers(
+    backend: BackendType,
+    workspace_path: &Path,
+) -> tokio::task::JoinHandle<()> {
     let workspace_path = workspace_path.to_path_buf();
 
     tokio::spawn(async move {
@@ -132,44 +138,44 @@ pub fn prefetch_common_layers(workspace_path: &Path) -> tokio::task::JoinHandle<
         let project_type = detect_project_type_with_options(&workspace_path, false);
 
         // Ensure base layer exists (most commonly needed)
-        if let Err(e) = ensure_layer_exists("base", None).await {
+        if let Err(e) = ensure_layer_exists(backend, "base", None).await {
             debug!("Pre-fetch base layer failed: {}", e);
         }
 
         // Pre-fetch language-specific layers based on detected type
         match project_type {
             ProjectType::Rust => {
-                let _ = ensure_layer_exists("rust", Some("base")).await;
+                let _ = ensure_layer_exists(backend, "rust", Some("base")).await;
             }
             ProjectType::Golang => {
-                let _ = ensure_layer_exists("golang", Some("base")).await;
+                let _ = ensure_layer_exists(backend, "golang", Some("base")).await;
             }
             ProjectType::NodeJS => {
-                let _ = ensure_layer_exists("nodejs", Some("base")).await;
+                let _ = ensure_layer_exists(backend, "nodejs", Some("base")).await;
             }
             ProjectType::Python => {
-                let _ = ensure_layer_exists("python", Some("base")).await;
+                let _ = ensure_layer_exists(backend, "python", Some("base")).await;
             }
             ProjectType::Java => {
-                let _ = ensure_layer_exists("java", Some("base")).await;
+                let _ = ensure_layer_exists(backend, "java", Some("base")).await;
             }
             ProjectType::Nix => {
-                let _ = ensure_layer_exists("nix", Some("base")).await;
+                let _ = ensure_layer_exists(backend, "nix", Some("base")).await;
             }
             ProjectType::Php => {
-                let _ = ensure_layer_exists("php", Some("base")).await;
+                let _ = ensure_layer_exists(backend, "php", Some("base")).await;
             }
             ProjectType::Cpp => {
-                let _ = ensure_layer_exists("cpp", Some("base")).await;
+                let _ = ensure_layer_exists(backend, "cpp", Some("base")).await;
             }
             ProjectType::CSharp => {
-                let _ = ensure_layer_exists("csharp", Some("base")).await;
+                let _ = ensure_layer_exists(backend, "csharp", Some("base")).await;
             }
             ProjectType::Multi(types) => {
                 // Pre-fetch all detected language layers
                 for lang_type in types {
                     let layer = lang_type.language_layer();
-                    let _ = ensure_layer_exists(layer, Some("base")).await;
+                    let _ = ensure_layer_exists(backend, layer, Some("base")).await;
                 }
             }
             ProjectType::Generic => {
@@ -183,7 +189,11 @@ pub fn prefetch_common_layers(workspace_path: &Path) -> tokio::task::JoinHandle<
 }
 
 /// Ensure a specific layer exists, building it if necessary
-async fn ensure_layer_exists(layer: &str, base_image: Option<&str>) -> Result<()> {
+async fn ensure_layer_exists(
+    backend: BackendType,
+    layer: &str,
+    base_image: Option<&str>,
+) -> Result<()> {
     // Get the image name for this layer
     let image_name = match layer {
         "base" => "localhost/jail-ai-base:latest",
@@ -203,7 +213,7 @@ async fn ensure_layer_exists(layer: &str, base_image: Option<&str>) -> Result<()
     };
 
     // Check if image already exists (using cached check)
-    if image_exists(image_name).await? {
+    if image_exists(backend, image_name).await? {
         debug!("✅ Layer {} already exists, skipping pre-fetch", layer);
         return Ok(());
     }
@@ -211,7 +221,7 @@ async fn ensure_layer_exists(layer: &str, base_image: Option<&str>) -> Result<()
     info!("📥 Pre-fetching layer: {}", layer);
 
     // Build the layer (non-verbose to avoid cluttering output)
-    build_shared_layer(layer, base_image, false, false).await?;
+    build_shared_layer(backend, layer, base_image, false, false).await?;
 
     info!("✓ Pre-fetched layer: {}", layer);
     Ok(())
@@ -225,7 +235,9 @@ mod tests {
     fn test_parallel_build_empty() {
         let rt = tokio::runtime::Runtime::new().unwrap();
         rt.block_on(async {
-            let result = build_language_layers_parallel("base", &[], &[], false, false).await;
+            let result =
+                build_language_layers_parallel(BackendType::Podman, "base", &[], &[], false, false)
+                    .await;
             assert!(result.is_ok());
             assert!(result.unwrap().is_empty());
         });
@@ -233,7 +245,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_ensure_layer_exists_unknown() {
-        let result = ensure_layer_exists("unknown-layer", None).await;
+        let result = ensure_layer_exists(BackendType::Podman, "unknown-layer", None).await;
         assert!(result.is_ok()); // Should not error on unknown layers
     }
 }
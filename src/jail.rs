@@ -1,42 +1,186 @@
-use crate::backend::{create_backend, JailBackend};
+use crate::backend::{
+    create_backend, ExecOptions, ExecResult, ExecStream, JailBackend, JailStats, LogOptions,
+};
 use crate::config::JailConfig;
-use crate::error::Result;
+use crate::cow::CowDirs;
+use crate::error::{JailError, Result};
+use crate::state::State;
+use std::path::Path;
 use std::sync::Arc;
+use tracing::{debug, info};
+
+/// Pick a free host port for each mapping with host port 0
+///
+/// The port is released before the jail binds it, another process could take it in between.
+fn allocate_host_ports(config: &mut JailConfig) -> Result<()> {
+    for port in config
+        .port_mappings
+        .iter_mut()
+        .filter(|port| port.host_port == 0)
+    {
+        let ip: std::net::IpAddr = port
+            .host_ip
+            .parse()
+            .map_err(|_| JailError::Config(format!("Invalid host address '{}'", port.host_ip)))?;
+        let address = if port.protocol == "udp" {
+            std::net::UdpSocket::bind((ip, 0))?.local_addr()?
+        } else {
+            std::net::TcpListener::bind((ip, 0))?.local_addr()?
+        };
+        port.host_port = address.port();
+        info!(
+            "Allocated host port {} for container port {}/{}",
+            port.host_port, port.container_port, port.protocol
+        );
+    }
+    Ok(())
+}
 
 /// Jail manager that handles jail lifecycle
 pub struct JailManager {
     config: JailConfig,
-    backend: Arc<Box<dyn JailBackend>>,
+    backend: Arc<dyn JailBackend>,
 }
 
 impl JailManager {
     pub fn new(config: JailConfig) -> Self {
-        let backend = Arc::new(create_backend(&config));
+        let backend = Arc::from(create_backend(&config));
+        Self { config, backend }
+    }
+
+    /// Create a manager driving an existing backend instead of the configured one
+    pub fn with_backend(config: JailConfig, backend: Arc<dyn JailBackend>) -> Self {
         Self { config, backend }
     }
 
+    /// Backend shared with jails derived from this one
+    pub fn backend(&self) -> Arc<dyn JailBackend> {
+        Arc::clone(&self.backend)
+    }
+
     /// Create a new jail
-    pub async fn create(&self) -> Result<()> {
+    ///
+    /// Host ports given as 0 are replaced by free ones, so the recorded mapping is the actual one.
+    pub async fn create(&mut self) -> Result<()> {
+        allocate_host_ports(&mut self.config)?;
         self.backend.create(&self.config).await
     }
 
     /// Remove the jail
     pub async fn remove(&self, remove_volume: bool) -> Result<()> {
-        self.backend.remove(&self.config.name, remove_volume).await
+        self.backend
+            .remove(&self.config.name, remove_volume)
+            .await?;
+        State::forget_jail(&self.config.name);
+        // Pending copy-on-write changes go with the volumes
+        if remove_volume {
+            if let Err(e) = CowDirs::for_jail(&self.config.name).and_then(|dirs| dirs.remove()) {
+                debug!(
+                    "Could not remove workspace changes of {}: {}",
+                    self.config.name, e
+                );
+            }
+        }
+        Ok(())
     }
 
     /// Execute a command in the jail
     pub async fn exec(&self, command: &[String], interactive: bool) -> Result<String> {
+        self.exec_with(command, &ExecOptions::interactive(interactive))
+            .await?
+            .into_output()
+    }
+
+    /// Execute a command in the jail with explicit user, workdir, env and terminal options
+    ///
+    /// Activity is recorded when the command starts and ends, for idle reaping.
+    pub async fn exec_with(&self, command: &[String], options: &ExecOptions) -> Result<ExecResult> {
+        State::record_activity(&self.config.name);
+        let result = self
+            .backend
+            .exec_with(&self.config.name, command, options)
+            .await;
+        State::record_activity(&self.config.name);
+        result
+    }
+
+    /// Execute a command in the jail, yielding its output lines as they arrive
+    pub async fn exec_stream(
+        &self,
+        command: &[String],
+        options: &ExecOptions,
+    ) -> Result<ExecStream> {
+        State::record_activity(&self.config.name);
         self.backend
-            .exec(&self.config.name, command, interactive)
+            .exec_stream(&self.config.name, command, options)
             .await
     }
 
+    /// Command line running a command in the jail, for spawning it in a terminal
+    pub fn exec_argv(&self, command: &[String], options: &ExecOptions) -> Result<Vec<String>> {
+        State::record_activity(&self.config.name);
+        self.backend.exec_argv(&self.config.name, command, options)
+    }
+
     /// Check if jail exists
     pub async fn exists(&self) -> Result<bool> {
         self.backend.exists(&self.config.name).await
     }
 
+    /// Check if the jail is running
+    pub async fn is_running(&self) -> Result<bool> {
+        self.backend.is_running(&self.config.name).await
+    }
+
+    /// Stop the jail, keeping it for later use
+    pub async fn stop(&self) -> Result<()> {
+        self.backend.stop(&self.config.name).await
+    }
+
+    /// Restart the jail
+    pub async fn restart(&self) -> Result<()> {
+        self.backend.restart(&self.config.name).await
+    }
+
+    /// Freeze all processes of the jail
+    pub async fn pause(&self) -> Result<()> {
+        self.backend.pause(&self.config.name).await
+    }
+
+    /// Resume a paused jail
+    pub async fn unpause(&self) -> Result<()> {
+        self.backend.unpause(&self.config.name).await
+    }
+
+    /// Print the jail output to stdout
+    pub async fn logs(&self, options: &LogOptions) -> Result<()> {
+        self.backend.logs(&self.config.name, options).await
+    }
+
+    /// Current resource usage of the jail
+    pub async fn stats(&self) -> Result<JailStats> {
+        self.backend.stats(&self.config.name).await
+    }
+
+    /// Host ports published by the running jail
+    pub async fn port_mappings(&self) -> Result<Vec<crate::config::PortMapping>> {
+        self.backend.port_mappings(&self.config.name).await
+    }
+
+    /// Copy a host file or directory into the jail
+    pub async fn copy_in(&self, source: &Path, target: &Path) -> Result<()> {
+        self.backend
+            .copy_in(&self.config.name, source, target)
+            .await
+    }
+
+    /// Copy a file or directory out of the jail to the host
+    pub async fn copy_out(&self, source: &Path, target: &Path) -> Result<()> {
+        self.backend
+            .copy_out(&self.config.name, source, target)
+            .await
+    }
+
     /// Get jail configuration
     pub fn config(&self) -> &JailConfig {
         &self.config
@@ -75,16 +219,36 @@ impl JailBuilder {
     }
 
     pub fn bind_mount(
-        mut self,
+        self,
+        source: impl Into<std::path::PathBuf>,
+        target: impl Into<std::path::PathBuf>,
+        readonly: bool,
+    ) -> Self {
+        self.mount(crate::config::BindMount {
+            source: source.into(),
+            target: target.into(),
+            readonly,
+            workspace: false,
+        })
+    }
+
+    /// Bind mount the project workspace
+    pub fn workspace_mount(
+        self,
         source: impl Into<std::path::PathBuf>,
         target: impl Into<std::path::PathBuf>,
         readonly: bool,
     ) -> Self {
-        self.config.bind_mounts.push(crate::config::BindMount {
+        self.mount(crate::config::BindMount {
             source: source.into(),
             target: target.into(),
             readonly,
-        });
+            workspace: true,
+        })
+    }
+
+    pub fn mount(mut self, mount: crate::config::BindMount) -> Self {
+        self.config.bind_mounts.push(mount);
         self
     }
 
@@ -121,17 +285,8 @@ impl JailBuilder {
         self
     }
 
-    pub fn port_mapping(
-        mut self,
-        host_port: u16,
-        container_port: u16,
-        protocol: impl Into<String>,
-    ) -> Self {
-        self.config.port_mappings.push(crate::config::PortMapping {
-            host_port,
-            container_port,
-            protocol: protocol.into(),
-        });
+    pub fn port_mapping(mut self, port_mapping: crate::config::PortMapping) -> Self {
+        self.config.port_mappings.push(port_mapping);
         self
     }
 
@@ -145,6 +300,49 @@ impl JailBuilder {
         self
     }
 
+    /// Replace all resource limits
+    pub fn limits(mut self, limits: crate::config::ResourceLimits) -> Self {
+        self.config.limits = limits;
+        self
+    }
+
+    pub fn pids_limit(mut self, pids: u32) -> Self {
+        self.config.limits.pids_limit = Some(pids);
+        self
+    }
+
+    pub fn memory_swap_limit(mut self, mb: u64) -> Self {
+        self.config.limits.memory_swap_mb = Some(mb);
+        self
+    }
+
+    pub fn shm_size(mut self, mb: u64) -> Self {
+        self.config.limits.shm_size_mb = Some(mb);
+        self
+    }
+
+    pub fn blkio_weight(mut self, weight: u16) -> Self {
+        self.config.limits.blkio_weight = Some(weight);
+        self
+    }
+
+    /// Set a ulimit, replacing any previous value for the same resource
+    pub fn ulimit(mut self, ulimit: crate::config::Ulimit) -> Self {
+        let ulimits = &mut self.config.limits.ulimits;
+        ulimits.retain(|existing| existing.name != ulimit.name);
+        ulimits.push(ulimit);
+        self
+    }
+
+    /// Mount a size-capped tmpfs, replacing any previous tmpfs on the same target
+    pub fn tmpfs(mut self, target: impl Into<std::path::PathBuf>, size_mb: u64) -> Self {
+        let target = target.into();
+        let tmpfs = &mut self.config.limits.tmpfs;
+        tmpfs.retain(|existing| existing.target != target);
+        tmpfs.push(crate::config::TmpfsMount { target, size_mb });
+        self
+    }
+
     pub fn upgrade(mut self, upgrade: bool) -> Self {
         self.config.upgrade = upgrade;
         self
@@ -184,9 +382,34 @@ impl JailBuilder {
         self
     }
 
+    pub fn hardened(mut self, hardened: bool) -> Self {
+        self.config.hardened = hardened;
+        self
+    }
+
+    pub fn seccomp(mut self, seccomp: bool) -> Self {
+        self.config.seccomp = seccomp;
+        self
+    }
+
+    pub fn cow(mut self, cow: bool) -> Self {
+        self.config.cow = cow;
+        self
+    }
+
+    pub fn shared_home(mut self, shared_home: bool) -> Self {
+        self.config.shared_home = shared_home;
+        self
+    }
+
     pub fn build(self) -> JailManager {
         JailManager::new(self.config)
     }
+
+    /// Build a manager on top of an existing backend (e.g. one shared with another jail)
+    pub fn build_with_backend(self, backend: Arc<dyn JailBackend>) -> JailManager {
+        JailManager::with_backend(self.config, backend)
+    }
 }
 
 #[cfg(test)]
@@ -224,4 +447,58 @@ mod tests {
         let config = manager.config();
         assert!(!config.no_nix);
     }
+
+    #[tokio::test]
+    async fn test_jail_manager_with_fake_backend() {
+        use crate::backend::fake::{FakeBackend, FakeCall};
+
+        let fake = FakeBackend::new();
+        let mut jail = JailBuilder::new("test-jail")
+            .base_image("alpine:latest")
+            .build_with_backend(Arc::new(fake.clone()));
+
+        assert!(!jail.exists().await.unwrap());
+        jail.create().await.unwrap();
+        assert!(jail.exists().await.unwrap());
+        assert_eq!(jail.inspect().await.unwrap().base_image, "alpine:latest");
+
+        // Jails built from the shared backend see the same containers
+        let other = JailBuilder::new("test-jail").build_with_backend(jail.backend());
+        other.remove(false).await.unwrap();
+        assert!(!jail.exists().await.unwrap());
+
+        assert_eq!(
+            fake.calls(),
+            vec![
+                FakeCall::Create("test-jail".to_string()),
+                FakeCall::Remove {
+                    name: "test-jail".to_string(),
+                    remove_volume: false
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_create_allocates_free_host_ports() {
+        use crate::backend::fake::FakeBackend;
+        use crate::cli::Commands;
+
+        let fake = FakeBackend::new();
+        let mut jail = JailBuilder::new("test-jail")
+            .port_mapping(Commands::parse_port("0:3000").unwrap())
+            .port_mapping(Commands::parse_port("8080:80").unwrap())
+            .build_with_backend(Arc::new(fake.clone()));
+        jail.create().await.unwrap();
+
+        let recorded = jail.inspect().await.unwrap().port_mappings;
+        assert_ne!(recorded[0].host_port, 0);
+        assert_eq!(recorded[0].container_port, 3000);
+        assert_eq!(recorded[0].host_ip, "127.0.0.1");
+        assert_eq!(recorded[1].host_port, 8080);
+        assert_eq!(
+            jail.config().port_mappings[0].host_port,
+            recorded[0].host_port
+        );
+    }
 }
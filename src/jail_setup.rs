@@ -93,6 +93,49 @@ pub fn setup_default_environment(builder: JailBuilder) -> JailBuilder {
     builder
 }
 
+/// Process limit of agent jails unless overridden with --pids-limit
+pub const DEFAULT_AGENT_PIDS_LIMIT: u32 = 4096;
+
+/// Size in MB of the tmpfs mounted on /tmp in agent jails unless overridden with --tmpfs
+pub const DEFAULT_AGENT_TMP_SIZE_MB: u64 = 2048;
+
+/// Secure defaults for agent jails: cap the number of processes and the size of /tmp
+pub fn agent_limit_defaults(builder: JailBuilder) -> JailBuilder {
+    builder
+        .pids_limit(DEFAULT_AGENT_PIDS_LIMIT)
+        .tmpfs("/tmp", DEFAULT_AGENT_TMP_SIZE_MB)
+}
+
+/// Apply the extended resource limits given on the command line
+pub fn apply_resource_limits(
+    mut builder: JailBuilder,
+    options: &crate::cli::ResourceLimitOptions,
+) -> Result<JailBuilder> {
+    use crate::cli::Commands;
+    use crate::error::JailError;
+
+    if let Some(pids) = options.pids_limit {
+        builder = builder.pids_limit(pids);
+    }
+    if let Some(swap) = options.memory_swap {
+        builder = builder.memory_swap_limit(swap);
+    }
+    if let Some(shm) = options.shm_size {
+        builder = builder.shm_size(shm);
+    }
+    if let Some(weight) = options.blkio_weight {
+        builder = builder.blkio_weight(weight);
+    }
+    for ulimit in &options.ulimit {
+        builder = builder.ulimit(Commands::parse_ulimit(ulimit).map_err(JailError::Config)?);
+    }
+    for tmpfs in &options.tmpfs {
+        let tmpfs = Commands::parse_tmpfs(tmpfs).map_err(JailError::Config)?;
+        builder = builder.tmpfs(tmpfs.target, tmpfs.size_mb);
+    }
+    Ok(builder)
+}
+
 /// Agent configuration flags
 pub struct AgentConfigFlags {
     pub claude_dir: bool,
@@ -274,6 +317,21 @@ pub fn get_jail_ai_config_dir() -> Result<std::path::PathBuf> {
     Ok(base_dir.join("jail-ai"))
 }
 
+/// Get the jail-ai data directory path (XDG_DATA_HOME or ~/.local/share/jail-ai)
+pub fn get_jail_ai_data_dir() -> Result<std::path::PathBuf> {
+    let base_dir = if let Ok(data_home) = std::env::var("XDG_DATA_HOME") {
+        std::path::PathBuf::from(data_home)
+    } else if let Ok(home) = std::env::var("HOME") {
+        std::path::PathBuf::from(home).join(".local").join("share")
+    } else {
+        return Err(crate::error::JailError::Config(
+            "Could not determine data directory (HOME not set)".to_string(),
+        ));
+    };
+
+    Ok(base_dir.join("jail-ai"))
+}
+
 /// Recursively copy a directory
 pub fn copy_dir_recursive(src: &Path, dst: &Path) -> Result<()> {
     use tracing::debug;
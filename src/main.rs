@@ -3,8 +3,14 @@ mod agents;
 mod backend;
 mod cli;
 mod config;
+mod config_schema;
+mod config_show;
+mod cow;
+mod drift;
 mod ebpf;
+mod ephemeral;
 mod error;
+mod gc;
 mod git_gpg;
 mod image;
 mod image_layers;
@@ -12,15 +18,22 @@ mod image_parallel;
 mod jail;
 mod jail_setup;
 mod project_detection;
+mod reap;
+mod session;
+mod settings;
+mod snapshot;
+mod socket_proxy;
 mod state;
 mod strings;
 mod tui;
+mod volume;
 mod worktree;
 
 use clap::Parser;
 use cli::{Cli, Commands};
 use config::JailConfig;
 use jail::JailBuilder;
+use std::sync::Arc;
 use tracing::{error, info, warn};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
@@ -111,19 +124,41 @@ async fn main() {
         .init();
 
     if let Err(e) = run(cli.command, cli.verbose).await {
-        error!("Error: {}", e);
-        std::process::exit(1);
+        match &e {
+            // The command ran attached to the terminal and already reported its failure
+            error::JailError::CommandExit { stderr, .. } if stderr.is_empty() => {}
+            _ => {
+                error!("Error: {}", e);
+                if let Some(hint) = e.hint() {
+                    eprintln!("💡 Hint: {hint}");
+                }
+            }
+        }
+        std::process::exit(e.exit_code());
     }
 }
 
 async fn run(command: Option<Commands>, verbose: bool) -> error::Result<()> {
+    // Jails found without an explicit backend are looked up with the one of the command
+    if let Some(backend) = command.as_ref().and_then(Commands::backend) {
+        Commands::parse_backend(backend)
+            .map_err(error::JailError::Config)?
+            .select();
+    }
+
     // 🔮 Phase 4: Background pre-fetching of common layers (opt-in via JAIL_AI_PREFETCH=1)
     // This spawns a background task that builds commonly needed layers while the user
     // continues with their work, reducing perceived latency for subsequent operations
     let cwd = std::env::current_dir()?;
-    let _prefetch_handle = image_parallel::prefetch_common_layers(&cwd);
+    let _prefetch_handle =
+        image_parallel::prefetch_common_layers(config::BackendType::detect(), &cwd);
     // Note: We don't await this handle - it runs in the background
 
+    // Stop jails left idle before doing anything else (opt-in via JAIL_AI_REAP_IDLE=2h)
+    if !matches!(command, Some(Commands::Reap { .. })) {
+        auto_reap_idle().await;
+    }
+
     match command {
         None => {
             // Default behavior: auto-init and exec based on workspace (git root if available)
@@ -139,7 +174,7 @@ async fn run(command: Option<Commands>, verbose: bool) -> error::Result<()> {
                 let jail_name = format!("{base_name}__default");
 
                 info!("Creating jail '{}'...", jail_name);
-                let jail = create_default_jail(&jail_name, &workspace_dir, verbose).await?;
+                let mut jail = create_default_jail(&jail_name, &workspace_dir, verbose).await?;
                 jail.create().await?;
                 info!("Jail '{}' created successfully", jail_name);
 
@@ -156,11 +191,7 @@ async fn run(command: Option<Commands>, verbose: bool) -> error::Result<()> {
 
             // Exec into jail with interactive shell
             info!("Executing interactive shell in jail '{}'...", jail_name);
-            let jail = JailBuilder::new(jail_name.clone())
-                .backend(config::BackendType::detect())
-                .verbose(verbose)
-                .build();
-
+            let jail = existing_jail(Some(jail_name)).await?;
             jail.exec(&["/usr/bin/zsh".to_string()], true).await?;
         }
         Some(command) => match command {
@@ -175,6 +206,7 @@ async fn run(command: Option<Commands>, verbose: bool) -> error::Result<()> {
                 host_network,
                 memory,
                 cpu,
+                limits,
                 config,
                 no_workspace,
                 workspace_path,
@@ -195,19 +227,105 @@ async fn run(command: Option<Commands>, verbose: bool) -> error::Result<()> {
                 no_nix,
                 no_block_host,
                 podman,
+                hardened,
+                seccomp,
+                cow,
+                profile,
             } => {
-                let jail = if let Some(config_path) = config {
-                    // Load from config file
-                    let config_str = tokio::fs::read_to_string(&config_path).await?;
-                    let config: JailConfig = serde_json::from_str(&config_str)?;
-                    jail::JailManager::new(config)
+                let (mut jail, git_gpg) = if let Some(config_path) = config {
+                    // Load from config file (older schema versions are migrated)
+                    let config = config_schema::load(&config_path)?;
+                    (jail::JailManager::new(config), git_gpg)
                 } else {
-                    // Build from CLI args
-                    let backend_type = if let Some(backend_str) = backend {
-                        Commands::parse_backend(&backend_str).map_err(error::JailError::Config)?
-                    } else {
-                        config::BackendType::detect()
+                    // Layer configuration files (global defaults, profile, project) under CLI args
+                    let mut params = agent_commands::AgentCommandParams {
+                        backend,
+                        image,
+                        mount,
+                        port,
+                        env,
+                        no_network,
+                        host_network,
+                        memory,
+                        cpu,
+                        limits,
+                        no_workspace,
+                        workspace_path,
+                        claude_dir,
+                        claude_code_router_dir,
+                        copilot_dir,
+                        cursor_dir,
+                        gemini_dir,
+                        coderabbit_dir,
+                        codex_dir,
+                        jules_dir,
+                        pi_dir,
+                        agent_configs,
+                        git_gpg,
+                        upgrade,
+                        force_layers: layers,
+                        cloud: false,
+                        shell: false,
+                        isolated,
+                        verbose,
+                        auth: false,
+                        no_nix,
+                        no_block_host,
+                        podman,
+                        hardened,
+                        seccomp,
+                        cow,
+                        ephemeral: false,
+                        session: None,
+                        shared_home: false,
+                        auto_recreate: false,
+                        tui: false,
+                        profile,
+                        args: Vec::new(),
                     };
+                    settings::resolve_settings(
+                        params.profile.as_deref(),
+                        agent_commands::get_git_root().as_deref(),
+                    )?
+                    .apply_to_params(&mut params);
+                    let agent_commands::AgentCommandParams {
+                        backend,
+                        image,
+                        mount,
+                        port,
+                        env,
+                        no_network,
+                        host_network,
+                        memory,
+                        cpu,
+                        limits,
+                        no_workspace,
+                        workspace_path,
+                        claude_dir,
+                        claude_code_router_dir,
+                        copilot_dir,
+                        cursor_dir,
+                        gemini_dir,
+                        coderabbit_dir,
+                        codex_dir,
+                        jules_dir,
+                        pi_dir,
+                        agent_configs,
+                        git_gpg,
+                        upgrade,
+                        force_layers: layers,
+                        isolated,
+                        no_nix,
+                        no_block_host,
+                        podman,
+                        hardened,
+                        seccomp,
+                        cow,
+                        ..
+                    } = params;
+
+                    // Build from CLI args
+                    let backend_type = agent_commands::resolve_backend_type(backend.as_deref())?;
 
                     // Auto-generate name from current directory if not provided
                     let jail_name = if let Some(name) = name {
@@ -253,7 +371,7 @@ async fn run(command: Option<Commands>, verbose: bool) -> error::Result<()> {
                             workspace_dir.display(),
                             workspace_path
                         );
-                        builder = builder.bind_mount(workspace_dir, workspace_path, false);
+                        builder = builder.workspace_mount(workspace_dir, workspace_path, false);
                     }
 
                     // Mount agent config directories
@@ -295,11 +413,7 @@ async fn run(command: Option<Commands>, verbose: bool) -> error::Result<()> {
                     for port_str in port {
                         let port_mapping =
                             Commands::parse_port(&port_str).map_err(error::JailError::Config)?;
-                        builder = builder.port_mapping(
-                            port_mapping.host_port,
-                            port_mapping.container_port,
-                            &port_mapping.protocol,
-                        );
+                        builder = builder.port_mapping(port_mapping);
                     }
 
                     // Parse environment variables
@@ -316,6 +430,7 @@ async fn run(command: Option<Commands>, verbose: bool) -> error::Result<()> {
                     if let Some(cpu_quota) = cpu {
                         builder = builder.cpu_quota(cpu_quota);
                     }
+                    builder = jail_setup::apply_resource_limits(builder, &limits)?;
 
                     // Set upgrade flag
                     builder = builder.upgrade(upgrade);
@@ -338,7 +453,9 @@ async fn run(command: Option<Commands>, verbose: bool) -> error::Result<()> {
                     // Set podman_socket flag for Podman-in-Podman support
                     builder = builder.podman_socket(podman);
 
-                    builder.build()
+                    builder = builder.hardened(hardened).seccomp(seccomp).cow(cow);
+
+                    (builder.build(), git_gpg)
                 };
 
                 jail.create().await?;
@@ -359,7 +476,8 @@ async fn run(command: Option<Commands>, verbose: bool) -> error::Result<()> {
                 force,
                 volume,
             } => {
-                let jail_name = resolve_jail_name(name).await?;
+                let jail = existing_jail(name).await?;
+                let jail_name = jail.config().name.clone();
 
                 if !force {
                     use std::io::{self, BufRead, Write};
@@ -374,11 +492,6 @@ async fn run(command: Option<Commands>, verbose: bool) -> error::Result<()> {
                     }
                 }
 
-                let config = JailConfig {
-                    name: jail_name.clone(),
-                    ..Default::default()
-                };
-                let jail = jail::JailManager::new(config);
                 jail.remove(volume).await?;
 
                 info!("Jail removed: {}", jail_name);
@@ -386,40 +499,122 @@ async fn run(command: Option<Commands>, verbose: bool) -> error::Result<()> {
 
             Commands::Status { name } => {
                 let jail_name = resolve_jail_name(name).await?;
-                let config = JailConfig {
-                    name: jail_name.clone(),
-                    ..Default::default()
-                };
-                let jail = jail::JailManager::new(config);
-                let exists = jail.exists().await?;
-                if exists {
+                if let Some(jail) = find_jail(&jail_name).await? {
                     println!("✓ Jail '{}' exists", jail_name);
+                    if jail.is_running().await? {
+                        println!("  State:    running");
+                        match jail.stats().await {
+                            Ok(stats) => {
+                                println!("  CPU:      {}", stats.cpu);
+                                println!("  Memory:   {}", stats.memory);
+                                println!("  Net I/O:  {}", stats.net_io);
+                                println!("  Block I/O: {}", stats.block_io);
+                                println!("  PIDs:     {}", stats.pids);
+                            }
+                            Err(e) => warn!("Resource usage unavailable: {}", e),
+                        }
+                    } else {
+                        println!("  State:    stopped");
+                    }
                 } else {
                     println!("✗ Jail '{}' does not exist", jail_name);
                 }
             }
 
-            Commands::Save { name, output } => {
-                let jail_name = resolve_jail_name(name).await?;
+            Commands::Stop { name } => {
+                let jail = existing_jail(name).await?;
+                jail.stop().await?;
+                println!("✓ Jail '{}' stopped", jail.config().name);
+            }
 
-                // Create a temporary jail manager to inspect the actual configuration
-                let temp_config = JailConfig {
-                    name: jail_name.clone(),
-                    ..Default::default()
-                };
-                let jail = jail::JailManager::new(temp_config);
+            Commands::Restart { name } => {
+                let jail = existing_jail(name).await?;
+                jail.restart().await?;
+                println!("✓ Jail '{}' restarted", jail.config().name);
+            }
 
-                // Check if jail exists
-                if !jail.exists().await? {
-                    return Err(error::JailError::NotFound(format!(
-                        "Jail '{jail_name}' does not exist"
-                    )));
+            Commands::Pause { name } => {
+                let jail = existing_jail(name).await?;
+                jail.pause().await?;
+                println!("✓ Jail '{}' paused", jail.config().name);
+            }
+
+            Commands::Unpause { name } => {
+                let jail = existing_jail(name).await?;
+                jail.unpause().await?;
+                println!("✓ Jail '{}' resumed", jail.config().name);
+            }
+
+            Commands::Port { name } => {
+                let jail = existing_jail(name).await?;
+                if !jail.is_running().await? {
+                    println!("Jail '{}' is not running", jail.config().name);
+                    return Ok(());
+                }
+                let ports = jail.port_mappings().await?;
+                if ports.is_empty() {
+                    println!("Jail '{}' publishes no ports", jail.config().name);
+                }
+                for port in &ports {
+                    for (host_port, container_port) in port.ports() {
+                        let host = match port.host_ip.parse::<std::net::IpAddr>() {
+                            Ok(ip) => std::net::SocketAddr::new(ip, host_port).to_string(),
+                            Err(_) => format!("{}:{}", port.host_ip, host_port),
+                        };
+                        println!("{}/{} -> {}", container_port, port.protocol, host);
+                    }
                 }
+            }
+
+            Commands::Logs { name, follow, tail } => {
+                let jail = existing_jail(name).await?;
+                jail.logs(&backend::LogOptions { follow, tail }).await?;
+            }
+
+            Commands::Cp { source, target } => {
+                use cli::CopyPath;
+
+                match (
+                    Commands::parse_copy_path(&source),
+                    Commands::parse_copy_path(&target),
+                ) {
+                    (CopyPath::Host(source), CopyPath::Jail { name, path }) => {
+                        let jail = existing_jail(name).await?;
+                        jail.copy_in(&source, &path).await?;
+                        println!(
+                            "✓ Copied {} to {}:{}",
+                            source.display(),
+                            jail.config().name,
+                            path.display()
+                        );
+                    }
+                    (CopyPath::Jail { name, path }, CopyPath::Host(target)) => {
+                        let jail = existing_jail(name).await?;
+                        jail.copy_out(&path, &target).await?;
+                        println!(
+                            "✓ Copied {}:{} to {}",
+                            jail.config().name,
+                            path.display(),
+                            target.display()
+                        );
+                    }
+                    _ => {
+                        return Err(error::JailError::Config(
+                            "Exactly one of SOURCE and TARGET must be a jail path (JAIL:/path or :/path)"
+                                .to_string(),
+                        ));
+                    }
+                }
+            }
+
+            Commands::Save { name, output } => {
+                let jail = existing_jail(name).await?;
+                let jail_name = jail.config().name.clone();
 
                 // Inspect the jail to get its actual configuration
                 let config = jail.inspect().await?;
 
-                let json = serde_json::to_string_pretty(&config)?;
+                let json = config_schema::to_json_string(&config)?;
                 tokio::fs::write(&output, json).await?;
                 println!(
                     "✓ Configuration for jail '{}' saved to: {}",
@@ -465,13 +660,16 @@ async fn run(command: Option<Commands>, verbose: bool) -> error::Result<()> {
                 run_agent_command(agents::Agent::Pi, common, args, verbose).await?;
             }
 
-            Commands::List { current, backend } => {
+            Commands::List {
+                current,
+                backend,
+                json,
+                agent,
+                running,
+                stale,
+            } => {
                 // Determine backend to use
-                let backend_type = if let Some(backend_str) = backend {
-                    Commands::parse_backend(&backend_str).map_err(error::JailError::Config)?
-                } else {
-                    config::BackendType::detect()
-                };
+                let backend_type = agent_commands::resolve_backend_type(backend.as_deref())?;
 
                 let temp_config = JailConfig {
                     name: "temp".to_string(),
@@ -480,23 +678,40 @@ async fn run(command: Option<Commands>, verbose: bool) -> error::Result<()> {
                 };
                 let backend = backend::create_backend(&temp_config);
 
-                // Get all jails
-                let all_jails = backend.list_all().await?;
+                // Get all jails in one batched query
+                let mut jails = backend.list_summaries().await?;
 
                 // Filter by current directory if requested
-                let jails = if current {
+                if current {
                     let cwd = std::env::current_dir()?;
                     let workspace_dir = agent_commands::get_git_root().unwrap_or(cwd);
                     let base_name = cli::Commands::generate_jail_name(&workspace_dir);
-                    all_jails
-                        .into_iter()
-                        .filter(|name| name.starts_with(&base_name))
-                        .collect::<Vec<_>>()
-                } else {
-                    all_jails
-                };
-
-                if jails.is_empty() {
+                    jails.retain(|jail| jail.name.starts_with(&base_name));
+                }
+                // Accept agent commands and aliases (`claude`, `ccr`) as well as display names
+                let agent = agent.map(|agent| {
+                    agents::Agent::from_str(&agent)
+                        .map(|known| known.display_name().to_string())
+                        .unwrap_or(agent)
+                });
+                jails.retain(|jail| {
+                    agent
+                        .as_deref()
+                        .is_none_or(|agent| jail.agent.eq_ignore_ascii_case(agent))
+                        && (!running || jail.running)
+                        && (!stale || jail.outdated)
+                });
+                // Sessions follow the main jail of their agent
+                jails.sort_by_cached_key(|jail| {
+                    (
+                        session::main_jail_name(&jail.name),
+                        session::session_of(&jail.name).map(str::to_string),
+                    )
+                });
+
+                if json {
+                    println!("{}", serde_json::to_string_pretty(&jails)?);
+                } else if jails.is_empty() {
                     if current {
                         println!("No jails found for current directory");
                     } else {
@@ -504,25 +719,7 @@ async fn run(command: Option<Commands>, verbose: bool) -> error::Result<()> {
                     }
                 } else {
                     println!("Jails (backend: {backend_type:?}):");
-                    for jail_name in &jails {
-                        // Extract agent name from jail name
-                        let agent_suffix = agent_commands::extract_agent_name(jail_name);
-
-                        // Check if jail is running
-                        let config = JailConfig {
-                            name: jail_name.clone(),
-                            backend: backend_type,
-                            ..Default::default()
-                        };
-                        let jail = jail::JailManager::new(config);
-                        let status = if jail.exists().await? {
-                            "active"
-                        } else {
-                            "inactive"
-                        };
-
-                        println!("  {jail_name} [{status}] ({agent_suffix})");
-                    }
+                    print_jail_table(&jails);
                     println!("\nTotal: {} jail(s)", jails.len());
                 }
             }
@@ -613,6 +810,162 @@ async fn run(command: Option<Commands>, verbose: bool) -> error::Result<()> {
                 info!("Clean-all operation completed");
             }
 
+            Commands::Reap {
+                idle,
+                backend,
+                dry_run,
+            } => {
+                let threshold =
+                    Commands::parse_duration(&idle).map_err(error::JailError::Config)?;
+                let backend_type = agent_commands::resolve_backend_type(backend.as_deref())?;
+                let reaped = reap_idle_jails(backend_type, threshold, dry_run).await?;
+                if reaped.is_empty() {
+                    println!(
+                        "No jail idle for more than {}",
+                        format_uptime(threshold.as_secs())
+                    );
+                }
+            }
+
+            Commands::Gc {
+                backend,
+                dry_run,
+                force,
+            } => {
+                let backend_type = agent_commands::resolve_backend_type(backend.as_deref())?;
+                let temp_config = JailConfig {
+                    name: "temp".to_string(),
+                    backend: backend_type,
+                    ..Default::default()
+                };
+                let backend = backend::create_backend(&temp_config);
+
+                // Bubblewrap jails run images from the podman (or docker) image store
+                let bubblewrap = config::BackendType::Bubblewrap;
+                let images_in_use =
+                    if backend_type != bubblewrap && bubblewrap.image_engine() == backend_type {
+                        let temp_config = JailConfig {
+                            name: "temp".to_string(),
+                            backend: bubblewrap,
+                            ..Default::default()
+                        };
+                        backend::create_backend(&temp_config)
+                            .list_summaries()
+                            .await
+                            .unwrap_or_default()
+                            .into_iter()
+                            .map(|jail| jail.image)
+                            .collect()
+                    } else {
+                        std::collections::HashSet::new()
+                    };
+
+                let plan = gc::find_garbage(backend.as_ref(), &images_in_use).await?;
+                if plan.is_empty() {
+                    println!("Nothing to clean up");
+                    return Ok(());
+                }
+                print_gc_plan(&plan);
+                if dry_run {
+                    return Ok(());
+                }
+
+                if !force {
+                    use std::io::{self, BufRead, Write};
+                    print!("Remove these items? [y/N] ");
+                    io::stdout().flush()?;
+                    let mut line = String::new();
+                    io::stdin().lock().read_line(&mut line)?;
+                    if !line.trim().eq_ignore_ascii_case("y") {
+                        info!("Aborted");
+                        return Ok(());
+                    }
+                }
+
+                let failures = gc::remove_garbage(std::sync::Arc::from(backend), &plan).await;
+                if failures > 0 {
+                    return Err(error::JailError::Backend(format!(
+                        "{failures} item(s) could not be removed"
+                    )));
+                }
+            }
+
+            Commands::Config { command } => match command {
+                cli::ConfigCommands::Show {
+                    effective,
+                    agent,
+                    json,
+                    common,
+                } => {
+                    if effective {
+                        let agent = agents::Agent::from_str(&agent).ok_or_else(|| {
+                            error::JailError::Config(format!("Unknown agent '{agent}'"))
+                        })?;
+                        let effective = config_show::compute_effective_config(
+                            agent.command_name(),
+                            agent_params(*common, Vec::new(), verbose),
+                        )
+                        .await?;
+                        config_show::print_effective_config(&effective, json)?;
+                    } else {
+                        config_show::show_file_settings(common.profile.as_deref(), json)?;
+                    }
+                }
+                cli::ConfigCommands::Schema => {
+                    println!(
+                        "{}",
+                        serde_json::to_string_pretty(&config_schema::json_schema())?
+                    );
+                }
+                cli::ConfigCommands::Validate { file } => {
+                    let content = tokio::fs::read_to_string(&file).await?;
+                    let problems = config_schema::validate(&content);
+                    if problems.is_empty() {
+                        println!("✓ {} is valid", file.display());
+                    } else {
+                        println!("✗ {} has {} problem(s):", file.display(), problems.len());
+                        for problem in &problems {
+                            println!("  • {}", problem);
+                        }
+                        return Err(error::JailError::Config(format!(
+                            "Invalid configuration file: {}",
+                            file.display()
+                        )));
+                    }
+                }
+            },
+
+            Commands::Volume { command } => run_volume_command(command).await?,
+
+            Commands::Snapshot { command } => run_snapshot_command(command).await?,
+
+            Commands::Changes { name, diff } => {
+                let jail = existing_jail(name).await?;
+                let config = jail.inspect().await?;
+                let workspace = cow::cow_workspace(&config)?;
+                let dirs = cow::CowDirs::for_jail(&config.name)?;
+                let changes = cow::list_changes(&dirs.upper, &workspace)?;
+                if changes.is_empty() {
+                    println!("No changes in {}", workspace.display());
+                } else if diff {
+                    cow::print_diff(&dirs.upper, &workspace, &changes).await?;
+                } else {
+                    for change in &changes {
+                        println!("  {change}");
+                    }
+                }
+            }
+
+            Commands::Apply { name, force } => {
+                run_cow_command(name, force, true).await?;
+            }
+
+            Commands::Discard { name, force } => {
+                run_cow_command(name, force, false).await?;
+            }
+
+            Commands::SocketProxy { jail } => socket_proxy::serve(&jail).await?,
+
             Commands::Upgrade {
                 name,
                 image,
@@ -624,8 +977,8 @@ async fn run(command: Option<Commands>, verbose: bool) -> error::Result<()> {
                     upgrade_all_jails(image, force, verbose).await?;
                 } else {
                     // Upgrade single jail
-                    let jail_name = resolve_jail_name(name).await?;
-                    upgrade_single_jail(&jail_name, image, force, verbose).await?;
+                    let temp_jail = existing_jail(name).await?;
+                    upgrade_single_jail(&temp_jail, image, force, verbose).await?;
                 }
             }
         },
@@ -634,6 +987,59 @@ async fn run(command: Option<Commands>, verbose: bool) -> error::Result<()> {
     Ok(())
 }
 
+/// Convert agent command line options into agent command parameters
+fn agent_params(
+    common: cli::AgentCommandOptions,
+    args: Vec<String>,
+    verbose: bool,
+) -> agent_commands::AgentCommandParams {
+    agent_commands::AgentCommandParams {
+        backend: common.backend,
+        image: common.image,
+        mount: common.mount,
+        port: common.port,
+        env: common.env,
+        no_network: common.no_network,
+        host_network: common.host_network,
+        memory: common.memory,
+        cpu: common.cpu,
+        limits: common.limits,
+        no_workspace: common.no_workspace,
+        workspace_path: common.workspace_path,
+        claude_dir: common.claude_dir,
+        claude_code_router_dir: common.claude_code_router_dir,
+        copilot_dir: common.copilot_dir,
+        cursor_dir: common.cursor_dir,
+        gemini_dir: common.gemini_dir,
+        coderabbit_dir: common.coderabbit_dir,
+        codex_dir: common.codex_dir,
+        jules_dir: common.jules_dir,
+        pi_dir: common.pi_dir,
+        agent_configs: common.agent_configs,
+        git_gpg: common.git_gpg,
+        upgrade: common.upgrade,
+        force_layers: common.layers,
+        cloud: common.cloud,
+        shell: common.shell,
+        isolated: common.isolated,
+        verbose,
+        auth: common.auth,
+        no_nix: common.no_nix,
+        no_block_host: common.no_block_host,
+        podman: common.podman,
+        hardened: common.hardened,
+        seccomp: common.seccomp,
+        cow: common.cow,
+        ephemeral: common.ephemeral,
+        session: common.session,
+        shared_home: common.shared_home,
+        auto_recreate: common.auto_recreate,
+        tui: common.tui,
+        profile: common.profile,
+        args,
+    }
+}
+
 /// Helper function to run an AI agent command
 /// This consolidates the common logic for all agent commands
 async fn run_agent_command(
@@ -660,46 +1066,8 @@ async fn run_agent_command(
         .validate_config_flags(&config_flags)
         .map_err(error::JailError::Config)?;
 
-    agent_commands::run_ai_agent_command(
-        agent.command_name(),
-        agent_commands::AgentCommandParams {
-            backend: common.backend,
-            image: common.image,
-            mount: common.mount,
-            port: common.port,
-            env: common.env,
-            no_network: common.no_network,
-            host_network: common.host_network,
-            memory: common.memory,
-            cpu: common.cpu,
-            no_workspace: common.no_workspace,
-            workspace_path: common.workspace_path,
-            claude_dir: common.claude_dir,
-            claude_code_router_dir: common.claude_code_router_dir,
-            copilot_dir: common.copilot_dir,
-            cursor_dir: common.cursor_dir,
-            gemini_dir: common.gemini_dir,
-            coderabbit_dir: common.coderabbit_dir,
-            codex_dir: common.codex_dir,
-            jules_dir: common.jules_dir,
-            pi_dir: common.pi_dir,
-            agent_configs: common.agent_configs,
-            git_gpg: common.git_gpg,
-            upgrade: common.upgrade,
-            force_layers: common.layers,
-            cloud: common.cloud,
-            shell: common.shell,
-            isolated: common.isolated,
-            verbose,
-            auth: common.auth,
-            no_nix: common.no_nix,
-            no_block_host: common.no_block_host,
-            podman: common.podman,
-            tui: common.tui,
-            args,
-        },
-    )
-    .await
+    agent_commands::run_ai_agent_command(agent.command_name(), agent_params(common, args, verbose))
+        .await
 }
 
 /// Helper function to create a jail with default configuration
@@ -726,7 +1094,7 @@ async fn create_default_jail(
     agent_commands::validate_workspace_directory(&workspace_dir)?;
 
     info!("Auto-mounting {} to /workspace", workspace_dir.display());
-    builder = builder.bind_mount(workspace_dir, "/workspace", false);
+    builder = builder.workspace_mount(workspace_dir, "/workspace", false);
 
     Ok(builder.build())
 }
@@ -758,18 +1126,455 @@ async fn resolve_jail_name(name: Option<String>) -> error::Result<String> {
     }
 }
 
+/// Print jail summaries as an aligned table
+fn print_jail_table(jails: &[backend::JailSummary]) {
+    let rows: Vec<[String; 9]> = jails
+        .iter()
+        .map(|jail| {
+            [
+                jail.name.clone(),
+                jail.agent.clone(),
+                session::session_of(&jail.name).unwrap_or("-").to_string(),
+                if jail.running { "running" } else { "stopped" }.to_string(),
+                if jail.outdated {
+                    format!("{} (outdated)", jail.image)
+                } else {
+                    jail.image.clone()
+                },
+                if jail.host_blocking { "yes" } else { "no" }.to_string(),
+                jail.uptime_secs
+                    .map(format_uptime)
+                    .unwrap_or_else(|| "-".to_string()),
+                jail.home_size
+                    .map(format_size)
+                    .unwrap_or_else(|| "-".to_string()),
+                jail.workspace
+                    .as_ref()
+                    .map(|path| path.display().to_string())
+                    .unwrap_or_else(|| "-".to_string()),
+            ]
+        })
+        .collect();
+
+    let header = [
+        "NAME",
+        "AGENT",
+        "SESSION",
+        "STATE",
+        "IMAGE",
+        "EBPF",
+        "UPTIME",
+        "HOME",
+        "WORKSPACE",
+    ];
+    let mut widths = header.map(str::len);
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let print_row = |cells: Vec<&str>| {
+        let line: Vec<String> = cells
+            .iter()
+            .zip(widths)
+            .map(|(cell, width)| format!("{cell:<width$}"))
+            .collect();
+        println!("  {}", line.join("  ").trim_end());
+    };
+    print_row(header.to_vec());
+    for row in &rows {
+        print_row(row.iter().map(String::as_str).collect());
+    }
+}
+
+/// Stop the jails of a backend idle for at least `threshold`, reporting each of them
+async fn reap_idle_jails(
+    backend_type: config::BackendType,
+    threshold: std::time::Duration,
+    dry_run: bool,
+) -> error::Result<Vec<reap::IdleJail>> {
+    let temp_config = JailConfig {
+        name: "temp".to_string(),
+        backend: backend_type,
+        ..Default::default()
+    };
+    let backend = backend::create_backend(&temp_config);
+    // Without activity records jails are judged by their uptime
+    let activity = state::State::load()
+        .unwrap_or_else(|e| {
+            warn!("{}, ignoring recorded activity", e);
+            state::State::default()
+        })
+        .last_activity;
+
+    let reaped = reap::reap_idle(
+        backend.as_ref(),
+        &activity,
+        threshold,
+        chrono::Utc::now(),
+        dry_run,
+    )
+    .await?;
+    for jail in &reaped {
+        let idle = format_uptime(jail.idle.as_secs());
+        if dry_run {
+            println!("Would stop idle jail {} (idle {idle})", jail.name);
+        } else {
+            println!("✓ Stopped idle jail {} (idle {idle})", jail.name);
+        }
+    }
+    Ok(reaped)
+}
+
+/// Backend handling the volumes of the given (or detected) backend type
+fn volume_backend(backend: Option<&str>) -> error::Result<Box<dyn backend::JailBackend>> {
+    let temp_config = JailConfig {
+        name: "temp".to_string(),
+        backend: agent_commands::resolve_backend_type(backend)?,
+        ..Default::default()
+    };
+    Ok(backend::create_backend(&temp_config))
+}
+
+async fn run_volume_command(command: cli::VolumeCommands) -> error::Result<()> {
+    match command {
+        cli::VolumeCommands::Ls { backend } => {
+            let volumes = volume_backend(backend.as_deref())?.list_volumes().await?;
+            if volumes.is_empty() {
+                println!("No jail volumes found");
+                return Ok(());
+            }
+            let width = volumes.iter().map(|v| v.name.len()).max().unwrap_or(0);
+            println!("  {:<width$}  {:>10}  OWNER", "NAME", "SIZE");
+            for volume in &volumes {
+                println!(
+                    "  {:<width$}  {:>10}  {}",
+                    volume.name,
+                    volume
+                        .size
+                        .map(format_size)
+                        .unwrap_or_else(|| "-".to_string()),
+                    volume::volume_owner(&volume.name).unwrap_or("-")
+                );
+            }
+        }
+        cli::VolumeCommands::Export {
+            volume,
+            archive,
+            backend,
+        } => {
+            let volume = volume::resolve_volume_name(&volume);
+            volume_backend(backend.as_deref())?
+                .export_volume(&volume, &archive)
+                .await?;
+            println!("✓ Exported {} to {}", volume, archive.display());
+        }
+        cli::VolumeCommands::Import {
+            volume,
+            archive,
+            backend,
+        } => {
+            if !archive.is_file() {
+                return Err(error::JailError::NotFound(format!(
+                    "Archive not found: {}",
+                    archive.display()
+                )));
+            }
+            let volume = volume::resolve_volume_name(&volume);
+            volume_backend(backend.as_deref())?
+                .import_volume(&volume, &archive)
+                .await?;
+            println!("✓ Imported {} into {}", archive.display(), volume);
+        }
+        cli::VolumeCommands::Clone {
+            source,
+            target,
+            backend,
+        } => {
+            let source = volume::resolve_volume_name(&source);
+            let target = volume::resolve_volume_name(&target);
+            let backend = volume_backend(backend.as_deref())?;
+            volume::clone_volume(backend.as_ref(), &source, &target).await?;
+            println!("✓ Cloned {} into {}", source, target);
+        }
+        cli::VolumeCommands::Reset {
+            volume,
+            all,
+            force,
+            backend,
+        } => {
+            let volume = volume::resolve_volume_name(&volume);
+            let keep = if all {
+                Vec::new()
+            } else {
+                volume::reset_keep_paths(&volume)
+            };
+
+            if !force {
+                use std::io::{self, BufRead, Write};
+                println!("Everything in {volume} will be deleted.");
+                if !keep.is_empty() {
+                    println!("Kept: {}", keep.join(", "));
+                }
+                print!("Continue? [y/N] ");
+                io::stdout().flush()?;
+                let mut line = String::new();
+                io::stdin().lock().read_line(&mut line)?;
+                if !line.trim().eq_ignore_ascii_case("y") {
+                    info!("Aborted");
+                    return Ok(());
+                }
+            }
+
+            volume_backend(backend.as_deref())?
+                .reset_volume(&volume, &keep)
+                .await?;
+            println!("✓ Reset {volume}");
+        }
+    }
+    Ok(())
+}
+
+async fn run_snapshot_command(command: cli::SnapshotCommands) -> error::Result<()> {
+    let dir = snapshot::snapshots_dir()?;
+    match command {
+        cli::SnapshotCommands::Create { name, label } => {
+            let jail = existing_jail(name).await?;
+            let snapshot = snapshot::create_snapshot(
+                jail.backend().as_ref(),
+                &dir,
+                &jail.config().name,
+                label,
+                chrono::Utc::now(),
+            )
+            .await?;
+            println!(
+                "✓ Snapshot {} of jail '{}' created",
+                snapshot.id, snapshot.jail
+            );
+        }
+        cli::SnapshotCommands::List { name } => {
+            let snapshots = snapshot::list_snapshots(&dir, name.as_deref())?;
+            if snapshots.is_empty() {
+                println!("No snapshots found");
+                return Ok(());
+            }
+            let width = snapshots.iter().map(|s| s.jail.len()).max().unwrap_or(0);
+            println!(
+                "  {:<24}  {:<width$}  {:<19}  LABEL",
+                "ID", "JAIL", "CREATED"
+            );
+            for snapshot in &snapshots {
+                println!(
+                    "  {:<24}  {:<width$}  {:<19}  {}",
+                    snapshot.id,
+                    snapshot.jail,
+                    snapshot
+                        .created
+                        .with_timezone(&chrono::Local)
+                        .format("%Y-%m-%d %H:%M:%S"),
+                    snapshot.label.as_deref().unwrap_or("-")
+                );
+            }
+        }
+        cli::SnapshotCommands::Restore { id, force } => {
+            let snapshot = snapshot::find_snapshot(&dir, &id)?;
+            if !force {
+                use std::io::{self, BufRead, Write};
+                println!(
+                    "Jail '{}' will be recreated from snapshot {}.",
+                    snapshot.jail, snapshot.id
+                );
+                println!("Changes made in the jail and its home volume since then will be lost.");
+                print!("Continue? [y/N] ");
+                io::stdout().flush()?;
+                let mut line = String::new();
+                io::stdin().lock().read_line(&mut line)?;
+                if !line.trim().eq_ignore_ascii_case("y") {
+                    info!("Aborted");
+                    return Ok(());
+                }
+            }
+
+            let backend = backend::create_backend(&snapshot.config);
+            snapshot::restore_snapshot(backend.as_ref(), &dir, &snapshot).await?;
+            println!(
+                "✓ Jail '{}' restored from snapshot {}",
+                snapshot.jail, snapshot.id
+            );
+        }
+        cli::SnapshotCommands::Delete { id } => {
+            let snapshot = snapshot::find_snapshot(&dir, &id)?;
+            let backend = backend::create_backend(&snapshot.config);
+            snapshot::delete_snapshot(backend.as_ref(), &dir, &snapshot).await?;
+            println!("✓ Snapshot {} deleted", snapshot.id);
+        }
+    }
+    Ok(())
+}
+
+/// Apply or discard the workspace changes of a copy-on-write jail
+async fn run_cow_command(name: Option<String>, force: bool, apply: bool) -> error::Result<()> {
+    let jail = existing_jail(name).await?;
+    let config = jail.inspect().await?;
+    let workspace = cow::cow_workspace(&config)?;
+    let dirs = cow::CowDirs::for_jail(&config.name)?;
+    let changes = cow::list_changes(&dirs.upper, &workspace)?;
+    if changes.is_empty() {
+        println!("No changes in {}", workspace.display());
+        return Ok(());
+    }
+
+    for change in &changes {
+        println!("  {change}");
+    }
+    if !force {
+        use std::io::{self, BufRead, Write};
+        if apply {
+            println!(
+                "These changes will be written to {} and the jail stopped.",
+                workspace.display()
+            );
+        } else {
+            println!("These changes will be lost and the jail stopped.");
+        }
+        print!("Continue? [y/N] ");
+        io::stdout().flush()?;
+        let mut line = String::new();
+        io::stdin().lock().read_line(&mut line)?;
+        if !line.trim().eq_ignore_ascii_case("y") {
+            info!("Aborted");
+            return Ok(());
+        }
+    }
+
+    let backend = jail.backend();
+    if apply {
+        cow::apply(backend.as_ref(), &config.name, &dirs, &workspace, &changes).await?;
+        println!(
+            "✓ Applied {} change(s) to {}",
+            changes.len(),
+            workspace.display()
+        );
+    } else {
+        cow::discard(backend.as_ref(), &config.name, &dirs).await?;
+        println!("✓ Discarded {} change(s)", changes.len());
+    }
+    Ok(())
+}
+
+/// List what `gc` would remove, with sizes and the total reclaimable space
+fn print_gc_plan(plan: &gc::GcPlan) {
+    let size = |bytes: Option<u64>| bytes.map(format_size).unwrap_or_else(|| "?".to_string());
+
+    if !plan.jails.is_empty() {
+        println!("Jails of missing workspaces:");
+        for jail in &plan.jails {
+            let workspace = jail
+                .workspace
+                .as_deref()
+                .unwrap_or(std::path::Path::new("-"));
+            println!("  - {} ({})", jail.name, workspace.display());
+        }
+    }
+    if !plan.volumes.is_empty() {
+        println!("Volumes without jail:");
+        for volume in &plan.volumes {
+            println!("  - {} ({})", volume.name, size(volume.size));
+        }
+    }
+    if !plan.images.is_empty() {
+        println!("Images not used by any jail:");
+        for image in &plan.images {
+            println!("  - {} ({})", image.name, size(image.size));
+        }
+    }
+    println!(
+        "Reclaimable space: up to {}",
+        format_size(plan.reclaimable_bytes())
+    );
+}
+
+/// Reaping pass run on each invocation when `JAIL_AI_REAP_IDLE` is set
+///
+/// Failures are only logged so they never prevent the requested command from running.
+async fn auto_reap_idle() {
+    let Ok(idle) = std::env::var(reap::REAP_IDLE_ENV) else {
+        return;
+    };
+    let threshold = match Commands::parse_duration(&idle) {
+        Ok(threshold) => threshold,
+        Err(e) => {
+            warn!("Ignoring {}: {}", reap::REAP_IDLE_ENV, e);
+            return;
+        }
+    };
+    let backend_type = config::BackendType::detect();
+    if let Err(e) = reap_idle_jails(backend_type, threshold, false).await {
+        warn!("Failed to stop idle jails: {}", e);
+    }
+}
+
+/// Human-readable duration, e.g. `3d 4h`, `2h 5m`, `42s`
+fn format_uptime(secs: u64) -> String {
+    let (days, hours, minutes) = (secs / 86_400, secs % 86_400 / 3_600, secs % 3_600 / 60);
+    if days > 0 {
+        format!("{days}d {hours}h")
+    } else if hours > 0 {
+        format!("{hours}h {minutes}m")
+    } else if minutes > 0 {
+        format!("{minutes}m")
+    } else {
+        format!("{secs}s")
+    }
+}
+
+/// Human-readable size in binary units, e.g. `1.5 GiB`
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{size:.1} {}", UNITS[unit])
+    }
+}
+
+/// Manager for an existing jail, given by name or auto-detected from the current directory
+///
+/// The jail is driven by the backend it was created with, looked up among the available ones.
+async fn existing_jail(name: Option<String>) -> error::Result<jail::JailManager> {
+    let jail_name = resolve_jail_name(name).await?;
+    find_jail(&jail_name)
+        .await?
+        .ok_or_else(|| error::JailError::NotFound(format!("Jail '{jail_name}' does not exist")))
+}
+
+/// Manager for a jail on the backend that owns it, `None` when no backend has it
+async fn find_jail(jail_name: &str) -> error::Result<Option<jail::JailManager>> {
+    let backends = backend::available_backends();
+    Ok(backend::find_jail_backend(jail_name, &backends)
+        .await
+        .map(|(backend_type, backend)| {
+            JailBuilder::new(jail_name)
+                .backend(backend_type)
+                .build_with_backend(backend)
+        }))
+}
+
 async fn upgrade_single_jail(
-    jail_name: &str,
+    temp_jail: &jail::JailManager,
     image: Option<String>,
     force: bool,
     verbose: bool,
 ) -> error::Result<()> {
-    // Create a temporary jail manager to inspect the existing configuration
-    let temp_config = JailConfig {
-        name: jail_name.to_string(),
-        ..Default::default()
-    };
-    let temp_jail = jail::JailManager::new(temp_config);
+    let jail_name = temp_jail.config().name.as_str();
 
     // Check if jail exists
     if !temp_jail.exists().await? {
@@ -829,27 +1634,24 @@ async fn upgrade_single_jail(
 
     // Restore bind mounts
     for mount in &old_config.bind_mounts {
-        builder = builder.bind_mount(mount.source.clone(), mount.target.clone(), mount.readonly);
+        builder = builder.mount(mount.clone());
     }
 
     // Restore port mappings
     for port_mapping in &old_config.port_mappings {
-        builder = builder.port_mapping(
-            port_mapping.host_port,
-            port_mapping.container_port,
-            &port_mapping.protocol,
-        );
+        builder = builder.port_mapping(port_mapping.clone());
     }
 
     // Restore resource limits
-    if let Some(memory) = old_config.limits.memory_mb {
-        builder = builder.memory_limit(memory);
-    }
-    if let Some(cpu) = old_config.limits.cpu_quota {
-        builder = builder.cpu_quota(cpu);
-    }
+    builder = builder.limits(old_config.limits.clone());
+
+    // Keep the security posture of the jail
+    builder = builder
+        .hardened(old_config.hardened)
+        .seccomp(old_config.seccomp)
+        .cow(old_config.cow);
 
-    let new_jail = builder.build();
+    let mut new_jail = builder.build_with_backend(temp_jail.backend());
     new_jail.create().await?;
 
     // Create .claude.json file inside the container if needed (only for Claude agent)
@@ -870,32 +1672,37 @@ async fn upgrade_single_jail(
 /// Upgrade all jails with the specified image (or their current image if not specified)
 async fn upgrade_all_jails(image: Option<String>, force: bool, verbose: bool) -> error::Result<()> {
     // Determine which backends to upgrade
-    let backends = config::BackendType::all_available();
+    let backends = backend::available_backends();
     if backends.is_empty() {
         warn!("No backends are available on this system");
         return Ok(());
     }
 
+    upgrade_jails(&backends, image, force, verbose).await?;
+    Ok(())
+}
+
+/// Upgrade every jail of the given backends, returning the success and failure counts
+async fn upgrade_jails(
+    backends: &[(config::BackendType, Arc<dyn backend::JailBackend>)],
+    image: Option<String>,
+    force: bool,
+    verbose: bool,
+) -> error::Result<(usize, usize)> {
     let mut all_jails = Vec::new();
 
     // Collect all jails from all backends
-    for backend_type in &backends {
-        let temp_config = JailConfig {
-            name: "temp".to_string(),
-            backend: *backend_type,
-            ..Default::default()
-        };
-        let backend = backend::create_backend(&temp_config);
+    for (backend_type, backend) in backends {
         let jails = backend.list_all().await?;
 
         for jail_name in jails {
-            all_jails.push((jail_name, *backend_type));
+            all_jails.push((jail_name, *backend_type, Arc::clone(backend)));
         }
     }
 
     if all_jails.is_empty() {
         println!("No jails found to upgrade");
-        return Ok(());
+        return Ok((0, 0));
     }
 
     info!("Found {} jail(s) to upgrade", all_jails.len());
@@ -907,7 +1714,7 @@ async fn upgrade_all_jails(image: Option<String>, force: bool, verbose: bool) ->
             "The following {} jail(s) will be upgraded:",
             all_jails.len()
         );
-        for (jail_name, backend_type) in &all_jails {
+        for (jail_name, backend_type, _) in &all_jails {
             println!("  - {jail_name} (backend: {backend_type:?})");
         }
         if let Some(ref img) = image {
@@ -922,17 +1729,20 @@ async fn upgrade_all_jails(image: Option<String>, force: bool, verbose: bool) ->
         stdin.lock().read_line(&mut line)?;
         if !line.trim().eq_ignore_ascii_case("y") {
             info!("Upgrade aborted");
-            return Ok(());
+            return Ok((0, 0));
         }
     }
 
-    // Upgrade each jail
+    // Upgrade each jail with the backend that owns it
     let mut success_count = 0;
     let mut error_count = 0;
 
-    for (jail_name, _backend_type) in all_jails {
+    for (jail_name, backend_type, backend) in all_jails {
         info!("Upgrading jail: {}", jail_name);
-        match upgrade_single_jail(&jail_name, image.clone(), true, verbose).await {
+        let temp_jail = JailBuilder::new(&jail_name)
+            .backend(backend_type)
+            .build_with_backend(backend);
+        match upgrade_single_jail(&temp_jail, image.clone(), true, verbose).await {
             Ok(_) => {
                 success_count += 1;
             }
@@ -946,12 +1756,48 @@ async fn upgrade_all_jails(image: Option<String>, force: bool, verbose: bool) ->
     println!("\n✓ Upgrade complete: {success_count} succeeded, {error_count} failed");
     info!("Upgrade-all operation completed");
 
-    Ok(())
+    Ok((success_count, error_count))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use backend::fake::{FakeBackend, FakeCall};
+    use backend::JailBackend;
+
+    #[test]
+    fn test_format_uptime_and_size() {
+        assert_eq!(format_uptime(42), "42s");
+        assert_eq!(format_uptime(125), "2m");
+        assert_eq!(format_uptime(2 * 3_600 + 5 * 60), "2h 5m");
+        assert_eq!(format_uptime(3 * 86_400 + 4 * 3_600), "3d 4h");
+
+        assert_eq!(format_size(512), "512 B");
+        assert_eq!(format_size(1536), "1.5 KiB");
+        assert_eq!(format_size(3 * 1024 * 1024 * 1024), "3.0 GiB");
+    }
+
+    #[tokio::test]
+    async fn test_list_summaries_from_fake_backend() {
+        let jail = JailBuilder::new("jail__demo__abc12345__claude")
+            .base_image("alpine:3.19")
+            .bind_mount("/src/demo", "/workspace", false)
+            .build();
+        let backend = FakeBackend::new().with_jail(jail.config().clone(), false);
+
+        let summaries = backend.list_summaries().await.unwrap();
+        assert_eq!(summaries.len(), 1);
+        assert_eq!(summaries[0].agent, "Claude");
+        assert_eq!(
+            summaries[0].workspace,
+            Some(std::path::PathBuf::from("/src/demo"))
+        );
+        assert!(!summaries[0].running);
+
+        let json = serde_json::to_value(&summaries).unwrap();
+        assert_eq!(json[0]["image"], "alpine:3.19");
+        assert_eq!(json[0]["uptime_secs"], serde_json::Value::Null);
+    }
 
     #[tokio::test]
     async fn test_jail_config_serialization() {
@@ -1018,7 +1864,9 @@ mod tests {
     #[tokio::test]
     async fn test_upgrade_single_jail_nonexistent() {
         // Test that upgrading a non-existent jail returns an error
-        let result = upgrade_single_jail("nonexistent-jail", None, true, false).await;
+        let temp_jail =
+            JailBuilder::new("nonexistent-jail").build_with_backend(Arc::new(FakeBackend::new()));
+        let result = upgrade_single_jail(&temp_jail, None, true, false).await;
         assert!(result.is_err());
         if let Err(e) = result {
             match e {
@@ -1029,4 +1877,66 @@ mod tests {
             }
         }
     }
+
+    #[tokio::test]
+    async fn test_upgrade_single_jail_recreates_with_same_config() {
+        let existing = JailBuilder::new("jail__project__abc12345__claude")
+            .base_image("alpine:3.19")
+            .env("FOO", "bar")
+            .bind_mount("/src", "/workspace", false)
+            .port_mapping(Commands::parse_port("8080:80").unwrap())
+            .memory_limit(2048)
+            .build()
+            .config()
+            .clone();
+        let fake = FakeBackend::new().with_jail(existing, true);
+        let temp_jail = JailBuilder::new("jail__project__abc12345__claude")
+            .build_with_backend(Arc::new(fake.clone()));
+
+        upgrade_single_jail(&temp_jail, Some("alpine:3.20".to_string()), true, false)
+            .await
+            .unwrap();
+
+        let upgraded = fake.jail("jail__project__abc12345__claude").unwrap();
+        assert_eq!(upgraded.base_image, "alpine:3.20");
+        assert_eq!(
+            upgraded.environment,
+            vec![("FOO".to_string(), "bar".to_string())]
+        );
+        assert_eq!(upgraded.bind_mounts.len(), 1);
+        assert_eq!(upgraded.port_mappings.len(), 1);
+        assert_eq!(upgraded.limits.memory_mb, Some(2048));
+        assert_eq!(
+            fake.calls(),
+            vec![
+                FakeCall::Remove {
+                    name: "jail__project__abc12345__claude".to_string(),
+                    remove_volume: false
+                },
+                FakeCall::Create("jail__project__abc12345__claude".to_string()),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_upgrade_jails_continues_after_failure() {
+        let jail_config = |name: &str| JailBuilder::new(name).build().config().clone();
+        let podman = FakeBackend::new()
+            .with_jail(jail_config("jail__a__11111111__claude"), true)
+            .with_jail(jail_config("jail__b__22222222__codex"), false)
+            .fail_create("jail__a__11111111__claude");
+        let docker = FakeBackend::new().with_jail(jail_config("jail__c__33333333__gemini"), true);
+
+        let backends: Vec<(config::BackendType, Arc<dyn backend::JailBackend>)> = vec![
+            (config::BackendType::Podman, Arc::new(podman.clone())),
+            (config::BackendType::Docker, Arc::new(docker.clone())),
+        ];
+        let counts = upgrade_jails(&backends, None, true, false).await.unwrap();
+
+        assert_eq!(counts, (2, 1));
+        // Each jail is upgraded through the backend that owns it
+        assert!(podman.jail("jail__b__22222222__codex").is_some());
+        assert!(docker.jail("jail__c__33333333__gemini").is_some());
+        assert!(podman.jail("jail__c__33333333__gemini").is_none());
+    }
 }
@@ -0,0 +1,175 @@
+//! Idle jail detection and automatic stopping
+
+use crate::backend::JailBackend;
+use crate::error::Result;
+use chrono::{DateTime, Utc};
+use std::collections::BTreeMap;
+use std::time::Duration;
+use tracing::{debug, warn};
+
+/// Environment variable enabling an idle reaping pass on each invocation (e.g. `2h`)
+pub const REAP_IDLE_ENV: &str = "JAIL_AI_REAP_IDLE";
+
+/// Running jail found idle past the threshold
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IdleJail {
+    pub name: String,
+    pub idle: Duration,
+}
+
+/// How long a jail has been idle, from its last recorded activity and its start time
+///
+/// A jail restarted after its last activity counts as idle since the restart.
+fn idle_duration(
+    last_activity: Option<DateTime<Utc>>,
+    uptime_secs: Option<u64>,
+    now: DateTime<Utc>,
+) -> Option<Duration> {
+    let since_activity = last_activity.map(|at| (now - at).to_std().unwrap_or_default());
+    let since_start = uptime_secs.map(Duration::from_secs);
+    match (since_activity, since_start) {
+        (Some(activity), Some(start)) => Some(activity.min(start)),
+        (activity, start) => activity.or(start),
+    }
+}
+
+/// Stop running jails without attached sessions that were idle for at least `threshold`
+///
+/// Returns the jails stopped, or those that would be stopped with `dry_run`.
+pub async fn reap_idle(
+    backend: &dyn JailBackend,
+    last_activity: &BTreeMap<String, DateTime<Utc>>,
+    threshold: Duration,
+    now: DateTime<Utc>,
+    dry_run: bool,
+) -> Result<Vec<IdleJail>> {
+    let mut reaped = Vec::new();
+
+    for summary in backend.list_summaries().await? {
+        if !summary.running {
+            continue;
+        }
+        let last = last_activity.get(&summary.name).copied();
+        let Some(idle) = idle_duration(last, summary.uptime_secs, now) else {
+            debug!("No activity known for jail {}, skipping", summary.name);
+            continue;
+        };
+        if idle < threshold {
+            continue;
+        }
+
+        match backend.active_sessions(&summary.name).await {
+            Ok(0) => {}
+            Ok(sessions) => {
+                debug!(
+                    "Jail {} has {} active session(s), skipping",
+                    summary.name, sessions
+                );
+                continue;
+            }
+            Err(e) => {
+                warn!("Failed to list sessions of jail {}: {}", summary.name, e);
+                continue;
+            }
+        }
+
+        if !dry_run {
+            if let Err(e) = backend.stop(&summary.name).await {
+                warn!("Failed to stop idle jail {}: {}", summary.name, e);
+                continue;
+            }
+        }
+        reaped.push(IdleJail {
+            name: summary.name,
+            idle,
+        });
+    }
+
+    Ok(reaped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::fake::{FakeBackend, FakeCall};
+    use crate::jail::JailBuilder;
+
+    fn jail_config(name: &str) -> crate::config::JailConfig {
+        JailBuilder::new(name).build().config().clone()
+    }
+
+    #[test]
+    fn test_idle_duration() {
+        let now = DateTime::parse_from_rfc3339("2026-01-01T12:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let hours_ago = |hours| Some(now - chrono::Duration::hours(hours));
+
+        assert_eq!(
+            idle_duration(hours_ago(3), None, now),
+            Some(Duration::from_secs(3 * 3600))
+        );
+        // Restarted an hour ago, after the last activity
+        assert_eq!(
+            idle_duration(hours_ago(3), Some(3600), now),
+            Some(Duration::from_secs(3600))
+        );
+        assert_eq!(
+            idle_duration(None, Some(60), now),
+            Some(Duration::from_secs(60))
+        );
+        assert_eq!(idle_duration(None, None, now), None);
+    }
+
+    #[tokio::test]
+    async fn test_reap_idle_stops_only_idle_jails() {
+        let now = Utc::now();
+        let fake = FakeBackend::new()
+            .with_jail(jail_config("jail__a__00000000__claude"), true)
+            .with_jail(jail_config("jail__b__00000000__claude"), true)
+            .with_jail(jail_config("jail__c__00000000__claude"), true)
+            .with_jail(jail_config("jail__d__00000000__claude"), false)
+            .with_jail(jail_config("jail__e__00000000__claude"), true)
+            .with_sessions("jail__c__00000000__claude", 1);
+        let activity = BTreeMap::from([
+            (
+                "jail__a__00000000__claude".to_string(),
+                now - chrono::Duration::hours(3),
+            ),
+            (
+                "jail__b__00000000__claude".to_string(),
+                now - chrono::Duration::minutes(5),
+            ),
+            (
+                "jail__c__00000000__claude".to_string(),
+                now - chrono::Duration::hours(3),
+            ),
+            (
+                "jail__d__00000000__claude".to_string(),
+                now - chrono::Duration::hours(3),
+            ),
+        ]);
+        let threshold = Duration::from_secs(2 * 3600);
+
+        let planned = reap_idle(&fake, &activity, threshold, now, true)
+            .await
+            .unwrap();
+        assert_eq!(planned.len(), 1);
+        assert!(fake.calls().is_empty());
+
+        let reaped = reap_idle(&fake, &activity, threshold, now, false)
+            .await
+            .unwrap();
+        assert_eq!(
+            reaped,
+            vec![IdleJail {
+                name: "jail__a__00000000__claude".to_string(),
+                idle: Duration::from_secs(3 * 3600),
+            }]
+        );
+        assert_eq!(
+            fake.calls(),
+            vec![FakeCall::Stop("jail__a__00000000__claude".to_string())]
+        );
+    }
+}
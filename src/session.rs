@@ -0,0 +1,169 @@
+//! Parallel sessions of an agent in the same workspace, each in its own jail
+//!
+//! Session jails are named `jail__{project}__{hash}__session-{session}__{agent}` and share
+//! the Nix volume of the project. Their home volume is their own unless they share the
+//! one of the main `jail__{project}__{hash}__{agent}` jail.
+
+use crate::backend::JailBackend;
+use crate::cli::Commands;
+use crate::error::Result;
+use std::fs::{File, TryLockError};
+use std::path::{Path, PathBuf};
+
+const SESSION_SEGMENT: &str = "__session-";
+
+/// Session name usable in a jail name (no `__`, which separates name segments)
+fn sanitize_session(session: &str) -> String {
+    Commands::sanitize_jail_name(session).replace('_', "-")
+}
+
+/// Name of the jail of an agent session
+pub fn session_jail_name(base_name: &str, agent_command: &str, session: &str) -> String {
+    crate::agent_commands::agent_jail_name(
+        &format!("{base_name}{SESSION_SEGMENT}{}", sanitize_session(session)),
+        agent_command,
+    )
+}
+
+/// Session of a jail, `None` for the main jail of an agent
+pub fn session_of(name: &str) -> Option<&str> {
+    let (_, rest) = name.split_once(SESSION_SEGMENT)?;
+    Some(rest.split_once("__").map_or(rest, |(session, _)| session))
+}
+
+/// Name of the main jail of the agent a session jail belongs to
+pub fn main_jail_name(name: &str) -> String {
+    match session_of(name) {
+        Some(session) => name.replacen(&format!("{SESSION_SEGMENT}{session}"), "", 1),
+        None => name.to_string(),
+    }
+}
+
+/// Project base name with any session segment removed
+pub fn strip_session(base_name: &str) -> &str {
+    base_name
+        .rfind(SESSION_SEGMENT)
+        .map_or(base_name, |pos| &base_name[..pos])
+}
+
+/// Session picked by `next_session`, reserved for this process until dropped
+pub struct SessionReservation {
+    pub session: String,
+    _lock: File,
+}
+
+/// Lock files reserving numbered sessions
+fn reservation_dir() -> Result<PathBuf> {
+    Ok(crate::jail_setup::get_jail_ai_data_dir()?.join("sessions"))
+}
+
+/// Lock the reservation of a session jail, `None` when another process holds it
+fn try_reserve(dir: &Path, name: &str) -> Result<Option<File>> {
+    std::fs::create_dir_all(dir)?;
+    let lock = std::fs::OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(dir.join(format!("{name}.lock")))?;
+    match lock.try_lock() {
+        Ok(()) => Ok(Some(lock)),
+        Err(TryLockError::WouldBlock) => Ok(None),
+        Err(TryLockError::Error(e)) => Err(e.into()),
+    }
+}
+
+/// Whether a session jail can be used: not created yet, stopped, or without active sessions
+///
+/// Sessions are only counted in running jails, the backend cannot list them otherwise.
+async fn is_free(backend: &dyn JailBackend, name: &str) -> Result<bool> {
+    Ok(!backend.exists(name).await?
+        || !backend.is_running(name).await?
+        || backend.active_sessions(name).await? == 0)
+}
+
+/// First numbered session whose jail is free, reserved against concurrent jail-ai processes
+pub async fn next_session(
+    backend: &dyn JailBackend,
+    base_name: &str,
+    agent_command: &str,
+) -> Result<SessionReservation> {
+    next_session_in(&reservation_dir()?, backend, base_name, agent_command).await
+}
+
+async fn next_session_in(
+    dir: &Path,
+    backend: &dyn JailBackend,
+    base_name: &str,
+    agent_command: &str,
+) -> Result<SessionReservation> {
+    let mut number = 1;
+    loop {
+        let session = number.to_string();
+        let name = session_jail_name(base_name, agent_command, &session);
+        if let Some(lock) = try_reserve(dir, &name)? {
+            if is_free(backend, &name).await? {
+                return Ok(SessionReservation {
+                    session,
+                    _lock: lock,
+                });
+            }
+        }
+        number += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::fake::FakeBackend;
+    use crate::backend::JailBackend;
+    use crate::jail::JailBuilder;
+
+    const BASE: &str = "jail__app__12345678";
+
+    #[test]
+    fn test_session_names() {
+        let name = session_jail_name(BASE, "claude", "code_review");
+        assert_eq!(name, "jail__app__12345678__session-code-review__claude");
+        assert_eq!(session_of(&name), Some("code-review"));
+        assert_eq!(main_jail_name(&name), "jail__app__12345678__claude");
+        assert_eq!(crate::backend::extract_base_name(&name), BASE);
+
+        assert_eq!(session_of("jail__app__12345678__claude"), None);
+        assert_eq!(
+            main_jail_name("jail__app__12345678__claude"),
+            "jail__app__12345678__claude"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_next_session_skips_busy_jails() {
+        let dir = tempfile::tempdir().unwrap();
+        let jail = |session: &str| {
+            JailBuilder::new(session_jail_name(BASE, "claude", session))
+                .build()
+                .config()
+                .clone()
+        };
+        let fake = FakeBackend::new()
+            .with_jail(jail("1"), true)
+            .with_sessions(&session_jail_name(BASE, "claude", "1"), 1)
+            .with_jail(jail("2"), false)
+            .with_sessions(&session_jail_name(BASE, "claude", "2"), 2);
+
+        // Session 2 exists but is stopped: reused
+        let next = || next_session_in(dir.path(), &fake, BASE, "claude");
+        let reserved = next().await.unwrap();
+        assert_eq!(reserved.session, "2");
+
+        // Reserved by a concurrent process until dropped
+        assert_eq!(next().await.unwrap().session, "3");
+        drop(reserved);
+        assert_eq!(next().await.unwrap().session, "2");
+
+        fake.start(&session_jail_name(BASE, "claude", "2"))
+            .await
+            .unwrap();
+        assert_eq!(next().await.unwrap().session, "3");
+    }
+}
@@ -0,0 +1,1014 @@
+use crate::agent_commands::AgentCommandParams;
+use crate::cli::Commands;
+use crate::error::{JailError, Result};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use tracing::info;
+
+/// Name of the per-project configuration file, looked up at the git root
+pub const PROJECT_CONFIG_FILE: &str = ".jail-ai.toml";
+
+/// Name of the user configuration file in the jail-ai config directory
+pub const USER_CONFIG_FILE: &str = "config.toml";
+
+/// Jail options that can be stored in a configuration file
+///
+/// Keys mirror the agent command line flags (e.g. `git-gpg = true` for `--git-gpg`).
+/// Every field is optional so that files only need to list what they change.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct JailSettings {
+    #[serde(
+        default,
+        deserialize_with = "de_backend",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub backend: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image: Option<String>,
+    /// Bind mounts (format: source:target[:ro]), relative sources are resolved against the file's directory
+    #[serde(
+        default,
+        deserialize_with = "de_mounts",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub mounts: Vec<String>,
+    /// Port mappings (format: host_port:container_port[/protocol])
+    #[serde(
+        default,
+        deserialize_with = "de_ports",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub ports: Vec<String>,
+    #[serde(
+        default,
+        deserialize_with = "de_env",
+        skip_serializing_if = "BTreeMap::is_empty"
+    )]
+    pub env: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub no_network: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub host_network: Option<bool>,
+    /// Memory limit in MB
+    #[serde(
+        default,
+        deserialize_with = "de_memory",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub memory: Option<u64>,
+    /// CPU quota percentage (1-100)
+    #[serde(
+        default,
+        deserialize_with = "de_cpu",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub cpu: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub no_workspace: Option<bool>,
+    #[serde(
+        default,
+        deserialize_with = "de_workspace_path",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub workspace_path: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub claude_dir: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub claude_code_router_dir: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub copilot_dir: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cursor_dir: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gemini_dir: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub coderabbit_dir: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub codex_dir: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jules_dir: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pi_dir: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub agent_configs: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub git_gpg: Option<bool>,
+    /// Layers to force (same as --layers)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub layers: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cloud: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub isolated: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub no_nix: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub no_block_host: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub podman: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hardened: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seccomp: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cow: Option<bool>,
+}
+
+impl JailSettings {
+    /// Parse settings from TOML content
+    ///
+    /// `path` is only used for error messages and to resolve relative mount sources.
+    pub fn from_toml_str(content: &str, path: &Path) -> Result<Self> {
+        let mut settings: JailSettings =
+            toml::from_str(content).map_err(|e| toml_error(path, content, &e))?;
+
+        if settings.no_network == Some(true) && settings.host_network == Some(true) {
+            return Err(JailError::Config(format!(
+                "{}: 'no-network' and 'host-network' cannot both be enabled",
+                path.display()
+            )));
+        }
+
+        if let Some(base_dir) = path.parent() {
+            settings.mounts = settings
+                .mounts
+                .iter()
+                .map(|mount| resolve_mount_source(&expand_home(mount), base_dir))
+                .collect();
+        }
+
+        Ok(settings)
+    }
+
+    /// Load settings from a TOML file
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| JailError::Config(format!("Failed to read {}: {}", path.display(), e)))?;
+        Self::from_toml_str(&content, path)
+    }
+
+    /// Layer `over` on top of these settings
+    ///
+    /// Values set in `over` replace ours, list values are concatenated and env
+    /// variables from `over` override ours.
+    pub fn merge(&self, over: &JailSettings) -> JailSettings {
+        let mut env = self.env.clone();
+        env.extend(over.env.clone());
+
+        JailSettings {
+            backend: over.backend.clone().or_else(|| self.backend.clone()),
+            image: over.image.clone().or_else(|| self.image.clone()),
+            mounts: prepend(&self.mounts, &over.mounts),
+            ports: prepend(&self.ports, &over.ports),
+            env,
+            // A layer enabling one network mode resets the other one
+            no_network: over
+                .no_network
+                .or(over.host_network.filter(|&host| host).map(|_| false))
+                .or(self.no_network),
+            host_network: over
+                .host_network
+                .or(over.no_network.filter(|&none| none).map(|_| false))
+                .or(self.host_network),
+            memory: over.memory.or(self.memory),
+            cpu: over.cpu.or(self.cpu),
+            no_workspace: over.no_workspace.or(self.no_workspace),
+            workspace_path: over
+                .workspace_path
+                .clone()
+                .or_else(|| self.workspace_path.clone()),
+            claude_dir: over.claude_dir.or(self.claude_dir),
+            claude_code_router_dir: over.claude_code_router_dir.or(self.claude_code_router_dir),
+            copilot_dir: over.copilot_dir.or(self.copilot_dir),
+            cursor_dir: over.cursor_dir.or(self.cursor_dir),
+            gemini_dir: over.gemini_dir.or(self.gemini_dir),
+            coderabbit_dir: over.coderabbit_dir.or(self.coderabbit_dir),
+            codex_dir: over.codex_dir.or(self.codex_dir),
+            jules_dir: over.jules_dir.or(self.jules_dir),
+            pi_dir: over.pi_dir.or(self.pi_dir),
+            agent_configs: over.agent_configs.or(self.agent_configs),
+            git_gpg: over.git_gpg.or(self.git_gpg),
+            layers: if over.layers.is_empty() {
+                self.layers.clone()
+            } else {
+                over.layers.clone()
+            },
+            cloud: over.cloud.or(self.cloud),
+            isolated: over.isolated.or(self.isolated),
+            no_nix: over.no_nix.or(self.no_nix),
+            no_block_host: over.no_block_host.or(self.no_block_host),
+            podman: over.podman.or(self.podman),
+            hardened: over.hardened.or(self.hardened),
+            seccomp: over.seccomp.or(self.seccomp),
+            cow: over.cow.or(self.cow),
+        }
+    }
+
+    /// Settings explicitly given on the command line (flags left at their default are skipped)
+    pub fn from_params(params: &AgentCommandParams) -> JailSettings {
+        let flag = |enabled: bool| enabled.then_some(true);
+
+        JailSettings {
+            backend: params.backend.clone(),
+            image: (params.image != crate::cli::DEFAULT_IMAGE).then(|| params.image.clone()),
+            mounts: params.mount.clone(),
+            ports: params.port.clone(),
+            env: params
+                .env
+                .iter()
+                .filter_map(|e| e.split_once('='))
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+            no_network: flag(params.no_network),
+            host_network: flag(params.host_network),
+            memory: params.memory,
+            cpu: params.cpu,
+            no_workspace: flag(params.no_workspace),
+            workspace_path: (params.workspace_path != crate::cli::DEFAULT_WORKSPACE_PATH)
+                .then(|| params.workspace_path.clone()),
+            claude_dir: flag(params.claude_dir),
+            claude_code_router_dir: flag(params.claude_code_router_dir),
+            copilot_dir: flag(params.copilot_dir),
+            cursor_dir: flag(params.cursor_dir),
+            gemini_dir: flag(params.gemini_dir),
+            coderabbit_dir: flag(params.coderabbit_dir),
+            codex_dir: flag(params.codex_dir),
+            jules_dir: flag(params.jules_dir),
+            pi_dir: flag(params.pi_dir),
+            agent_configs: flag(params.agent_configs),
+            git_gpg: flag(params.git_gpg),
+            layers: params.force_layers.clone(),
+            cloud: flag(params.cloud),
+            isolated: flag(params.isolated),
+            no_nix: flag(params.no_nix),
+            no_block_host: flag(params.no_block_host),
+            podman: flag(params.podman),
+            hardened: flag(params.hardened),
+            seccomp: flag(params.seccomp),
+            cow: flag(params.cow),
+        }
+    }
+
+    /// Merge settings into agent command parameters, command line flags take precedence
+    ///
+    /// Flags can only be switched on from the command line, so boolean settings are
+    /// applied whenever the corresponding flag was not given. List values (mounts,
+    /// ports, env) are prepended so that command line entries come last and win.
+    pub fn apply_to_params(&self, params: &mut AgentCommandParams) {
+        if params.backend.is_none() {
+            params.backend = self.backend.clone();
+        }
+        if params.image == crate::cli::DEFAULT_IMAGE {
+            if let Some(image) = &self.image {
+                params.image = image.clone();
+            }
+        }
+
+        params.mount = prepend(&self.mounts, &params.mount);
+        params.port = prepend(&self.ports, &params.port);
+        let env: Vec<String> = self.env.iter().map(|(k, v)| format!("{k}={v}")).collect();
+        params.env = prepend(&env, &params.env);
+
+        // --no-network and --host-network are mutually exclusive on the command line,
+        // so a flag given there overrides the opposite setting from the file
+        if !params.host_network {
+            params.no_network |= self.no_network.unwrap_or(false);
+        }
+        if !params.no_network {
+            params.host_network |= self.host_network.unwrap_or(false);
+        }
+
+        params.memory = params.memory.or(self.memory);
+        params.cpu = params.cpu.or(self.cpu);
+        params.no_workspace |= self.no_workspace.unwrap_or(false);
+        if params.workspace_path == crate::cli::DEFAULT_WORKSPACE_PATH {
+            if let Some(workspace_path) = &self.workspace_path {
+                params.workspace_path = workspace_path.clone();
+            }
+        }
+
+        params.claude_dir |= self.claude_dir.unwrap_or(false);
+        params.claude_code_router_dir |= self.claude_code_router_dir.unwrap_or(false);
+        params.copilot_dir |= self.copilot_dir.unwrap_or(false);
+        params.cursor_dir |= self.cursor_dir.unwrap_or(false);
+        params.gemini_dir |= self.gemini_dir.unwrap_or(false);
+        params.coderabbit_dir |= self.coderabbit_dir.unwrap_or(false);
+        params.codex_dir |= self.codex_dir.unwrap_or(false);
+        params.jules_dir |= self.jules_dir.unwrap_or(false);
+        params.pi_dir |= self.pi_dir.unwrap_or(false);
+        params.agent_configs |= self.agent_configs.unwrap_or(false);
+        params.git_gpg |= self.git_gpg.unwrap_or(false);
+
+        if params.force_layers.is_empty() {
+            params.force_layers = self.layers.clone();
+        }
+
+        params.cloud |= self.cloud.unwrap_or(false);
+        params.isolated |= self.isolated.unwrap_or(false);
+        params.no_nix |= self.no_nix.unwrap_or(false);
+        params.no_block_host |= self.no_block_host.unwrap_or(false);
+        params.podman |= self.podman.unwrap_or(false);
+        params.hardened |= self.hardened.unwrap_or(false);
+        params.seccomp |= self.seccomp.unwrap_or(false);
+        params.cow |= self.cow.unwrap_or(false);
+    }
+}
+
+/// Load the project configuration file from the given project root, if present
+pub fn load_project_settings(project_root: &Path) -> Result<Option<JailSettings>> {
+    let path = project_root.join(PROJECT_CONFIG_FILE);
+    if !path.is_file() {
+        return Ok(None);
+    }
+
+    info!("Loading project configuration from {}", path.display());
+    JailSettings::load(&path).map(Some)
+}
+
+/// User configuration (~/.config/jail-ai/config.toml)
+///
+/// ```toml
+/// [defaults]
+/// memory = 8192
+///
+/// [profile.strict]
+/// no-network = true
+/// mounts = ["~/datasets:/data:ro"]
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct UserConfig {
+    /// Global defaults applied to every jail
+    #[serde(default)]
+    pub defaults: JailSettings,
+    /// Named profiles selectable with --profile
+    #[serde(default)]
+    pub profile: BTreeMap<String, JailSettings>,
+}
+
+impl UserConfig {
+    /// Parse the user configuration from TOML content
+    ///
+    /// Relative mount sources are resolved against the directory of `path`.
+    pub fn from_toml_str(content: &str, path: &Path) -> Result<Self> {
+        let mut config: UserConfig =
+            toml::from_str(content).map_err(|e| toml_error(path, content, &e))?;
+
+        for (name, settings) in std::iter::once(("defaults", &mut config.defaults)).chain(
+            config
+                .profile
+                .iter_mut()
+                .map(|(name, settings)| (name.as_str(), settings)),
+        ) {
+            if settings.no_network == Some(true) && settings.host_network == Some(true) {
+                return Err(JailError::Config(format!(
+                    "{}: 'no-network' and 'host-network' cannot both be enabled in [{}]",
+                    path.display(),
+                    name
+                )));
+            }
+            settings.mounts = settings
+                .mounts
+                .iter()
+                .map(|mount| match path.parent() {
+                    Some(base_dir) => resolve_mount_source(&expand_home(mount), base_dir),
+                    None => expand_home(mount),
+                })
+                .collect();
+        }
+
+        Ok(config)
+    }
+
+    /// Load the user configuration, returning an empty configuration if the file is missing
+    pub fn load() -> Result<Self> {
+        let path = crate::jail_setup::get_jail_ai_config_dir()?.join(USER_CONFIG_FILE);
+        if !path.is_file() {
+            return Ok(Self::default());
+        }
+
+        info!("Loading user configuration from {}", path.display());
+        let content = std::fs::read_to_string(&path)
+            .map_err(|e| JailError::Config(format!("Failed to read {}: {}", path.display(), e)))?;
+        Self::from_toml_str(&content, &path)
+    }
+
+    /// Look up a profile by name
+    pub fn profile(&self, name: &str) -> Result<&JailSettings> {
+        self.profile.get(name).ok_or_else(|| {
+            let available: Vec<&str> = self.profile.keys().map(String::as_str).collect();
+            JailError::Config(if available.is_empty() {
+                format!("Unknown profile '{name}': no profiles defined in ~/.config/jail-ai/{USER_CONFIG_FILE}")
+            } else {
+                format!(
+                    "Unknown profile '{}'. Available profiles: {}",
+                    name,
+                    available.join(", ")
+                )
+            })
+        })
+    }
+}
+
+/// Where a configuration value came from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Source {
+    /// Built-in default
+    Default,
+    /// Detected from the environment (workspace, host settings, credentials)
+    AutoDetect,
+    /// `[defaults]` in the user configuration file
+    Global,
+    /// Named profile from the user configuration file
+    Profile(String),
+    /// Project `.jail-ai.toml`
+    Project,
+    /// Command line flag
+    Cli,
+}
+
+impl std::fmt::Display for Source {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Source::Default => write!(f, "default"),
+            Source::AutoDetect => write!(f, "auto-detect"),
+            Source::Global => write!(f, "global"),
+            Source::Profile(name) => write!(f, "profile:{name}"),
+            Source::Project => write!(f, "project"),
+            Source::Cli => write!(f, "cli"),
+        }
+    }
+}
+
+impl Serialize for Source {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// Settings layers in precedence order (lowest first), kept apart to report provenance
+#[derive(Debug, Clone, Default)]
+pub struct SettingsLayers {
+    layers: Vec<(Source, JailSettings)>,
+}
+
+impl SettingsLayers {
+    /// Load the configuration file layers: global defaults → profile → project file
+    pub fn load(profile: Option<&str>, project_root: Option<&Path>) -> Result<Self> {
+        let user_config = UserConfig::load()?;
+        let mut layers = SettingsLayers::default();
+        layers.push(Source::Global, user_config.defaults.clone());
+
+        if let Some(name) = profile {
+            info!("Using profile: {}", name);
+            layers.push(
+                Source::Profile(name.to_string()),
+                user_config.profile(name)?.clone(),
+            );
+        }
+
+        if let Some(root) = project_root {
+            if let Some(project_settings) = load_project_settings(root)? {
+                layers.push(Source::Project, project_settings);
+            }
+        }
+
+        Ok(layers)
+    }
+
+    /// Add a layer on top of the existing ones
+    pub fn push(&mut self, source: Source, settings: JailSettings) {
+        self.layers.push((source, settings));
+    }
+
+    /// Merge all layers into the final settings
+    pub fn merged(&self) -> JailSettings {
+        self.layers
+            .iter()
+            .fold(JailSettings::default(), |acc, (_, settings)| {
+                acc.merge(settings)
+            })
+    }
+
+    /// Find the highest layer that sets `key` (kebab-case setting name)
+    pub fn source_of(&self, key: &str) -> Option<Source> {
+        self.source_of_any(&[key])
+    }
+
+    /// Find the highest layer that sets any of `keys`, for settings decided together
+    pub fn source_of_any(&self, keys: &[&str]) -> Option<Source> {
+        self.layers.iter().rev().find_map(|(source, settings)| {
+            let value = serde_json::to_value(settings).ok()?;
+            keys.iter()
+                .any(|key| value.get(*key).is_some())
+                .then(|| source.clone())
+        })
+    }
+
+    /// Find the highest layer whose list (or table) `key` contains `entry`
+    pub fn source_of_entry(&self, key: &str, entry: &str) -> Option<Source> {
+        self.layers.iter().rev().find_map(|(source, settings)| {
+            let found = match key {
+                "mounts" => settings.mounts.iter().any(|m| m == entry),
+                "ports" => settings.ports.iter().any(|p| p == entry),
+                "env" => entry
+                    .split_once('=')
+                    .is_some_and(|(k, v)| settings.env.get(k).is_some_and(|val| val == v)),
+                _ => false,
+            };
+            found.then(|| source.clone())
+        })
+    }
+}
+
+/// Resolve the settings from all configuration files
+///
+/// Layering order: global defaults → profile → project file. CLI flags are
+/// applied on top by [`JailSettings::apply_to_params`].
+pub fn resolve_settings(
+    profile: Option<&str>,
+    project_root: Option<&Path>,
+) -> Result<JailSettings> {
+    Ok(SettingsLayers::load(profile, project_root)?.merged())
+}
+
+fn prepend(first: &[String], then: &[String]) -> Vec<String> {
+    first.iter().chain(then).cloned().collect()
+}
+
+/// Expand a leading `~/` in a mount source to the user's home directory
+fn expand_home(mount: &str) -> String {
+    match (mount.strip_prefix("~/"), std::env::var("HOME")) {
+        (Some(rest), Ok(home)) => format!("{}/{}", home.trim_end_matches('/'), rest),
+        _ => mount.to_string(),
+    }
+}
+
+/// Make a relative mount source absolute, relative to `base_dir`
+fn resolve_mount_source(mount: &str, base_dir: &Path) -> String {
+    match mount.split_once(':') {
+        Some((source, rest)) if Path::new(source).is_relative() && !source.starts_with('~') => {
+            let source = base_dir.join(source);
+            let source = source.strip_prefix(".").unwrap_or(&source);
+            format!("{}:{}", normalize_path(source).display(), rest)
+        }
+        _ => mount.to_string(),
+    }
+}
+
+/// Drop `.` components and fold `..` without touching the filesystem
+fn normalize_path(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            std::path::Component::CurDir => {}
+            std::path::Component::ParentDir => {
+                normalized.pop();
+            }
+            other => normalized.push(other),
+        }
+    }
+    normalized
+}
+
+/// Convert a TOML error into a config error pointing at the offending line
+fn toml_error(path: &Path, content: &str, err: &toml::de::Error) -> JailError {
+    match err.span() {
+        Some(span) => {
+            let line = content[..span.start.min(content.len())]
+                .matches('\n')
+                .count()
+                + 1;
+            JailError::Config(format!("{}:{}: {}", path.display(), line, err.message()))
+        }
+        None => JailError::Config(format!("{}: {}", path.display(), err.message())),
+    }
+}
+
+fn de_backend<'de, D>(deserializer: D) -> std::result::Result<Option<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let backend = String::deserialize(deserializer)?;
+    Commands::parse_backend(&backend).map_err(serde::de::Error::custom)?;
+    Ok(Some(backend))
+}
+
+fn de_mounts<'de, D>(deserializer: D) -> std::result::Result<Vec<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let mounts = Vec::<String>::deserialize(deserializer)?;
+    for mount in &mounts {
+        let parts: Vec<&str> = mount.split(':').collect();
+        if parts.len() < 2 || parts.len() > 3 || parts[0].is_empty() || parts[1].is_empty() {
+            return Err(serde::de::Error::custom(format!(
+                "invalid mount '{mount}', expected source:target[:ro]"
+            )));
+        }
+        if !parts[1].starts_with('/') {
+            return Err(serde::de::Error::custom(format!(
+                "invalid mount '{mount}', target must be an absolute path"
+            )));
+        }
+        if let Some(mode) = parts.get(2) {
+            if *mode != "ro" && *mode != "rw" {
+                return Err(serde::de::Error::custom(format!(
+                    "invalid mount mode '{mode}' in '{mount}', expected ro or rw"
+                )));
+            }
+        }
+    }
+    Ok(mounts)
+}
+
+fn de_ports<'de, D>(deserializer: D) -> std::result::Result<Vec<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let ports = Vec::<String>::deserialize(deserializer)?;
+    for port in &ports {
+        Commands::parse_port(port).map_err(serde::de::Error::custom)?;
+    }
+    Ok(ports)
+}
+
+fn de_env<'de, D>(deserializer: D) -> std::result::Result<BTreeMap<String, String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let env = BTreeMap::<String, String>::deserialize(deserializer)?;
+    for key in env.keys() {
+        if key.is_empty() || key.contains('=') {
+            return Err(serde::de::Error::custom(format!(
+                "invalid environment variable name '{key}'"
+            )));
+        }
+    }
+    Ok(env)
+}
+
+fn de_memory<'de, D>(deserializer: D) -> std::result::Result<Option<u64>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let memory = u64::deserialize(deserializer)?;
+    if memory == 0 {
+        return Err(serde::de::Error::custom(
+            "memory limit must be greater than 0 MB",
+        ));
+    }
+    Ok(Some(memory))
+}
+
+fn de_cpu<'de, D>(deserializer: D) -> std::result::Result<Option<u32>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let cpu = u32::deserialize(deserializer)?;
+    if cpu == 0 || cpu > 100 {
+        return Err(serde::de::Error::custom(format!(
+            "invalid CPU quota {cpu}, expected a percentage between 1 and 100"
+        )));
+    }
+    Ok(Some(cpu))
+}
+
+fn de_workspace_path<'de, D>(deserializer: D) -> std::result::Result<Option<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let path = String::deserialize(deserializer)?;
+    if !path.starts_with('/') {
+        return Err(serde::de::Error::custom(format!(
+            "invalid workspace path '{path}', expected an absolute path"
+        )));
+    }
+    Ok(Some(path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(content: &str) -> Result<JailSettings> {
+        JailSettings::from_toml_str(content, Path::new("/project/.jail-ai.toml"))
+    }
+
+    fn default_params() -> AgentCommandParams {
+        AgentCommandParams {
+            backend: None,
+            image: crate::cli::DEFAULT_IMAGE.to_string(),
+            mount: vec![],
+            port: vec![],
+            env: vec![],
+            no_network: false,
+            host_network: false,
+            memory: None,
+            cpu: None,
+            limits: Default::default(),
+            no_workspace: false,
+            workspace_path: crate::cli::DEFAULT_WORKSPACE_PATH.to_string(),
+            claude_dir: false,
+            claude_code_router_dir: false,
+            copilot_dir: false,
+            cursor_dir: false,
+            gemini_dir: false,
+            coderabbit_dir: false,
+            codex_dir: false,
+            jules_dir: false,
+            pi_dir: false,
+            agent_configs: false,
+            git_gpg: false,
+            upgrade: false,
+            force_layers: vec![],
+            cloud: false,
+            shell: false,
+            isolated: false,
+            verbose: false,
+            auth: false,
+            no_nix: false,
+            no_block_host: false,
+            podman: false,
+            hardened: false,
+            seccomp: false,
+            cow: false,
+            ephemeral: false,
+            session: None,
+            shared_home: false,
+            auto_recreate: false,
+            tui: false,
+            profile: None,
+            args: vec![],
+        }
+    }
+
+    #[test]
+    fn test_parse_full_settings() {
+        let settings = parse(
+            r#"
+image = "alpine:latest"
+mounts = ["./data:/data:ro", "/srv/cache:/cache"]
+ports = ["8080:80", "5353:53/udp"]
+memory = 4096
+cpu = 50
+git-gpg = true
+no-block-host = true
+layers = ["rust", "python"]
+
+[env]
+RUST_LOG = "debug"
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(settings.image.as_deref(), Some("alpine:latest"));
+        assert_eq!(
+            settings.mounts,
+            vec!["/project/data:/data:ro", "/srv/cache:/cache"]
+        );
+        assert_eq!(settings.ports, vec!["8080:80", "5353:53/udp"]);
+        assert_eq!(settings.memory, Some(4096));
+        assert_eq!(settings.cpu, Some(50));
+        assert_eq!(settings.git_gpg, Some(true));
+        assert_eq!(settings.no_block_host, Some(true));
+        assert_eq!(settings.layers, vec!["rust", "python"]);
+        assert_eq!(
+            settings.env.get("RUST_LOG").map(String::as_str),
+            Some("debug")
+        );
+    }
+
+    #[test]
+    fn test_unknown_key_reports_line() {
+        let err = parse("memory = 1024\ngit_gpg = true\n").unwrap_err();
+        let msg = err.to_string();
+        assert!(msg.contains("/project/.jail-ai.toml:2:"), "{msg}");
+        assert!(msg.contains("git_gpg"), "{msg}");
+    }
+
+    #[test]
+    fn test_invalid_values_report_line() {
+        let msg = parse("cpu = 50\n\nports = [\"80\"]\n")
+            .unwrap_err()
+            .to_string();
+        assert!(msg.contains(".jail-ai.toml:3:"), "{msg}");
+        assert!(msg.contains("Invalid port mapping format"), "{msg}");
+
+        let msg = parse("cpu = 250\n").unwrap_err().to_string();
+        assert!(msg.contains(".jail-ai.toml:1:"), "{msg}");
+
+        let msg = parse("mounts = [\"/src:relative\"]\n")
+            .unwrap_err()
+            .to_string();
+        assert!(msg.contains("absolute path"), "{msg}");
+
+        let msg = parse("backend = \"lxc\"\n").unwrap_err().to_string();
+        assert!(msg.contains("lxc"), "{msg}");
+
+        let msg = parse("memory = \"lots\"\n").unwrap_err().to_string();
+        assert!(msg.contains(".jail-ai.toml:1:"), "{msg}");
+    }
+
+    #[test]
+    fn test_conflicting_network_settings() {
+        assert!(parse("no-network = true\nhost-network = true\n").is_err());
+    }
+
+    #[test]
+    fn test_cli_wins_over_file() {
+        let settings = parse(
+            r#"
+image = "alpine:latest"
+memory = 4096
+cpu = 50
+workspace-path = "/code"
+layers = ["rust"]
+host-network = true
+ports = ["8080:80"]
+
+[env]
+MODE = "file"
+"#,
+        )
+        .unwrap();
+
+        let mut params = default_params();
+        params.image = "debian:latest".to_string();
+        params.memory = Some(1024);
+        params.no_network = true;
+        params.force_layers = vec!["python".to_string()];
+        params.env = vec!["MODE=cli".to_string()];
+        settings.apply_to_params(&mut params);
+
+        assert_eq!(params.image, "debian:latest");
+        assert_eq!(params.memory, Some(1024));
+        assert_eq!(params.cpu, Some(50));
+        assert_eq!(params.workspace_path, "/code");
+        assert_eq!(params.force_layers, vec!["python"]);
+        assert!(params.no_network);
+        assert!(!params.host_network);
+        assert_eq!(params.port, vec!["8080:80"]);
+        // CLI entries come last so they override file entries
+        assert_eq!(params.env, vec!["MODE=file", "MODE=cli"]);
+    }
+
+    #[test]
+    fn test_file_enables_flags() {
+        let settings = parse(
+            "git-gpg = true\npodman = true\nno-block-host = true\nhardened = true\nseccomp = true\ncow = true\n",
+        )
+        .unwrap();
+        let mut params = default_params();
+        settings.apply_to_params(&mut params);
+
+        assert!(params.git_gpg);
+        assert!(params.podman);
+        assert!(params.no_block_host);
+        assert!(params.hardened && params.seccomp && params.cow);
+        assert!(!params.isolated);
+        assert_eq!(params.image, crate::cli::DEFAULT_IMAGE);
+    }
+
+    #[test]
+    fn test_user_config_profiles() {
+        let config = UserConfig::from_toml_str(
+            r#"
+[defaults]
+memory = 8192
+git-gpg = true
+
+[profile.strict]
+no-network = true
+memory = 2048
+mounts = ["/srv/data:/data:ro"]
+
+[profile.dev]
+agent-configs = true
+mounts = ["datasets:/data"]
+"#,
+            Path::new("/home/user/.config/jail-ai/config.toml"),
+        )
+        .unwrap();
+
+        assert_eq!(config.defaults.memory, Some(8192));
+        assert_eq!(config.profile.len(), 2);
+
+        let strict = config.profile("strict").unwrap();
+        let merged = config.defaults.merge(strict);
+        assert_eq!(merged.memory, Some(2048));
+        assert_eq!(merged.git_gpg, Some(true));
+        assert_eq!(merged.no_network, Some(true));
+        assert_eq!(merged.mounts, vec!["/srv/data:/data:ro"]);
+
+        assert_eq!(
+            config.profile("dev").unwrap().mounts,
+            vec!["/home/user/.config/jail-ai/datasets:/data"]
+        );
+
+        let err = config.profile("missing").unwrap_err().to_string();
+        assert!(err.contains("dev, strict"), "{err}");
+    }
+
+    #[test]
+    fn test_user_config_unknown_key_in_profile() {
+        let err = UserConfig::from_toml_str(
+            "[profile.strict]\nno-network = true\nnetwork = false\n",
+            Path::new("/config.toml"),
+        )
+        .unwrap_err()
+        .to_string();
+        assert!(err.contains("/config.toml:3:"), "{err}");
+    }
+
+    #[test]
+    fn test_merge_layers() {
+        let global =
+            parse("host-network = true\nports = [\"8080:80\"]\n[env]\nA = \"1\"\nB = \"1\"\n")
+                .unwrap();
+        let project =
+            parse("no-network = true\nports = [\"9090:90\"]\n[env]\nB = \"2\"\n").unwrap();
+        let merged = global.merge(&project);
+
+        assert_eq!(merged.no_network, Some(true));
+        assert_eq!(merged.host_network, Some(false));
+        assert_eq!(merged.ports, vec!["8080:80", "9090:90"]);
+        assert_eq!(merged.env.get("A").map(String::as_str), Some("1"));
+        assert_eq!(merged.env.get("B").map(String::as_str), Some("2"));
+
+        // A layer can switch a flag back off
+        let dev = parse("git-gpg = true\n").unwrap();
+        let project = parse("git-gpg = false\n").unwrap();
+        assert_eq!(dev.merge(&project).git_gpg, Some(false));
+    }
+
+    #[test]
+    fn test_settings_layers_provenance() {
+        let mut layers = SettingsLayers::default();
+        layers.push(
+            Source::Global,
+            parse("memory = 8192\nports = [\"8080:80\"]\n").unwrap(),
+        );
+        layers.push(
+            Source::Profile("strict".to_string()),
+            parse("no-network = true\nmemory = 2048\n").unwrap(),
+        );
+        layers.push(Source::Project, parse("[env]\nMODE = \"dev\"\n").unwrap());
+
+        let mut params = default_params();
+        params.cpu = Some(10);
+        layers.push(Source::Cli, JailSettings::from_params(&params));
+
+        assert_eq!(
+            layers.source_of("memory"),
+            Some(Source::Profile("strict".to_string()))
+        );
+        assert_eq!(layers.source_of("cpu"), Some(Source::Cli));
+        assert_eq!(layers.source_of("git-gpg"), None);
+        assert_eq!(
+            layers.source_of_entry("ports", "8080:80"),
+            Some(Source::Global)
+        );
+        assert_eq!(
+            layers.source_of_entry("env", "MODE=dev"),
+            Some(Source::Project)
+        );
+        assert_eq!(layers.merged().memory, Some(2048));
+
+        // The network mode comes from the last layer setting either network key
+        layers.push(Source::Project, parse("host-network = true\n").unwrap());
+        assert_eq!(
+            layers.source_of("no-network"),
+            Some(Source::Profile("strict".to_string()))
+        );
+        assert_eq!(
+            layers.source_of_any(&["no-network", "host-network"]),
+            Some(Source::Project)
+        );
+        assert_eq!(
+            Source::Profile("strict".to_string()).to_string(),
+            "profile:strict"
+        );
+    }
+
+    #[test]
+    fn test_from_params_skips_defaults() {
+        let params = default_params();
+        assert_eq!(JailSettings::from_params(&params), JailSettings::default());
+    }
+
+    #[test]
+    fn test_load_project_settings() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(load_project_settings(dir.path()).unwrap().is_none());
+
+        std::fs::write(dir.path().join(PROJECT_CONFIG_FILE), "isolated = true\n").unwrap();
+        let settings = load_project_settings(dir.path()).unwrap().unwrap();
+        assert_eq!(settings.isolated, Some(true));
+    }
+}
@@ -0,0 +1,404 @@
+//! Jail snapshots: the container filesystem committed to an image plus an archive of the home volume
+//!
+//! Snapshots are recorded under `$XDG_DATA_HOME/jail-ai/snapshots` as `<id>.json`
+//! with the home volume in `<id>.home.tar`, both readable only by the user. Secret environment
+//! values are not recorded: a restore takes them from the live jail or the host environment.
+
+use crate::backend::JailBackend;
+use crate::config::JailConfig;
+use crate::error::{JailError, Result};
+use crate::image::SNAPSHOT_REPOSITORY;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use tracing::{debug, info, warn};
+
+/// Snapshot of a jail
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    /// Identifier, also the tag of the snapshot image
+    pub id: String,
+    pub jail: String,
+    /// Free-form description given at creation
+    #[serde(default)]
+    pub label: Option<String>,
+    pub created: DateTime<Utc>,
+    pub image: String,
+    /// The home volume was saved alongside the image
+    pub has_home: bool,
+    /// Jail configuration when the snapshot was taken, secrets redacted
+    pub config: JailConfig,
+}
+
+impl Snapshot {
+    fn metadata_path(dir: &Path, id: &str) -> PathBuf {
+        dir.join(format!("{id}.json"))
+    }
+
+    fn home_archive(&self, dir: &Path) -> PathBuf {
+        dir.join(format!("{}.home.tar", self.id))
+    }
+}
+
+/// Directory holding snapshot records and home archives
+pub fn snapshots_dir() -> Result<PathBuf> {
+    Ok(crate::jail_setup::get_jail_ai_data_dir()?.join("snapshots"))
+}
+
+/// Snapshot identifier: creation time to the millisecond and a hash of the jail name, valid as
+/// an image tag
+fn snapshot_id(jail: &str, created: DateTime<Utc>) -> String {
+    let hash = hex::encode(Sha256::digest(jail.as_bytes()));
+    format!("{}-{}", created.format("%Y%m%d-%H%M%S%3f"), &hash[..8])
+}
+
+/// Reserve an unused snapshot identifier by creating its (empty) record
+///
+/// Snapshots of a jail taken within the same millisecond get a numbered suffix.
+fn reserve_id(dir: &Path, jail: &str, created: DateTime<Utc>) -> Result<String> {
+    use std::os::unix::fs::OpenOptionsExt;
+    let base = snapshot_id(jail, created);
+    for attempt in 1.. {
+        let id = match attempt {
+            1 => base.clone(),
+            n => format!("{base}-{n}"),
+        };
+        match std::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .mode(0o600)
+            .open(Snapshot::metadata_path(dir, &id))
+        {
+            Ok(_) => return Ok(id),
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e.into()),
+        }
+    }
+    unreachable!("snapshot identifiers are unbounded")
+}
+
+/// Commit a jail and export its home volume
+pub async fn create_snapshot(
+    backend: &dyn JailBackend,
+    dir: &Path,
+    jail: &str,
+    label: Option<String>,
+    created: DateTime<Utc>,
+) -> Result<Snapshot> {
+    let config = backend.inspect(jail).await?;
+    std::fs::create_dir_all(dir)?;
+    let id = reserve_id(dir, jail, created)?;
+    let mut snapshot = Snapshot {
+        image: format!("{SNAPSHOT_REPOSITORY}:{id}"),
+        id,
+        jail: jail.to_string(),
+        label,
+        created,
+        has_home: false,
+        config: config.without_secrets(),
+    };
+    if let Err(e) = save_jail(backend, dir, &mut snapshot).await {
+        // Release the identifier
+        let _ = std::fs::remove_file(Snapshot::metadata_path(dir, &snapshot.id));
+        return Err(e);
+    }
+
+    let content = serde_json::to_string_pretty(&snapshot)?;
+    std::fs::write(Snapshot::metadata_path(dir, &snapshot.id), content)?;
+    Ok(snapshot)
+}
+
+/// Commit the jail to the snapshot image and export its home volume
+async fn save_jail(backend: &dyn JailBackend, dir: &Path, snapshot: &mut Snapshot) -> Result<()> {
+    let mut labels = vec![
+        ("jail-ai.snapshot.jail".to_string(), snapshot.jail.clone()),
+        (
+            "jail-ai.snapshot.created".to_string(),
+            snapshot.created.to_rfc3339(),
+        ),
+    ];
+    if let Some(label) = &snapshot.label {
+        labels.push(("jail-ai.snapshot.label".to_string(), label.clone()));
+    }
+    info!("Committing jail {} to {}", snapshot.jail, snapshot.image);
+    backend
+        .commit(&snapshot.jail, &snapshot.image, &labels)
+        .await?;
+
+    // Sessions sharing the home of their main jail save that one
+    let home = crate::backend::home_volume_name(&snapshot.config);
+    match backend
+        .export_volume(&home, &snapshot.home_archive(dir))
+        .await
+    {
+        Ok(()) => snapshot.has_home = true,
+        Err(JailError::NotFound(_)) => debug!("Jail {} has no home volume", snapshot.jail),
+        Err(e) => {
+            // Do not leave a snapshot image without its record
+            let _ = backend.remove_image(&snapshot.image).await;
+            return Err(e);
+        }
+    }
+    Ok(())
+}
+
+/// Recorded snapshots, oldest first, optionally only those of one jail
+pub fn list_snapshots(dir: &Path, jail: Option<&str>) -> Result<Vec<Snapshot>> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Ok(Vec::new());
+    };
+
+    let mut snapshots = Vec::new();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.extension().is_none_or(|ext| ext != "json") {
+            continue;
+        }
+        let snapshot: Snapshot = match std::fs::read_to_string(&path)
+            .map_err(JailError::from)
+            .and_then(|content| Ok(serde_json::from_str(&content)?))
+        {
+            Ok(snapshot) => snapshot,
+            Err(e) => {
+                debug!("Skipping snapshot record {}: {}", path.display(), e);
+                continue;
+            }
+        };
+        if jail.is_none_or(|jail| snapshot.jail == jail) {
+            snapshots.push(snapshot);
+        }
+    }
+    snapshots.sort_by_key(|snapshot| snapshot.created);
+    Ok(snapshots)
+}
+
+/// Recorded snapshot with this identifier
+pub fn find_snapshot(dir: &Path, id: &str) -> Result<Snapshot> {
+    let path = Snapshot::metadata_path(dir, id);
+    let content = std::fs::read_to_string(&path)
+        .map_err(|_| JailError::NotFound(format!("Snapshot '{id}' not found")))?;
+    Ok(serde_json::from_str(&content)?)
+}
+
+/// Recreate the jail from a snapshot, keeping its name and current mounts
+///
+/// The home volume is replaced by the saved one. The shared Nix volume is left as is.
+pub async fn restore_snapshot(
+    backend: &dyn JailBackend,
+    dir: &Path,
+    snapshot: &Snapshot,
+) -> Result<()> {
+    let exists = backend.exists(&snapshot.jail).await?;
+    let mut config = if exists {
+        backend.inspect(&snapshot.jail).await?
+    } else {
+        let mut config = snapshot.config.clone();
+        fill_secrets(&mut config, |key| std::env::var(key).ok());
+        config
+    };
+    config.base_image = snapshot.image.clone();
+    config.upgrade = true;
+
+    if exists && backend.is_running(&snapshot.jail).await? {
+        backend.stop(&snapshot.jail).await?;
+    }
+    if snapshot.has_home {
+        let volume = crate::backend::home_volume_name(&snapshot.config);
+        match backend.reset_volume(&volume, &[]).await {
+            Ok(()) | Err(JailError::NotFound(_)) => {}
+            Err(e) => return Err(e),
+        }
+        backend
+            .import_volume(&volume, &snapshot.home_archive(dir))
+            .await?;
+    }
+
+    info!("Recreating jail {} from {}", snapshot.jail, snapshot.image);
+    backend.create(&config).await
+}
+
+/// Put back redacted environment values from `lookup`, dropping those it does not know
+fn fill_secrets(config: &mut JailConfig, lookup: impl Fn(&str) -> Option<String>) {
+    config.environment.retain_mut(|(key, value)| {
+        if value != crate::config::REDACTED {
+            return true;
+        }
+        match lookup(key) {
+            Some(secret) => {
+                *value = secret;
+                true
+            }
+            None => {
+                warn!("{} is not set, the restored jail goes without it", key);
+                false
+            }
+        }
+    });
+}
+
+/// Remove the snapshot image, home archive and record
+pub async fn delete_snapshot(
+    backend: &dyn JailBackend,
+    dir: &Path,
+    snapshot: &Snapshot,
+) -> Result<()> {
+    if let Err(e) = backend.remove_image(&snapshot.image).await {
+        debug!("Could not remove image {}: {}", snapshot.image, e);
+    }
+    if snapshot.has_home {
+        let _ = std::fs::remove_file(snapshot.home_archive(dir));
+    }
+    std::fs::remove_file(Snapshot::metadata_path(dir, &snapshot.id))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::fake::{FakeBackend, FakeCall};
+    use crate::jail::JailBuilder;
+
+    const JAIL: &str = "jail__app__12345678__claude";
+
+    #[test]
+    fn test_snapshot_id() {
+        let created = DateTime::parse_from_rfc3339("2026-03-04T05:06:07Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let id = snapshot_id(JAIL, created);
+        assert!(id.starts_with("20260304-050607000-"));
+        assert_eq!(id.len(), "20260304-050607000-".len() + 8);
+        assert!(crate::image::is_snapshot_image(&format!(
+            "{SNAPSHOT_REPOSITORY}:{id}"
+        )));
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_create_restore_delete() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = JailBuilder::new(JAIL)
+            .base_image("localhost/jail-ai-agent-claude:base-claude")
+            .bind_mount("/src/app", "/workspace", false)
+            .build()
+            .config()
+            .clone();
+        let fake = FakeBackend::new().with_jail(config, true);
+
+        let snapshot = create_snapshot(
+            &fake,
+            dir.path(),
+            JAIL,
+            Some("before refactor".to_string()),
+            Utc::now(),
+        )
+        .await
+        .unwrap();
+        assert!(snapshot.has_home);
+        let listed = list_snapshots(dir.path(), Some(JAIL)).unwrap();
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].label.as_deref(), Some("before refactor"));
+        assert!(list_snapshots(dir.path(), Some("other"))
+            .unwrap()
+            .is_empty());
+
+        let found = find_snapshot(dir.path(), &snapshot.id).unwrap();
+        restore_snapshot(&fake, dir.path(), &found).await.unwrap();
+        let restored = fake.jail(JAIL).unwrap();
+        assert_eq!(restored.base_image, snapshot.image);
+        assert_eq!(restored.bind_mounts.len(), 1);
+
+        let home = format!("{JAIL}__home");
+        let calls = fake.calls();
+        assert!(matches!(&calls[0], FakeCall::Commit { image, .. } if *image == snapshot.image));
+        assert!(matches!(&calls[1], FakeCall::ExportVolume { volume, .. } if *volume == home));
+        assert_eq!(calls[2], FakeCall::Stop(JAIL.to_string()));
+        assert_eq!(
+            calls[3],
+            FakeCall::ResetVolume {
+                volume: home.clone(),
+                keep: Vec::new(),
+            }
+        );
+        assert!(matches!(&calls[4], FakeCall::ImportVolume { volume, .. } if *volume == home));
+
+        delete_snapshot(&fake, dir.path(), &found).await.unwrap();
+        assert!(list_snapshots(dir.path(), None).unwrap().is_empty());
+        assert!(fake
+            .calls()
+            .contains(&FakeCall::RemoveImage(snapshot.image)));
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_record_keeps_no_secrets() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = tempfile::tempdir().unwrap();
+        let config = JailBuilder::new(JAIL)
+            .env("GITHUB_TOKEN", "ghp_secret")
+            .env("EDITOR", "vim")
+            .build()
+            .config()
+            .clone();
+        let fake = FakeBackend::new().with_jail(config, true);
+
+        let snapshot = create_snapshot(&fake, dir.path(), JAIL, None, Utc::now())
+            .await
+            .unwrap();
+        let record = Snapshot::metadata_path(dir.path(), &snapshot.id);
+        let content = std::fs::read_to_string(&record).unwrap();
+        assert!(!content.contains("ghp_secret"));
+        assert!(content.contains("vim"));
+        let mode = std::fs::metadata(&record).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+
+        let mut restored = snapshot.config.clone();
+        fill_secrets(&mut restored, |key| {
+            (key == "GITHUB_TOKEN").then(|| "ghp_current".to_string())
+        });
+        assert!(restored
+            .environment
+            .contains(&("GITHUB_TOKEN".to_string(), "ghp_current".to_string())));
+        let mut unset = snapshot.config.clone();
+        fill_secrets(&mut unset, |_| None);
+        assert_eq!(
+            unset.environment,
+            vec![("EDITOR".to_string(), "vim".to_string())]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_snapshots_in_the_same_millisecond() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = JailBuilder::new(JAIL).build().config().clone();
+        let fake = FakeBackend::new().with_jail(config, true);
+
+        let created = Utc::now();
+        let first = create_snapshot(&fake, dir.path(), JAIL, None, created)
+            .await
+            .unwrap();
+        let second = create_snapshot(&fake, dir.path(), JAIL, None, created)
+            .await
+            .unwrap();
+        assert_ne!(first.id, second.id);
+        assert_ne!(first.image, second.image);
+        assert_eq!(list_snapshots(dir.path(), Some(JAIL)).unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_of_shared_home_session() {
+        let dir = tempfile::tempdir().unwrap();
+        let session = crate::session::session_jail_name("jail__app__12345678", "claude", "2");
+        let mut config = JailBuilder::new(&session).build().config().clone();
+        config.shared_home = true;
+        let fake = FakeBackend::new().with_jail(config, true);
+
+        create_snapshot(&fake, dir.path(), &session, None, Utc::now())
+            .await
+            .unwrap();
+        let home = format!("{JAIL}__home");
+        assert!(fake
+            .calls()
+            .iter()
+            .any(|call| matches!(call, FakeCall::ExportVolume { volume, .. } if *volume == home)));
+    }
+}
@@ -0,0 +1,1559 @@
+//! Filtered podman/docker socket for Podman-in-Podman
+//!
+//! `--podman` mounts a per-jail proxy socket instead of the user's engine socket. The proxy,
+//! a detached `jail-ai socket-proxy` process, forwards an allowlist of the libpod and Docker
+//! APIs: containers created through it are labelled as children of the jail and are the only
+//! ones it reaches, without privileges, host namespaces or bind mounts outside the workspace.
+//! Denied requests are logged to `$XDG_DATA_HOME/jail-ai/socket-proxy/<jail>.log`.
+
+use crate::backend::podman_api::{encode_path_segment, read_chunked_body, read_response};
+use crate::config::JailConfig;
+use crate::error::{JailError, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{UnixListener, UnixStream};
+use tracing::{debug, info, warn};
+
+/// Label marking the containers created through the proxy of a jail
+const PARENT_LABEL: &str = "jail-ai.parent";
+
+/// Names routed as API endpoints when given in place of a container
+const RESERVED_NAMES: &[&str] = &["json", "create", "prune", "stats", "showmounted"];
+
+/// Largest request body read to be checked (container and exec creation)
+const MAX_BODY: usize = 1024 * 1024;
+
+/// Denial reason, or the value that passed the checks
+type Check<T> = std::result::Result<T, String>;
+
+/// Image build query parameters forwarded as is
+///
+/// Others are denied, like `remote` (fetched by the engine outside the jail's network),
+/// `outputs` and `secrets` (host paths) or `isolation`. `networkmode` and `volume` are checked.
+const BUILD_PARAMS: &[&str] = &[
+    "t",
+    "dockerfile",
+    "q",
+    "nocache",
+    "cachefrom",
+    "pull",
+    "pullpolicy",
+    "rm",
+    "forcerm",
+    "memory",
+    "memswap",
+    "cpushares",
+    "cpusetcpus",
+    "cpuperiod",
+    "cpuquota",
+    "shmsize",
+    "buildargs",
+    "labels",
+    "annotations",
+    "target",
+    "platform",
+    "allplatforms",
+    "squash",
+    "layers",
+    "jobs",
+    "format",
+    "extrahosts",
+    "dnsservers",
+    "dnsoptions",
+    "dnssearch",
+    "ulimits",
+    "manifest",
+    "timestamp",
+    "sourcedatepoch",
+    "rewritetimestamp",
+    "omithistory",
+    "identitylabel",
+    "ignorefile",
+    "nohosts",
+    "httpproxy",
+    "unsetenv",
+    "unsetlabel",
+    "retry",
+    "retry-delay",
+    "version",
+];
+
+/// Proxy settings of a jail, recorded so the proxy is restarted with its jail
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ProxyConfig {
+    jail: String,
+    /// Engine socket allowed requests are forwarded to
+    upstream: PathBuf,
+    /// Proxy socket, in the directory mounted into the jail
+    socket: PathBuf,
+    /// Host directory child containers may bind mount
+    workspace: Option<WorkspaceMount>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct WorkspaceMount {
+    source: PathBuf,
+    /// Path of the workspace in the jail, accepted in place of the host path
+    target: PathBuf,
+    /// Child mounts must be read-only (read-only or copy-on-write workspace)
+    readonly: bool,
+}
+
+fn records_dir() -> Result<PathBuf> {
+    Ok(crate::jail_setup::get_jail_ai_data_dir()?.join("socket-proxy"))
+}
+
+fn record_file(jail: &str, extension: &str) -> Result<PathBuf> {
+    Ok(records_dir()?.join(format!("{jail}.{extension}")))
+}
+
+/// Directory holding the proxy socket of a jail, mounted into the jail
+///
+/// Socket paths are limited to 108 bytes, so it is named after a hash of the jail name.
+pub fn socket_dir(jail: &str) -> PathBuf {
+    let runtime_dir = std::env::var_os("XDG_RUNTIME_DIR")
+        .filter(|dir| !dir.is_empty())
+        .map_or_else(std::env::temp_dir, PathBuf::from);
+    let hash = hex::encode(Sha256::digest(jail.as_bytes()));
+    runtime_dir.join("jail-ai-proxy").join(&hash[..12])
+}
+
+fn load(jail: &str) -> Result<Option<ProxyConfig>> {
+    match std::fs::read_to_string(record_file(jail, "json")?) {
+        Ok(content) => Ok(Some(serde_json::from_str(&content)?)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Record the proxy of a jail forwarding to `upstream`, and (re)start it
+pub async fn prepare(config: &JailConfig, engine: &str, upstream: &Path) -> Result<()> {
+    let proxy = ProxyConfig {
+        jail: config.name.clone(),
+        upstream: upstream.to_path_buf(),
+        socket: socket_dir(&config.name).join(format!("{engine}.sock")),
+        workspace: crate::backend::workspace_mount(config).map(|mount| WorkspaceMount {
+            source: mount.source.clone(),
+            target: mount.target.clone(),
+            readonly: mount.readonly || config.cow,
+        }),
+    };
+    std::fs::create_dir_all(records_dir()?)?;
+    std::fs::write(
+        record_file(&config.name, "json")?,
+        serde_json::to_string_pretty(&proxy)?,
+    )?;
+
+    stop(&config.name);
+    ensure_running(&config.name).await
+}
+
+/// PID of the running proxy of a jail
+fn running_pid(jail: &str) -> Option<u32> {
+    let pid = std::fs::read_to_string(record_file(jail, "pid").ok()?)
+        .ok()?
+        .trim()
+        .parse::<u32>()
+        .ok()?;
+    // The PID may have been reused since the proxy exited
+    let cmdline = std::fs::read(format!("/proc/{pid}/cmdline")).ok()?;
+    let args: Vec<&[u8]> = cmdline.split(|&byte| byte == 0).collect();
+    (args.contains(&b"socket-proxy".as_slice()) && args.contains(&jail.as_bytes())).then_some(pid)
+}
+
+/// Start the proxy of a jail unless it runs already, nothing to do for jails without one
+pub async fn ensure_running(jail: &str) -> Result<()> {
+    let Some(proxy) = load(jail)? else {
+        return Ok(());
+    };
+    if running_pid(jail).is_some() {
+        return Ok(());
+    }
+
+    use std::os::unix::fs::PermissionsExt;
+    use std::os::unix::process::CommandExt;
+    let dir = socket_dir(jail);
+    std::fs::create_dir_all(&dir)?;
+    std::fs::set_permissions(&dir, std::fs::Permissions::from_mode(0o700))?;
+    let _ = std::fs::remove_file(&proxy.socket);
+
+    let log = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(record_file(jail, "log")?)?;
+    let child = std::process::Command::new(std::env::current_exe()?)
+        .arg("socket-proxy")
+        .arg(jail)
+        // Denied requests are logged to stdout
+        .env("NO_COLOR", "1")
+        .stdin(std::process::Stdio::null())
+        .stdout(log.try_clone()?)
+        .stderr(log)
+        // Keep it out of the terminal process group, Ctrl-C must not stop it
+        .process_group(0)
+        .spawn()
+        .map_err(|e| JailError::Backend(format!("Failed to start the socket proxy: {e}")))?;
+    std::fs::write(record_file(jail, "pid")?, child.id().to_string())?;
+
+    // The socket appears once the proxy listens
+    let mut attempts = 0;
+    while !proxy.socket.exists() && attempts < 100 {
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        attempts += 1;
+    }
+    info!(
+        "Started socket proxy for {} at {}",
+        jail,
+        proxy.socket.display()
+    );
+    Ok(())
+}
+
+/// Stop the proxy of a jail, keeping its record for the next start
+pub fn stop(jail: &str) {
+    if let Some(pid) = running_pid(jail) {
+        debug!("Stopping socket proxy {} of {}", pid, jail);
+        let _ = std::process::Command::new("kill")
+            .arg(pid.to_string())
+            .status();
+    }
+}
+
+/// Stop the proxy of a jail and forget it
+pub fn remove(jail: &str) {
+    stop(jail);
+    for extension in ["json", "pid", "log"] {
+        if let Ok(path) = record_file(jail, extension) {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+    let _ = std::fs::remove_dir_all(socket_dir(jail));
+}
+
+/// Serve the proxy socket of a jail until the jail is removed (`jail-ai socket-proxy`)
+pub async fn serve(jail: &str) -> Result<()> {
+    let proxy = Arc::new(load(jail)?.ok_or_else(|| {
+        JailError::NotFound(format!("No socket proxy recorded for jail '{jail}'"))
+    })?);
+    let _ = std::fs::remove_file(&proxy.socket);
+    let listener = UnixListener::bind(&proxy.socket)?;
+    info!(
+        "Forwarding {} to {}",
+        proxy.socket.display(),
+        proxy.upstream.display()
+    );
+
+    // The jail is created once the proxy listens, only look for it after a while
+    let period = Duration::from_secs(60);
+    let mut check = tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+    loop {
+        tokio::select! {
+            accepted = listener.accept() => {
+                let (stream, _) = accepted?;
+                let proxy = Arc::clone(&proxy);
+                tokio::spawn(async move {
+                    if let Err(e) = handle(&proxy, stream).await {
+                        debug!("Proxied connection failed: {}", e);
+                    }
+                });
+            }
+            _ = check.tick() => {
+                let path = format!("/containers/{}/json", encode_path_segment(jail));
+                if matches!(lookup(&proxy.upstream, &path).await, Ok((404, _))) {
+                    info!("Jail {} no longer exists, stopping", jail);
+                    let _ = std::fs::remove_file(&proxy.socket);
+                    return Ok(());
+                }
+            }
+        }
+    }
+}
+
+/// Request line and headers of an HTTP/1.1 request
+#[derive(Debug)]
+struct RequestHead {
+    method: String,
+    target: String,
+    headers: Vec<(String, String)>,
+}
+
+impl RequestHead {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut head = format!("{} {} HTTP/1.1\r\n", self.method, self.target);
+        for (name, value) in &self.headers {
+            head.push_str(&format!("{name}: {value}\r\n"));
+        }
+        head.push_str("\r\n");
+        head.into_bytes()
+    }
+}
+
+fn invalid_data(message: impl Into<String>) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, message.into())
+}
+
+/// Read the head of the next request, `None` when the client closed the connection
+async fn read_head(client: &mut BufReader<UnixStream>) -> std::io::Result<Option<RequestHead>> {
+    let mut request_line = String::new();
+    if client.read_line(&mut request_line).await? == 0 {
+        return Ok(None);
+    }
+    let mut parts = request_line.split_whitespace();
+    let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+        return Err(invalid_data(format!(
+            "invalid request line: {}",
+            request_line.trim()
+        )));
+    };
+    let mut head = RequestHead {
+        method: method.to_string(),
+        target: target.to_string(),
+        headers: Vec::new(),
+    };
+
+    loop {
+        let mut line = String::new();
+        if client.read_line(&mut line).await? == 0 {
+            return Err(invalid_data("connection closed in headers"));
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            return Ok(Some(head));
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            head.headers
+                .push((name.trim().to_string(), value.trim().to_string()));
+        }
+    }
+}
+
+async fn read_body(
+    head: &RequestHead,
+    client: &mut BufReader<UnixStream>,
+) -> std::io::Result<Vec<u8>> {
+    let body = if head
+        .header("transfer-encoding")
+        .is_some_and(|value| value.eq_ignore_ascii_case("chunked"))
+    {
+        read_chunked_body(client).await?
+    } else {
+        let length = match head.header("content-length") {
+            Some(length) => length
+                .parse::<usize>()
+                .map_err(|_| invalid_data("invalid Content-Length"))?,
+            None => 0,
+        };
+        if length > MAX_BODY {
+            return Err(invalid_data("request body too large"));
+        }
+        let mut body = vec![0; length];
+        client.read_exact(&mut body).await?;
+        body
+    };
+    if body.len() > MAX_BODY {
+        return Err(invalid_data("request body too large"));
+    }
+    Ok(body)
+}
+
+/// GET a Docker-compatible endpoint of the engine, understood by podman and docker alike
+async fn lookup(upstream: &Path, path: &str) -> std::io::Result<(u16, Value)> {
+    let mut stream = BufReader::new(UnixStream::connect(upstream).await?);
+    let request = format!("GET {path} HTTP/1.1\r\nHost: d\r\nConnection: close\r\n\r\n");
+    stream.get_mut().write_all(request.as_bytes()).await?;
+    let (response, _) = read_response(&mut stream).await?;
+    let body = serde_json::from_slice(&response.body).unwrap_or(Value::Null);
+    Ok((response.status, body))
+}
+
+/// Whether a container was created through the proxy of this jail
+async fn is_child(proxy: &ProxyConfig, id: &str) -> bool {
+    let path = format!("/containers/{}/json", encode_path_segment(id));
+    match lookup(&proxy.upstream, &path).await {
+        Ok((200, container)) => {
+            container["Config"]["Labels"][PARENT_LABEL].as_str() == Some(proxy.jail.as_str())
+        }
+        _ => false,
+    }
+}
+
+/// What the proxy does with a request, by endpoint
+#[derive(Debug, PartialEq, Eq)]
+enum Route {
+    Forward,
+    /// Container list, restricted to the children of the jail
+    ListContainers,
+    CreateContainer {
+        libpod: bool,
+    },
+    /// Image build, its query parameters checked
+    Build,
+    /// Endpoint of an existing container, `exec` when creating an exec session in it and
+    /// `rename` when renaming it
+    Container {
+        id: String,
+        exec: bool,
+        rename: bool,
+    },
+    /// Endpoint of an exec session
+    Exec(String),
+    Deny,
+}
+
+/// API path without its version and `/libpod` prefixes, and whether it is a libpod one
+fn api_path(path: &str) -> (&str, bool) {
+    let path = match path.strip_prefix("/v") {
+        Some(rest) if rest.starts_with(|c: char| c.is_ascii_digit()) => {
+            rest.find('/').map_or("", |pos| &rest[pos..])
+        }
+        _ => path,
+    };
+    match path.strip_prefix("/libpod") {
+        Some(rest) if rest.is_empty() || rest.starts_with('/') => (rest, true),
+        _ => (path, false),
+    }
+}
+
+/// Container and exec identifiers: names, IDs or ID prefixes
+fn is_identifier(id: &str) -> bool {
+    !id.is_empty()
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-'))
+}
+
+fn route(method: &str, path: &str) -> Route {
+    let (path, libpod) = api_path(path);
+    let segments: Vec<&str> = path.trim_start_matches('/').split('/').collect();
+    let read = method == "GET" || method == "HEAD";
+    let post = method == "POST";
+
+    match segments.as_slice() {
+        ["_ping"] | ["version"] | ["info"] if read => Route::Forward,
+        ["images", ..] if read => Route::Forward,
+        ["images", "create"] | ["images", "pull"] if post => Route::Forward,
+        ["build"] if post => Route::Build,
+        ["containers", "json"] if read => Route::ListContainers,
+        ["containers", "create"] if post => Route::CreateContainer { libpod },
+        ["containers", id, rest @ ..] if is_identifier(id) && !RESERVED_NAMES.contains(id) => {
+            Route::Container {
+                id: id.to_string(),
+                exec: post && rest == ["exec"],
+                rename: post && rest == ["rename"],
+            }
+        }
+        ["exec", id, ..] if is_identifier(id) => Route::Exec(id.to_string()),
+        _ => Route::Deny,
+    }
+}
+
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => decoded.push(b' '),
+            b'%' if i + 2 < bytes.len() => {
+                let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).ok();
+                match hex.and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+                    Some(byte) => {
+                        decoded.push(byte);
+                        i += 2;
+                    }
+                    None => decoded.push(b'%'),
+                }
+            }
+            byte => decoded.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// First value of a query parameter, decoded
+fn query_param(query: &str, key: &str) -> Option<String> {
+    query
+        .split('&')
+        .filter_map(|pair| pair.split_once('=').or(Some((pair, ""))))
+        .find(|(name, _)| percent_decode(name) == key)
+        .map(|(_, value)| percent_decode(value))
+}
+
+/// Query of a container list with a filter on the parent label of the jail added
+///
+/// Like the engines, only the first `filters` parameter is used.
+fn with_label_filter(query: &str, jail: &str) -> Check<String> {
+    let mut filters = match query_param(query, "filters") {
+        Some(filters) if !filters.is_empty() => serde_json::from_str(&filters)
+            .map_err(|e| format!("invalid container list filters: {e}"))?,
+        _ => serde_json::json!({}),
+    };
+    let label = format!("{PARENT_LABEL}={jail}");
+    let filters_map = filters
+        .as_object_mut()
+        .ok_or("container list filters must be an object")?;
+    match filters_map
+        .entry("label")
+        .or_insert_with(|| Value::Array(Vec::new()))
+    {
+        Value::Array(labels) => labels.push(Value::String(label)),
+        // Legacy `{"label": {"key=value": true}}` form
+        Value::Object(labels) => {
+            labels.insert(label, Value::Bool(true));
+        }
+        _ => return Err("invalid label filter".to_string()),
+    }
+
+    let mut params: Vec<String> = query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .filter(|pair| {
+            percent_decode(pair.split_once('=').map_or(pair, |(name, _)| name)) != "filters"
+        })
+        .map(str::to_string)
+        .collect();
+    params.push(format!(
+        "filters={}",
+        encode_path_segment(&filters.to_string())
+    ));
+    Ok(params.join("&"))
+}
+
+/// Field of a JSON object, matched case-insensitively as the engines decode it
+fn field<'a>(object: &'a mut Value, name: &str) -> Check<Option<&'a mut Value>> {
+    let map = object
+        .as_object_mut()
+        .ok_or_else(|| format!("expected an object around {name}"))?;
+    let keys: Vec<String> = map
+        .keys()
+        .filter(|key| key.eq_ignore_ascii_case(name))
+        .cloned()
+        .collect();
+    match keys.as_slice() {
+        [] => Ok(None),
+        [key] => Ok(map.get_mut(key)),
+        _ => Err(format!("{name} is given more than once")),
+    }
+}
+
+/// Whether a field holds anything besides its zero value
+fn is_set(value: Option<&mut Value>) -> bool {
+    match value {
+        None | Some(Value::Null) | Some(Value::Bool(false)) => false,
+        Some(Value::String(value)) => !value.is_empty(),
+        Some(Value::Array(values)) => !values.is_empty(),
+        Some(Value::Object(values)) => !values.is_empty(),
+        Some(Value::Number(number)) => number.as_f64() != Some(0.0),
+        Some(Value::Bool(true)) => true,
+    }
+}
+
+fn deny_fields(object: &mut Value, names: &[&str]) -> Check<()> {
+    for name in names {
+        if is_set(field(object, name)?) {
+            return Err(format!("{name} is not allowed"));
+        }
+    }
+    Ok(())
+}
+
+/// Container names must not pass for jails or API endpoints
+fn check_name(name: &str) -> Check<()> {
+    if name.starts_with("jail__") || RESERVED_NAMES.contains(&name) {
+        return Err(format!("container name '{name}' is not allowed"));
+    }
+    Ok(())
+}
+
+/// Named volumes of jails (home and Nix) are off limits
+fn check_volume_name(name: &str) -> Check<()> {
+    if name.starts_with("jail__") {
+        return Err(format!("volume '{name}' belongs to a jail"));
+    }
+    Ok(())
+}
+
+/// Namespace modes other than private ones (`host`, `container:ID`, `ns:PATH`...) are denied
+fn check_namespace_mode(name: &str, mode: &str) -> Check<()> {
+    if mode == "host" || mode.contains(':') {
+        return Err(format!("{name} '{mode}' is not allowed"));
+    }
+    Ok(())
+}
+
+/// Security options may not lift confinement, and seccomp keeps the engine default profile
+fn check_security_opt(option: &str) -> Check<()> {
+    let (name, value) = option.split_once(['=', ':']).unwrap_or((option, ""));
+    let custom_seccomp = name == "seccomp" && value != "builtin";
+    if option.contains("unconfined") || option.contains("disable") || custom_seccomp {
+        return Err(format!("security option '{option}' is not allowed"));
+    }
+    Ok(())
+}
+
+/// Host path of a bind mount source, given as a jail or host path inside the workspace
+///
+/// Symlinks are resolved so the engine gets a path that cannot leave the workspace.
+fn resolve_source(proxy: &ProxyConfig, source: &str, readonly: bool) -> Check<String> {
+    let workspace = proxy
+        .workspace
+        .as_ref()
+        .ok_or("host bind mounts are not allowed in a jail without workspace")?;
+    let path = Path::new(source);
+    let host_path = match path.strip_prefix(&workspace.target) {
+        Ok(rest) => workspace.source.join(rest),
+        Err(_) => path.to_path_buf(),
+    };
+    let resolved = host_path
+        .canonicalize()
+        .map_err(|_| format!("bind mount source {source} does not exist"))?;
+    let root = workspace.source.canonicalize().map_err(|e| {
+        format!(
+            "workspace {} is unavailable: {e}",
+            workspace.source.display()
+        )
+    })?;
+    if !resolved.starts_with(&root) {
+        return Err(format!(
+            "bind mount source {source} is outside the workspace"
+        ));
+    }
+    if workspace.readonly && !readonly {
+        return Err(format!(
+            "bind mount of {source} must be read-only, the workspace is"
+        ));
+    }
+    Ok(resolved.display().to_string())
+}
+
+/// Query of an image build, with its network mode and bind mounts checked
+fn check_build_query(proxy: &ProxyConfig, query: &str) -> Check<String> {
+    let mut params = Vec::new();
+    for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+        let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+        let (name, value) = (percent_decode(name), percent_decode(value));
+        match name.as_str() {
+            "networkmode" => check_namespace_mode(&name, &value)?,
+            "volume" => {
+                let bind = check_bind(proxy, &value)?;
+                params.push(format!("volume={}", encode_path_segment(&bind)));
+                continue;
+            }
+            name if BUILD_PARAMS.contains(&name) => {}
+            name => return Err(format!("build parameter '{name}' is not allowed")),
+        }
+        params.push(pair.to_string());
+    }
+    Ok(params.join("&"))
+}
+
+/// `SOURCE:TARGET[:OPTIONS]` bind of a Docker container, host paths resolved
+fn check_bind(proxy: &ProxyConfig, bind: &str) -> Check<String> {
+    let (source, rest) = bind
+        .split_once(':')
+        .ok_or_else(|| format!("invalid bind '{bind}'"))?;
+    if !source.starts_with('/') {
+        check_volume_name(source)?;
+        return Ok(bind.to_string());
+    }
+    let readonly = rest
+        .split_once(':')
+        .is_some_and(|(_, options)| options.split(',').any(|option| option == "ro"));
+    Ok(format!(
+        "{}:{rest}",
+        resolve_source(proxy, source, readonly)?
+    ))
+}
+
+fn check_docker_mount(proxy: &ProxyConfig, mount: &mut Value) -> Check<()> {
+    let kind = field(mount, "Type")?
+        .and_then(|kind| kind.as_str())
+        .unwrap_or("volume")
+        .to_string();
+    let readonly = field(mount, "ReadOnly")?
+        .and_then(|readonly| readonly.as_bool())
+        .unwrap_or(false);
+    if let Some(options) = field(mount, "VolumeOptions")? {
+        // The local driver mounts any host path with `device` options
+        deny_fields(options, &["DriverConfig"])?;
+    }
+    let source = match field(mount, "Source")? {
+        Some(Value::String(source)) => Some(source),
+        _ => None,
+    };
+    match (kind.as_str(), source) {
+        ("bind", Some(source)) => *source = resolve_source(proxy, source, readonly)?,
+        ("bind", None) => return Err("bind mount without source".to_string()),
+        ("volume", Some(source)) => check_volume_name(source)?,
+        ("volume", None) | ("tmpfs", _) => {}
+        (kind, _) => return Err(format!("{kind} mounts are not allowed")),
+    }
+    Ok(())
+}
+
+/// Label the container as a child of the jail
+fn add_parent_label(body: &mut Value, key: &str, jail: &str) -> Check<()> {
+    if field(body, key)?.is_none() {
+        body[key] = Value::Null;
+    }
+    if let Some(labels) = field(body, key)? {
+        if labels.is_null() {
+            *labels = serde_json::json!({});
+        }
+        labels
+            .as_object_mut()
+            .ok_or_else(|| format!("{key} must be an object"))?
+            .insert(PARENT_LABEL.to_string(), Value::String(jail.to_string()));
+    }
+    Ok(())
+}
+
+/// Check a Docker `ContainerCreate` body
+fn check_docker_create(proxy: &ProxyConfig, body: &mut Value) -> Check<()> {
+    if let Some(host) = field(body, "HostConfig")? {
+        deny_fields(
+            host,
+            &[
+                "Privileged",
+                "CapAdd",
+                "Devices",
+                "DeviceRequests",
+                "DeviceCgroupRules",
+                "VolumesFrom",
+                "CgroupParent",
+                "Runtime",
+                "Annotations",
+            ],
+        )?;
+        // Empty lists unmask /proc and /sys paths
+        for name in ["MaskedPaths", "ReadonlyPaths"] {
+            if field(host, name)?.is_some() {
+                return Err(format!("{name} is not allowed"));
+            }
+        }
+        for name in [
+            "NetworkMode",
+            "PidMode",
+            "IpcMode",
+            "UTSMode",
+            "UsernsMode",
+            "CgroupnsMode",
+        ] {
+            if let Some(Value::String(mode)) = field(host, name)? {
+                check_namespace_mode(name, mode)?;
+            }
+        }
+        if let Some(log) = field(host, "LogConfig")? {
+            // Podman turns the path option into the host log file of the container
+            if let Some(config) = field(log, "Config")? {
+                if is_set(field(config, "path")?) {
+                    return Err("LogConfig.Config.path is not allowed".to_string());
+                }
+            }
+        }
+        if let Some(Value::Array(options)) = field(host, "SecurityOpt")? {
+            for option in options.iter() {
+                check_security_opt(option.as_str().unwrap_or_default())?;
+            }
+        }
+        if let Some(Value::Array(binds)) = field(host, "Binds")? {
+            for bind in binds.iter_mut() {
+                let checked = check_bind(proxy, bind.as_str().unwrap_or_default())?;
+                *bind = Value::String(checked);
+            }
+        }
+        if let Some(Value::Array(mounts)) = field(host, "Mounts")? {
+            for mount in mounts.iter_mut() {
+                check_docker_mount(proxy, mount)?;
+            }
+        }
+    }
+    add_parent_label(body, "Labels", &proxy.jail)
+}
+
+/// Check a libpod `SpecGenerator` body
+fn check_libpod_create(proxy: &ProxyConfig, body: &mut Value) -> Check<()> {
+    deny_fields(
+        body,
+        &[
+            "privileged",
+            "cap_add",
+            "devices",
+            "device_cgroup_rule",
+            "volumes_from",
+            "rootfs",
+            "cgroup_parent",
+            "oci_runtime",
+            "conmon_pid_file",
+            "init_path",
+            "seccomp_profile_path",
+            "annotations",
+            "unmask",
+            "secrets",
+            "secret_env",
+            "network_options",
+        ],
+    )?;
+    if let Some(Value::String(name)) = field(body, "name")? {
+        check_name(name)?;
+    }
+    for name in ["netns", "pidns", "ipcns", "utsns", "userns", "cgroupns"] {
+        if let Some(namespace) = field(body, name)? {
+            let mode = field(namespace, "nsmode")?
+                .and_then(|mode| mode.as_str())
+                .unwrap_or_default();
+            if matches!(mode, "host" | "path" | "container" | "from_container") {
+                return Err(format!("{name} '{mode}' is not allowed"));
+            }
+        }
+    }
+    // The image policy takes the profile from the image labels
+    if let Some(Value::String(policy)) = field(body, "seccomp_policy")? {
+        if !policy.is_empty() && policy != "default" {
+            return Err(format!("seccomp_policy '{policy}' is not allowed"));
+        }
+    }
+    for name in ["selinux_opts", "apparmor_profile"] {
+        let options = match field(body, name)? {
+            Some(Value::Array(options)) => options.clone(),
+            Some(option) => vec![option.clone()],
+            None => Vec::new(),
+        };
+        for option in options {
+            check_security_opt(option.as_str().unwrap_or_default())?;
+        }
+    }
+    if let Some(Value::Object(log)) = field(body, "log_configuration")? {
+        if log.get("path").is_some_and(|path| !path.is_null()) {
+            return Err("log_configuration.path is not allowed".to_string());
+        }
+    }
+    if let Some(Value::Array(mounts)) = field(body, "mounts")? {
+        for mount in mounts.iter_mut() {
+            let kind = field(mount, "type")?
+                .and_then(|kind| kind.as_str())
+                .unwrap_or("bind")
+                .to_string();
+            let readonly = match field(mount, "options")? {
+                Some(Value::Array(options)) => options.iter().any(|option| option == "ro"),
+                _ => false,
+            };
+            match kind.as_str() {
+                "bind" => match field(mount, "source")? {
+                    Some(Value::String(source)) => {
+                        *source = resolve_source(proxy, source, readonly)?
+                    }
+                    _ => return Err("bind mount without source".to_string()),
+                },
+                "tmpfs" | "ramfs" | "devpts" | "image" => {}
+                kind => return Err(format!("{kind} mounts are not allowed")),
+            }
+        }
+    }
+    if let Some(Value::Array(overlays)) = field(body, "overlay_volumes")? {
+        // Overlay mounts never write to their source
+        for overlay in overlays.iter_mut() {
+            match field(overlay, "source")? {
+                Some(Value::String(source)) => *source = resolve_source(proxy, source, true)?,
+                _ => return Err("overlay volume without source".to_string()),
+            }
+        }
+    }
+    if let Some(Value::Array(volumes)) = field(body, "volumes")? {
+        for volume in volumes.iter_mut() {
+            if let Some(Value::String(name)) = field(volume, "Name")? {
+                check_volume_name(name)?;
+            }
+        }
+    }
+    add_parent_label(body, "labels", &proxy.jail)
+}
+
+/// What is forwarded for an allowed request
+enum Decision {
+    /// The request as is, its body streamed
+    Forward,
+    /// The request with a checked and rewritten body
+    ForwardBody(Vec<u8>),
+    Deny(String),
+}
+
+/// Apply the allowlist to a request, rewriting its target or body when needed
+async fn decide(
+    proxy: &ProxyConfig,
+    head: &mut RequestHead,
+    client: &mut BufReader<UnixStream>,
+) -> std::io::Result<Decision> {
+    let (path, query) = head
+        .target
+        .split_once('?')
+        .map_or((head.target.clone(), String::new()), |(path, query)| {
+            (path.to_string(), query.to_string())
+        });
+
+    let decision = match route(&head.method, &path) {
+        Route::Forward => Decision::Forward,
+        Route::Deny => Decision::Deny("endpoint not allowed".to_string()),
+        Route::ListContainers => match with_label_filter(&query, &proxy.jail) {
+            Ok(query) => {
+                head.target = format!("{path}?{query}");
+                Decision::Forward
+            }
+            Err(reason) => Decision::Deny(reason),
+        },
+        Route::CreateContainer { libpod } => {
+            let body = read_body(head, client).await?;
+            let checked = query_param(&query, "name")
+                .map_or(Ok(()), |name| check_name(&name))
+                .and_then(|()| {
+                    serde_json::from_slice::<Value>(&body)
+                        .map_err(|e| format!("invalid container definition: {e}"))
+                })
+                .and_then(|mut body| {
+                    if libpod {
+                        check_libpod_create(proxy, &mut body)?;
+                    } else {
+                        check_docker_create(proxy, &mut body)?;
+                    }
+                    Ok(body)
+                });
+            match checked {
+                Ok(body) => Decision::ForwardBody(body.to_string().into_bytes()),
+                Err(reason) => Decision::Deny(reason),
+            }
+        }
+        Route::Build => match check_build_query(proxy, &query) {
+            Ok(query) => {
+                if !query.is_empty() {
+                    head.target = format!("{path}?{query}");
+                }
+                Decision::Forward
+            }
+            Err(reason) => Decision::Deny(reason),
+        },
+        Route::Container { id, exec, rename } => {
+            if !is_child(proxy, &id).await {
+                Decision::Deny(format!("container {id} does not belong to this jail"))
+            } else if let Some(Err(reason)) = query_param(&query, "name")
+                .filter(|_| rename)
+                .map(|name| check_name(&name))
+            {
+                Decision::Deny(reason)
+            } else if exec {
+                let body = read_body(head, client).await?;
+                let checked = serde_json::from_slice::<Value>(&body)
+                    .map_err(|e| format!("invalid exec definition: {e}"))
+                    .and_then(|mut exec| deny_fields(&mut exec, &["Privileged"]));
+                match checked {
+                    Ok(()) => Decision::ForwardBody(body),
+                    Err(reason) => Decision::Deny(reason),
+                }
+            } else {
+                Decision::Forward
+            }
+        }
+        Route::Exec(id) => {
+            let path = format!("/exec/{}/json", encode_path_segment(&id));
+            let container = match lookup(&proxy.upstream, &path).await {
+                Ok((200, exec)) => exec["ContainerID"].as_str().map(str::to_string),
+                _ => None,
+            };
+            match container {
+                Some(container) if is_child(proxy, &container).await => Decision::Forward,
+                _ => Decision::Deny(format!("exec session {id} does not belong to this jail")),
+            }
+        }
+    };
+    Ok(decision)
+}
+
+/// Endpoints whose connection may switch to a raw stream: attach and exec start
+///
+/// Their container or exec session is checked to be a child of the jail by `decide`.
+fn allows_upgrade(method: &str, path: &str) -> bool {
+    let (path, _) = api_path(path);
+    let segments: Vec<&str> = path.trim_start_matches('/').split('/').collect();
+    method == "POST"
+        && matches!(
+            segments.as_slice(),
+            ["containers", _, "attach"] | ["exec", _, "start"]
+        )
+}
+
+/// Read the status line and headers of the upstream response, returned raw with the status
+async fn read_response_head(
+    upstream: &mut BufReader<UnixStream>,
+) -> std::io::Result<(u16, Vec<u8>)> {
+    let mut head = Vec::new();
+    loop {
+        let start = head.len();
+        if upstream.read_until(b'\n', &mut head).await? == 0 {
+            return Err(invalid_data("connection closed in response headers"));
+        }
+        if start > 0 && head[start..].trim_ascii().is_empty() {
+            break;
+        }
+    }
+    let status = std::str::from_utf8(&head)
+        .ok()
+        .and_then(|head| head.split_whitespace().nth(1))
+        .and_then(|code| code.parse::<u16>().ok())
+        .ok_or_else(|| invalid_data("invalid response status line"))?;
+    Ok((status, head))
+}
+
+/// Stream the body the client sends after the head of its request, as its headers frame it
+async fn forward_body(
+    head: &RequestHead,
+    client: &mut BufReader<UnixStream>,
+    upstream: &mut UnixStream,
+) -> std::io::Result<()> {
+    let closed = || invalid_data("connection closed in request body");
+    if head
+        .header("transfer-encoding")
+        .is_some_and(|value| value.eq_ignore_ascii_case("chunked"))
+    {
+        loop {
+            let mut size_line = String::new();
+            if client.read_line(&mut size_line).await? == 0 {
+                return Err(closed());
+            }
+            upstream.write_all(size_line.as_bytes()).await?;
+            let size = size_line
+                .trim()
+                .split(';')
+                .next()
+                .and_then(|size| u64::from_str_radix(size, 16).ok())
+                .ok_or_else(|| invalid_data("invalid chunk size"))?;
+            if size == 0 {
+                // Trailers up to the final empty line
+                loop {
+                    let mut trailer = String::new();
+                    if client.read_line(&mut trailer).await? == 0 {
+                        return Err(closed());
+                    }
+                    upstream.write_all(trailer.as_bytes()).await?;
+                    if trailer.trim().is_empty() {
+                        return Ok(());
+                    }
+                }
+            }
+            // Chunk data and its CRLF
+            let chunk = size + 2;
+            if tokio::io::copy(&mut (&mut *client).take(chunk), upstream).await? != chunk {
+                return Err(closed());
+            }
+        }
+    } else if let Some(length) = head.header("content-length") {
+        let length = length
+            .parse::<u64>()
+            .map_err(|_| invalid_data("invalid Content-Length"))?;
+        if tokio::io::copy(&mut (&mut *client).take(length), upstream).await? != length {
+            return Err(closed());
+        }
+    }
+    Ok(())
+}
+
+/// Content-Length of a raw response head
+fn response_length(head: &[u8]) -> Option<u64> {
+    String::from_utf8_lossy(head).lines().find_map(|line| {
+        let (name, value) = line.split_once(':')?;
+        name.trim()
+            .eq_ignore_ascii_case("content-length")
+            .then(|| value.trim().parse().ok())?
+    })
+}
+
+/// Proxy one request of a client connection
+///
+/// Requests are forwarded with `Connection: close`, so every request of the client is checked
+/// on a connection of its own: one response is relayed, then the connection is closed. Only
+/// attach and exec start requests may upgrade: they are forwarded with `Connection: Upgrade`
+/// and their connection is passed through once the engine switches protocols (`101`) or
+/// hijacks it (`200`, as podman does).
+async fn handle(proxy: &ProxyConfig, stream: UnixStream) -> std::io::Result<()> {
+    let mut client = BufReader::new(stream);
+    let Some(mut head) = read_head(&mut client).await? else {
+        return Ok(());
+    };
+
+    let body = match decide(proxy, &mut head, &mut client).await? {
+        Decision::Forward => None,
+        Decision::ForwardBody(body) => Some(body),
+        Decision::Deny(reason) => {
+            warn!("Denied {} {}: {}", head.method, head.target, reason);
+            let body = serde_json::json!({
+                "message": format!("jail-ai: {reason}"),
+                "cause": reason,
+                "response": 403,
+            })
+            .to_string();
+            let response = format!(
+                "HTTP/1.1 403 Forbidden\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            );
+            return client.get_mut().write_all(response.as_bytes()).await;
+        }
+    };
+    debug!("Forwarding {} {}", head.method, head.target);
+
+    let path = head.target.split('?').next().unwrap_or_default();
+    let upgrade = allows_upgrade(&head.method, path) && head.header("upgrade").is_some();
+    head.headers.retain(|(name, _)| {
+        let name = name.to_ascii_lowercase();
+        let framing = name == "content-length" || name == "transfer-encoding";
+        let replaced =
+            name == "connection" || (name == "upgrade" && !upgrade) || (framing && body.is_some());
+        !replaced
+    });
+    let connection = if upgrade { "Upgrade" } else { "close" };
+    head.headers
+        .push(("Connection".to_string(), connection.to_string()));
+    if let Some(body) = &body {
+        head.headers
+            .push(("Content-Length".to_string(), body.len().to_string()));
+    }
+
+    let mut upstream = BufReader::new(UnixStream::connect(&proxy.upstream).await?);
+    upstream.get_mut().write_all(&head.to_bytes()).await?;
+    match &body {
+        Some(body) => upstream.get_mut().write_all(body).await?,
+        None => forward_body(&head, &mut client, upstream.get_mut()).await?,
+    }
+
+    let (status, response_head) = read_response_head(&mut upstream).await?;
+    client.get_mut().write_all(&response_head).await?;
+    if upgrade && (status == 101 || status == 200) {
+        tokio::io::copy_bidirectional(&mut client, &mut upstream).await?;
+        return Ok(());
+    }
+    // Anything else the client sent is dropped unread. The engine closes the connection
+    // after its response, unless it was asked to upgrade and answered with an error.
+    match response_length(&response_head).filter(|_| upgrade) {
+        Some(length) => {
+            tokio::io::copy(&mut (&mut upstream).take(length), client.get_mut()).await?;
+        }
+        None => {
+            tokio::io::copy(&mut upstream, client.get_mut()).await?;
+        }
+    }
+    client.get_mut().shutdown().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn proxy_config(workspace: &Path, readonly: bool) -> ProxyConfig {
+        ProxyConfig {
+            jail: "jail__app__12345678__claude".to_string(),
+            upstream: PathBuf::from("/run/user/1000/podman/podman.sock"),
+            socket: PathBuf::from("/run/user/1000/jail-ai-proxy/0123456789ab/podman.sock"),
+            workspace: Some(WorkspaceMount {
+                source: workspace.to_path_buf(),
+                target: PathBuf::from("/workspace"),
+                readonly,
+            }),
+        }
+    }
+
+    #[test]
+    fn test_route() {
+        assert_eq!(route("GET", "/v4.0.0/libpod/_ping"), Route::Forward);
+        assert_eq!(route("GET", "/v1.41/images/json"), Route::Forward);
+        assert_eq!(route("DELETE", "/v1.41/images/alpine"), Route::Deny);
+        assert_eq!(
+            route("GET", "/v1.41/containers/json"),
+            Route::ListContainers
+        );
+        assert_eq!(
+            route("POST", "/v4.0.0/libpod/containers/create"),
+            Route::CreateContainer { libpod: true }
+        );
+        assert_eq!(
+            route("POST", "/containers/web/exec"),
+            Route::Container {
+                id: "web".to_string(),
+                exec: true,
+                rename: false,
+            }
+        );
+        assert_eq!(
+            route("POST", "/v1.41/exec/abc123/start"),
+            Route::Exec("abc123".to_string())
+        );
+        assert_eq!(route("POST", "/v1.41/containers/prune"), Route::Deny);
+        assert_eq!(
+            route("POST", "/containers/a%2F..%2Fprune/start"),
+            Route::Deny
+        );
+        assert_eq!(route("POST", "/v4.0.0/libpod/volumes/create"), Route::Deny);
+        assert_eq!(route("GET", "/v1.41/events"), Route::Deny);
+        assert_eq!(route("POST", "/v1.41/build"), Route::Build);
+        assert_eq!(
+            route("POST", "/v4.0.0/libpod/containers/web/rename"),
+            Route::Container {
+                id: "web".to_string(),
+                exec: false,
+                rename: true,
+            }
+        );
+
+        assert!(allows_upgrade("POST", "/v1.41/containers/web/attach"));
+        assert!(allows_upgrade("POST", "/v4.0.0/libpod/exec/abc123/start"));
+        assert!(!allows_upgrade("POST", "/v1.41/containers/web/start"));
+        assert!(!allows_upgrade("GET", "/v1.41/containers/web/attach"));
+    }
+
+    #[test]
+    fn test_with_label_filter() {
+        let query = with_label_filter("all=1", "jail__a").unwrap();
+        assert_eq!(query_param(&query, "all").as_deref(), Some("1"));
+        let filters: Value =
+            serde_json::from_str(&query_param(&query, "filters").unwrap()).unwrap();
+        assert_eq!(
+            filters["label"],
+            serde_json::json!(["jail-ai.parent=jail__a"])
+        );
+
+        let existing = format!(
+            "filters={}&filters=%7B%7D",
+            encode_path_segment(r#"{"label":["app=web"],"status":["running"]}"#)
+        );
+        let query = with_label_filter(&existing, "jail__a").unwrap();
+        assert_eq!(query.matches("filters=").count(), 1);
+        let filters: Value =
+            serde_json::from_str(&query_param(&query, "filters").unwrap()).unwrap();
+        assert_eq!(
+            filters["label"],
+            serde_json::json!(["app=web", "jail-ai.parent=jail__a"])
+        );
+        assert_eq!(filters["status"], serde_json::json!(["running"]));
+    }
+
+    #[test]
+    fn test_docker_create_checks() {
+        let workspace = tempfile::tempdir().unwrap();
+        std::fs::create_dir(workspace.path().join("data")).unwrap();
+        let proxy = proxy_config(workspace.path(), false);
+        let root = workspace.path().canonicalize().unwrap();
+
+        let mut body = serde_json::json!({
+            "Image": "alpine",
+            "HostConfig": {
+                "Binds": ["/workspace/data:/data", "cache:/cache"],
+                "Mounts": [{ "Type": "bind", "Source": root.join("data"), "Target": "/src" }],
+                "NetworkMode": "bridge",
+            },
+        });
+        check_docker_create(&proxy, &mut body).unwrap();
+        assert_eq!(
+            body["HostConfig"]["Binds"][0],
+            format!("{}:/data", root.join("data").display())
+        );
+        assert_eq!(body["HostConfig"]["Binds"][1], "cache:/cache");
+        assert_eq!(body["Labels"][PARENT_LABEL], "jail__app__12345678__claude");
+
+        for (denied, reason) in [
+            (
+                serde_json::json!({ "HostConfig": { "privileged": true } }),
+                "Privileged",
+            ),
+            (
+                serde_json::json!({ "HostConfig": { "Binds": ["/etc:/etc"] } }),
+                "outside",
+            ),
+            (
+                serde_json::json!({ "HostConfig": { "Binds": ["/workspace/../..:/host"] } }),
+                "outside",
+            ),
+            (
+                serde_json::json!({ "HostConfig": { "Binds": ["jail__app__12345678__claude__home:/h"] } }),
+                "belongs to a jail",
+            ),
+            (
+                serde_json::json!({ "HostConfig": { "PidMode": "host" } }),
+                "PidMode",
+            ),
+            (
+                serde_json::json!({ "HostConfig": { "NetworkMode": "container:jail__x" } }),
+                "NetworkMode",
+            ),
+            (
+                serde_json::json!({ "HostConfig": { "SecurityOpt": ["seccomp=unconfined"] } }),
+                "security option",
+            ),
+            (
+                serde_json::json!({ "HostConfig": { "Privileged": false, "privileged": true } }),
+                "more than once",
+            ),
+            (
+                serde_json::json!({ "HostConfig": { "SecurityOpt": [
+                    "seccomp={\"defaultAction\":\"SCMP_ACT_ALLOW\"}"
+                ] } }),
+                "security option",
+            ),
+            (
+                serde_json::json!({ "HostConfig": { "LogConfig": {
+                    "Type": "k8s-file",
+                    "Config": { "path": "/home/user/.bashrc" }
+                } } }),
+                "LogConfig",
+            ),
+        ] {
+            let mut body = denied;
+            let err = check_docker_create(&proxy, &mut body).unwrap_err();
+            assert!(err.contains(reason), "{err}");
+        }
+
+        // Bind mounts of a read-only workspace must be read-only
+        let proxy = proxy_config(workspace.path(), true);
+        let mut body = serde_json::json!({ "HostConfig": { "Binds": ["/workspace:/src"] } });
+        assert!(check_docker_create(&proxy, &mut body).is_err());
+        let mut body = serde_json::json!({ "HostConfig": { "Binds": ["/workspace:/src:ro,z"] } });
+        check_docker_create(&proxy, &mut body).unwrap();
+    }
+
+    #[test]
+    fn test_build_query_checks() {
+        let workspace = tempfile::tempdir().unwrap();
+        std::fs::create_dir(workspace.path().join("cache")).unwrap();
+        let proxy = proxy_config(workspace.path(), false);
+        let root = workspace.path().canonicalize().unwrap();
+
+        assert_eq!(
+            check_build_query(&proxy, "t=app%3Alatest&networkmode=bridge").unwrap(),
+            "t=app%3Alatest&networkmode=bridge"
+        );
+        assert_eq!(
+            check_build_query(&proxy, "volume=%2Fworkspace%2Fcache%3A%2Fcache").unwrap(),
+            format!(
+                "volume={}",
+                encode_path_segment(&format!("{}:/cache", root.join("cache").display()))
+            )
+        );
+        for (query, reason) in [
+            ("networkmode=host", "networkmode"),
+            ("volume=%2Fetc%3A%2Fetc", "outside"),
+            ("remote=http%3A%2F%2F127.0.0.1%2F", "remote"),
+            ("outputs=type%3Dlocal%2Cdest%3D%2Ftmp", "outputs"),
+        ] {
+            let err = check_build_query(&proxy, query).unwrap_err();
+            assert!(err.contains(reason), "{err}");
+        }
+    }
+
+    #[test]
+    fn test_libpod_create_checks() {
+        let workspace = tempfile::tempdir().unwrap();
+        let proxy = proxy_config(workspace.path(), false);
+
+        let mut body = serde_json::json!({
+            "name": "web",
+            "image": "alpine",
+            "mounts": [{ "destination": "/src", "source": "/workspace", "type": "bind" }],
+            "netns": { "nsmode": "slirp4netns" },
+            "labels": { "app": "web" },
+        });
+        check_libpod_create(&proxy, &mut body).unwrap();
+        assert_eq!(
+            body["mounts"][0]["source"],
+            workspace
+                .path()
+                .canonicalize()
+                .unwrap()
+                .display()
+                .to_string()
+        );
+        assert_eq!(body["labels"]["app"], "web");
+        assert_eq!(body["labels"][PARENT_LABEL], "jail__app__12345678__claude");
+
+        for denied in [
+            serde_json::json!({ "privileged": true }),
+            serde_json::json!({ "name": "jail__app__12345678__codex" }),
+            serde_json::json!({ "netns": { "nsmode": "host" } }),
+            serde_json::json!({ "mounts": [{ "destination": "/h", "source": "/home", "type": "bind" }] }),
+            serde_json::json!({ "mounts": [{ "destination": "/h", "source": "/home/*", "type": "glob" }] }),
+            serde_json::json!({ "volumes": [{ "Name": "jail__app__12345678__nix", "Dest": "/nix" }] }),
+            serde_json::json!({ "log_configuration": { "path": "/home/user/.bashrc" } }),
+        ] {
+            let mut body = denied;
+            assert!(check_libpod_create(&proxy, &mut body).is_err(), "{body}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_upgrade_request_does_not_pass_pipelined_requests() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut proxy = proxy_config(dir.path(), false);
+        proxy.upstream = dir.path().join("engine.sock");
+        let listener = UnixListener::bind(&proxy.upstream).unwrap();
+
+        // Engine answering the first request, then recording anything else it receives
+        let engine = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut stream = BufReader::new(stream);
+            let head = read_head(&mut stream).await.unwrap().unwrap();
+            stream
+                .get_mut()
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\nOK")
+                .await
+                .unwrap();
+            stream.get_mut().shutdown().await.unwrap();
+            let mut rest = Vec::new();
+            stream.read_to_end(&mut rest).await.unwrap();
+            let second = tokio::time::timeout(Duration::from_millis(100), listener.accept()).await;
+            (head, rest, second.is_ok())
+        });
+
+        let (mut client, server) = UnixStream::pair().unwrap();
+        client
+            .write_all(
+                b"GET /v1.41/_ping HTTP/1.1\r\nHost: d\r\nConnection: Upgrade\r\nUpgrade: tcp\r\n\r\n\
+                  POST /v1.41/containers/create?name=escape HTTP/1.1\r\nHost: d\r\nContent-Length: 2\r\n\r\n{}",
+            )
+            .await
+            .unwrap();
+        handle(&proxy, server).await.unwrap();
+
+        let mut response = String::new();
+        client.read_to_string(&mut response).await.unwrap();
+        assert!(response.starts_with("HTTP/1.1 200 OK"), "{response}");
+        assert!(response.ends_with("\r\n\r\nOK"), "{response}");
+
+        let (head, rest, second_connection) = engine.await.unwrap();
+        assert_eq!(head.target, "/v1.41/_ping");
+        assert_eq!(head.header("upgrade"), None);
+        assert_eq!(head.header("connection"), Some("close"));
+        assert!(rest.is_empty(), "{}", String::from_utf8_lossy(&rest));
+        assert!(!second_connection);
+    }
+
+    #[tokio::test]
+    async fn test_forwarded_request_body_reaches_engine() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut proxy = proxy_config(dir.path(), false);
+        proxy.upstream = dir.path().join("engine.sock");
+        let listener = UnixListener::bind(&proxy.upstream).unwrap();
+
+        for request in [
+            "POST /v1.41/images/create?fromImage=alpine HTTP/1.1\r\nHost: d\r\nContent-Length: 5\r\n\r\nhello",
+            "POST /v1.41/images/create?fromImage=alpine HTTP/1.1\r\nHost: d\r\nTransfer-Encoding: chunked\r\n\r\n3\r\nhel\r\n2\r\nlo\r\n0\r\n\r\n",
+        ] {
+            // Engine echoing the request body once it has read all of it
+            let engine = async {
+                let (stream, _) = listener.accept().await.unwrap();
+                let mut stream = BufReader::new(stream);
+                let head = read_head(&mut stream).await.unwrap().unwrap();
+                let body = read_body(&head, &mut stream).await.unwrap();
+                let response = format!("HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n", body.len());
+                stream.get_mut().write_all(response.as_bytes()).await.unwrap();
+                stream.get_mut().write_all(&body).await.unwrap();
+            };
+
+            let (mut client, server) = UnixStream::pair().unwrap();
+            client.write_all(request.as_bytes()).await.unwrap();
+            let proxied = tokio::time::timeout(Duration::from_secs(5), async {
+                tokio::join!(handle(&proxy, server), engine).0
+            });
+            proxied.await.unwrap().unwrap();
+
+            let mut response = String::new();
+            client.read_to_string(&mut response).await.unwrap();
+            assert!(response.ends_with("\r\n\r\nhello"), "{response}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_attach_upgrade_passes_both_directions() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut proxy = proxy_config(dir.path(), false);
+        proxy.upstream = dir.path().join("engine.sock");
+        let listener = UnixListener::bind(&proxy.upstream).unwrap();
+        let jail = proxy.jail.clone();
+
+        // Docker switches protocols, podman hijacks the connection after a 200
+        for status in ["101 UPGRADED", "200 OK"] {
+            let engine = async {
+                // The proxy looks the container up first
+                let (stream, _) = listener.accept().await.unwrap();
+                let mut stream = BufReader::new(stream);
+                read_head(&mut stream).await.unwrap().unwrap();
+                let container =
+                    serde_json::json!({ "Config": { "Labels": { PARENT_LABEL: jail } } })
+                        .to_string();
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    container.len(),
+                    container
+                );
+                stream
+                    .get_mut()
+                    .write_all(response.as_bytes())
+                    .await
+                    .unwrap();
+                drop(stream);
+
+                let (stream, _) = listener.accept().await.unwrap();
+                let mut stream = BufReader::new(stream);
+                let head = read_head(&mut stream).await.unwrap().unwrap();
+                let response = format!("HTTP/1.1 {status}\r\nUpgrade: tcp\r\n\r\n");
+                stream
+                    .get_mut()
+                    .write_all(response.as_bytes())
+                    .await
+                    .unwrap();
+                let mut stdin = [0; 4];
+                stream.read_exact(&mut stdin).await.unwrap();
+                stream.get_mut().write_all(b"pong").await.unwrap();
+                (head, stdin)
+            };
+
+            let (client, server) = UnixStream::pair().unwrap();
+            let attach = async {
+                let mut client = BufReader::new(client);
+                client
+                    .get_mut()
+                    .write_all(b"POST /v1.41/containers/child/attach?stdin=1&stream=1 HTTP/1.1\r\nHost: d\r\nConnection: Upgrade\r\nUpgrade: tcp\r\n\r\n")
+                    .await
+                    .unwrap();
+                let mut line = String::new();
+                while line != "\r\n" {
+                    line.clear();
+                    client.read_line(&mut line).await.unwrap();
+                }
+                client.get_mut().write_all(b"ping").await.unwrap();
+                let mut stdout = [0; 4];
+                client.read_exact(&mut stdout).await.unwrap();
+                stdout
+            };
+            let proxied = tokio::time::timeout(Duration::from_secs(5), async {
+                let (proxied, (head, stdin), stdout) =
+                    tokio::join!(handle(&proxy, server), engine, attach);
+                (proxied, head, stdin, stdout)
+            });
+            let (proxied, head, stdin, stdout) = proxied.await.unwrap();
+            proxied.unwrap();
+
+            assert_eq!(head.header("connection"), Some("Upgrade"));
+            assert_eq!(head.header("upgrade"), Some("tcp"));
+            assert_eq!(&stdin, b"ping");
+            assert_eq!(&stdout, b"pong");
+        }
+    }
+}
@@ -1,19 +1,29 @@
 use crate::error::{JailError, Result};
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tracing::{debug, warn};
 
 #[derive(Debug, Serialize, Deserialize, Default)]
 pub struct State {
-    pub last_weekly_upgrade_check: Option<chrono::DateTime<chrono::Utc>>,
+    pub last_weekly_upgrade_check: Option<DateTime<Utc>>,
+    /// Last time a command was executed or attached in each jail
+    #[serde(default)]
+    pub last_activity: BTreeMap<String, DateTime<Utc>>,
 }
 
 impl State {
     pub fn load() -> Result<Self> {
-        let path = get_state_path()?;
+        Self::load_from(&get_state_path()?)
+    }
+
+    fn load_from(path: &Path) -> Result<Self> {
         if !path.exists() {
             return Ok(Self::default());
         }
-        let content = std::fs::read_to_string(&path).map_err(|e| {
+        let content = std::fs::read_to_string(path).map_err(|e| {
             JailError::Config(format!(
                 "Failed to read state file {}: {}",
                 path.display(),
@@ -30,32 +40,151 @@ impl State {
         Ok(state)
     }
 
-    pub fn save(&self) -> Result<()> {
-        let path = get_state_path()?;
-        if let Some(parent) = path.parent() {
-            std::fs::create_dir_all(parent).map_err(|e| {
-                JailError::Config(format!(
-                    "Failed to create state directory {}: {}",
-                    parent.display(),
-                    e
-                ))
-            })?;
-        }
+    /// Write the state to a temporary file replacing the state file, readers never see it partly written
+    fn save_to(&self, path: &Path) -> Result<()> {
+        let parent = state_dir(path)?;
         let content = serde_json::to_string_pretty(self)
             .map_err(|e| JailError::Config(format!("Failed to serialize state: {}", e)))?;
-        std::fs::write(&path, content).map_err(|e| {
+        let write_error = |e: std::io::Error| {
             JailError::Config(format!(
                 "Failed to write state file {}: {}",
                 path.display(),
                 e
             ))
-        })?;
+        };
+        let mut file = tempfile::NamedTempFile::new_in(parent).map_err(write_error)?;
+        file.write_all(content.as_bytes()).map_err(write_error)?;
+        file.persist(path).map_err(|e| write_error(e.error))?;
+        Ok(())
+    }
+
+    /// Remember that the jail was used now (best effort, failures are only logged)
+    pub fn record_activity(name: &str) {
+        let result = Self::update(|state| {
+            state.last_activity.insert(name.to_string(), Utc::now());
+            true
+        });
+        if let Err(e) = result {
+            debug!("Failed to update state file: {}", e);
+        }
+    }
+
+    /// Drop the activity record of a removed jail
+    pub fn forget_jail(name: &str) {
+        if let Err(e) = Self::update(|state| state.last_activity.remove(name).is_some()) {
+            debug!("Failed to update state file: {}", e);
+        }
+    }
+
+    /// Remember when the weekly upgrade check was last offered
+    pub fn record_upgrade_check(at: DateTime<Utc>) -> Result<()> {
+        Self::update(|state| {
+            state.last_weekly_upgrade_check = Some(at);
+            true
+        })
+    }
+
+    /// Load, modify and save the state under a lock, skipping the write when nothing changed
+    ///
+    /// The lock keeps concurrent jail-ai processes (parallel sessions) from losing each
+    /// other's changes. A corrupt state file is replaced.
+    fn update(change: impl FnOnce(&mut Self) -> bool) -> Result<()> {
+        Self::update_at(&get_state_path()?, change)
+    }
+
+    fn update_at(path: &Path, change: impl FnOnce(&mut Self) -> bool) -> Result<()> {
+        let lock_path = path.with_extension("json.lock");
+        let lock_error = |e: std::io::Error| {
+            JailError::Config(format!(
+                "Failed to lock state file {}: {}",
+                lock_path.display(),
+                e
+            ))
+        };
+        state_dir(path)?;
+        let lock = std::fs::OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&lock_path)
+            .map_err(lock_error)?;
+        lock.lock().map_err(lock_error)?;
+
+        let mut state = Self::load_from(path).unwrap_or_else(|e| {
+            warn!("{}, starting over", e);
+            Self::default()
+        });
+        if change(&mut state) {
+            state.save_to(path)?;
+        }
         Ok(())
     }
 }
 
+/// Directory of the state file, created when missing
+fn state_dir(path: &Path) -> Result<&Path> {
+    let parent = path
+        .parent()
+        .ok_or_else(|| JailError::Config(format!("Invalid state path {}", path.display())))?;
+    std::fs::create_dir_all(parent).map_err(|e| {
+        JailError::Config(format!(
+            "Failed to create state directory {}: {}",
+            parent.display(),
+            e
+        ))
+    })?;
+    Ok(parent)
+}
+
 fn get_state_path() -> Result<PathBuf> {
     let home_dir = std::env::var("HOME")
         .map_err(|_| JailError::Config("HOME environment variable not set".to_string()))?;
     Ok(PathBuf::from(home_dir).join(".config/jail-ai/state.json"))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_concurrent_updates_are_kept() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("state.json");
+
+        let threads: Vec<_> = (0..8)
+            .map(|i| {
+                let path = path.clone();
+                std::thread::spawn(move || {
+                    State::update_at(&path, |state| {
+                        state.last_activity.insert(format!("jail{i}"), Utc::now());
+                        true
+                    })
+                    .unwrap();
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+
+        assert_eq!(State::load_from(&path).unwrap().last_activity.len(), 8);
+    }
+
+    #[test]
+    fn test_corrupt_state_is_replaced() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("state.json");
+        std::fs::write(&path, "{\"last_activity\": {\"jail").unwrap();
+        assert!(State::load_from(&path).is_err());
+
+        State::update_at(&path, |state| {
+            state.last_activity.insert("jail".to_string(), Utc::now());
+            true
+        })
+        .unwrap();
+        assert!(State::load_from(&path)
+            .unwrap()
+            .last_activity
+            .contains_key("jail"));
+    }
+}
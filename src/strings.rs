@@ -14,11 +14,15 @@ pub const RECREATE_CONTAINER: &str = "  • Recreate container with the correct
 pub const ENSURE_LATEST_TOOLS: &str = "  • Ensure you have the latest tools and security patches";
 pub const DATA_PRESERVED: &str = "\nYour data in /home/agent will be preserved during the rebuild.";
 pub const WOULD_YOU_LIKE_REBUILD: &str = "\nWould you like to rebuild now? (y/N): ";
+pub const CONFIG_DRIFT_DETECTED: &str =
+    "\n⚙️  Jail '{}' does not match the requested configuration:";
 
 /// Status and progress messages
 pub const CHECKING_UPDATES: &str = "Checking for updates...";
 pub const USER_CHOSE_UPGRADE: &str = "User chose to upgrade";
 pub const USER_DECLINED_UPGRADE: &str = "User declined rebuild, continuing with existing container";
+pub const USER_DECLINED_DRIFT_RECREATE: &str =
+    "User declined recreation, continuing with the existing configuration";
 pub const CONTAINER_UP_TO_DATE: &str = "Container and layers are up to date";
 
 /// Jail creation messages
@@ -34,32 +34,18 @@ struct Pane {
 }
 
 impl Pane {
-    fn new(title: &str, jail_name: &str, command: &[String], size: PtySize) -> Result<Self> {
+    fn new(title: &str, argv: &[String], size: PtySize) -> Result<Self> {
         let pty_system = portable_pty::native_pty_system();
 
         let pair = pty_system
             .openpty(size)
             .map_err(|e| JailError::Backend(format!("Failed to open PTY: {e}")))?;
 
-        let mut cmd = CommandBuilder::new("podman");
-        cmd.arg("exec");
-        cmd.arg("-it");
-        // Forward terminal environment so colors and capabilities work correctly
-        // inside the container. Use xterm-256color as TERM: kitty's own terminfo
-        // is not installed in the container, and xterm-256color is universally
-        // available while still enabling full 256-color support.
-        cmd.arg("-e");
-        cmd.arg("TERM=xterm-256color");
-        cmd.arg("-e");
-        cmd.arg("COLORTERM=truecolor");
-        if let Ok(lang) = std::env::var("LANG") {
-            cmd.arg("-e");
-            cmd.arg(format!("LANG={lang}"));
-        }
-        cmd.arg(jail_name);
-        for arg in command {
-            cmd.arg(arg);
-        }
+        let (program, args) = argv
+            .split_first()
+            .ok_or_else(|| JailError::Backend("Empty pane command".to_string()))?;
+        let mut cmd = CommandBuilder::new(program);
+        cmd.args(args);
 
         let child = pair
             .slave
@@ -272,15 +258,43 @@ enum Tab {
 }
 
 pub struct Tui {
-    jail_name: String,
-    agent_command: Vec<String>,
+    agent_label: String,
+    agent_argv: Vec<String>,
+    shell_argv: Vec<String>,
 }
 
 impl Tui {
-    pub fn new(jail_name: impl Into<String>, agent_command: Vec<String>) -> Self {
+    /// Create a TUI running the agent and a shell, each from a full command line
+    /// (as built by `JailManager::exec_argv`)
+    pub fn new(
+        agent_label: impl Into<String>,
+        agent_argv: Vec<String>,
+        shell_argv: Vec<String>,
+    ) -> Self {
         Self {
-            jail_name: jail_name.into(),
-            agent_command,
+            agent_label: agent_label.into(),
+            agent_argv,
+            shell_argv,
+        }
+    }
+
+    /// Exec options for the panes: a terminal with the host's color and locale settings
+    pub fn pane_exec_options() -> crate::backend::ExecOptions {
+        // Forward terminal environment so colors and capabilities work correctly
+        // inside the container. Use xterm-256color as TERM: kitty's own terminfo
+        // is not installed in the container, and xterm-256color is universally
+        // available while still enabling full 256-color support.
+        let mut env = vec![
+            ("TERM".to_string(), "xterm-256color".to_string()),
+            ("COLORTERM".to_string(), "truecolor".to_string()),
+        ];
+        if let Ok(lang) = std::env::var("LANG") {
+            env.push(("LANG".to_string(), lang));
+        }
+
+        crate::backend::ExecOptions {
+            env,
+            ..crate::backend::ExecOptions::interactive(true)
         }
     }
 
@@ -321,17 +335,8 @@ impl Tui {
             pixel_height: 0,
         };
 
-        let agent_label = self
-            .agent_command
-            .first()
-            .cloned()
-            .unwrap_or_else(|| "agent".to_string());
-
-        let mut agent_pane =
-            Pane::new(&agent_label, &self.jail_name, &self.agent_command, pty_size)?;
-
-        let shell_cmd = vec!["/usr/bin/zsh".to_string()];
-        let mut shell_pane = Pane::new("shell", &self.jail_name, &shell_cmd, pty_size)?;
+        let mut agent_pane = Pane::new(&self.agent_label, &self.agent_argv, pty_size)?;
+        let mut shell_pane = Pane::new("shell", &self.shell_argv, pty_size)?;
 
         let mut active_tab = Tab::Agent;
         let mut prefix_mode = false;
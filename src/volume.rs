@@ -0,0 +1,141 @@
+//! Management of the `__home` and `__nix` volumes of jails
+
+use crate::agents::Agent;
+use crate::backend::JailBackend;
+use crate::error::Result;
+use std::path::Path;
+use tracing::debug;
+
+const HOME_SUFFIX: &str = "__home";
+const NIX_SUFFIX: &str = "__nix";
+
+/// Volume name from a volume or jail name (a jail name stands for its home volume)
+pub fn resolve_volume_name(name: &str) -> String {
+    if name.ends_with(HOME_SUFFIX) || name.ends_with(NIX_SUFFIX) {
+        name.to_string()
+    } else {
+        format!("{name}{HOME_SUFFIX}")
+    }
+}
+
+/// Jail using a home volume, or project base name of the jails sharing a Nix volume
+pub fn volume_owner(volume: &str) -> Option<&str> {
+    volume
+        .strip_suffix(HOME_SUFFIX)
+        .or_else(|| volume.strip_suffix(NIX_SUFFIX))
+}
+
+/// Agent of the jail owning a home volume
+fn volume_agent(volume: &str) -> Option<Agent> {
+    let jail = volume.strip_suffix(HOME_SUFFIX)?;
+    Agent::from_str(jail.rsplit("__").next()?)
+}
+
+/// Paths kept by `volume reset`: the credentials of the jail agent
+///
+/// Nix volumes and home volumes of jails without agent keep nothing.
+pub fn reset_keep_paths(volume: &str) -> Vec<String> {
+    volume_agent(volume)
+        .map(|agent| vec![agent.auth_credential_path().to_string()])
+        .unwrap_or_default()
+}
+
+/// Copy the content of a volume into another one, through a temporary archive
+pub async fn clone_volume(backend: &dyn JailBackend, source: &str, target: &str) -> Result<()> {
+    let data_dir = crate::jail_setup::get_jail_ai_data_dir()?;
+    std::fs::create_dir_all(&data_dir)?;
+    clone_volume_in(backend, &data_dir, source, target).await
+}
+
+/// Copy a volume through an archive in a private temporary directory of `dir`
+async fn clone_volume_in(
+    backend: &dyn JailBackend,
+    dir: &Path,
+    source: &str,
+    target: &str,
+) -> Result<()> {
+    let tmp = tempfile::Builder::new()
+        .prefix("jail-ai-volume-")
+        .tempdir_in(dir)?;
+    let archive = tmp.path().join("volume.tar");
+
+    let result = match backend.export_volume(source, &archive).await {
+        Ok(()) => backend.import_volume(target, &archive).await,
+        Err(e) => Err(e),
+    };
+    if let Err(e) = tmp.close() {
+        debug!("Could not remove {}: {}", archive.display(), e);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::fake::{FakeBackend, FakeCall};
+
+    #[test]
+    fn test_resolve_volume_name() {
+        assert_eq!(
+            resolve_volume_name("jail__app__12345678__claude"),
+            "jail__app__12345678__claude__home"
+        );
+        assert_eq!(
+            resolve_volume_name("jail__app__12345678__claude__home"),
+            "jail__app__12345678__claude__home"
+        );
+        assert_eq!(
+            resolve_volume_name("jail__app__12345678__nix"),
+            "jail__app__12345678__nix"
+        );
+        assert_eq!(
+            volume_owner("jail__app__12345678__claude__home"),
+            Some("jail__app__12345678__claude")
+        );
+        assert_eq!(
+            volume_owner("jail__app__12345678__nix"),
+            Some("jail__app__12345678")
+        );
+    }
+
+    #[test]
+    fn test_reset_keep_paths() {
+        assert_eq!(
+            reset_keep_paths("jail__app__12345678__claude__home"),
+            vec![".claude/.credentials.json".to_string()]
+        );
+        assert!(reset_keep_paths("jail__app__12345678__nix").is_empty());
+        assert!(reset_keep_paths("jail__app__12345678__default__home").is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_clone_volume_exports_then_imports() {
+        let fake = FakeBackend::new();
+        let dir = tempfile::tempdir().unwrap();
+        clone_volume_in(
+            &fake,
+            dir.path(),
+            "jail__a__11111111__claude__home",
+            "jail__b__22222222__claude__home",
+        )
+        .await
+        .unwrap();
+
+        let calls = fake.calls();
+        let [FakeCall::ExportVolume {
+            volume: source,
+            archive: exported,
+        }, FakeCall::ImportVolume {
+            volume: target,
+            archive: imported,
+        }] = calls.as_slice()
+        else {
+            panic!("Unexpected calls: {calls:?}");
+        };
+        assert_eq!(source, "jail__a__11111111__claude__home");
+        assert_eq!(target, "jail__b__22222222__claude__home");
+        assert_eq!(exported, imported);
+        assert!(exported.starts_with(dir.path()));
+        assert!(!exported.exists());
+    }
+}